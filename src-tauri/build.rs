@@ -1,3 +1,16 @@
 fn main() {
+    // WinFsp's DLL is only loaded once a bucket is mounted, so the app still starts on machines
+    // without WinFsp installed
+    let windows = std::env::var("CARGO_CFG_TARGET_OS").is_ok_and(|os| os == "windows");
+    if windows && std::env::var_os("CARGO_FEATURE_MOUNT").is_some() {
+        let dll = match std::env::var("CARGO_CFG_TARGET_ARCH").as_deref() {
+            Ok("x86") => "winfsp-x86.dll",
+            Ok("aarch64") => "winfsp-a64.dll",
+            _ => "winfsp-x64.dll",
+        };
+        println!("cargo:rustc-link-lib=dylib=delayimp");
+        println!("cargo:rustc-link-arg=/DELAYLOAD:{}", dll);
+    }
+
     tauri_build::build()
 }
@@ -0,0 +1,459 @@
+use aws_sdk_s3::presigning::PresigningConfig;
+use aws_sdk_s3::types::{Delete, Object, ObjectIdentifier};
+use aws_sdk_s3::Client;
+use clap::{Parser, Subcommand};
+use r2drive_core::client::{self, error_message, is_folder_marker, list_all_objects};
+use r2drive_core::network::{self, NetworkSettings};
+use r2drive_core::profiles;
+use r2drive_core::sync::{self, normalize_prefix, SyncAction, SyncActionKind, SyncDirection, SyncOptions};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::Duration;
+use tokio::task::JoinSet;
+use urlencoding::encode;
+use walkdir::WalkDir;
+
+// Scripted access to buckets through the app's saved profiles: secrets come from the same
+// keychain entries, and requests and sync plans from the same code as the GUI. Buckets are
+// addressed as r2://bucket/key; anything else is a local path.
+const SCHEME: &str = "r2://";
+const TRANSFER_CONCURRENCY: usize = 8;
+/// SigV4 presigned URLs can't outlive a week.
+const MAX_PRESIGN_SECS: u64 = 7 * 24 * 3600;
+/// Most keys DeleteObjects takes at once.
+const DELETE_BATCH: usize = 1000;
+
+#[derive(Parser)]
+#[command(name = "r2drive-cli", version, about = "Work with buckets through R2Drive's saved profiles")]
+struct Cli {
+    /// Saved profile to connect with; optional when only one is saved.
+    #[arg(long, global = true, env = "R2DRIVE_PROFILE")]
+    profile: Option<String>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// List buckets, or what is under r2://bucket/prefix.
+    Ls {
+        target: Option<String>,
+        /// Every object under the prefix rather than one level.
+        #[arg(short, long)]
+        recursive: bool,
+    },
+    /// Copy between a local path and r2://, or from one r2:// location to another.
+    Cp {
+        source: String,
+        destination: String,
+        /// Copy a folder or prefix with everything under it.
+        #[arg(short, long)]
+        recursive: bool,
+    },
+    /// Make the destination match the source: a local folder and an r2:// prefix, either way.
+    Sync {
+        source: String,
+        destination: String,
+        /// Delete files on the destination that the source doesn't have.
+        #[arg(long)]
+        delete: bool,
+        /// Print what would be done without changing anything.
+        #[arg(long)]
+        dry_run: bool,
+        /// Gitignore-style pattern to leave alone, on top of the folder's .r2ignore. Repeatable.
+        #[arg(long)]
+        exclude: Vec<String>,
+    },
+    /// Delete an object, or with --recursive everything under a prefix.
+    Rm {
+        target: String,
+        #[arg(short, long)]
+        recursive: bool,
+    },
+    /// Print a presigned download URL for an object.
+    Presign {
+        target: String,
+        /// Seconds the URL stays valid, a week at most.
+        #[arg(long, default_value_t = 3600)]
+        expires: u64,
+    },
+}
+
+enum Location {
+    Local(PathBuf),
+    Remote { bucket: String, key: String },
+}
+
+impl Location {
+    fn parse(raw: &str) -> Result<Self, String> {
+        let Some(rest) = raw.strip_prefix(SCHEME) else {
+            return Ok(Location::Local(PathBuf::from(raw)));
+        };
+        let (bucket, key) = rest.split_once('/').unwrap_or((rest, ""));
+        if bucket.is_empty() {
+            return Err(format!("No bucket in {}", raw));
+        }
+        Ok(Location::Remote { bucket: bucket.to_string(), key: key.to_string() })
+    }
+}
+
+fn remote(raw: &str) -> Result<(String, String), String> {
+    match Location::parse(raw)? {
+        Location::Remote { bucket, key } => Ok((bucket, key)),
+        Location::Local(_) => Err(format!("Expected an {}bucket/key location, got {}", SCHEME, raw)),
+    }
+}
+
+fn uri(bucket: &str, key: &str) -> String {
+    format!("{}{}/{}", SCHEME, bucket, key)
+}
+
+struct Session {
+    client: Client,
+    read_only: bool,
+    /// The app's data dir, whose journal lists downloads in progress.
+    data_dir: PathBuf,
+}
+
+impl Session {
+    fn writable(&self) -> Result<(), String> {
+        if self.read_only { Err("The profile is read-only".to_string()) } else { Ok(()) }
+    }
+}
+
+/// Connects with the named profile, or the only one saved, and the app's network settings.
+async fn connect(name: Option<&str>) -> Result<Session, String> {
+    let config_dir = r2drive_core::config_dir()?;
+    let mut saved = profiles::load(&config_dir);
+    let profile = match name {
+        Some(name) => saved.into_iter().find(|p| p.name == name).ok_or_else(|| format!("No profile named '{}'", name))?,
+        None if saved.len() == 1 => saved.remove(0),
+        None if saved.is_empty() => return Err("No saved profiles; add one in R2Drive first".to_string()),
+        None => {
+            let names: Vec<&str> = saved.iter().map(|p| p.name.as_str()).collect();
+            return Err(format!("Several profiles are saved; pick one with --profile: {}", names.join(", ")));
+        }
+    };
+    let endpoint = profile.endpoint()?;
+    let credentials = profile.credentials()?;
+    let settings = NetworkSettings::load(&config_dir);
+    let config = client::client_config(
+        &endpoint,
+        profile.region.clone(),
+        &credentials,
+        profile.force_path_style,
+        network::http_client(&settings.proxy, &settings.timeouts),
+        network::timeout_config(&settings.timeouts),
+    )
+    .await;
+    Ok(Session { client: Client::from_conf(config.build()), read_only: profile.read_only, data_dir: r2drive_core::data_dir()? })
+}
+
+fn date(secs: Option<i64>) -> String {
+    secs.and_then(|s| chrono::DateTime::from_timestamp(s, 0))
+        .map(|d| d.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_else(|| " ".repeat(19))
+}
+
+fn print_object(object: &Object) {
+    let modified = object.last_modified().map(|d| d.secs());
+    println!("{}  {:>12}  {}", date(modified), object.size().unwrap_or(0), object.key().unwrap_or_default());
+}
+
+async fn ls(session: &Session, target: Option<String>, recursive: bool) -> Result<(), String> {
+    let Some(target) = target else {
+        let resp = session.client.list_buckets().send().await.map_err(|e| error_message(&e))?;
+        for bucket in resp.buckets() {
+            println!("{}  {}", date(bucket.creation_date().map(|d| d.secs())), bucket.name().unwrap_or_default());
+        }
+        return Ok(());
+    };
+    let (bucket, prefix) = remote(&target)?;
+    if recursive {
+        for object in list_all_objects(&session.client, &bucket, Some(&prefix)).await? {
+            print_object(&object);
+        }
+        return Ok(());
+    }
+
+    let mut continuation_token = None;
+    loop {
+        let resp = session.client.list_objects_v2()
+            .bucket(&bucket)
+            .prefix(&prefix)
+            .delimiter("/")
+            .set_continuation_token(continuation_token)
+            .send()
+            .await
+            .map_err(|e| error_message(&e))?;
+        for folder in resp.common_prefixes() {
+            println!("{}  {:>12}  {}", date(None), "PRE", folder.prefix().unwrap_or_default());
+        }
+        // The marker of the listed folder itself isn't in it
+        for object in resp.contents().iter().filter(|o| o.key() != Some(prefix.as_str())) {
+            print_object(object);
+        }
+        if resp.is_truncated().unwrap_or(false) {
+            continuation_token = resp.next_continuation_token;
+        } else {
+            break;
+        }
+    }
+    Ok(())
+}
+
+async fn upload(session: &Session, path: &Path, bucket: &str, key: &str) -> Result<(), String> {
+    sync::upload(&session.client, bucket, key, path).await?;
+    println!("upload: {} -> {}", path.display(), uri(bucket, key));
+    Ok(())
+}
+
+async fn download(session: &Session, bucket: &str, key: &str, path: &Path) -> Result<(), String> {
+    client::download(&session.client, bucket, key, path, &session.data_dir).await?;
+    println!("download: {} -> {}", uri(bucket, key), path.display());
+    Ok(())
+}
+
+async fn copy(session: &Session, from: (&str, &str), bucket: &str, key: &str) -> Result<(), String> {
+    session.client.copy_object()
+        .copy_source(format!("{}/{}", from.0, encode(from.1)))
+        .bucket(bucket)
+        .key(key)
+        .send()
+        .await
+        .map_err(|e| error_message(&e))?;
+    println!("copy: {} -> {}", uri(from.0, from.1), uri(bucket, key));
+    Ok(())
+}
+
+/// Last segment of a key, refused if it can't be a file name.
+fn key_name(key: &str) -> Result<&str, String> {
+    let name = key.rsplit('/').next().unwrap_or(key);
+    match sync::local_path(Path::new(""), name) {
+        Some(_) if !name.is_empty() => Ok(name),
+        _ => Err(format!("{} is a prefix; add --recursive", key)),
+    }
+}
+
+/// `key` itself, or `name` inside it when it names a folder.
+fn key_in(key: &str, name: &str) -> String {
+    if key.is_empty() || key.ends_with('/') { format!("{}{}", key, name) } else { key.to_string() }
+}
+
+/// Copies one file or object, or with `recursive` a whole folder or prefix, following the
+/// layout of `aws s3 cp`. Stops at the first failure.
+async fn cp(session: &Session, source: &str, destination: &str, recursive: bool) -> Result<(), String> {
+    match (Location::parse(source)?, Location::parse(destination)?) {
+        (Location::Local(path), Location::Remote { bucket, key }) => {
+            session.writable()?;
+            if !path.is_dir() {
+                let name = path.file_name().ok_or_else(|| format!("{} is not a file", path.display()))?.to_string_lossy();
+                return upload(session, &path, &bucket, &key_in(&key, &name)).await;
+            }
+            if !recursive {
+                return Err(format!("{} is a folder; add --recursive", path.display()));
+            }
+            let prefix = normalize_prefix(&key);
+            for entry in WalkDir::new(&path).into_iter().filter_map(|e| e.ok()).filter(|e| e.file_type().is_file()) {
+                let Ok(relative) = entry.path().strip_prefix(&path) else { continue };
+                let relative: Vec<String> = relative.components().map(|c| c.as_os_str().to_string_lossy().to_string()).collect();
+                upload(session, entry.path(), &bucket, &format!("{}{}", prefix, relative.join("/"))).await?;
+            }
+            Ok(())
+        }
+        (Location::Remote { bucket, key }, Location::Local(path)) => {
+            if !recursive {
+                let name = key_name(&key)?;
+                let into_folder = path.is_dir() || destination.ends_with('/') || destination.ends_with('\\');
+                let target = if into_folder { path.join(name) } else { path };
+                return download(session, &bucket, &key, &target).await;
+            }
+            let prefix = normalize_prefix(&key);
+            for object in list_all_objects(&session.client, &bucket, Some(&prefix)).await? {
+                let Some(key) = object.key() else { continue };
+                if is_folder_marker(key, object.size().unwrap_or(0)) {
+                    continue;
+                }
+                match sync::local_path(&path, &key[prefix.len()..]) {
+                    Some(target) => download(session, &bucket, key, &target).await?,
+                    None => eprintln!("skipped: {} is not a valid local path", uri(&bucket, key)),
+                }
+            }
+            Ok(())
+        }
+        (Location::Remote { bucket: from_bucket, key: from_key }, Location::Remote { bucket, key }) => {
+            session.writable()?;
+            if !recursive {
+                let name = key_name(&from_key)?;
+                return copy(session, (&from_bucket, &from_key), &bucket, &key_in(&key, name)).await;
+            }
+            let (from, to) = (normalize_prefix(&from_key), normalize_prefix(&key));
+            for object in list_all_objects(&session.client, &from_bucket, Some(&from)).await? {
+                let Some(key) = object.key() else { continue };
+                copy(session, (&from_bucket, key), &bucket, &format!("{}{}", to, &key[from.len()..])).await?;
+            }
+            Ok(())
+        }
+        (Location::Local(_), Location::Local(_)) => Err(format!("One side of cp must be an {} location", SCHEME)),
+    }
+}
+
+fn label(kind: SyncActionKind) -> &'static str {
+    match kind {
+        SyncActionKind::Upload => "upload",
+        SyncActionKind::Download => "download",
+        SyncActionKind::DeleteRemote => "delete",
+        SyncActionKind::DeleteLocal => "delete local",
+        SyncActionKind::KeepBoth => "keep both",
+        SyncActionKind::Skip => "skip",
+    }
+}
+
+async fn run_action(client: &Client, bucket: &str, key: &str, path: Option<&Path>, data_dir: &Path, kind: SyncActionKind) -> Result<(), String> {
+    let path = path.ok_or("Not a valid local path")?;
+    match kind {
+        SyncActionKind::Upload => sync::upload(client, bucket, key, path).await.map(|_| ()),
+        SyncActionKind::Download => client::download(client, bucket, key, path, data_dir).await.map(|_| ()),
+        SyncActionKind::DeleteRemote => client.delete_object()
+            .bucket(bucket)
+            .key(key)
+            .send()
+            .await
+            .map(|_| ())
+            .map_err(|e| error_message(&e)),
+        SyncActionKind::DeleteLocal => tokio::fs::remove_file(path).await.map_err(|e| e.to_string()),
+        // Only planned for conflicts, which need a record of an earlier sync
+        SyncActionKind::KeepBoth | SyncActionKind::Skip => Ok(()),
+    }
+}
+
+/// Prints how an action went; true when it failed.
+fn report(joined: Result<(SyncAction, Result<(), String>), tokio::task::JoinError>) -> bool {
+    match joined {
+        Ok((action, Ok(()))) => {
+            println!("{}: {}", label(action.action), action.relative);
+            false
+        }
+        Ok((action, Err(e))) => {
+            eprintln!("failed: {}: {}", action.relative, e);
+            true
+        }
+        Err(e) => {
+            eprintln!("failed: {}", e);
+            true
+        }
+    }
+}
+
+/// One-way sync with the app's planner. Nothing is recorded between runs, so files are compared
+/// by size and time alone and no conflicts are raised.
+async fn sync_folder(session: &Session, source: &str, destination: &str, options: SyncOptions, dry_run: bool) -> Result<(), String> {
+    let (direction, root, bucket, key) = match (Location::parse(source)?, Location::parse(destination)?) {
+        (Location::Local(root), Location::Remote { bucket, key }) => (SyncDirection::Upload, root, bucket, key),
+        (Location::Remote { bucket, key }, Location::Local(root)) => (SyncDirection::Download, root, bucket, key),
+        _ => return Err(format!("sync needs a local folder on one side and an {} prefix on the other", SCHEME)),
+    };
+    if direction == SyncDirection::Upload && !dry_run {
+        session.writable()?;
+    }
+    let prefix = normalize_prefix(&key);
+    let rules = sync::ignore_rules(&root, &options.ignore)?;
+    // A download may create the folder
+    let local = if direction == SyncDirection::Download && !root.exists() { BTreeMap::new() } else { sync::scan_local(&root, &rules)? };
+    let mut remote = sync::scan_remote(&session.client, &bucket, &prefix).await?;
+    remote.retain(|relative, _| !sync::is_ignored(&rules, relative, false));
+    let (actions, _) = sync::plan(direction, &local, &remote, &HashMap::new(), &options);
+
+    let mut failed = 0;
+    let mut tasks = JoinSet::new();
+    for action in actions.into_iter().filter(|a| a.action != SyncActionKind::Skip) {
+        if dry_run {
+            println!("(dry run) {}: {} ({})", label(action.action), action.relative, action.reason);
+            continue;
+        }
+        while tasks.len() >= TRANSFER_CONCURRENCY {
+            if let Some(joined) = tasks.join_next().await {
+                failed += usize::from(report(joined));
+            }
+        }
+        let (client, bucket, data_dir) = (session.client.clone(), bucket.clone(), session.data_dir.clone());
+        let (key, path) = (format!("{}{}", prefix, action.relative), sync::local_path(&root, &action.relative));
+        tasks.spawn(async move {
+            let outcome = run_action(&client, &bucket, &key, path.as_deref(), &data_dir, action.action).await;
+            (action, outcome)
+        });
+    }
+    while let Some(joined) = tasks.join_next().await {
+        failed += usize::from(report(joined));
+    }
+    if failed > 0 {
+        return Err(format!("{} files failed to sync", failed));
+    }
+    Ok(())
+}
+
+async fn rm(session: &Session, target: &str, recursive: bool) -> Result<(), String> {
+    session.writable()?;
+    let (bucket, key) = remote(target)?;
+    if !recursive {
+        key_name(&key)?;
+        session.client.delete_object().bucket(&bucket).key(&key).send().await.map_err(|e| error_message(&e))?;
+        println!("delete: {}", uri(&bucket, &key));
+        return Ok(());
+    }
+
+    let objects = list_all_objects(&session.client, &bucket, Some(&normalize_prefix(&key))).await?;
+    let mut failed = 0;
+    for batch in objects.chunks(DELETE_BATCH) {
+        let ids = batch.iter().filter_map(|o| ObjectIdentifier::builder().key(o.key()?).build().ok()).collect();
+        let delete = Delete::builder().set_objects(Some(ids)).quiet(true).build().map_err(|e| e.to_string())?;
+        let resp = session.client.delete_objects().bucket(&bucket).delete(delete).send().await.map_err(|e| error_message(&e))?;
+        for error in resp.errors() {
+            eprintln!("failed: {}: {}", error.key().unwrap_or_default(), error.message().unwrap_or_default());
+        }
+        failed += resp.errors().len();
+    }
+    println!("Deleted {} objects", objects.len() - failed);
+    if failed > 0 {
+        return Err(format!("{} objects could not be deleted", failed));
+    }
+    Ok(())
+}
+
+async fn presign(session: &Session, target: &str, expires: u64) -> Result<(), String> {
+    let (bucket, key) = remote(target)?;
+    key_name(&key)?;
+    if expires == 0 || expires > MAX_PRESIGN_SECS {
+        return Err(format!("--expires must be between 1 and {} seconds", MAX_PRESIGN_SECS));
+    }
+    let config = PresigningConfig::expires_in(Duration::from_secs(expires)).map_err(|e| e.to_string())?;
+    let request = session.client.get_object().bucket(&bucket).key(&key).presigned(config).await.map_err(|e| error_message(&e))?;
+    println!("{}", request.uri());
+    Ok(())
+}
+
+async fn run(cli: Cli) -> Result<(), String> {
+    let session = connect(cli.profile.as_deref()).await?;
+    match cli.command {
+        Command::Ls { target, recursive } => ls(&session, target, recursive).await,
+        Command::Cp { source, destination, recursive } => cp(&session, &source, &destination, recursive).await,
+        Command::Sync { source, destination, delete, dry_run, exclude } => {
+            let options = SyncOptions { delete, ignore: exclude, ..Default::default() };
+            sync_folder(&session, &source, &destination, options, dry_run).await
+        }
+        Command::Rm { target, recursive } => rm(&session, &target, recursive).await,
+        Command::Presign { target, expires } => presign(&session, &target, expires).await,
+    }
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    match run(Cli::parse()).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("r2drive-cli: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,212 @@
+use aws_config::meta::region::RegionProviderChain;
+use aws_config::timeout::TimeoutConfig;
+use aws_sdk_s3::config::{Region, SharedHttpClient};
+use aws_sdk_s3::error::{DisplayErrorContext, ProvideErrorMetadata, SdkError};
+use aws_sdk_s3::primitives::ByteStream;
+use aws_sdk_s3::types::Object;
+use aws_sdk_s3::Client;
+use std::path::Path;
+use std::time::{Duration, UNIX_EPOCH};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::watch;
+
+use crate::partial;
+use crate::sse_c::{SseCHeaders, SseCKey};
+
+/// Metadata marking an object encrypted client-side by the app. Only the app, with the
+/// passphrase unlocked, can read those.
+pub const ENCRYPTED_META: &str = "r2drive-enc";
+
+/// Keys a connection was opened with. `session_token` and `expires_at` are only set for
+/// temporary credentials.
+#[derive(Clone)]
+pub struct Credentials {
+    pub account_id: String,
+    pub access_key: String,
+    pub secret_key: String,
+    pub session_token: Option<String>,
+    /// Unix seconds.
+    pub expires_at: Option<i64>,
+    /// SSE-C key sent on every request that reads or writes object data.
+    pub sse_c: Option<SseCKey>,
+}
+
+impl Credentials {
+    pub fn new(account_id: String, access_key: String, secret_key: String) -> Self {
+        Self { account_id, access_key, secret_key, session_token: None, expires_at: None, sse_c: None }
+    }
+}
+
+/// S3 endpoint for an account. Buckets created under a jurisdiction are only reachable through
+/// that jurisdiction's endpoint.
+pub fn r2_endpoint(account_id: &str, jurisdiction: Option<&str>) -> Result<String, String> {
+    match jurisdiction.map(|j| j.trim().to_lowercase()).as_deref() {
+        None | Some("") | Some("default") => Ok(format!("https://{}.r2.cloudflarestorage.com", account_id)),
+        Some(j @ ("eu" | "fedramp")) => Ok(format!("https://{}.{}.r2.cloudflarestorage.com", account_id, j)),
+        Some(other) => Err(format!("Unknown jurisdiction '{}'", other)),
+    }
+}
+
+/// SDK config for any S3-compatible endpoint with static or temporary credentials. Callers add
+/// their own interceptors before building the client.
+pub async fn client_config(
+    endpoint: &str,
+    region: Option<String>,
+    credentials: &Credentials,
+    force_path_style: bool,
+    http_client: SharedHttpClient,
+    timeouts: TimeoutConfig,
+) -> aws_sdk_s3::config::Builder {
+    let region_provider = match region {
+        Some(r) => RegionProviderChain::first_try(Region::new(r)),
+        None => RegionProviderChain::default_provider().or_else(Region::new("auto")),
+    };
+    let creds = aws_credential_types::Credentials::new(
+        &credentials.access_key,
+        &credentials.secret_key,
+        credentials.session_token.clone(),
+        credentials.expires_at.map(|t| UNIX_EPOCH + Duration::from_secs(t.max(0) as u64)),
+        "Static",
+    );
+
+    let config = aws_config::defaults(aws_config::BehaviorVersion::latest())
+        .region(region_provider)
+        .endpoint_url(endpoint)
+        .credentials_provider(creds)
+        .http_client(http_client)
+        .timeout_config(timeouts)
+        .load()
+        .await;
+
+    let mut s3_config = aws_sdk_s3::config::Builder::from(&config).force_path_style(force_path_style);
+    if let Some(key) = credentials.sse_c.clone() {
+        s3_config = s3_config.interceptor(SseCHeaders(key));
+    }
+    s3_config
+}
+
+/// The service's message for a failed request, else its error code, else the whole error chain.
+pub fn error_message<E, R>(err: &SdkError<E, R>) -> String
+where
+    E: ProvideErrorMetadata + std::error::Error + 'static,
+    R: std::fmt::Debug,
+{
+    match (err.code(), err.message()) {
+        (_, Some(message)) if !message.is_empty() => message.to_string(),
+        (Some(code), _) => code.to_string(),
+        _ => DisplayErrorContext(err).to_string(),
+    }
+}
+
+/// Zero-byte keys ending in '/' are folder markers: they represent (possibly empty) folders,
+/// never files.
+pub fn is_folder_marker(key: &str, size: i64) -> bool {
+    key.ends_with('/') && size == 0
+}
+
+pub fn folder_marker_key(prefix: &str) -> String {
+    if prefix.ends_with('/') { prefix.to_string() } else { format!("{}/", prefix) }
+}
+
+/// Paginates through every object under `prefix` (the whole bucket when None).
+pub async fn list_all_objects(client: &Client, bucket: &str, prefix: Option<&str>) -> Result<Vec<Object>, String> {
+    let mut continuation_token = None;
+    let mut objects = Vec::new();
+
+    loop {
+        let resp = client.list_objects_v2()
+            .bucket(bucket)
+            .set_prefix(prefix.map(|p| p.to_string()))
+            .set_continuation_token(continuation_token)
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+
+        objects.extend(resp.contents().iter().cloned());
+
+        if resp.is_truncated().unwrap_or(false) {
+            continuation_token = resp.next_continuation_token;
+        } else {
+            break;
+        }
+    }
+
+    Ok(objects)
+}
+
+/// Transforms a download on its way to disk; the app decrypts encrypted objects through this.
+pub trait BodyFilter: Send {
+    /// Takes the next piece of the body and returns what to write for it.
+    fn update(&mut self, data: &[u8]) -> Result<Vec<u8>, String>;
+    /// Returns what's left to write once the body has ended, failing if it was cut short.
+    fn finish(self: Box<Self>) -> Result<Vec<u8>, String>;
+}
+
+/// How `save_body` writes a download.
+#[derive(Default)]
+pub struct SaveOptions {
+    pub filter: Option<Box<dyn BodyFilter>>,
+    /// Writing holds while this reads true, as when transfers are paused in the app.
+    pub paused: Option<watch::Receiver<bool>>,
+}
+
+/// Streams a response body into `target` via a `.r2part` sibling that is recorded in the partials
+/// journal under `data_dir`, fsynced and renamed on success, so an interrupted download never
+/// leaves a truncated file under the final name. Returns the bytes written.
+pub async fn save_body(data_dir: &Path, body: ByteStream, options: SaveOptions, target: &Path) -> Result<u64, String> {
+    let part = partial::begin(data_dir, target)?;
+    let result = match write_stream(body, options, &part).await {
+        Ok(written) => tokio::fs::rename(&part, target).await.map(|_| written).map_err(|e| e.to_string()),
+        Err(e) => Err(e),
+    };
+    if result.is_err() {
+        let _ = tokio::fs::remove_file(&part).await;
+    }
+    partial::finish(data_dir, &part);
+    result
+}
+
+async fn write_stream(mut body: ByteStream, options: SaveOptions, path: &Path) -> Result<u64, String> {
+    let SaveOptions { mut filter, mut paused } = options;
+    let mut file = tokio::fs::File::create(path).await.map_err(|e| e.to_string())?;
+    let mut written = 0u64;
+    while let Some(chunk) = body.try_next().await.map_err(|e| e.to_string())? {
+        if let Some(paused) = paused.as_mut() {
+            let _ = paused.wait_for(|p| !*p).await;
+        }
+        let chunk = match filter.as_mut() {
+            Some(filter) => filter.update(&chunk)?,
+            None => chunk.to_vec(),
+        };
+        file.write_all(&chunk).await.map_err(|e| e.to_string())?;
+        written += chunk.len() as u64;
+    }
+    if let Some(filter) = filter {
+        let tail = filter.finish()?;
+        file.write_all(&tail).await.map_err(|e| e.to_string())?;
+        written += tail.len() as u64;
+    }
+    file.flush().await.map_err(|e| e.to_string())?;
+    file.sync_all().await.map_err(|e| e.to_string())?;
+    Ok(written)
+}
+
+/// Downloads an object to `target` with `save_body` and gives it the object's modification
+/// time. Objects encrypted by the app are refused, as there is no key to decrypt them with here.
+pub async fn download(client: &Client, bucket: &str, key: &str, target: &Path, data_dir: &Path) -> Result<u64, String> {
+    let resp = client.get_object().bucket(bucket).key(key).send().await.map_err(|e| error_message(&e))?;
+    if resp.metadata().is_some_and(|m| m.contains_key(ENCRYPTED_META)) {
+        return Err(format!("{} is encrypted by R2Drive; download it in the app", key));
+    }
+    if let Some(dir) = target.parent() {
+        tokio::fs::create_dir_all(dir).await.map_err(|e| e.to_string())?;
+    }
+    let modified = resp.last_modified().map(|d| d.secs()).filter(|s| *s > 0);
+    let size = save_body(data_dir, resp.body, SaveOptions::default(), target).await?;
+
+    if let Some(secs) = modified {
+        let file = std::fs::File::options().write(true).open(target).map_err(|e| e.to_string())?;
+        let _ = file.set_modified(UNIX_EPOCH + Duration::from_secs(secs as u64));
+    }
+    Ok(size)
+}
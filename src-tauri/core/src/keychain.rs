@@ -0,0 +1,36 @@
+use keyring::Entry;
+
+// Secrets are stored in the OS keychain (Keychain on macOS, Credential Manager on Windows,
+// Secret Service on Linux) under this service name, keyed by profile name.
+const SERVICE: &str = "r2drive";
+
+fn entry(account: &str) -> Result<Entry, String> {
+    Entry::new(SERVICE, account).map_err(|e| format!("Keychain unavailable: {}", e))
+}
+
+pub fn store_secret(account: &str, secret: &str) -> Result<(), String> {
+    entry(account)?
+        .set_password(secret)
+        .map_err(|e| format!("Could not save to the keychain: {}", e))
+}
+
+/// `Ok(None)` when nothing is stored for `account`.
+pub fn load_secret(account: &str) -> Result<Option<String>, String> {
+    match entry(account)?.get_password() {
+        Ok(secret) => Ok(Some(secret)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(format!("Could not read from the keychain: {}", e)),
+    }
+}
+
+pub fn delete_secret(account: &str) -> Result<(), String> {
+    match entry(account)?.delete_credential() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(format!("Could not remove from the keychain: {}", e)),
+    }
+}
+
+/// Whether the keychain can be reached at all; used by the startup health check.
+pub fn probe() -> Result<(), String> {
+    load_secret("__health_check__").map(|_| ())
+}
@@ -0,0 +1,26 @@
+// The parts of R2Drive that don't need a window: saved profiles and their keychain secrets,
+// building S3 clients, and planning syncs. The app and r2drive-cli both build on these, so a
+// script sees the same profiles and makes the same sync decisions as the GUI.
+pub mod client;
+pub mod keychain;
+pub mod network;
+pub mod partial;
+pub mod profiles;
+pub mod sse_c;
+pub mod sync;
+
+/// Tauri bundle identifier; must match `identifier` in tauri.conf.json, since the app keeps its
+/// files in a folder named after it.
+pub const APP_IDENTIFIER: &str = "com.chagl.r2-drive";
+
+/// The app's config dir (profiles.json, settings.json), the same one Tauri's `app_config_dir`
+/// resolves to.
+pub fn config_dir() -> Result<std::path::PathBuf, String> {
+    dirs::config_dir().map(|d| d.join(APP_IDENTIFIER)).ok_or_else(|| "No config folder on this system".to_string())
+}
+
+/// The app's data dir (the partial downloads journal), the same one Tauri's `app_data_dir`
+/// resolves to.
+pub fn data_dir() -> Result<std::path::PathBuf, String> {
+    dirs::data_dir().map(|d| d.join(APP_IDENTIFIER)).ok_or_else(|| "No data folder on this system".to_string())
+}
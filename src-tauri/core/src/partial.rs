@@ -0,0 +1,69 @@
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+// Downloads are written next to their target as `<name>.r2part` and renamed once complete.
+// Every partial in flight is recorded in a small journal in the app's data dir, shared by the
+// app and r2drive-cli, so leftovers from a crash can be removed on the app's next launch.
+pub const PARTIAL_EXT: &str = "r2part";
+const JOURNAL_FILE: &str = "partials.json";
+
+static JOURNAL_LOCK: Mutex<()> = Mutex::new(());
+
+pub fn partial_path(target: &Path) -> PathBuf {
+    let mut name = target.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(PARTIAL_EXT);
+    target.with_file_name(name)
+}
+
+fn read_journal(path: &Path) -> Vec<String> {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|raw| serde_json::from_str(&raw).ok())
+        .unwrap_or_default()
+}
+
+fn write_journal(path: &Path, entries: &[String]) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let raw = serde_json::to_string(entries).map_err(|e| e.to_string())?;
+    std::fs::write(path, raw).map_err(|e| e.to_string())
+}
+
+/// Records a partial file as in flight in the journal under `data_dir` and returns its path.
+pub fn begin(data_dir: &Path, target: &Path) -> Result<PathBuf, String> {
+    let part = partial_path(target);
+    let _guard = JOURNAL_LOCK.lock().unwrap();
+    let journal = data_dir.join(JOURNAL_FILE);
+    let mut entries = read_journal(&journal);
+    let entry = part.to_string_lossy().to_string();
+    if !entries.contains(&entry) {
+        entries.push(entry);
+    }
+    write_journal(&journal, &entries)?;
+    Ok(part)
+}
+
+/// Drops a partial from the journal once it has been renamed into place or removed.
+pub fn finish(data_dir: &Path, part: &Path) {
+    let _guard = JOURNAL_LOCK.lock().unwrap();
+    let journal = data_dir.join(JOURNAL_FILE);
+    let entry = part.to_string_lossy().to_string();
+    let entries: Vec<String> = read_journal(&journal).into_iter().filter(|e| *e != entry).collect();
+    let _ = write_journal(&journal, &entries);
+}
+
+/// Removes partial files left behind by downloads that never finished.
+pub fn cleanup_stale(data_dir: &Path) {
+    let _guard = JOURNAL_LOCK.lock().unwrap();
+    let journal = data_dir.join(JOURNAL_FILE);
+    for entry in read_journal(&journal) {
+        let path = PathBuf::from(entry);
+        // Only ever delete files we named ourselves
+        if path.extension().is_some_and(|e| e == PARTIAL_EXT) {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+    let _ = write_journal(&journal, &[]);
+}
@@ -0,0 +1,127 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::client::{r2_endpoint, Credentials};
+use crate::keychain;
+use crate::sse_c::SseCKey;
+
+// Saved connections live in profiles.json under the config dir; their secret keys live in the
+// OS keychain, so the app and the command line share both.
+pub const PROFILES_FILE: &str = "profiles.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Provider {
+    R2,
+    /// Any other S3-compatible service; needs an explicit endpoint.
+    S3,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Profile {
+    pub name: String,
+    pub provider: Provider,
+    #[serde(default)]
+    pub account_id: String,
+    #[serde(default)]
+    pub jurisdiction: Option<String>,
+    #[serde(default)]
+    pub endpoint: Option<String>,
+    #[serde(default)]
+    pub region: Option<String>,
+    pub access_key: String,
+    /// Only ever sent by the UI; never written to disk or returned. Leave blank on update to keep
+    /// the stored secret.
+    #[serde(default, skip_serializing)]
+    pub secret_key: String,
+    #[serde(default)]
+    pub force_path_style: bool,
+    /// Blocks every command that would modify the bucket while connected through this profile.
+    #[serde(default)]
+    pub read_only: bool,
+    /// Bucket -> base URL it's publicly served from (an r2.dev or custom domain), for buckets
+    /// whose public access isn't visible through the Cloudflare API.
+    #[serde(default)]
+    pub public_urls: BTreeMap<String, String>,
+    /// Send an SSE-C key on every request that reads or writes object data.
+    #[serde(default)]
+    pub sse_c: bool,
+    /// Base64 SSE-C key. Write-only like `secret_key`: kept in the keychain, and left blank on
+    /// update to keep the stored one.
+    #[serde(default, skip_serializing)]
+    pub sse_c_key: String,
+}
+
+impl Profile {
+    pub fn endpoint(&self) -> Result<String, String> {
+        match self.provider {
+            Provider::R2 => r2_endpoint(&self.account_id, self.jurisdiction.as_deref()),
+            Provider::S3 => self.endpoint.clone()
+                .filter(|e| !e.trim().is_empty())
+                .ok_or_else(|| "S3-compatible profiles need an endpoint".to_string()),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("Profile name cannot be empty".to_string());
+        }
+        if self.provider == Provider::R2 && self.account_id.trim().is_empty() {
+            return Err("R2 profiles need an account id".to_string());
+        }
+        if self.access_key.trim().is_empty() || self.secret_key.is_empty() {
+            return Err("Access key and secret key are required".to_string());
+        }
+        if self.sse_c {
+            if self.sse_c_key.is_empty() {
+                return Err("An SSE-C key is required when SSE-C is on".to_string());
+            }
+            SseCKey::parse(&self.sse_c_key)?;
+        }
+        if let Some((bucket, _)) = self.public_urls.iter().find(|(_, url)| !url.starts_with("https://") && !url.starts_with("http://")) {
+            return Err(format!("Public URL for '{}' must start with http:// or https://", bucket));
+        }
+        self.endpoint().map(|_| ())
+    }
+
+    /// Credentials to connect with, secrets read from the keychain.
+    pub fn credentials(&self) -> Result<Credentials, String> {
+        let secret_key = secret_for(self)?;
+        let sse_c = if self.sse_c { Some(SseCKey::parse(&sse_c_key_for(self)?)?) } else { None };
+        Ok(Credentials { sse_c, ..Credentials::new(self.account_id.clone(), self.access_key.clone(), secret_key) })
+    }
+}
+
+pub fn profiles_path(config_dir: &Path) -> PathBuf {
+    config_dir.join(PROFILES_FILE)
+}
+
+/// Saved profiles, or none when the file is missing or unreadable.
+pub fn load(config_dir: &Path) -> Vec<Profile> {
+    std::fs::read_to_string(profiles_path(config_dir))
+        .ok()
+        .and_then(|raw| serde_json::from_str(&raw).ok())
+        .unwrap_or_default()
+}
+
+pub fn secret_for(profile: &Profile) -> Result<String, String> {
+    if !profile.secret_key.is_empty() {
+        return Ok(profile.secret_key.clone());
+    }
+    keychain::load_secret(&profile.name)?
+        .ok_or_else(|| format!("No secret key stored for profile '{}'", profile.name))
+}
+
+/// Keychain account holding a profile's SSE-C key.
+pub fn sse_c_account(name: &str) -> String {
+    format!("{}#sse-c", name)
+}
+
+pub fn sse_c_key_for(profile: &Profile) -> Result<String, String> {
+    if !profile.sse_c_key.is_empty() {
+        return Ok(profile.sse_c_key.clone());
+    }
+    keychain::load_secret(&sse_c_account(&profile.name))?
+        .ok_or_else(|| format!("No SSE-C key stored for profile '{}'", profile.name))
+}
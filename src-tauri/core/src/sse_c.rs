@@ -0,0 +1,94 @@
+use aws_sdk_s3::config::interceptors::BeforeTransmitInterceptorContextMut;
+use aws_sdk_s3::config::{ConfigBag, Intercept, RuntimeComponents};
+use aws_smithy_runtime_api::box_error::BoxError;
+use aws_smithy_runtime_api::client::orchestrator::Metadata;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use md5::{Digest, Md5};
+
+// Server-side encryption with a customer-provided key: the endpoint encrypts with a key the
+// client sends on every request that reads or writes object data, and never stores it. A profile
+// can hold a key (in the keychain), which is then sent on all of its connection's requests;
+// single commands can pass their own key instead.
+const ALGORITHM: &str = "AES256";
+
+/// Operations whose object data is encrypted, which then need the key.
+const DATA_OPERATIONS: &[&str] = &["PutObject", "GetObject", "HeadObject", "CreateMultipartUpload", "UploadPart"];
+/// Operations that read a source object as well as write one.
+const COPY_OPERATIONS: &[&str] = &["CopyObject", "UploadPartCopy"];
+
+#[derive(Debug, Clone)]
+pub struct SseCKey {
+    /// The 256-bit key, base64.
+    key: String,
+    /// Base64 MD5 of the raw key, which the endpoint uses to check it arrived intact.
+    md5: String,
+}
+
+impl SseCKey {
+    /// Parses a base64-encoded 256-bit key.
+    pub fn parse(key: &str) -> Result<Self, String> {
+        let raw = STANDARD.decode(key.trim()).map_err(|_| "SSE-C key must be base64".to_string())?;
+        if raw.len() != 32 {
+            return Err(format!("SSE-C key must be 32 bytes, got {}", raw.len()));
+        }
+        Ok(Self { key: STANDARD.encode(&raw), md5: STANDARD.encode(Md5::digest(&raw)) })
+    }
+
+    pub fn algorithm(&self) -> String {
+        ALGORITHM.to_string()
+    }
+
+    pub fn key(&self) -> String {
+        self.key.clone()
+    }
+
+    pub fn key_md5(&self) -> String {
+        self.md5.clone()
+    }
+}
+
+/// Parses a key passed to a single command; blank means none.
+pub fn parse_optional(key: Option<String>) -> Result<Option<SseCKey>, String> {
+    key.filter(|k| !k.trim().is_empty()).map(|k| SseCKey::parse(&k)).transpose()
+}
+
+/// SDK interceptor that adds a connection's SSE-C key to every request that needs it. Headers a
+/// command already set, with a key of its own, are left alone. Copies assume the source was
+/// encrypted with the same key.
+#[derive(Debug)]
+pub struct SseCHeaders(pub SseCKey);
+
+impl Intercept for SseCHeaders {
+    fn name(&self) -> &'static str {
+        "SseCHeaders"
+    }
+
+    fn modify_before_signing(
+        &self,
+        context: &mut BeforeTransmitInterceptorContextMut<'_>,
+        _runtime_components: &RuntimeComponents,
+        cfg: &mut ConfigBag,
+    ) -> Result<(), BoxError> {
+        let operation = cfg.load::<Metadata>().map(|m| m.name().to_string()).unwrap_or_default();
+        let copy = COPY_OPERATIONS.contains(&operation.as_str());
+        if !copy && !DATA_OPERATIONS.contains(&operation.as_str()) {
+            return Ok(());
+        }
+
+        let headers = context.request_mut().headers_mut();
+        let mut prefixes = vec!["x-amz-server-side-encryption-customer"];
+        if copy {
+            prefixes.push("x-amz-copy-source-server-side-encryption-customer");
+        }
+        for prefix in prefixes {
+            if headers.contains_key(format!("{}-key", prefix)) {
+                continue;
+            }
+            headers.insert(format!("{}-algorithm", prefix), ALGORITHM);
+            headers.insert(format!("{}-key", prefix), self.0.key.clone());
+            headers.insert(format!("{}-key-md5", prefix), self.0.md5.clone());
+        }
+        Ok(())
+    }
+}
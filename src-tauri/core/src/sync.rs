@@ -0,0 +1,380 @@
+use aws_sdk_s3::primitives::ByteStream;
+use aws_sdk_s3::Client;
+use serde::{Deserialize, Serialize};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Component, Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use walkdir::WalkDir;
+
+use crate::client::{error_message, is_folder_marker, list_all_objects};
+
+// A sync compares a local folder with a prefix by size and modification time, producing a plan
+// of per-file actions that can be reviewed and edited before being applied. Given what both sides
+// looked like after the last sync (`SyncedState`), a file changed on both sides since then is
+// caught as a conflict rather than overwritten.
+// Filesystems and S3 round modification times differently
+const MTIME_TOLERANCE_SECS: i64 = 2;
+pub const IGNORE_FILE: &str = ".r2ignore";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SyncDirection {
+    /// The local folder is the source; the prefix is made to match it.
+    Upload,
+    /// The prefix is the source; the local folder is made to match it.
+    Download,
+    /// Changes on either side since the last sync, deletions included, are carried to the other.
+    Both,
+}
+
+/// Which side wins when a file changed on both sides since the last sync.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[allow(clippy::enum_variant_names)]
+pub enum ConflictPolicy {
+    /// The side with the later modification time.
+    #[default]
+    KeepNewer,
+    KeepLocal,
+    KeepRemote,
+    /// The local file is renamed to a conflict copy and both versions end up on both sides.
+    KeepBoth,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SyncOptions {
+    /// Delete files on the destination that the source doesn't have. Two-way syncs always carry
+    /// deletions over, but only of files they synced before.
+    pub delete: bool,
+    /// Gitignore-style patterns, added to those in the local root's `.r2ignore`. Matching paths
+    /// are left alone on both sides.
+    pub ignore: Vec<String>,
+    pub conflict: ConflictPolicy,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncActionKind {
+    Upload,
+    Download,
+    DeleteRemote,
+    DeleteLocal,
+    /// Rename the local file to `conflict_copy`, download the remote one in its place and upload
+    /// the copy.
+    KeepBoth,
+    Skip,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncAction {
+    pub action: SyncActionKind,
+    /// Path below the local root and the prefix, with `/` separators.
+    pub relative: String,
+    /// Bytes to transfer; 0 for deletes and skips.
+    pub size: u64,
+    pub reason: String,
+    /// Both sides as they were when planned, recorded as the synced state once the action is done.
+    #[serde(default)]
+    pub local: Option<FileState>,
+    #[serde(default)]
+    pub remote: Option<FileState>,
+    /// Relative path the local version is kept under, for `keep_both`.
+    #[serde(default)]
+    pub conflict_copy: Option<String>,
+}
+
+/// A file changed on both sides since the last sync, and how it was resolved.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncConflict {
+    pub relative: String,
+    pub local: FileState,
+    pub remote: FileState,
+    pub resolution: ConflictPolicy,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncPlan {
+    pub local_root: String,
+    pub bucket: String,
+    pub prefix: String,
+    pub direction: SyncDirection,
+    pub actions: Vec<SyncAction>,
+    #[serde(default)]
+    pub conflicts: Vec<SyncConflict>,
+}
+
+/// Both sides of a file as of its last sync; a side is unset if the file wasn't there.
+#[derive(Debug, Clone, Default)]
+pub struct SyncedState {
+    pub local: Option<FileState>,
+    pub remote: Option<FileState>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileState {
+    pub size: u64,
+    /// Unix seconds.
+    pub modified: i64,
+    /// Remote side only.
+    #[serde(default)]
+    pub etag: Option<String>,
+}
+
+impl FileState {
+    /// Whether this differs from what was recorded at the last sync. ETags decide for remote
+    /// files; otherwise size and time do.
+    fn changed_since(&self, synced: Option<&FileState>) -> bool {
+        let Some(synced) = synced else { return true };
+        match (&self.etag, &synced.etag) {
+            (Some(a), Some(b)) => a != b,
+            _ => self.size != synced.size || (self.modified - synced.modified).abs() > MTIME_TOLERANCE_SECS,
+        }
+    }
+}
+
+pub fn normalize_prefix(prefix: &str) -> String {
+    if prefix.is_empty() || prefix.ends_with('/') { prefix.to_string() } else { format!("{}/", prefix) }
+}
+
+/// `relative` as a path under `root`, or None if it would escape it.
+pub fn local_path(root: &Path, relative: &str) -> Option<PathBuf> {
+    let relative = Path::new(relative);
+    if relative.components().all(|c| matches!(c, Component::Normal(_))) {
+        Some(root.join(relative))
+    } else {
+        None
+    }
+}
+
+fn modified_secs(metadata: &std::fs::Metadata) -> i64 {
+    metadata.modified().ok().and_then(|t| t.duration_since(UNIX_EPOCH).ok()).map(|d| d.as_secs() as i64).unwrap_or(0)
+}
+
+/// The root's `.r2ignore` followed by `patterns`, so the latter can re-include with `!`.
+pub fn ignore_rules(root: &Path, patterns: &[String]) -> Result<Gitignore, String> {
+    let mut builder = GitignoreBuilder::new(root);
+    let file = root.join(IGNORE_FILE);
+    if file.is_file() {
+        if let Some(e) = builder.add(&file) {
+            return Err(format!("Invalid {}: {}", IGNORE_FILE, e));
+        }
+    }
+    for pattern in patterns {
+        builder.add_line(None, pattern).map_err(|e| format!("Invalid ignore pattern '{}': {}", pattern, e))?;
+    }
+    builder.build().map_err(|e| e.to_string())
+}
+
+/// Whether a relative path, or a folder it is in, is ignored.
+pub fn is_ignored(rules: &Gitignore, relative: &str, is_dir: bool) -> bool {
+    rules.matched_path_or_any_parents(relative, is_dir).is_ignore()
+}
+
+/// Every file under `root` by relative path, without ignored ones. Ignored folders are not
+/// walked into.
+pub fn scan_local(root: &Path, rules: &Gitignore) -> Result<BTreeMap<String, FileState>, String> {
+    if !root.is_dir() {
+        return Err(format!("{} is not a folder", root.display()));
+    }
+    let mut files = BTreeMap::new();
+    let walker = WalkDir::new(root)
+        .into_iter()
+        .filter_entry(|e| e.depth() == 0 || !rules.matched(e.path(), e.file_type().is_dir()).is_ignore());
+    for entry in walker.filter_map(|e| e.ok()).filter(|e| e.file_type().is_file()) {
+        let Ok(relative) = entry.path().strip_prefix(root) else { continue };
+        let relative: Vec<String> = relative.components().map(|c| c.as_os_str().to_string_lossy().to_string()).collect();
+        let Ok(metadata) = entry.metadata() else { continue };
+        files.insert(relative.join("/"), FileState { size: metadata.len(), modified: modified_secs(&metadata), etag: None });
+    }
+    Ok(files)
+}
+
+/// Every object under `prefix` by key relative to it. Folder markers are left out.
+pub async fn scan_remote(client: &Client, bucket: &str, prefix: &str) -> Result<BTreeMap<String, FileState>, String> {
+    let objects = list_all_objects(client, bucket, Some(prefix)).await?;
+    Ok(objects
+        .into_iter()
+        .filter(|o| !is_folder_marker(o.key().unwrap_or_default(), o.size().unwrap_or(0)))
+        .filter_map(|o| {
+            let relative = o.key()?.strip_prefix(prefix)?.to_string();
+            let state = FileState {
+                size: o.size().unwrap_or(0).max(0) as u64,
+                modified: o.last_modified().map(|d| d.secs()).unwrap_or(0),
+                etag: o.e_tag().map(str::to_string),
+            };
+            Some((relative, state))
+        })
+        .collect())
+}
+
+/// `relative` with " (conflict <date time>)" before its extension.
+fn conflict_copy_name(relative: &str) -> String {
+    let (dir, name) = match relative.rsplit_once('/') {
+        Some((dir, name)) => (format!("{}/", dir), name),
+        None => (String::new(), relative),
+    };
+    let (stem, ext) = match name.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() => (stem, format!(".{}", ext)),
+        _ => (name, String::new()),
+    };
+    format!("{}{} (conflict {}){}", dir, stem, chrono::Local::now().format("%Y-%m-%d %H%M%S"), ext)
+}
+
+fn action(action: SyncActionKind, relative: &str, size: u64, reason: &str, local: Option<&FileState>, remote: Option<&FileState>) -> SyncAction {
+    SyncAction {
+        action,
+        relative: relative.to_string(),
+        size,
+        reason: reason.to_string(),
+        local: local.cloned(),
+        remote: remote.cloned(),
+        conflict_copy: None,
+    }
+}
+
+/// The action settling a file changed on both sides, per `policy`.
+fn resolve(relative: &str, local: &FileState, remote: &FileState, policy: ConflictPolicy) -> SyncAction {
+    let upload = |reason| action(SyncActionKind::Upload, relative, local.size, reason, Some(local), Some(remote));
+    let download = |reason| action(SyncActionKind::Download, relative, remote.size, reason, Some(local), Some(remote));
+    match policy {
+        ConflictPolicy::KeepNewer if local.modified >= remote.modified => upload("conflict: local copy is newer"),
+        ConflictPolicy::KeepNewer => download("conflict: bucket copy is newer"),
+        ConflictPolicy::KeepLocal => upload("conflict: keeping local copy"),
+        ConflictPolicy::KeepRemote => download("conflict: keeping bucket copy"),
+        ConflictPolicy::KeepBoth => SyncAction {
+            conflict_copy: Some(conflict_copy_name(relative)),
+            ..action(SyncActionKind::KeepBoth, relative, local.size + remote.size, "conflict: keeping both", Some(local), Some(remote))
+        },
+    }
+}
+
+/// Two-way: each side is compared with what it was at the last sync. A change on one side is
+/// copied or deleted on the other; a file missing on one side that the other hasn't touched
+/// since was deleted there. Without a previous sync, differing files are conflicts.
+fn plan_two_way(
+    local: &BTreeMap<String, FileState>,
+    remote: &BTreeMap<String, FileState>,
+    synced: &HashMap<String, SyncedState>,
+    options: &SyncOptions,
+) -> (Vec<SyncAction>, Vec<SyncConflict>) {
+    let mut relatives: Vec<&String> = local.keys().chain(remote.keys()).collect();
+    relatives.sort();
+    relatives.dedup();
+
+    let mut actions = Vec::new();
+    let mut conflicts = Vec::new();
+    for relative in relatives {
+        let (l, r, last) = (local.get(relative), remote.get(relative), synced.get(relative));
+        let skip = |reason| action(SyncActionKind::Skip, relative, 0, reason, l, r);
+        let next = match (l, r) {
+            (Some(l), Some(r)) => {
+                let local_changed = l.changed_since(last.and_then(|s| s.local.as_ref()));
+                let remote_changed = r.changed_since(last.and_then(|s| s.remote.as_ref()));
+                let same = l.size == r.size && (l.modified - r.modified).abs() <= MTIME_TOLERANCE_SECS;
+                match (local_changed, remote_changed) {
+                    (false, false) => skip("unchanged"),
+                    _ if same => skip("unchanged"),
+                    (true, false) => action(SyncActionKind::Upload, relative, l.size, "changed locally", Some(l), Some(r)),
+                    (false, true) => action(SyncActionKind::Download, relative, r.size, "changed in the bucket", Some(l), Some(r)),
+                    (true, true) => {
+                        conflicts.push(SyncConflict { relative: relative.clone(), local: l.clone(), remote: r.clone(), resolution: options.conflict });
+                        resolve(relative, l, r, options.conflict)
+                    }
+                }
+            }
+            (Some(l), None) => match last.and_then(|s| s.remote.as_ref()) {
+                Some(_) if !l.changed_since(last.and_then(|s| s.local.as_ref())) => {
+                    action(SyncActionKind::DeleteLocal, relative, 0, "deleted from the bucket", Some(l), None)
+                }
+                // An edit beats a deletion on the other side
+                Some(_) => action(SyncActionKind::Upload, relative, l.size, "changed locally, deleted from the bucket", Some(l), None),
+                None => action(SyncActionKind::Upload, relative, l.size, "new", Some(l), None),
+            },
+            (None, Some(r)) => match last.and_then(|s| s.local.as_ref()) {
+                Some(_) if !r.changed_since(last.and_then(|s| s.remote.as_ref())) => {
+                    action(SyncActionKind::DeleteRemote, relative, 0, "deleted locally", None, Some(r))
+                }
+                _ if local_path(Path::new(""), relative).is_none() => skip("key is not a valid local path"),
+                Some(_) => action(SyncActionKind::Download, relative, r.size, "changed in the bucket, deleted locally", None, Some(r)),
+                None => action(SyncActionKind::Download, relative, r.size, "new", None, Some(r)),
+            },
+            (None, None) => continue,
+        };
+        actions.push(next);
+    }
+    (actions, conflicts)
+}
+
+/// Compares source and destination file by file. A file both sides changed since `synced` is a
+/// conflict, settled by `options.conflict` whichever the direction.
+pub fn plan(
+    direction: SyncDirection,
+    local: &BTreeMap<String, FileState>,
+    remote: &BTreeMap<String, FileState>,
+    synced: &HashMap<String, SyncedState>,
+    options: &SyncOptions,
+) -> (Vec<SyncAction>, Vec<SyncConflict>) {
+    let (source, destination, copy, delete, source_name) = match direction {
+        SyncDirection::Upload => (local, remote, SyncActionKind::Upload, SyncActionKind::DeleteRemote, "local folder"),
+        SyncDirection::Download => (remote, local, SyncActionKind::Download, SyncActionKind::DeleteLocal, "bucket"),
+        SyncDirection::Both => return plan_two_way(local, remote, synced, options),
+    };
+    let sides = |relative: &str| (local.get(relative), remote.get(relative));
+
+    let mut actions = Vec::new();
+    let mut conflicts = Vec::new();
+    for (relative, src) in source {
+        let (l, r) = sides(relative);
+        if let (Some(l), Some(r), Some(last)) = (l, r, synced.get(relative)) {
+            let differ = l.size != r.size || (l.modified - r.modified).abs() > MTIME_TOLERANCE_SECS;
+            if differ && l.changed_since(last.local.as_ref()) && r.changed_since(last.remote.as_ref()) {
+                let resolved = resolve(relative, l, r, options.conflict);
+                conflicts.push(SyncConflict { relative: relative.clone(), local: l.clone(), remote: r.clone(), resolution: options.conflict });
+                actions.push(resolved);
+                continue;
+            }
+        }
+        let reason = match destination.get(relative) {
+            None => "new",
+            Some(dst) if dst.size != src.size => "size differs",
+            Some(dst) if src.modified > dst.modified + MTIME_TOLERANCE_SECS => "source is newer",
+            Some(dst) if dst.modified > src.modified + MTIME_TOLERANCE_SECS && direction == SyncDirection::Download => {
+                // A local edit newer than the bucket copy would be lost
+                actions.push(action(SyncActionKind::Skip, relative, 0, "local copy is newer", l, r));
+                continue;
+            }
+            Some(_) => {
+                actions.push(action(SyncActionKind::Skip, relative, 0, "unchanged", l, r));
+                continue;
+            }
+        };
+        if direction == SyncDirection::Download && local_path(Path::new(""), relative).is_none() {
+            actions.push(action(SyncActionKind::Skip, relative, 0, "key is not a valid local path", l, r));
+            continue;
+        }
+        actions.push(action(copy, relative, src.size, reason, l, r));
+    }
+    for relative in destination.keys().filter(|r| !source.contains_key(*r)) {
+        let (l, r) = sides(relative);
+        let kind = if options.delete { delete } else { SyncActionKind::Skip };
+        actions.push(action(kind, relative, 0, &format!("not in the {}", source_name), l, r));
+    }
+    actions.sort_by(|a, b| a.relative.cmp(&b.relative));
+    (actions, conflicts)
+}
+
+/// Uploads a file, returning the new object's ETag.
+pub async fn upload(client: &Client, bucket: &str, key: &str, path: &Path) -> Result<Option<String>, String> {
+    let body = ByteStream::from_path(path).await.map_err(|e| e.to_string())?;
+    let resp = client.put_object()
+        .bucket(bucket)
+        .key(key)
+        .content_type(mime_guess::from_path(path).first_or_octet_stream().to_string())
+        .body(body)
+        .send()
+        .await
+        .map_err(|e| error_message(&e))?;
+    Ok(resp.e_tag().map(str::to_string))
+}
@@ -0,0 +1,74 @@
+use serde::Serialize;
+use tauri::{AppHandle, State};
+
+use crate::jobs;
+use crate::watch::WatchState;
+
+// Job kinds that move object data between this machine and the bucket
+const TRANSFER_KINDS: &[&str] = &["upload", "download"];
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ActivitySource {
+    /// An operation run from this app (deletes, renames, bucket changes...).
+    Operation,
+    Transfer,
+    /// A change spotted by a prefix watch, possibly made by someone else.
+    Watch,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ActivityEntry {
+    pub timestamp: i64,
+    pub source: ActivitySource,
+    pub kind: String,
+    pub key: Option<String>,
+    pub summary: String,
+    pub succeeded: bool,
+}
+
+/// What happened in `bucket` since `since` (unix seconds), newest first. Built from the job
+/// archive and the diffs of any active prefix watches; nothing is read from the bucket itself.
+#[tauri::command]
+pub fn get_activity(
+    bucket: String,
+    since: i64,
+    app: AppHandle,
+    watch_state: State<'_, WatchState>,
+) -> Vec<ActivityEntry> {
+    let mut entries: Vec<ActivityEntry> = jobs::load_all(&app)
+        .into_iter()
+        .filter(|job| job.finished_at >= since && job.bucket.as_deref() == Some(bucket.as_str()))
+        .map(|job| ActivityEntry {
+            timestamp: job.finished_at,
+            source: if TRANSFER_KINDS.contains(&job.kind.as_str()) {
+                ActivitySource::Transfer
+            } else {
+                ActivitySource::Operation
+            },
+            key: job.params.get("key").and_then(|k| k.as_str()).map(|k| k.to_string()),
+            summary: job.error.unwrap_or(job.summary),
+            kind: job.kind,
+            succeeded: job.succeeded,
+        })
+        .collect();
+
+    for (seen, changes) in watch_state.recent_changes(&bucket, since) {
+        let keyed = changes.added.iter().map(|o| ("added", &o.key))
+            .chain(changes.changed.iter().map(|o| ("changed", &o.key)))
+            .chain(changes.removed.iter().map(|k| ("removed", k)));
+        for (kind, key) in keyed {
+            entries.push(ActivityEntry {
+                timestamp: seen,
+                source: ActivitySource::Watch,
+                kind: kind.to_string(),
+                key: Some(key.clone()),
+                summary: format!("{} {}", key, kind),
+                succeeded: true,
+            });
+        }
+    }
+
+    entries.sort_by_key(|e| std::cmp::Reverse(e.timestamp));
+    entries
+}
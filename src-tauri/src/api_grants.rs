@@ -0,0 +1,238 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, State};
+
+use crate::error::{AppError, ErrorKind};
+use crate::jobs;
+use crate::s3::{self, AppState};
+use crate::sync::normalize_prefix;
+
+// Grants for the local automation API. Only a SHA-256 of each token is stored; the token itself
+// is returned once, when the grant is issued.
+const TOKEN_PREFIX: &str = "r2d_";
+
+static GRANTS_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GrantAccess {
+    ReadOnly,
+    ReadWrite,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiGrant {
+    pub id: String,
+    pub name: String,
+    pub bucket: String,
+    /// Limits the grant to keys under this prefix; `None` covers the whole bucket.
+    pub prefix: Option<String>,
+    pub access: GrantAccess,
+    /// Connection every call made with the grant goes through, fixed when it's issued; `None` is
+    /// the default connection.
+    #[serde(default)]
+    pub connection_id: Option<String>,
+    pub created_at: i64,
+    pub expires_at: i64,
+    #[serde(skip_serializing_if = "String::is_empty", default)]
+    token_hash: String,
+}
+
+impl ApiGrant {
+    /// Whether a key, or a prefix, in `bucket` lies within the grant. The prefix is matched as a
+    /// folder, so `logs` doesn't reach `logs-private/`, and keys with `..` segments never match.
+    pub fn covers(&self, bucket: &str, key: &str) -> bool {
+        bucket == self.bucket
+            && !key.split('/').any(|segment| segment == "..")
+            && self.prefix.as_deref().is_none_or(|p| key.starts_with(&normalize_prefix(p)))
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IssuedGrant {
+    pub grant: ApiGrant,
+    pub token: String,
+}
+
+fn grants_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = app.path().app_config_dir().map_err(|e| e.to_string())?;
+    Ok(dir.join("api_grants.json"))
+}
+
+fn hash_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+/// Reads the stored grants, dropping any that have expired.
+fn load_active(app: &AppHandle) -> Vec<ApiGrant> {
+    let now = jobs::now_secs();
+    grants_path(app)
+        .ok()
+        .and_then(|p| std::fs::read_to_string(p).ok())
+        .and_then(|raw| serde_json::from_str::<Vec<ApiGrant>>(&raw).ok())
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|g| g.expires_at > now)
+        .collect()
+}
+
+/// The unexpired grant a token was issued for, without its hash.
+pub fn grant_for_token(app: &AppHandle, token: &str) -> Option<ApiGrant> {
+    let hash = hash_token(token);
+    let _guard = GRANTS_LOCK.lock().unwrap();
+    load_active(app).iter().find(|g| g.token_hash == hash).map(redacted)
+}
+
+fn save(app: &AppHandle, grants: &[ApiGrant]) -> Result<(), String> {
+    let path = grants_path(app)?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let raw = serde_json::to_string_pretty(grants).map_err(|e| e.to_string())?;
+    std::fs::write(path, raw).map_err(|e| e.to_string())
+}
+
+/// Hides the token hash from the UI.
+fn redacted(grant: &ApiGrant) -> ApiGrant {
+    ApiGrant { token_hash: String::new(), ..grant.clone() }
+}
+
+/// Issues a token limited to `bucket` (and optionally `prefix`) on one connection that expires
+/// after `ttl_minutes`. The returned token is not stored and cannot be shown again.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub fn create_api_grant(
+    name: String,
+    bucket: String,
+    prefix: Option<String>,
+    access: GrantAccess,
+    ttl_minutes: u32,
+    app: AppHandle,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<IssuedGrant, AppError> {
+    if bucket.trim().is_empty() {
+        return Err(AppError::new(ErrorKind::InvalidInput, "A grant must be limited to a bucket"));
+    }
+    if ttl_minutes == 0 {
+        return Err(AppError::new(ErrorKind::InvalidInput, "Grant lifetime must be at least one minute"));
+    }
+    let connection_id = connection_id.filter(|c| !c.is_empty());
+    s3::connection(&state, connection_id.as_deref())?;
+
+    let mut secret = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut secret);
+    let token = format!("{}{}", TOKEN_PREFIX, URL_SAFE_NO_PAD.encode(secret));
+
+    let now = jobs::now_secs();
+    let grant = ApiGrant {
+        id: format!("{}-{:08x}", now, rand::random::<u32>()),
+        name,
+        bucket,
+        prefix: prefix.filter(|p| !p.is_empty()).map(|p| normalize_prefix(&p)),
+        access,
+        connection_id,
+        created_at: now,
+        expires_at: now + ttl_minutes as i64 * 60,
+        token_hash: hash_token(&token),
+    };
+
+    let _guard = GRANTS_LOCK.lock().unwrap();
+    let mut grants = load_active(&app);
+    grants.push(grant.clone());
+    save(&app, &grants)?;
+
+    Ok(IssuedGrant { grant: redacted(&grant), token })
+}
+
+/// Grants that have not expired yet, soonest to expire first.
+#[tauri::command]
+pub fn list_api_grants(app: AppHandle) -> Vec<ApiGrant> {
+    let _guard = GRANTS_LOCK.lock().unwrap();
+    let mut grants: Vec<ApiGrant> = load_active(&app).iter().map(redacted).collect();
+    grants.sort_by_key(|g| g.expires_at);
+    grants
+}
+
+#[tauri::command]
+pub fn revoke_api_grant(id: String, app: AppHandle) -> Result<(), AppError> {
+    let _guard = GRANTS_LOCK.lock().unwrap();
+    let grants = load_active(&app);
+    if !grants.iter().any(|g| g.id == id) {
+        return Err(AppError::new(ErrorKind::NotFound, "Grant not found or already expired"));
+    }
+    let remaining: Vec<ApiGrant> = grants.into_iter().filter(|g| g.id != id).collect();
+    Ok(save(&app, &remaining)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grant(prefix: Option<&str>) -> ApiGrant {
+        ApiGrant {
+            id: "grant".to_string(),
+            name: "test".to_string(),
+            bucket: "files".to_string(),
+            prefix: prefix.map(str::to_string),
+            access: GrantAccess::ReadOnly,
+            connection_id: None,
+            created_at: 0,
+            expires_at: i64::MAX,
+            token_hash: String::new(),
+        }
+    }
+
+    #[test]
+    fn prefix_is_matched_as_a_folder() {
+        for prefix in ["docs", "docs/"] {
+            let grant = grant(Some(prefix));
+            assert!(grant.covers("files", "docs/"));
+            assert!(grant.covers("files", "docs/report.pdf"));
+            assert!(grant.covers("files", "docs/2024/report.pdf"));
+            assert!(!grant.covers("files", "docs"));
+            assert!(!grant.covers("files", "docs-private/"));
+            assert!(!grant.covers("files", "docs-private/report.pdf"));
+            assert!(!grant.covers("files", "other/report.pdf"));
+        }
+    }
+
+    #[test]
+    fn parent_segments_never_match() {
+        let docs = grant(Some("docs"));
+        assert!(!docs.covers("files", "docs/../secrets/key.pem"));
+        assert!(!docs.covers("files", "docs/a/../../secrets/key.pem"));
+        assert!(!docs.covers("files", "docs/.."));
+        assert!(docs.covers("files", "docs/..hidden"));
+        assert!(docs.covers("files", "docs/a..b"));
+
+        let whole = grant(None);
+        assert!(!whole.covers("files", "../files/key.pem"));
+        assert!(!whole.covers("files", ".."));
+    }
+
+    #[test]
+    fn empty_or_missing_prefix_covers_the_bucket() {
+        for prefix in [None, Some("")] {
+            let grant = grant(prefix);
+            assert!(grant.covers("files", ""));
+            assert!(grant.covers("files", "report.pdf"));
+            assert!(grant.covers("files", "docs-private/report.pdf"));
+        }
+    }
+
+    #[test]
+    fn other_buckets_are_not_covered() {
+        for prefix in [None, Some(""), Some("docs")] {
+            let grant = grant(prefix);
+            assert!(!grant.covers("other", "docs/report.pdf"));
+            assert!(!grant.covers("files-backup", "docs/report.pdf"));
+            assert!(!grant.covers("", "docs/report.pdf"));
+        }
+    }
+}
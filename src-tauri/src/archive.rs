@@ -0,0 +1,726 @@
+use aws_sdk_s3::primitives::{ByteStream, DateTimeFormat};
+use aws_sdk_s3::Client;
+use bytes::Bytes;
+use flate2::write::DeflateDecoder;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use tauri::{AppHandle, Emitter, State};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
+use tokio::task::JoinSet;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::error::{AppError, ErrorKind};
+use crate::jobs;
+use crate::partial;
+use crate::s3::{self, acquire_client, acquire_write_client, is_folder_marker, list_all_objects, AppState};
+use crate::thumbnails;
+
+// Remote ZIPs are read the way unzip reads local ones: the end-of-central-directory record from
+// the tail, then the central directory, then only the bytes of the member being extracted.
+const EOCD_SIGNATURE: u32 = 0x0605_4b50;
+const ZIP64_LOCATOR_SIGNATURE: u32 = 0x0706_4b50;
+const ZIP64_EOCD_SIGNATURE: u32 = 0x0606_4b50;
+const CENTRAL_SIGNATURE: u32 = 0x0201_4b50;
+const LOCAL_SIGNATURE: u32 = 0x0403_4b50;
+const EOCD_SIZE: usize = 22;
+// The record plus the longest possible archive comment
+const TAIL_BYTES: u64 = EOCD_SIZE as u64 + u16::MAX as u64;
+const MAX_CENTRAL_DIRECTORY: u64 = 64 * 1024 * 1024;
+const PROGRESS_INTERVAL_BYTES: u64 = 4 * 1024 * 1024;
+// Members up to this size are uploaded from memory, bigger ones are spooled to a temp file
+const SPOOL_THRESHOLD: u64 = 16 * 1024 * 1024;
+const DEFAULT_EXTRACT_CONCURRENCY: usize = 8;
+const MAX_EXTRACT_CONCURRENCY: usize = 32;
+static EXTRACT_RUN: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ArchiveEntry {
+    pub name: String,
+    pub size: u64,
+    pub compressed_size: u64,
+    /// "stored", "deflate" or the raw method number for anything else.
+    pub method: String,
+    pub crc32: u32,
+    pub is_dir: bool,
+    pub encrypted: bool,
+    #[serde(skip)]
+    method_id: u16,
+    #[serde(skip)]
+    local_offset: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ArchiveListing {
+    pub entries: Vec<ArchiveEntry>,
+    /// Size of the whole archive.
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ZipCompression {
+    /// No compression; best for media and other already-compressed files.
+    Store,
+    #[default]
+    Deflate,
+}
+
+/// Payload of `zip://progress`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ZipProgress {
+    pub save_path: String,
+    pub files_done: u64,
+    pub files_total: u64,
+    pub bytes_done: u64,
+    pub bytes_total: u64,
+    pub current: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ZipSummary {
+    pub files: u64,
+    /// Uncompressed bytes of all members.
+    pub bytes: u64,
+    /// Size of the ZIP written.
+    pub archive_size: u64,
+}
+
+enum ZipMessage {
+    File { name: String, size: u64, modified: Option<zip::DateTime> },
+    Directory(String),
+    Data(Bytes),
+}
+
+/// Payload of `extract://progress`. Archives are read as a stream, so there is no total.
+#[derive(Debug, Clone, Serialize)]
+pub struct ExtractProgress {
+    pub path: String,
+    pub files_done: u64,
+    pub bytes_done: u64,
+    pub current: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExtractSummary {
+    pub files: u64,
+    pub bytes: u64,
+    /// Members left out because their path would escape the prefix.
+    pub skipped: Vec<String>,
+}
+
+enum Member {
+    Memory(Vec<u8>),
+    Spooled(PathBuf),
+}
+
+enum ExtractMessage {
+    Member { name: String, size: u64, data: Member },
+    Skipped(String),
+}
+
+fn u16_at(bytes: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(bytes.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn u32_at(bytes: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(bytes.get(at..at + 4)?.try_into().ok()?))
+}
+
+fn u64_at(bytes: &[u8], at: usize) -> Option<u64> {
+    Some(u64::from_le_bytes(bytes.get(at..at + 8)?.try_into().ok()?))
+}
+
+fn corrupt() -> AppError {
+    AppError::new(ErrorKind::Unsupported, "Not a ZIP archive, or the archive is damaged")
+}
+
+async fn read_range(client: &Client, bucket: &str, key: &str, range: String) -> Result<(Vec<u8>, Option<u64>), AppError> {
+    let resp = client.get_object().bucket(bucket).key(key).range(range).send().await?;
+    let total = crate::text::object_size(resp.content_range());
+    let data = resp.body.collect().await.map_err(|e| e.to_string())?.into_bytes().to_vec();
+    Ok((data, total))
+}
+
+/// Finds the central directory: (offset, size, entry count, archive size).
+async fn locate_central_directory(client: &Client, bucket: &str, key: &str) -> Result<(u64, u64, u64, u64), AppError> {
+    let (tail, total) = read_range(client, bucket, key, format!("bytes=-{}", TAIL_BYTES)).await?;
+    let size = total.unwrap_or(tail.len() as u64);
+    let tail_start = size - tail.len() as u64;
+
+    let eocd = (0..=tail.len().saturating_sub(EOCD_SIZE))
+        .rev()
+        .find(|&i| u32_at(&tail, i) == Some(EOCD_SIGNATURE))
+        .ok_or_else(corrupt)?;
+    let entries = u16_at(&tail, eocd + 10).ok_or_else(corrupt)?;
+    let cd_size = u32_at(&tail, eocd + 12).ok_or_else(corrupt)?;
+    let cd_offset = u32_at(&tail, eocd + 16).ok_or_else(corrupt)?;
+    if entries != u16::MAX && cd_size != u32::MAX && cd_offset != u32::MAX {
+        return Ok((cd_offset as u64, cd_size as u64, entries as u64, size));
+    }
+
+    // ZIP64: the locator sits right before the classic record and points at the real one
+    let locator = eocd.checked_sub(20).filter(|&l| u32_at(&tail, l) == Some(ZIP64_LOCATOR_SIGNATURE)).ok_or_else(corrupt)?;
+    let record_offset = u64_at(&tail, locator + 8).ok_or_else(corrupt)?;
+    let record = match record_offset.checked_sub(tail_start) {
+        Some(at) => tail.get(at as usize..).map(|r| r.to_vec()).ok_or_else(corrupt)?,
+        None => read_range(client, bucket, key, format!("bytes={}-{}", record_offset, record_offset + 55)).await?.0,
+    };
+    if u32_at(&record, 0) != Some(ZIP64_EOCD_SIGNATURE) {
+        return Err(corrupt());
+    }
+    let entries = u64_at(&record, 32).ok_or_else(corrupt)?;
+    let cd_size = u64_at(&record, 40).ok_or_else(corrupt)?;
+    let cd_offset = u64_at(&record, 48).ok_or_else(corrupt)?;
+    Ok((cd_offset, cd_size, entries, size))
+}
+
+fn parse_central_directory(bytes: &[u8], count: u64) -> Result<Vec<ArchiveEntry>, AppError> {
+    let mut entries = Vec::with_capacity(count.min(100_000) as usize);
+    let mut at = 0;
+    while u32_at(bytes, at) == Some(CENTRAL_SIGNATURE) {
+        let field = |offset| u16_at(bytes, at + offset).ok_or_else(corrupt);
+        let flags = field(8)?;
+        let method_id = field(10)?;
+        let name_len = field(28)? as usize;
+        let extra_len = field(30)? as usize;
+        let comment_len = field(32)? as usize;
+        let crc32 = u32_at(bytes, at + 16).ok_or_else(corrupt)?;
+        let mut compressed_size = u32_at(bytes, at + 20).ok_or_else(corrupt)? as u64;
+        let mut size = u32_at(bytes, at + 24).ok_or_else(corrupt)? as u64;
+        let mut local_offset = u32_at(bytes, at + 42).ok_or_else(corrupt)? as u64;
+
+        let name_start = at + 46;
+        let name = bytes.get(name_start..name_start + name_len).ok_or_else(corrupt)?;
+        let extra = bytes.get(name_start + name_len..name_start + name_len + extra_len).ok_or_else(corrupt)?;
+
+        // ZIP64 extra field: the 64-bit values of whichever fields overflowed, in this order
+        let mut field_at = 0;
+        while let (Some(id), Some(len)) = (u16_at(extra, field_at), u16_at(extra, field_at + 2)) {
+            let data = extra.get(field_at + 4..field_at + 4 + len as usize).ok_or_else(corrupt)?;
+            if id == 0x0001 {
+                let mut values = data.chunks_exact(8).map(|c| u64::from_le_bytes(c.try_into().unwrap()));
+                for value in [&mut size, &mut compressed_size, &mut local_offset] {
+                    if *value == u32::MAX as u64 {
+                        *value = values.next().ok_or_else(corrupt)?;
+                    }
+                }
+            }
+            field_at += 4 + len as usize;
+        }
+
+        // Names are UTF-8 when flagged and nearly always ASCII otherwise
+        let name = String::from_utf8_lossy(name).into_owned();
+        entries.push(ArchiveEntry {
+            is_dir: name.ends_with('/'),
+            name,
+            size,
+            compressed_size,
+            method: match method_id {
+                0 => "stored".to_string(),
+                8 => "deflate".to_string(),
+                other => other.to_string(),
+            },
+            crc32,
+            encrypted: flags & 1 != 0,
+            method_id,
+            local_offset,
+        });
+        at = name_start + name_len + extra_len + comment_len;
+    }
+    Ok(entries)
+}
+
+async fn read_listing(client: &Client, bucket: &str, key: &str) -> Result<ArchiveListing, AppError> {
+    let (cd_offset, cd_size, count, size) = locate_central_directory(client, bucket, key).await?;
+    if cd_size > MAX_CENTRAL_DIRECTORY {
+        return Err(AppError::new(ErrorKind::Unsupported, "Archive has too many entries to browse"));
+    }
+    if cd_size == 0 {
+        return Ok(ArchiveListing { entries: Vec::new(), size });
+    }
+    let (bytes, _) = read_range(client, bucket, key, format!("bytes={}-{}", cd_offset, cd_offset + cd_size - 1)).await?;
+    let entries = parse_central_directory(&bytes, count)?;
+    Ok(ArchiveListing { entries, size })
+}
+
+/// Streams one member's compressed bytes from the archive into `part`, inflating as it goes.
+async fn extract_to(client: &Client, bucket: &str, key: &str, entry: &ArchiveEntry, part: &Path) -> Result<u64, AppError> {
+    // The local header repeats the name and has its own extra field, so its length is only
+    // known once it's read
+    let header_end = entry.local_offset + 29;
+    let (header, _) = read_range(client, bucket, key, format!("bytes={}-{}", entry.local_offset, header_end)).await?;
+    if u32_at(&header, 0) != Some(LOCAL_SIGNATURE) {
+        return Err(corrupt());
+    }
+    let name_len = u16_at(&header, 26).ok_or_else(corrupt)? as u64;
+    let extra_len = u16_at(&header, 28).ok_or_else(corrupt)? as u64;
+    let data_start = entry.local_offset + 30 + name_len + extra_len;
+
+    let mut file = tokio::fs::File::create(part).await.map_err(|e| e.to_string())?;
+    let mut hasher = crc32fast::Hasher::new();
+    let mut written = 0u64;
+    if entry.compressed_size > 0 {
+        let resp = client.get_object()
+            .bucket(bucket)
+            .key(key)
+            .range(format!("bytes={}-{}", data_start, data_start + entry.compressed_size - 1))
+            .send()
+            .await?;
+        let mut body = resp.body;
+        let mut inflater = (entry.method_id == 8).then(|| DeflateDecoder::new(Vec::new()));
+        while let Some(chunk) = body.try_next().await.map_err(|e| e.to_string())? {
+            let out: &[u8] = match inflater.as_mut() {
+                Some(inflater) => {
+                    inflater.get_mut().clear();
+                    inflater.write_all(&chunk).map_err(|e| e.to_string())?;
+                    inflater.get_ref()
+                }
+                None => &chunk,
+            };
+            hasher.update(out);
+            file.write_all(out).await.map_err(|e| e.to_string())?;
+            written += out.len() as u64;
+        }
+        if let Some(mut inflater) = inflater {
+            // Output of the last chunk was already written; keep only what finishing flushes
+            inflater.get_mut().clear();
+            let rest = inflater.finish().map_err(|e| e.to_string())?;
+            hasher.update(&rest);
+            file.write_all(&rest).await.map_err(|e| e.to_string())?;
+            written += rest.len() as u64;
+        }
+    }
+    file.flush().await.map_err(|e| e.to_string())?;
+
+    if hasher.finalize() != entry.crc32 || written != entry.size {
+        return Err(AppError::new(ErrorKind::Internal, format!("{} failed its CRC check", entry.name)));
+    }
+    Ok(written)
+}
+
+/// Lists the members of a remote ZIP by reading only its central directory.
+#[tauri::command]
+pub async fn list_archive(
+    bucket: String,
+    key: String,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<ArchiveListing, AppError> {
+    let client = acquire_client(&state, connection_id.as_deref())?;
+    read_listing(&client, &bucket, &key).await
+}
+
+/// Extracts one member of a remote ZIP to `save_path`, fetching just that member's bytes.
+/// Stored and deflated members are supported. Returns the bytes written.
+#[tauri::command]
+pub async fn extract_archive_entry(
+    bucket: String,
+    key: String,
+    entry: String,
+    save_path: String,
+    app: AppHandle,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<u64, AppError> {
+    let job = jobs::start("extract_archive_entry", Some(&bucket), json!({ "key": key, "entry": entry, "save_path": save_path }));
+    let result: Result<u64, AppError> = async {
+        let client = acquire_client(&state, connection_id.as_deref())?;
+        let listing = read_listing(&client, &bucket, &key).await?;
+        let found = listing.entries.iter().find(|e| e.name == entry)
+            .ok_or_else(|| AppError::new(ErrorKind::NotFound, format!("'{}' is not in the archive", entry)))?;
+        if found.is_dir {
+            return Err(AppError::new(ErrorKind::InvalidInput, format!("'{}' is a folder", entry)));
+        }
+        if found.encrypted {
+            return Err(AppError::new(ErrorKind::Unsupported, "Encrypted archive members are not supported"));
+        }
+        if found.method_id != 0 && found.method_id != 8 {
+            return Err(AppError::new(ErrorKind::Unsupported, format!("Compression method {} is not supported", found.method)));
+        }
+
+        let target = Path::new(&save_path);
+        let part = partial::begin(&app, target)?;
+        let result = match extract_to(&client, &bucket, &key, found, &part).await {
+            Ok(written) => tokio::fs::rename(&part, target).await.map(|_| written).map_err(|e| AppError::from(e.to_string())),
+            Err(e) => Err(e),
+        };
+        if result.is_err() {
+            let _ = tokio::fs::remove_file(&part).await;
+        }
+        partial::finish(&app, &part);
+        result
+    }
+    .await;
+    job.finish(&app, &result, |n| format!("Extracted {} ({} bytes)", entry, n));
+
+    result
+}
+
+/// ZIP timestamps are DOS local times; object times are written as-is in UTC.
+fn zip_time(modified: Option<&aws_sdk_s3::primitives::DateTime>) -> Option<zip::DateTime> {
+    let text = modified?.fmt(DateTimeFormat::DateTime).ok()?;
+    let part = |range: std::ops::Range<usize>| text.get(range)?.parse::<u16>().ok();
+    zip::DateTime::from_date_and_time(
+        part(0..4)?,
+        part(5..7)? as u8,
+        part(8..10)? as u8,
+        part(11..13)? as u8,
+        part(14..16)? as u8,
+        part(17..19)? as u8,
+    )
+    .ok()
+}
+
+/// Longest folder shared by every selected key, so the ZIP holds the selection itself rather
+/// than the path leading to it.
+fn common_parent(keys: &[String]) -> String {
+    let parent = |key: &str| key.trim_end_matches('/').rfind('/').map(|i| key[..=i].to_string()).unwrap_or_default();
+    let mut common = keys.first().map(|k| parent(k)).unwrap_or_default();
+    for key in keys.iter().skip(1) {
+        while !key.starts_with(&common) {
+            common = parent(&common);
+        }
+    }
+    common
+}
+
+/// Runs on a blocking thread, writing members as the downloads stream them in.
+fn write_zip(path: PathBuf, compression: ZipCompression, mut rx: mpsc::Receiver<ZipMessage>) -> Result<u64, String> {
+    let file = std::fs::File::create(&path).map_err(|e| e.to_string())?;
+    let mut zip = ZipWriter::new(std::io::BufWriter::new(file));
+    let method = match compression {
+        ZipCompression::Store => CompressionMethod::Stored,
+        ZipCompression::Deflate => CompressionMethod::Deflated,
+    };
+    while let Some(message) = rx.blocking_recv() {
+        match message {
+            ZipMessage::File { name, size, modified } => {
+                let mut options = SimpleFileOptions::default()
+                    .compression_method(method)
+                    .large_file(size >= u32::MAX as u64)
+                    .unix_permissions(0o644);
+                if let Some(modified) = modified {
+                    options = options.last_modified_time(modified);
+                }
+                zip.start_file(name, options).map_err(|e| e.to_string())?;
+            }
+            ZipMessage::Directory(name) => {
+                zip.add_directory(name, SimpleFileOptions::default()).map_err(|e| e.to_string())?;
+            }
+            ZipMessage::Data(bytes) => zip.write_all(&bytes).map_err(|e| e.to_string())?,
+        }
+    }
+    let mut out = zip.finish().map_err(|e| e.to_string())?;
+    out.flush().map_err(|e| e.to_string())?;
+    let file = out.into_inner().map_err(|e| e.to_string())?;
+    file.sync_all().map_err(|e| e.to_string())?;
+    Ok(file.metadata().map_err(|e| e.to_string())?.len())
+}
+
+/// Downloads objects into a single ZIP at `save_path`. Keys ending in `/` are folders and take
+/// everything under them. Members are named relative to the folder the selection shares.
+/// Progress arrives as `zip://progress`.
+#[tauri::command]
+pub async fn download_as_zip(
+    bucket: String,
+    keys: Vec<String>,
+    save_path: String,
+    compression: Option<ZipCompression>,
+    app: AppHandle,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<ZipSummary, AppError> {
+    if keys.is_empty() {
+        return Err(AppError::new(ErrorKind::InvalidInput, "Nothing selected to download"));
+    }
+    let job = jobs::start("download_as_zip", Some(&bucket), json!({ "keys": keys, "save_path": save_path }));
+    let result: Result<ZipSummary, AppError> = async {
+        let client = acquire_client(&state, connection_id.as_deref())?;
+
+        let mut objects = Vec::new();
+        for key in &keys {
+            if key.ends_with('/') {
+                objects.extend(list_all_objects(&client, &bucket, Some(key)).await?);
+            } else {
+                let head = client.head_object().bucket(&bucket).key(key).send().await?;
+                objects.push(
+                    aws_sdk_s3::types::Object::builder()
+                        .key(key)
+                        .size(head.content_length().unwrap_or(0))
+                        .set_last_modified(head.last_modified().cloned())
+                        .build(),
+                );
+            }
+        }
+        // A folder and a file inside it may both be selected
+        objects.sort_by(|a, b| a.key().cmp(&b.key()));
+        objects.dedup_by(|a, b| a.key() == b.key());
+        let parent = common_parent(&keys);
+        let mut progress = ZipProgress {
+            save_path: save_path.clone(),
+            files_done: 0,
+            files_total: objects.iter().filter(|o| !is_folder_marker(o.key().unwrap_or_default(), o.size().unwrap_or(0))).count() as u64,
+            bytes_done: 0,
+            bytes_total: objects.iter().map(|o| o.size().unwrap_or(0).max(0) as u64).sum(),
+            current: None,
+        };
+        let _ = app.emit("zip://progress", &progress);
+
+        let target = PathBuf::from(&save_path);
+        let part = partial::begin(&app, &target)?;
+        let (tx, rx) = mpsc::channel(16);
+        let writer = tauri::async_runtime::spawn_blocking({
+            let part = part.clone();
+            move || write_zip(part, compression.unwrap_or_default(), rx)
+        });
+
+        let streamed: Result<(), AppError> = async {
+            for object in &objects {
+                let key = object.key().unwrap_or_default();
+                let name = key[parent.len()..].to_string();
+                let size = object.size().unwrap_or(0).max(0) as u64;
+                if name.is_empty() {
+                    continue;
+                }
+                // The writer only goes away on error, which the join below reports
+                if is_folder_marker(key, size as i64) {
+                    if tx.send(ZipMessage::Directory(name)).await.is_err() {
+                        break;
+                    }
+                    continue;
+                }
+
+                progress.current = Some(key.to_string());
+                if tx.send(ZipMessage::File { name, size, modified: zip_time(object.last_modified()) }).await.is_err() {
+                    break;
+                }
+                let resp = client.get_object().bucket(&bucket).key(key).send().await?;
+                let mut body = resp.body;
+                let mut since_progress = 0;
+                while let Some(chunk) = body.try_next().await.map_err(|e| e.to_string())? {
+                    progress.bytes_done += chunk.len() as u64;
+                    since_progress += chunk.len() as u64;
+                    if tx.send(ZipMessage::Data(chunk)).await.is_err() {
+                        break;
+                    }
+                    if since_progress >= PROGRESS_INTERVAL_BYTES {
+                        since_progress = 0;
+                        let _ = app.emit("zip://progress", &progress);
+                    }
+                }
+                progress.files_done += 1;
+                let _ = app.emit("zip://progress", &progress);
+            }
+            Ok(())
+        }
+        .await;
+        drop(tx);
+        let written = writer.await.map_err(|e| e.to_string());
+
+        let result = match (streamed, written) {
+            (Err(e), _) => Err(e),
+            (Ok(()), Err(e)) | (Ok(()), Ok(Err(e))) => Err(AppError::from(e)),
+            (Ok(()), Ok(Ok(archive_size))) => tokio::fs::rename(&part, &target)
+                .await
+                .map(|_| ZipSummary { files: progress.files_done, bytes: progress.bytes_done, archive_size })
+                .map_err(|e| AppError::from(e.to_string())),
+        };
+        if result.is_err() {
+            let _ = tokio::fs::remove_file(&part).await;
+        }
+        partial::finish(&app, &part);
+        result
+    }
+    .await;
+    job.finish(&app, &result, |s| format!("Zipped {} files into {}", s.files, save_path));
+
+    result
+}
+
+/// A member's path as a key suffix, or None when it is absolute or climbs out with `..`.
+fn member_key(name: &str) -> Option<String> {
+    let name = name.replace('\\', "/");
+    let mut parts = Vec::new();
+    for part in name.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => return None,
+            p if p.contains(':') => return None,
+            p => parts.push(p),
+        }
+    }
+    if name.starts_with('/') || parts.is_empty() {
+        return None;
+    }
+    Some(parts.join("/"))
+}
+
+fn read_member(mut reader: impl Read, size: u64, spool_dir: &Path, spool_tag: &str, index: u64) -> Result<Member, String> {
+    if size <= SPOOL_THRESHOLD {
+        let mut data = Vec::with_capacity(size as usize);
+        reader.read_to_end(&mut data).map_err(|e| e.to_string())?;
+        return Ok(Member::Memory(data));
+    }
+    let path = spool_dir.join(format!("{}{}", spool_tag, index));
+    let mut file = std::fs::File::create(&path).map_err(|e| e.to_string())?;
+    std::io::copy(&mut reader, &mut file).map_err(|e| e.to_string())?;
+    Ok(Member::Spooled(path))
+}
+
+/// Runs on a blocking thread, reading members in archive order and handing them to the uploaders.
+fn read_archive(path: &Path, spool_dir: &Path, spool_tag: &str, tx: mpsc::Sender<ExtractMessage>) -> Result<(), String> {
+    let lower = path.to_string_lossy().to_lowercase();
+    let file = std::fs::File::open(path).map_err(|e| format!("Cannot open {}: {}", path.display(), e))?;
+    let send = |name: String, size: u64, reader: &mut dyn Read, index: u64| -> Result<bool, String> {
+        let message = match member_key(&name) {
+            Some(key) => ExtractMessage::Member { name: key, size, data: read_member(reader, size, spool_dir, spool_tag, index)? },
+            None => ExtractMessage::Skipped(name),
+        };
+        // The receiver only goes away when an upload failed
+        Ok(tx.blocking_send(message).is_ok())
+    };
+
+    if lower.ends_with(".zip") {
+        let mut zip = zip::ZipArchive::new(std::io::BufReader::new(file)).map_err(|e| e.to_string())?;
+        for index in 0..zip.len() {
+            let mut member = zip.by_index(index).map_err(|e| e.to_string())?;
+            if member.is_dir() {
+                continue;
+            }
+            let (name, size) = (member.name().map_err(|e| e.to_string())?.to_string(), member.size());
+            if !send(name, size, &mut member, index as u64)? {
+                break;
+            }
+        }
+        return Ok(());
+    }
+
+    let reader: Box<dyn Read> = if lower.ends_with(".tar.gz") || lower.ends_with(".tgz") {
+        Box::new(flate2::read::GzDecoder::new(std::io::BufReader::new(file)))
+    } else if lower.ends_with(".tar") {
+        Box::new(std::io::BufReader::new(file))
+    } else {
+        return Err("Only .zip, .tar.gz, .tgz and .tar archives can be extracted".to_string());
+    };
+    let mut tar = tar::Archive::new(reader);
+    for (index, member) in tar.entries().map_err(|e| e.to_string())?.enumerate() {
+        let mut member = member.map_err(|e| e.to_string())?;
+        if !member.header().entry_type().is_file() {
+            continue;
+        }
+        let name = member.path().map_err(|e| e.to_string())?.to_string_lossy().to_string();
+        let size = member.size();
+        if !send(name, size, &mut member, index as u64)? {
+            break;
+        }
+    }
+    Ok(())
+}
+
+async fn upload_member(client: &Client, bucket: &str, key: &str, data: Member) -> Result<(), AppError> {
+    let content_type = mime_guess::from_path(key).first_or_octet_stream().to_string();
+    let (body, spooled) = match data {
+        Member::Memory(bytes) => (ByteStream::from(bytes), None),
+        Member::Spooled(path) => (ByteStream::from_path(&path).await.map_err(|e| e.to_string())?, Some(path)),
+    };
+    let result = client.put_object().bucket(bucket).key(key).content_type(content_type).body(body).send().await;
+    if let Some(path) = spooled {
+        let _ = tokio::fs::remove_file(path).await;
+    }
+    result.map_err(|e| {
+        let err = AppError::from(e);
+        AppError { message: format!("Failed to upload {}: {}", key, err.message), ..err }
+    })?;
+    Ok(())
+}
+
+/// Unpacks a local .zip, .tar.gz/.tgz or .tar under `prefix`, uploading members concurrently as
+/// they are read (8 at a time by default, never more than `max_connections`). Content types come from the file names, so a static
+/// site is ready to serve. Members whose path would leave the prefix are skipped.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn upload_archive(
+    bucket: String,
+    prefix: String,
+    path: String,
+    concurrency: Option<usize>,
+    app: AppHandle,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<ExtractSummary, AppError> {
+    let job = jobs::start("upload_archive", Some(&bucket), json!({ "prefix": prefix, "path": path }));
+    let result: Result<ExtractSummary, AppError> = async {
+        let client = acquire_write_client(&state, connection_id.as_deref())?;
+        // Each upload's requests take their own limiter permits; uploads beyond what the limiter
+        // allows would only hold their members in memory or on disk while they wait
+        let concurrency = s3::concurrency(&state, concurrency.unwrap_or(DEFAULT_EXTRACT_CONCURRENCY).clamp(1, MAX_EXTRACT_CONCURRENCY));
+        let prefix = if prefix.is_empty() || prefix.ends_with('/') { prefix.clone() } else { format!("{}/", prefix) };
+        let spool_dir = thumbnails::cache_dir(&app, "extract")?;
+        let spool_tag = format!("{}-{}-", std::process::id(), EXTRACT_RUN.fetch_add(1, Ordering::Relaxed));
+
+        let (tx, mut rx) = mpsc::channel(concurrency);
+        let reader = tauri::async_runtime::spawn_blocking({
+            let (path, spool_dir, spool_tag) = (PathBuf::from(&path), spool_dir.clone(), spool_tag.clone());
+            move || read_archive(&path, &spool_dir, &spool_tag, tx)
+        });
+
+        let mut progress = ExtractProgress { path: path.clone(), files_done: 0, bytes_done: 0, current: None };
+        let mut skipped = Vec::new();
+        let mut uploads = JoinSet::new();
+        let uploaded: Result<(), AppError> = async {
+            while let Some(message) = rx.recv().await {
+                let (name, size, data) = match message {
+                    ExtractMessage::Member { name, size, data } => (name, size, data),
+                    ExtractMessage::Skipped(name) => {
+                        skipped.push(name);
+                        continue;
+                    }
+                };
+                while uploads.len() >= concurrency {
+                    progress.bytes_done += uploads.join_next().await.unwrap().map_err(|e| e.to_string())??;
+                    progress.files_done += 1;
+                    let _ = app.emit("extract://progress", &progress);
+                }
+                let key = format!("{}{}", prefix, name);
+                progress.current = Some(key.clone());
+                let (client, bucket) = (client.clone(), bucket.clone());
+                uploads.spawn(async move {
+                    upload_member(&client, &bucket, &key, data).await?;
+                    Ok::<_, AppError>(size)
+                });
+            }
+            while let Some(done) = uploads.join_next().await {
+                progress.bytes_done += done.map_err(|e| e.to_string())??;
+                progress.files_done += 1;
+                let _ = app.emit("extract://progress", &progress);
+            }
+            Ok(())
+        }
+        .await;
+        uploads.abort_all();
+        // Dropping the receiver stops the reader at its next member
+        drop(rx);
+        let read = reader.await.map_err(|e| e.to_string())?;
+        // Members spooled but never uploaded
+        if uploaded.is_err() {
+            for entry in std::fs::read_dir(&spool_dir).into_iter().flatten().flatten() {
+                if entry.file_name().to_string_lossy().starts_with(&spool_tag) {
+                    let _ = std::fs::remove_file(entry.path());
+                }
+            }
+        }
+        uploaded?;
+        read.map_err(|e| AppError::new(ErrorKind::InvalidInput, e))?;
+
+        Ok(ExtractSummary { files: progress.files_done, bytes: progress.bytes_done, skipped })
+    }
+    .await;
+    job.finish(&app, &result, |s| format!("Extracted {} files from {}", s.files, path));
+
+    result
+}
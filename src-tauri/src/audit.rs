@@ -0,0 +1,319 @@
+use aws_sdk_s3::types::Object;
+use aws_sdk_s3::Client;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tokio::task::JoinSet;
+
+use crate::error::AppError;
+use crate::index;
+use crate::jobs;
+use crate::s3::{acquire_client, is_folder_marker, list_all_objects, AppState};
+
+// Bit-rot checking. The first audit of an object records the SHA-256 of its bytes; later audits
+// hash it again and compare. An object whose ETag and size are unchanged but whose bytes hash
+// differently has been corrupted; one with a new ETag or size was rewritten on purpose and is only
+// reported as changed. Records live in audit/<profile>.sqlite, scoped per profile the same way as
+// the index, so two accounts with a bucket of the same name never share digests.
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS objects (
+        bucket TEXT NOT NULL,
+        key TEXT NOT NULL,
+        size INTEGER NOT NULL,
+        etag TEXT NOT NULL,
+        sha256 TEXT,
+        sample_sha256 TEXT,
+        recorded_at INTEGER NOT NULL,
+        verified_at INTEGER,
+        PRIMARY KEY (bucket, key)
+    ) WITHOUT ROWID;
+";
+const AUDIT_CONCURRENCY: usize = 4;
+// Each of the three ranges a sampled audit reads
+const SAMPLE_RANGE: i64 = 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditMode {
+    /// Hash every byte of every object.
+    #[default]
+    Full,
+    /// Hash the first, middle and last MiB of each object. Much cheaper on large archives, but only
+    /// catches damage within those ranges. Kept apart from full digests.
+    Sample,
+}
+
+/// Payload of `audit://progress`.
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditProgress {
+    pub bucket: String,
+    pub done: usize,
+    pub total: usize,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditIssue {
+    pub key: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct AuditReport {
+    pub total: usize,
+    /// Seen for the first time; their digest is now the reference.
+    pub recorded: usize,
+    /// Hashed the same as recorded.
+    pub verified: usize,
+    /// Rewritten since recorded (new ETag or size); the record now follows the new content.
+    pub changed: Vec<AuditIssue>,
+    /// Same ETag and size but different bytes. Their record keeps the last good digest.
+    pub corrupted: Vec<AuditIssue>,
+    /// Recorded but no longer listed.
+    pub missing: Vec<String>,
+    pub failed: Vec<AuditIssue>,
+    pub bytes: u64,
+}
+
+struct Record {
+    size: i64,
+    etag: String,
+    digest: Option<String>,
+}
+
+enum Outcome {
+    Recorded,
+    Verified,
+    Changed(String),
+    Corrupted(String),
+}
+
+struct Audited {
+    outcome: Outcome,
+    digest: String,
+    read: u64,
+}
+
+fn open(app: &AppHandle, connection_id: Option<&str>) -> Result<Connection, String> {
+    let dir = app.path().app_data_dir().map_err(|e| e.to_string())?.join("audit");
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let name = index::db_name(app, connection_id);
+    let db = Connection::open(dir.join(format!("{}.sqlite", name))).map_err(|e| e.to_string())?;
+    db.busy_timeout(Duration::from_secs(5)).map_err(|e| e.to_string())?;
+    db.execute_batch(SCHEMA).map_err(|e| e.to_string())?;
+    Ok(db)
+}
+
+fn digest_column(mode: AuditMode) -> &'static str {
+    match mode {
+        AuditMode::Full => "sha256",
+        AuditMode::Sample => "sample_sha256",
+    }
+}
+
+fn load(db: &Connection, bucket: &str, prefix: &str, mode: AuditMode) -> Result<HashMap<String, Record>, String> {
+    let mut stmt = db.prepare(&format!(
+        "SELECT key, size, etag, {} FROM objects WHERE bucket = ?1 AND substr(key, 1, length(?2)) = ?2",
+        digest_column(mode)
+    )).map_err(|e| e.to_string())?;
+    let rows = stmt.query_map(params![bucket, prefix], |r| {
+        Ok((r.get::<_, String>(0)?, Record { size: r.get(1)?, etag: r.get(2)?, digest: r.get(3)? }))
+    }).map_err(|e| e.to_string())?;
+    rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+}
+
+/// Replaces an object's record with new content, dropping the digest of the other mode since it
+/// was taken of the old content.
+fn record(db: &Connection, bucket: &str, object: &Object, mode: AuditMode, digest: &str) -> Result<(), String> {
+    let now = jobs::now_secs();
+    let key = object.key().unwrap_or_default();
+    let etag = object.e_tag().unwrap_or_default().trim_matches('"');
+    let changed = db.query_row(
+        "SELECT size != ?3 OR etag != ?4 FROM objects WHERE bucket = ?1 AND key = ?2",
+        params![bucket, key, object.size().unwrap_or(0), etag],
+        |r| r.get::<_, bool>(0),
+    ).optional().map_err(|e| e.to_string())?;
+    if changed.unwrap_or(true) {
+        db.execute(
+            "INSERT OR REPLACE INTO objects (bucket, key, size, etag, recorded_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![bucket, key, object.size().unwrap_or(0), etag, now],
+        ).map_err(|e| e.to_string())?;
+    }
+    db.execute(
+        &format!("UPDATE objects SET {} = ?3, verified_at = ?4 WHERE bucket = ?1 AND key = ?2", digest_column(mode)),
+        params![bucket, key, digest, now],
+    ).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+async fn hash_range(client: &Client, bucket: &str, key: &str, range: Option<String>, hasher: &mut Sha256) -> Result<u64, String> {
+    let resp = client.get_object()
+        .bucket(bucket)
+        .key(key)
+        .set_range(range)
+        .send()
+        .await
+        .map_err(|e| AppError::from(e).message)?;
+    let mut body = resp.body;
+    let mut read = 0u64;
+    while let Some(chunk) = body.try_next().await.map_err(|e| e.to_string())? {
+        hasher.update(&chunk);
+        read += chunk.len() as u64;
+    }
+    Ok(read)
+}
+
+/// Hex SHA-256 of the object, or of its sample ranges. Returns the digest and bytes read.
+async fn digest(client: &Client, bucket: &str, key: &str, size: i64, mode: AuditMode) -> Result<(String, u64), String> {
+    let mut hasher = Sha256::new();
+    let ranges = if mode == AuditMode::Full || size <= 3 * SAMPLE_RANGE {
+        vec![None]
+    } else {
+        [0, size / 2 - SAMPLE_RANGE / 2, size - SAMPLE_RANGE]
+            .iter()
+            .map(|start| Some(format!("bytes={}-{}", start, start + SAMPLE_RANGE - 1)))
+            .collect()
+    };
+    let mut read = 0;
+    for range in ranges {
+        read += hash_range(client, bucket, key, range, &mut hasher).await?;
+    }
+    Ok((format!("{:x}", hasher.finalize()), read))
+}
+
+async fn audit_object(client: &Client, bucket: &str, object: &Object, record: Option<&Record>, mode: AuditMode) -> Result<Audited, String> {
+    let key = object.key().unwrap_or_default();
+    let size = object.size().unwrap_or(0);
+    let etag = object.e_tag().unwrap_or_default().trim_matches('"');
+    let (digest, read) = digest(client, bucket, key, size, mode).await?;
+
+    let outcome = match record {
+        Some(r) if r.size != size || r.etag != etag => Outcome::Changed(format!("Rewritten: was {} bytes with ETag {}", r.size, r.etag)),
+        Some(Record { digest: Some(expected), .. }) if *expected != digest => {
+            Outcome::Corrupted(format!("SHA-256 is {}, recorded {}", digest, expected))
+        }
+        Some(Record { digest: Some(_), .. }) => Outcome::Verified,
+        _ => Outcome::Recorded,
+    };
+    Ok(Audited { outcome, digest, read })
+}
+
+/// Hashes every object under `prefix` and compares it with the digest recorded by earlier audits,
+/// recording objects seen for the first time. Emits `audit://progress`.
+#[tauri::command]
+pub async fn audit(
+    bucket: String,
+    prefix: Option<String>,
+    mode: Option<AuditMode>,
+    app: AppHandle,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<AuditReport, AppError> {
+    let mode = mode.unwrap_or_default();
+    let prefix = prefix.unwrap_or_default();
+    let job = jobs::start("audit", Some(&bucket), json!({ "prefix": prefix, "mode": mode }));
+    let result: Result<AuditReport, AppError> = async {
+        let client = acquire_client(&state, connection_id.as_deref())?;
+        let objects: Vec<Object> = list_all_objects(&client, &bucket, Some(&prefix))
+            .await?
+            .into_iter()
+            .filter(|o| !is_folder_marker(o.key().unwrap_or_default(), o.size().unwrap_or(0)))
+            .collect();
+        let mut records = load(&open(&app, connection_id.as_deref())?, &bucket, &prefix, mode)?;
+
+        let mut report = AuditReport { total: objects.len(), ..Default::default() };
+        let mut progress = AuditProgress { bucket: bucket.clone(), done: 0, total: objects.len(), bytes: 0 };
+        let _ = app.emit("audit://progress", &progress);
+
+        let objects = Arc::new(objects);
+        let mut digests = Vec::new();
+        let mut finish = |progress: &mut AuditProgress, index: usize, audited: Result<Audited, String>| {
+            let key = objects[index].key().unwrap_or_default().to_string();
+            match audited {
+                Ok(Audited { outcome, digest, read }) => {
+                    if !matches!(outcome, Outcome::Corrupted(_)) {
+                        digests.push((index, digest));
+                    }
+                    match outcome {
+                        Outcome::Recorded => report.recorded += 1,
+                        Outcome::Verified => report.verified += 1,
+                        Outcome::Changed(reason) => report.changed.push(AuditIssue { key, reason }),
+                        Outcome::Corrupted(reason) => report.corrupted.push(AuditIssue { key, reason }),
+                    }
+                    report.bytes += read;
+                    progress.bytes += read;
+                }
+                Err(reason) => report.failed.push(AuditIssue { key, reason }),
+            }
+            progress.done += 1;
+            let _ = app.emit("audit://progress", &*progress);
+        };
+
+        let mut tasks: JoinSet<(usize, Result<Audited, String>)> = JoinSet::new();
+        for index in 0..objects.len() {
+            while tasks.len() >= AUDIT_CONCURRENCY {
+                if let Some(Ok((index, outcome))) = tasks.join_next().await {
+                    finish(&mut progress, index, outcome);
+                }
+            }
+            let key = objects[index].key().unwrap_or_default();
+            let record = records.remove(key);
+            let (client, bucket, objects) = (client.clone(), bucket.clone(), objects.clone());
+            tasks.spawn(async move {
+                (index, audit_object(&client, &bucket, &objects[index], record.as_ref(), mode).await)
+            });
+        }
+        while let Some(joined) = tasks.join_next().await {
+            if let Ok((index, outcome)) = joined {
+                finish(&mut progress, index, outcome);
+            }
+        }
+
+        let mut db = open(&app, connection_id.as_deref())?;
+        let tx = db.transaction().map_err(|e| e.to_string())?;
+        for (index, digest) in digests {
+            record(&tx, &bucket, &objects[index], mode, &digest)?;
+        }
+        tx.commit().map_err(|e| e.to_string())?;
+
+        // Whatever wasn't listed is gone; its record stays so it's reported again next time
+        report.missing = records.into_keys().collect();
+        report.missing.sort();
+        Ok(report)
+    }
+    .await;
+    job.finish(&app, &result, |r| {
+        format!(
+            "Audited {} objects: {} corrupted, {} changed, {} missing, {} failed",
+            r.total,
+            r.corrupted.len(),
+            r.changed.len(),
+            r.missing.len(),
+            r.failed.len()
+        )
+    });
+
+    result
+}
+
+/// Forgets the recorded digests under `prefix`, e.g. after objects were deleted on purpose.
+/// Returns how many records were removed.
+#[tauri::command]
+pub fn clear_audit_records(
+    bucket: String,
+    prefix: Option<String>,
+    app: AppHandle,
+    connection_id: Option<String>,
+) -> Result<usize, AppError> {
+    let db = open(&app, connection_id.as_deref())?;
+    let removed = db.execute(
+        "DELETE FROM objects WHERE bucket = ?1 AND substr(key, 1, length(?2)) = ?2",
+        params![bucket, prefix.unwrap_or_default()],
+    ).map_err(|e| e.to_string())?;
+    Ok(removed)
+}
@@ -0,0 +1,553 @@
+use aws_sdk_s3::types::{
+    AbortIncompleteMultipartUpload, BucketLifecycleConfiguration, BucketLocationConstraint, CorsConfiguration,
+    CorsRule as S3CorsRule, CreateBucketConfiguration, Delete, ExpirationStatus, LifecycleExpiration,
+    LifecycleRule as S3LifecycleRule, LifecycleRuleFilter, ObjectIdentifier,
+};
+use aws_sdk_s3::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tauri::{AppHandle, Emitter, State};
+
+use crate::capabilities::{self, Capability};
+use crate::error::{AppError, ErrorKind};
+use crate::jobs;
+use crate::s3::{acquire_client, acquire_write_client, AppState};
+
+// R2 location hints, passed as the S3 LocationConstraint
+const R2_LOCATION_HINTS: [&str; 6] = ["wnam", "enam", "weur", "eeur", "apac", "oc"];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EmptyBucketProgress {
+    pub bucket: String,
+    pub phase: String, // "objects" | "multipart" | "done"
+    pub deleted_objects: u64,
+    pub aborted_uploads: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EmptyBucketResult {
+    pub deleted_objects: u64,
+    pub aborted_uploads: u64,
+}
+
+fn emit_progress(app: &AppHandle, progress: &EmptyBucketProgress) {
+    let _ = app.emit("empty-bucket://progress", progress);
+}
+
+/// Deletes every object page by page, then aborts all pending multipart uploads.
+pub async fn empty_bucket_inner(app: &AppHandle, client: &Client, bucket: &str) -> Result<EmptyBucketResult, String> {
+    let mut progress = EmptyBucketProgress {
+        bucket: bucket.to_string(),
+        phase: "objects".to_string(),
+        deleted_objects: 0,
+        aborted_uploads: 0,
+    };
+    emit_progress(app, &progress);
+
+    // Always list from the start: the keys we just deleted are gone, so the next page is the next batch
+    loop {
+        let resp = client.list_objects_v2()
+            .bucket(bucket)
+            .max_keys(1000)
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+
+        let ids: Vec<ObjectIdentifier> = resp
+            .contents()
+            .iter()
+            .filter_map(|o| o.key())
+            .map(|k| ObjectIdentifier::builder().key(k).build().unwrap())
+            .collect();
+
+        if ids.is_empty() {
+            break;
+        }
+
+        let count = ids.len() as u64;
+        let delete = Delete::builder().set_objects(Some(ids)).quiet(true).build().unwrap();
+        let out = client.delete_objects()
+            .bucket(bucket)
+            .delete(delete)
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+
+        if let Some(err) = out.errors().first() {
+            return Err(format!(
+                "Failed to delete {}: {}",
+                err.key().unwrap_or_default(),
+                err.message().unwrap_or_default()
+            ));
+        }
+
+        progress.deleted_objects += count;
+        emit_progress(app, &progress);
+    }
+
+    progress.phase = "multipart".to_string();
+    emit_progress(app, &progress);
+
+    let mut key_marker = None;
+    let mut upload_id_marker = None;
+    loop {
+        let resp = client.list_multipart_uploads()
+            .bucket(bucket)
+            .set_key_marker(key_marker)
+            .set_upload_id_marker(upload_id_marker)
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+
+        for upload in resp.uploads() {
+            client.abort_multipart_upload()
+                .bucket(bucket)
+                .key(upload.key().unwrap_or_default())
+                .upload_id(upload.upload_id().unwrap_or_default())
+                .send()
+                .await
+                .map_err(|e| e.to_string())?;
+            progress.aborted_uploads += 1;
+        }
+        emit_progress(app, &progress);
+
+        if resp.is_truncated().unwrap_or(false) {
+            key_marker = resp.next_key_marker().map(|s| s.to_string());
+            upload_id_marker = resp.next_upload_id_marker().map(|s| s.to_string());
+        } else {
+            break;
+        }
+    }
+
+    progress.phase = "done".to_string();
+    emit_progress(app, &progress);
+
+    Ok(EmptyBucketResult {
+        deleted_objects: progress.deleted_objects,
+        aborted_uploads: progress.aborted_uploads,
+    })
+}
+
+#[tauri::command]
+pub async fn empty_bucket(
+    bucket: String,
+    app: AppHandle,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<EmptyBucketResult, AppError> {
+    let job = jobs::start("empty_bucket", Some(&bucket), json!({}));
+    let result: Result<EmptyBucketResult, AppError> = async {
+        let client = acquire_write_client(&state, connection_id.as_deref())?;
+
+        Ok(empty_bucket_inner(&app, &client, &bucket).await?)
+    }
+    .await;
+    job.finish(&app, &result, |r| format!("Deleted {} objects, aborted {} uploads", r.deleted_objects, r.aborted_uploads));
+
+    result
+}
+
+fn validate_bucket_name(name: &str) -> Result<(), String> {
+    let valid_chars = name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+    if name.len() < 3 || name.len() > 63 || !valid_chars || name.starts_with('-') || name.ends_with('-') {
+        return Err("Bucket names must be 3-63 characters of lowercase letters, digits and hyphens, \
+                    and cannot start or end with a hyphen"
+            .to_string());
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn create_bucket(
+    bucket: String,
+    location_hint: Option<String>,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    let conn = connection_id.as_deref();
+    validate_bucket_name(&bucket)?;
+    capabilities::require(&state, conn, Capability::CreateBucket)?;
+
+    let client = acquire_write_client(&state, conn)?;
+
+    let mut req = client.create_bucket().bucket(&bucket);
+    if let Some(hint) = location_hint.filter(|h| !h.is_empty()) {
+        if !R2_LOCATION_HINTS.contains(&hint.as_str()) {
+            return Err(AppError::new(ErrorKind::InvalidInput, format!("Unknown location hint: {}", hint)));
+        }
+        req = req.create_bucket_configuration(
+            CreateBucketConfiguration::builder()
+                .location_constraint(BucketLocationConstraint::from(hint.as_str()))
+                .build(),
+        );
+    }
+
+    req.send()
+        .await
+        .map_err(|e| capabilities::map_error(&state, conn, Capability::CreateBucket, e))?;
+
+    Ok(())
+}
+
+/// Deletes a bucket. Buckets must be empty unless `force` is set, in which case every object and
+/// pending multipart upload is removed first (reporting progress like empty_bucket).
+#[tauri::command]
+pub async fn delete_bucket(
+    bucket: String,
+    force: bool,
+    app: AppHandle,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    let job = jobs::start("delete_bucket", Some(&bucket), json!({ "force": force }));
+    let result: Result<(), AppError> = async {
+        let client = acquire_write_client(&state, connection_id.as_deref())?;
+
+        if force {
+            empty_bucket_inner(&app, &client, &bucket).await?;
+        }
+
+        client.delete_bucket()
+            .bucket(&bucket)
+            .send()
+            .await
+            .map_err(|e| {
+                let code = e.as_service_error().and_then(|se| se.meta().code()).unwrap_or_default();
+                if code == "BucketNotEmpty" {
+                    "Bucket is not empty".to_string()
+                } else {
+                    e.to_string()
+                }
+            })?;
+
+        Ok(())
+    }
+    .await;
+    job.finish(&app, &result, |_| format!("Deleted bucket {}", bucket));
+
+    result
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CorsRule {
+    pub id: Option<String>,
+    pub allowed_origins: Vec<String>,
+    pub allowed_methods: Vec<String>,
+    #[serde(default)]
+    pub allowed_headers: Vec<String>,
+    #[serde(default)]
+    pub expose_headers: Vec<String>,
+    pub max_age_seconds: Option<i32>,
+}
+
+const CORS_METHODS: [&str; 5] = ["GET", "PUT", "POST", "DELETE", "HEAD"];
+
+#[tauri::command]
+pub async fn get_bucket_cors(
+    bucket: String,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<CorsRule>, AppError> {
+    let conn = connection_id.as_deref();
+    capabilities::require(&state, conn, Capability::Cors)?;
+    let client = acquire_client(&state, conn)?;
+
+    let resp = match client.get_bucket_cors().bucket(&bucket).send().await {
+        Ok(r) => r,
+        Err(e) => {
+            let code = e.as_service_error().and_then(|se| se.meta().code()).unwrap_or_default();
+            if code == "NoSuchCORSConfiguration" {
+                return Ok(Vec::new());
+            }
+            return Err(capabilities::map_error(&state, conn, Capability::Cors, e));
+        }
+    };
+
+    Ok(resp
+        .cors_rules()
+        .iter()
+        .map(|r| CorsRule {
+            id: r.id().map(|s| s.to_string()),
+            allowed_origins: r.allowed_origins().to_vec(),
+            allowed_methods: r.allowed_methods().to_vec(),
+            allowed_headers: r.allowed_headers().to_vec(),
+            expose_headers: r.expose_headers().to_vec(),
+            max_age_seconds: r.max_age_seconds(),
+        })
+        .collect())
+}
+
+/// Replaces the bucket's CORS rules. An empty list removes the configuration.
+#[tauri::command]
+pub async fn put_bucket_cors(
+    bucket: String,
+    rules: Vec<CorsRule>,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    let conn = connection_id.as_deref();
+    capabilities::require(&state, conn, Capability::Cors)?;
+
+    let mut s3_rules = Vec::new();
+    for rule in rules.iter() {
+        if rule.allowed_origins.is_empty() || rule.allowed_methods.is_empty() {
+            return Err(AppError::new(ErrorKind::InvalidInput, "Each CORS rule needs at least one allowed origin and method"));
+        }
+        let methods: Vec<String> = rule.allowed_methods.iter().map(|m| m.to_ascii_uppercase()).collect();
+        if let Some(bad) = methods.iter().find(|m| !CORS_METHODS.contains(&m.as_str())) {
+            return Err(AppError::new(ErrorKind::InvalidInput, format!("Unsupported CORS method: {}", bad)));
+        }
+        s3_rules.push(
+            S3CorsRule::builder()
+                .set_id(rule.id.clone())
+                .set_allowed_origins(Some(rule.allowed_origins.clone()))
+                .set_allowed_methods(Some(methods))
+                .set_allowed_headers(Some(rule.allowed_headers.clone()))
+                .set_expose_headers(Some(rule.expose_headers.clone()))
+                .set_max_age_seconds(rule.max_age_seconds)
+                .build()
+                .map_err(|e| e.to_string())?,
+        );
+    }
+
+    let client = acquire_write_client(&state, conn)?;
+
+    if s3_rules.is_empty() {
+        client.delete_bucket_cors()
+            .bucket(&bucket)
+            .send()
+            .await
+            .map_err(|e| capabilities::map_error(&state, conn, Capability::Cors, e))?;
+        return Ok(());
+    }
+
+    let config = CorsConfiguration::builder()
+        .set_cors_rules(Some(s3_rules))
+        .build()
+        .map_err(|e| e.to_string())?;
+    client.put_bucket_cors()
+        .bucket(&bucket)
+        .cors_configuration(config)
+        .send()
+        .await
+        .map_err(|e| capabilities::map_error(&state, conn, Capability::Cors, e))?;
+
+    Ok(())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LifecycleRule {
+    pub id: String,
+    /// Empty prefix applies the rule to the whole bucket.
+    #[serde(default)]
+    pub prefix: String,
+    pub enabled: bool,
+    pub expiration_days: Option<i32>,
+    pub abort_incomplete_multipart_days: Option<i32>,
+}
+
+#[tauri::command]
+pub async fn get_bucket_lifecycle(
+    bucket: String,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<LifecycleRule>, AppError> {
+    let conn = connection_id.as_deref();
+    capabilities::require(&state, conn, Capability::Lifecycle)?;
+    let client = acquire_client(&state, conn)?;
+
+    let resp = match client.get_bucket_lifecycle_configuration().bucket(&bucket).send().await {
+        Ok(r) => r,
+        Err(e) => {
+            let code = e.as_service_error().and_then(|se| se.meta().code()).unwrap_or_default();
+            if code == "NoSuchLifecycleConfiguration" {
+                return Ok(Vec::new());
+            }
+            return Err(capabilities::map_error(&state, conn, Capability::Lifecycle, e));
+        }
+    };
+
+    Ok(resp
+        .rules()
+        .iter()
+        .map(|r| {
+            #[allow(deprecated)]
+            let legacy_prefix = r.prefix();
+            LifecycleRule {
+                id: r.id().unwrap_or_default().to_string(),
+                prefix: r.filter().and_then(|f| f.prefix()).or(legacy_prefix).unwrap_or_default().to_string(),
+                enabled: *r.status() == ExpirationStatus::Enabled,
+                expiration_days: r.expiration().and_then(|e| e.days()),
+                abort_incomplete_multipart_days: r
+                    .abort_incomplete_multipart_upload()
+                    .and_then(|a| a.days_after_initiation()),
+            }
+        })
+        .collect())
+}
+
+/// Replaces the bucket's lifecycle rules. An empty list removes the configuration.
+#[tauri::command]
+pub async fn put_bucket_lifecycle(
+    bucket: String,
+    rules: Vec<LifecycleRule>,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    let conn = connection_id.as_deref();
+    capabilities::require(&state, conn, Capability::Lifecycle)?;
+
+    let mut s3_rules = Vec::new();
+    for rule in &rules {
+        if rule.id.is_empty() {
+            return Err(AppError::new(ErrorKind::InvalidInput, "Each lifecycle rule needs an id"));
+        }
+        if rule.expiration_days.is_none() && rule.abort_incomplete_multipart_days.is_none() {
+            return Err(AppError::new(ErrorKind::InvalidInput, format!("Rule {} has no action", rule.id)));
+        }
+        if rule.expiration_days.is_some_and(|d| d < 1) || rule.abort_incomplete_multipart_days.is_some_and(|d| d < 1) {
+            return Err(AppError::new(ErrorKind::InvalidInput, format!("Rule {} must use at least 1 day", rule.id)));
+        }
+
+        let status = if rule.enabled { ExpirationStatus::Enabled } else { ExpirationStatus::Disabled };
+        s3_rules.push(
+            S3LifecycleRule::builder()
+                .id(&rule.id)
+                .filter(LifecycleRuleFilter::builder().prefix(&rule.prefix).build())
+                .status(status)
+                .set_expiration(rule.expiration_days.map(|d| LifecycleExpiration::builder().days(d).build()))
+                .set_abort_incomplete_multipart_upload(
+                    rule.abort_incomplete_multipart_days
+                        .map(|d| AbortIncompleteMultipartUpload::builder().days_after_initiation(d).build()),
+                )
+                .build()
+                .map_err(|e| e.to_string())?,
+        );
+    }
+
+    let client = acquire_write_client(&state, conn)?;
+
+    if s3_rules.is_empty() {
+        client.delete_bucket_lifecycle()
+            .bucket(&bucket)
+            .send()
+            .await
+            .map_err(|e| capabilities::map_error(&state, conn, Capability::Lifecycle, e))?;
+        return Ok(());
+    }
+
+    let config = BucketLifecycleConfiguration::builder()
+        .set_rules(Some(s3_rules))
+        .build()
+        .map_err(|e| e.to_string())?;
+    client.put_bucket_lifecycle_configuration()
+        .bucket(&bucket)
+        .lifecycle_configuration(config)
+        .send()
+        .await
+        .map_err(|e| capabilities::map_error(&state, conn, Capability::Lifecycle, e))?;
+
+    Ok(())
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BucketInfo {
+    pub name: String,
+    pub creation_date: Option<i64>,
+    pub location: Option<String>,
+    /// Default server-side encryption algorithm, e.g. "AES256".
+    pub encryption: Option<String>,
+    /// "Enabled", "Suspended" or "Disabled".
+    pub versioning: Option<String>,
+    pub object_lock: Option<bool>,
+    /// Settings that could not be read, with the reason, so the panel can explain gaps.
+    pub unavailable: Vec<String>,
+}
+
+/// Aggregates everything the "Bucket settings" panel shows. Individual lookups that fail or are
+/// unsupported by the provider are reported in `unavailable` rather than failing the whole call.
+#[tauri::command]
+pub async fn get_bucket_info(
+    bucket: String,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<BucketInfo, AppError> {
+    let conn = connection_id.as_deref();
+    let client = acquire_client(&state, conn)?;
+
+    let mut info = BucketInfo {
+        name: bucket.clone(),
+        creation_date: None,
+        location: None,
+        encryption: None,
+        versioning: None,
+        object_lock: None,
+        unavailable: Vec::new(),
+    };
+
+    let buckets = client.list_buckets().send().await?;
+    let listed = buckets.buckets().iter().find(|b| b.name() == Some(bucket.as_str()));
+    info.creation_date = listed.and_then(|b| b.creation_date()).map(|d| d.secs());
+
+    match client.get_bucket_location().bucket(&bucket).send().await {
+        Ok(r) => {
+            info.location = Some(r.location_constraint().map(|l| l.as_str().to_string()).unwrap_or_default());
+        }
+        Err(e) => info.unavailable.push(format!("location: {}", e)),
+    }
+
+    if capabilities::supports(&state, conn, Capability::Encryption) {
+        match client.get_bucket_encryption().bucket(&bucket).send().await {
+            Ok(r) => {
+                info.encryption = r
+                    .server_side_encryption_configuration()
+                    .and_then(|c| c.rules().first())
+                    .and_then(|rule| rule.apply_server_side_encryption_by_default())
+                    .map(|d| d.sse_algorithm().as_str().to_string());
+            }
+            Err(e) => {
+                let code = e.as_service_error().and_then(|se| se.meta().code()).unwrap_or_default();
+                if code != "ServerSideEncryptionConfigurationNotFoundError" {
+                    info.unavailable.push(format!("encryption: {}", capabilities::map_error(&state, conn, Capability::Encryption, e)));
+                }
+            }
+        }
+    } else {
+        info.unavailable.push("encryption: not supported by this provider".to_string());
+    }
+
+    if capabilities::supports(&state, conn, Capability::Versioning) {
+        match client.get_bucket_versioning().bucket(&bucket).send().await {
+            Ok(r) => {
+                info.versioning = Some(r.status().map(|s| s.as_str().to_string()).unwrap_or_else(|| "Disabled".to_string()));
+            }
+            Err(e) => info.unavailable.push(format!("versioning: {}", capabilities::map_error(&state, conn, Capability::Versioning, e))),
+        }
+    } else {
+        info.unavailable.push("versioning: not supported by this provider".to_string());
+    }
+
+    if capabilities::supports(&state, conn, Capability::ObjectLock) {
+        match client.get_object_lock_configuration().bucket(&bucket).send().await {
+            Ok(r) => {
+                info.object_lock = Some(
+                    r.object_lock_configuration()
+                        .and_then(|c| c.object_lock_enabled())
+                        .is_some_and(|s| s.as_str() == "Enabled"),
+                );
+            }
+            Err(e) => {
+                let code = e.as_service_error().and_then(|se| se.meta().code()).unwrap_or_default();
+                if code == "ObjectLockConfigurationNotFoundError" {
+                    info.object_lock = Some(false);
+                } else {
+                    info.unavailable.push(format!("object lock: {}", capabilities::map_error(&state, conn, Capability::ObjectLock, e)));
+                }
+            }
+        }
+    } else {
+        info.unavailable.push("object lock: not supported by this provider".to_string());
+    }
+
+    Ok(info)
+}
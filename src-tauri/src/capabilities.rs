@@ -0,0 +1,180 @@
+use aws_sdk_s3::error::{ProvideErrorMetadata, SdkError};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager, State};
+
+use crate::error::{AppError, ErrorKind};
+use crate::s3::{connection, connection_key, AppState};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Capability {
+    CreateBucket,
+    Checksums,
+    Tagging,
+    Versioning,
+    ObjectLock,
+    Lifecycle,
+    Cors,
+    Encryption,
+    ConditionalDeletes,
+}
+
+impl Capability {
+    const ALL: [Capability; 9] = [
+        Capability::CreateBucket,
+        Capability::Checksums,
+        Capability::Tagging,
+        Capability::Versioning,
+        Capability::ObjectLock,
+        Capability::Lifecycle,
+        Capability::Cors,
+        Capability::Encryption,
+        Capability::ConditionalDeletes,
+    ];
+
+    fn label(&self) -> &'static str {
+        match self {
+            Capability::CreateBucket => "Creating buckets",
+            Capability::Checksums => "Additional checksums",
+            Capability::Tagging => "Object tagging",
+            Capability::Versioning => "Versioning",
+            Capability::ObjectLock => "Object lock",
+            Capability::Lifecycle => "Lifecycle rules",
+            Capability::Cors => "CORS configuration",
+            Capability::Encryption => "Bucket encryption settings",
+            Capability::ConditionalDeletes => "Conditional deletes",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CapabilityMap {
+    pub provider: String,
+    pub endpoint: String,
+    pub supported: HashMap<Capability, bool>,
+}
+
+fn provider_for(endpoint: &str) -> &'static str {
+    if endpoint.contains(".r2.cloudflarestorage.com") {
+        "r2"
+    } else if endpoint.contains("amazonaws.com") {
+        "aws"
+    } else {
+        "s3-compatible"
+    }
+}
+
+/// Best-known defaults per provider. Unknown providers are assumed to support everything
+/// until a call proves otherwise.
+pub fn detect(endpoint: &str) -> CapabilityMap {
+    let provider = provider_for(endpoint);
+    let unsupported: &[Capability] = match provider {
+        "r2" => &[Capability::Tagging, Capability::Versioning, Capability::ObjectLock],
+        _ => &[],
+    };
+
+    CapabilityMap {
+        provider: provider.to_string(),
+        endpoint: endpoint.to_string(),
+        supported: Capability::ALL.iter().map(|c| (*c, !unsupported.contains(c))).collect(),
+    }
+}
+
+fn overrides_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = app.path().app_config_dir().map_err(|e| e.to_string())?;
+    Ok(dir.join("capabilities.json"))
+}
+
+fn load_overrides(app: &AppHandle) -> HashMap<String, HashMap<Capability, bool>> {
+    overrides_path(app)
+        .ok()
+        .and_then(|p| std::fs::read_to_string(p).ok())
+        .and_then(|raw| serde_json::from_str(&raw).ok())
+        .unwrap_or_default()
+}
+
+/// Detected defaults for `endpoint` with the user's saved overrides applied.
+pub fn resolve(app: &AppHandle, endpoint: &str) -> CapabilityMap {
+    let mut map = detect(endpoint);
+    if let Some(overrides) = load_overrides(app).get(endpoint) {
+        map.supported.extend(overrides);
+    }
+    map
+}
+
+fn unsupported_message(state: &AppState, id: Option<&str>, capability: Capability) -> String {
+    let provider = connection(state, id).map(|c| c.capabilities.provider).unwrap_or_default();
+    format!("{} is not supported by this provider ({})", capability.label(), provider)
+}
+
+pub fn supports(state: &AppState, id: Option<&str>, capability: Capability) -> bool {
+    connection(state, id)
+        .ok()
+        .and_then(|c| c.capabilities.supported.get(&capability).copied())
+        .unwrap_or(true)
+}
+
+/// Fails fast with a clear message when the connection's provider is known not to support
+/// `capability`.
+pub fn require(state: &AppState, id: Option<&str>, capability: Capability) -> Result<(), AppError> {
+    if supports(state, id, capability) {
+        Ok(())
+    } else {
+        Err(AppError::new(ErrorKind::Unsupported, unsupported_message(state, id, capability)))
+    }
+}
+
+/// Maps an SDK error to an `AppError`, turning NotImplemented/501 into a "not supported" error and
+/// remembering it for the rest of the session.
+pub fn map_error<E: ProvideErrorMetadata + std::error::Error + 'static>(
+    state: &AppState,
+    id: Option<&str>,
+    capability: Capability,
+    err: SdkError<E>,
+) -> AppError {
+    let status = err.raw_response().map(|r| r.status().as_u16());
+    let code = err.as_service_error().and_then(|e| e.code()).unwrap_or_default();
+    if status == Some(501) || code == "NotImplemented" {
+        if let Some(c) = state.connections.lock().unwrap().get_mut(connection_key(id)) {
+            c.capabilities.supported.insert(capability, false);
+        }
+        return AppError::new(ErrorKind::Unsupported, unsupported_message(state, id, capability));
+    }
+    err.into()
+}
+
+#[tauri::command]
+pub fn get_capabilities(connection_id: Option<String>, state: State<'_, AppState>) -> CapabilityMap {
+    connection(&state, connection_id.as_deref()).map(|c| c.capabilities).unwrap_or_default()
+}
+
+/// Overrides detection for the connection's endpoint and persists the choice.
+#[tauri::command]
+pub fn set_capability(
+    capability: Capability,
+    supported: bool,
+    connection_id: Option<String>,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<CapabilityMap, AppError> {
+    let endpoint = connection(&state, connection_id.as_deref())?.endpoint;
+
+    let mut overrides = load_overrides(&app);
+    overrides.entry(endpoint.clone()).or_default().insert(capability, supported);
+
+    let path = overrides_path(&app)?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let raw = serde_json::to_string_pretty(&overrides).map_err(|e| e.to_string())?;
+    std::fs::write(path, raw).map_err(|e| e.to_string())?;
+
+    // Every open connection to the same endpoint shares the override
+    let mut connections = state.connections.lock().unwrap();
+    for c in connections.values_mut().filter(|c| c.endpoint == endpoint) {
+        c.capabilities.supported.insert(capability, supported);
+    }
+    Ok(connections.get(connection_key(connection_id.as_deref())).map(|c| c.capabilities.clone()).unwrap_or_default())
+}
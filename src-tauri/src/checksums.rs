@@ -0,0 +1,256 @@
+use aws_sdk_s3::primitives::ByteStream;
+use aws_sdk_s3::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tauri::{AppHandle, Emitter, State};
+use tokio::task::JoinSet;
+
+use crate::error::{AppError, ErrorKind};
+use crate::hashing;
+use crate::jobs;
+use crate::s3::{acquire_client, acquire_write_client, is_folder_marker, list_all_objects, AppState};
+use crate::sync::normalize_prefix;
+use crate::verify::remote_sha256;
+
+// SHA256SUMS-style manifests, in the format `sha256sum` writes and `sha256sum -c` checks: one
+// `<hex digest>  <path>` line per object, with paths relative to the folder the manifest sits in.
+pub const DEFAULT_MANIFEST_NAME: &str = "SHA256SUMS";
+const HASH_CONCURRENCY: usize = 4;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ChecksumSource {
+    /// Stream every object.
+    #[default]
+    Remote,
+    /// Hash the local folder the prefix was uploaded from, which is far cheaper. Only files whose
+    /// size matches their object are taken from disk; the rest are streamed.
+    Local { root: String },
+}
+
+/// Payload of `checksums://progress`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ChecksumProgress {
+    pub bucket: String,
+    pub done: usize,
+    pub total: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ChecksumFailure {
+    pub path: String,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ManifestResult {
+    pub manifest_key: String,
+    pub files: usize,
+    /// Hashed from the local folder rather than streamed.
+    pub from_local: usize,
+    /// Left out of the manifest because they couldn't be read.
+    pub failed: Vec<ChecksumFailure>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ManifestVerification {
+    pub ok: usize,
+    pub mismatched: Vec<String>,
+    /// Listed in the manifest but not in the bucket.
+    pub missing: Vec<String>,
+    /// In the bucket but not in the manifest.
+    pub unlisted: Vec<String>,
+    pub failed: Vec<ChecksumFailure>,
+}
+
+/// One manifest line. Paths with a newline or backslash are escaped and the line marked with a
+/// leading backslash, as GNU coreutils does.
+fn manifest_line(digest: &str, path: &str) -> String {
+    if path.contains('\n') || path.contains('\\') {
+        format!("\\{}  {}\n", digest, path.replace('\\', "\\\\").replace('\n', "\\n"))
+    } else {
+        format!("{}  {}\n", digest, path)
+    }
+}
+
+/// Parses a manifest into path -> lowercase digest. `*` binary markers are accepted; blank lines
+/// and comments are skipped.
+pub fn parse_manifest(text: &str) -> Result<BTreeMap<String, String>, String> {
+    let mut entries = BTreeMap::new();
+    for (number, line) in text.lines().enumerate() {
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (escaped, line) = match line.strip_prefix('\\') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let (digest, path) = line
+            .split_once(' ')
+            .filter(|(d, _)| d.len() == 64 && d.chars().all(|c| c.is_ascii_hexdigit()))
+            .ok_or_else(|| format!("Line {} isn't a SHA-256 checksum line", number + 1))?;
+        let path = path.strip_prefix(' ').or_else(|| path.strip_prefix('*')).unwrap_or(path);
+        let path = if escaped { path.replace("\\n", "\n").replace("\\\\", "\\") } else { path.to_string() };
+        entries.insert(path, digest.to_ascii_lowercase());
+    }
+    Ok(entries)
+}
+
+/// Streams each key through SHA-256 with bounded concurrency. Returns (key, digest or error)
+/// pairs in completion order.
+async fn hash_objects(app: &AppHandle, client: &Client, bucket: &str, keys: Vec<String>) -> Vec<(String, Result<String, String>)> {
+    let keys = Arc::new(keys);
+    let mut progress = ChecksumProgress { bucket: bucket.to_string(), done: 0, total: keys.len() };
+    let _ = app.emit("checksums://progress", &progress);
+
+    let mut results = Vec::with_capacity(keys.len());
+    let mut finish = |progress: &mut ChecksumProgress, index: usize, digest: Result<String, String>| {
+        results.push((keys[index].clone(), digest));
+        progress.done += 1;
+        let _ = app.emit("checksums://progress", &*progress);
+    };
+
+    let mut tasks: JoinSet<(usize, Result<String, String>)> = JoinSet::new();
+    for index in 0..keys.len() {
+        while tasks.len() >= HASH_CONCURRENCY {
+            if let Some(Ok((index, digest))) = tasks.join_next().await {
+                finish(&mut progress, index, digest);
+            }
+        }
+        let (client, bucket, keys) = (client.clone(), bucket.to_string(), keys.clone());
+        tasks.spawn(async move { (index, remote_sha256(&client, &bucket, &keys[index]).await) });
+    }
+    while let Some(joined) = tasks.join_next().await {
+        if let Ok((index, digest)) = joined {
+            finish(&mut progress, index, digest);
+        }
+    }
+    results
+}
+
+/// Objects under `prefix` by path relative to it, leaving out folder markers and `exclude`.
+async fn list_relative(client: &Client, bucket: &str, prefix: &str, exclude: &str) -> Result<HashMap<String, i64>, String> {
+    Ok(list_all_objects(client, bucket, Some(prefix))
+        .await?
+        .into_iter()
+        .filter(|o| !is_folder_marker(o.key().unwrap_or_default(), o.size().unwrap_or(0)))
+        .filter(|o| o.key() != Some(exclude))
+        .filter_map(|o| Some((o.key()?.strip_prefix(prefix)?.to_string(), o.size().unwrap_or(0))))
+        .collect())
+}
+
+/// Hashes every object under `prefix` and uploads the result as `<prefix><name>`, replacing any
+/// manifest already there.
+#[tauri::command]
+pub async fn create_checksum_manifest(
+    bucket: String,
+    prefix: String,
+    name: Option<String>,
+    source: Option<ChecksumSource>,
+    app: AppHandle,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<ManifestResult, AppError> {
+    let prefix = normalize_prefix(&prefix);
+    let manifest_key = format!("{}{}", prefix, name.filter(|n| !n.trim().is_empty()).unwrap_or_else(|| DEFAULT_MANIFEST_NAME.to_string()));
+    let source = source.unwrap_or_default();
+    let job = jobs::start("checksum_manifest", Some(&bucket), json!({ "manifest_key": manifest_key }));
+    let result: Result<ManifestResult, AppError> = async {
+        let client = acquire_write_client(&state, connection_id.as_deref())?;
+        let objects = list_relative(&client, &bucket, &prefix, &manifest_key).await?;
+
+        let mut digests = BTreeMap::new();
+        if let ChecksumSource::Local { root } = &source {
+            let root = PathBuf::from(root);
+            let files: Vec<PathBuf> = objects.keys().map(|relative| root.join(relative)).filter(|p| p.is_file()).collect();
+            let hash_app = app.clone();
+            let hashes = tauri::async_runtime::spawn_blocking(move || hashing::hash_files(&hash_app, &root, &files))
+                .await
+                .map_err(|e| e.to_string())??;
+            for hash in hashes {
+                if objects.get(&hash.relative_path).is_some_and(|size| *size as u64 == hash.size) {
+                    digests.insert(hash.relative_path, hash.sha256);
+                }
+            }
+        }
+        let from_local = digests.len();
+
+        let remaining: Vec<String> = objects.keys().filter(|k| !digests.contains_key(*k)).map(|k| format!("{}{}", prefix, k)).collect();
+        let mut failed = Vec::new();
+        for (key, digest) in hash_objects(&app, &client, &bucket, remaining).await {
+            let path = key[prefix.len()..].to_string();
+            match digest {
+                Ok(digest) => {
+                    digests.insert(path, digest);
+                }
+                Err(error) => failed.push(ChecksumFailure { path, error }),
+            }
+        }
+
+        let manifest: String = digests.iter().map(|(path, digest)| manifest_line(digest, path)).collect();
+        client.put_object()
+            .bucket(&bucket)
+            .key(&manifest_key)
+            .content_type("text/plain; charset=utf-8")
+            .body(ByteStream::from(manifest.into_bytes()))
+            .send()
+            .await?;
+
+        Ok(ManifestResult { manifest_key: manifest_key.clone(), files: digests.len(), from_local, failed })
+    }
+    .await;
+    job.finish(&app, &result, |r| format!("Wrote {} with {} checksums", r.manifest_key, r.files));
+
+    result
+}
+
+/// Checks every object a manifest lists against its digest, and reports objects next to the
+/// manifest that it doesn't list.
+#[tauri::command]
+pub async fn verify_checksum_manifest(
+    bucket: String,
+    manifest_key: String,
+    app: AppHandle,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<ManifestVerification, AppError> {
+    let job = jobs::start("verify_checksum_manifest", Some(&bucket), json!({ "manifest_key": manifest_key }));
+    let result: Result<ManifestVerification, AppError> = async {
+        let client = acquire_client(&state, connection_id.as_deref())?;
+        let resp = client.get_object().bucket(&bucket).key(&manifest_key).send().await?;
+        let raw = resp.body.collect().await.map_err(|e| e.to_string())?.into_bytes();
+        let text = String::from_utf8(raw.to_vec()).map_err(|_| AppError::new(ErrorKind::InvalidInput, "Manifest isn't UTF-8 text"))?;
+        let expected = parse_manifest(&text).map_err(|e| AppError::new(ErrorKind::InvalidInput, e))?;
+
+        let base = manifest_key.rsplit_once('/').map(|(folder, _)| format!("{}/", folder)).unwrap_or_default();
+        let objects = list_relative(&client, &bucket, &base, &manifest_key).await?;
+
+        let mut report = ManifestVerification {
+            missing: expected.keys().filter(|p| !objects.contains_key(*p)).cloned().collect(),
+            unlisted: objects.keys().filter(|p| !expected.contains_key(*p)).cloned().collect(),
+            ..Default::default()
+        };
+        report.unlisted.sort();
+
+        let keys = expected.keys().filter(|p| objects.contains_key(*p)).map(|p| format!("{}{}", base, p)).collect();
+        for (key, digest) in hash_objects(&app, &client, &bucket, keys).await {
+            let path = key[base.len()..].to_string();
+            match digest {
+                Ok(digest) if digest == expected[&path] => report.ok += 1,
+                Ok(_) => report.mismatched.push(path),
+                Err(error) => report.failed.push(ChecksumFailure { path, error }),
+            }
+        }
+        report.mismatched.sort();
+        Ok(report)
+    }
+    .await;
+    job.finish(&app, &result, |r| {
+        format!("{} OK, {} mismatched, {} missing, {} failed", r.ok, r.mismatched.len(), r.missing.len(), r.failed.len())
+    });
+
+    result
+}
@@ -0,0 +1,140 @@
+use aws_sdk_s3::Client;
+use image::{ImageFormat, RgbaImage};
+use serde::Serialize;
+use serde_json::json;
+use std::io::Cursor;
+use std::path::PathBuf;
+use tauri::{AppHandle, State};
+
+use crate::encryption::TempFile;
+use crate::error::{AppError, ErrorKind};
+use crate::jobs;
+use crate::s3::{acquire_write_client, upload_path, wait_if_paused, AppState};
+use crate::shell_menu;
+use crate::sync::normalize_prefix;
+
+// Pasting into a folder: an image on the clipboard (a screenshot, say) is uploaded as a PNG
+// named after the time, copied files and folders under their own names. Nothing already in the
+// bucket is overwritten; a taken name gets " (2)", " (3)" and so on.
+const MAX_SUFFIX: u32 = 1000;
+
+enum Contents {
+    Image(Vec<u8>),
+    Files(Vec<PathBuf>),
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PastedKind {
+    Image,
+    Files,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PastedUpload {
+    pub kind: PastedKind,
+    /// Keys written, in clipboard order. For an image, the one key to share.
+    pub keys: Vec<String>,
+}
+
+/// Files take precedence: copying a file in some file managers also puts its icon on the
+/// clipboard as an image.
+fn read_clipboard() -> Result<Contents, AppError> {
+    let mut clipboard = arboard::Clipboard::new().map_err(|e| AppError::new(ErrorKind::Unsupported, format!("Cannot open the clipboard: {}", e)))?;
+    if let Ok(files) = clipboard.get().file_list() {
+        if !files.is_empty() {
+            return Ok(Contents::Files(files));
+        }
+    }
+    let image = clipboard.get_image().map_err(|_| AppError::new(ErrorKind::InvalidInput, "The clipboard holds no image or files"))?;
+    let rgba = RgbaImage::from_raw(image.width as u32, image.height as u32, image.bytes.into_owned())
+        .ok_or("The clipboard image has an unexpected size")?;
+    let mut png = Vec::new();
+    rgba.write_to(&mut Cursor::new(&mut png), ImageFormat::Png).map_err(|e| e.to_string())?;
+    Ok(Contents::Image(png))
+}
+
+async fn taken(client: &Client, bucket: &str, key: &str, folder: bool) -> Result<bool, AppError> {
+    if folder {
+        let resp = client.list_objects_v2().bucket(bucket).prefix(format!("{}/", key)).max_keys(1).send().await?;
+        return Ok(resp.key_count().unwrap_or(0) > 0);
+    }
+    match client.head_object().bucket(bucket).key(key).send().await {
+        Ok(_) => Ok(true),
+        Err(e) if e.as_service_error().is_some_and(|se| se.is_not_found()) => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// `name`, or the first "name (n).ext" after it that isn't taken under `prefix`.
+async fn free_name(client: &Client, bucket: &str, prefix: &str, name: &str, folder: bool) -> Result<String, AppError> {
+    let (stem, ext) = match name.rfind('.') {
+        Some(dot) if dot > 0 && !folder => (&name[..dot], &name[dot..]),
+        _ => (name, ""),
+    };
+    for n in 1..=MAX_SUFFIX {
+        let candidate = if n == 1 { name.to_string() } else { format!("{} ({}){}", stem, n, ext) };
+        if !taken(client, bucket, &format!("{}{}", prefix, candidate), folder).await? {
+            return Ok(candidate);
+        }
+    }
+    Err(AppError::new(ErrorKind::AlreadyExists, format!("Too many objects named like {}{}", prefix, name)))
+}
+
+/// Uploads what's on the clipboard to `prefix` and returns the new keys.
+#[tauri::command]
+pub async fn paste_to_bucket(
+    bucket: String,
+    prefix: Option<String>,
+    app: AppHandle,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<PastedUpload, AppError> {
+    let prefix = normalize_prefix(prefix.as_deref().unwrap_or_default());
+    // Reading can wait on the clipboard owner (X11 in particular)
+    let contents = tauri::async_runtime::spawn_blocking(read_clipboard).await.map_err(|e| e.to_string())??;
+
+    let job = jobs::start("clipboard_upload", Some(&bucket), json!({ "prefix": prefix }));
+    let result: Result<PastedUpload, AppError> = async {
+        wait_if_paused(&state).await;
+        let client = acquire_write_client(&state, connection_id.as_deref())?;
+        match contents {
+            Contents::Image(png) => {
+                let name = format!("Pasted image {}.png", chrono::Local::now().format("%Y-%m-%d %H%M%S"));
+                let key = format!("{}{}", prefix, free_name(&client, &bucket, &prefix, &name, false).await?);
+                // Spooled to a file so it goes through the same upload, encryption included
+                let spool = TempFile::new("png");
+                tokio::fs::write(&spool.0, png).await.map_err(|e| e.to_string())?;
+                upload_path(&app, &client, &bucket, &key, &spool.0).await?;
+                Ok(PastedUpload { kind: PastedKind::Image, keys: vec![key] })
+            }
+            Contents::Files(paths) => {
+                let mut keys = Vec::new();
+                for path in paths {
+                    let Some(name) = path.file_name().map(|n| n.to_string_lossy().to_string()) else { continue };
+                    let folder = path.is_dir();
+                    let free = free_name(&client, &bucket, &prefix, &name, folder).await?;
+                    // A folder is renamed as a whole, so the files in it keep their layout
+                    let (from, to) = (format!("{}{}", prefix, name), format!("{}{}", prefix, free));
+                    for (file, key) in shell_menu::collect(&path, &prefix) {
+                        wait_if_paused(&state).await;
+                        let key = format!("{}{}", to, &key[from.len()..]);
+                        upload_path(&app, &client, &bucket, &key, &file).await?;
+                        keys.push(key);
+                    }
+                }
+                if keys.is_empty() {
+                    return Err(AppError::new(ErrorKind::InvalidInput, "The copied files are empty folders or gone"));
+                }
+                Ok(PastedUpload { kind: PastedKind::Files, keys })
+            }
+        }
+    }
+    .await;
+    job.finish(&app, &result, |pasted| match pasted.kind {
+        PastedKind::Image => format!("Pasted {}", pasted.keys[0]),
+        PastedKind::Files => format!("Pasted {} files", pasted.keys.len()),
+    });
+
+    result
+}
@@ -0,0 +1,504 @@
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashSet;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+use urlencoding::encode;
+
+use crate::error::{AppError, ErrorKind};
+use crate::notifications;
+use crate::profiles;
+use crate::s3::{connection, connection_key, AppState};
+use crate::shares;
+
+// Optional: only used when the user supplies a Cloudflare API token. Covers what the S3 API
+// can't do — jurisdictions, account-level usage and public access.
+const API_BASE: &str = "https://api.cloudflare.com/client/v4";
+const JURISDICTIONS: &[&str] = &["default", "eu", "fedramp"];
+/// How often usage is checked against the notification limits.
+const USAGE_TICK: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Default)]
+pub struct CloudflareState {
+    http: reqwest::Client,
+    // (account_id, api_token)
+    session: Mutex<Option<(String, String)>>,
+    /// Usage limits already warned about.
+    over_limit: Mutex<HashSet<&'static str>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiMessage {
+    code: i64,
+    message: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Envelope<T> {
+    success: bool,
+    #[serde(default)]
+    errors: Vec<ApiMessage>,
+    result: Option<T>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CloudflareBucket {
+    pub name: String,
+    pub creation_date: Option<String>,
+    pub location: Option<String>,
+    pub jurisdiction: String,
+    pub storage_class: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BucketUsage {
+    pub bucket: String,
+    pub jurisdiction: String,
+    pub payload_size: u64,
+    pub metadata_size: u64,
+    pub object_count: u64,
+    pub upload_count: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AccountUsage {
+    pub buckets: Vec<BucketUsage>,
+    pub total_payload_size: u64,
+    pub total_object_count: u64,
+}
+
+impl CloudflareState {
+    fn session(&self) -> Result<(String, String), String> {
+        self.session.lock().unwrap().clone().ok_or_else(|| "Cloudflare API token not set".to_string())
+    }
+
+    /// Sends a request and unwraps the `{ success, errors, result }` envelope.
+    async fn call<T: DeserializeOwned>(&self, request: reqwest::RequestBuilder, token: &str) -> Result<T, String> {
+        let resp = request.bearer_auth(token).send().await.map_err(|e| e.to_string())?;
+        let status = resp.status();
+        let envelope: Envelope<T> = resp
+            .json()
+            .await
+            .map_err(|e| format!("Unexpected response from Cloudflare ({}): {}", status, e))?;
+
+        if !envelope.success {
+            let messages: Vec<String> = envelope.errors.iter().map(|e| format!("{} ({})", e.message, e.code)).collect();
+            return Err(format!("Cloudflare API error: {}", messages.join("; ")));
+        }
+        envelope.result.ok_or_else(|| "Cloudflare returned no result".to_string())
+    }
+
+    async fn list_buckets(&self, jurisdiction: &str) -> Result<Vec<CloudflareBucket>, String> {
+        #[derive(Deserialize)]
+        struct Listed {
+            name: String,
+            creation_date: Option<String>,
+            location: Option<String>,
+            storage_class: Option<String>,
+        }
+        #[derive(Deserialize)]
+        struct Listing {
+            buckets: Vec<Listed>,
+        }
+
+        let (account_id, token) = self.session()?;
+        let request = self.http
+            .get(format!("{}/accounts/{}/r2/buckets", API_BASE, account_id))
+            .header("cf-r2-jurisdiction", jurisdiction);
+        let listing: Listing = self.call(request, &token).await?;
+
+        Ok(listing.buckets.into_iter().map(|b| CloudflareBucket {
+            name: b.name,
+            creation_date: b.creation_date,
+            location: b.location,
+            jurisdiction: jurisdiction.to_string(),
+            storage_class: b.storage_class,
+        }).collect())
+    }
+
+    async fn usage(&self) -> Result<AccountUsage, String> {
+        let (account_id, token) = self.session()?;
+        let mut usage = AccountUsage { buckets: Vec::new(), total_payload_size: 0, total_object_count: 0 };
+
+        for jurisdiction in JURISDICTIONS {
+            for bucket in self.list_buckets(jurisdiction).await? {
+                let request = self.http
+                    .get(format!("{}/accounts/{}/r2/buckets/{}/usage", API_BASE, account_id, bucket.name))
+                    .header("cf-r2-jurisdiction", *jurisdiction);
+                let raw: serde_json::Value = self.call(request, &token).await?;
+
+                let entry = BucketUsage {
+                    bucket: bucket.name,
+                    jurisdiction: jurisdiction.to_string(),
+                    payload_size: counter(&raw, "payloadSize"),
+                    metadata_size: counter(&raw, "metadataSize"),
+                    object_count: counter(&raw, "objectCount"),
+                    upload_count: counter(&raw, "uploadCount"),
+                };
+                usage.total_payload_size += entry.payload_size;
+                usage.total_object_count += entry.object_count;
+                usage.buckets.push(entry);
+            }
+        }
+
+        Ok(usage)
+    }
+}
+
+fn check_jurisdiction(jurisdiction: Option<String>) -> Result<String, String> {
+    let jurisdiction = jurisdiction.unwrap_or_else(|| "default".to_string()).to_lowercase();
+    if !JURISDICTIONS.contains(&jurisdiction.as_str()) {
+        return Err(format!("Unknown jurisdiction '{}'. Expected one of: {}", jurisdiction, JURISDICTIONS.join(", ")));
+    }
+    Ok(jurisdiction)
+}
+
+// The usage endpoint reports counters as strings
+fn counter(value: &serde_json::Value, field: &str) -> u64 {
+    match value.get(field) {
+        Some(serde_json::Value::String(s)) => s.parse().unwrap_or(0),
+        Some(v) => v.as_u64().unwrap_or(0),
+        None => 0,
+    }
+}
+
+/// Verifies and stores an API token (kept in memory only). The account id defaults to the one
+/// used by the given S3 connection.
+#[tauri::command]
+pub async fn set_cloudflare_token(
+    token: String,
+    account_id: Option<String>,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+    cloudflare: State<'_, CloudflareState>,
+) -> Result<(), AppError> {
+    let account_id = match account_id.filter(|a| !a.is_empty()) {
+        Some(a) => a,
+        None => connection(&state, connection_id.as_deref())
+            .map(|c| c.credentials.account_id)
+            .map_err(|_| "No account id given and no active connection to take it from".to_string())?,
+    };
+
+    let request = cloudflare.http.get(format!("{}/user/tokens/verify", API_BASE));
+    let _: serde_json::Value = cloudflare.call(request, &token).await?;
+
+    *cloudflare.session.lock().unwrap() = Some((account_id, token));
+    Ok(())
+}
+
+#[tauri::command]
+pub fn clear_cloudflare_token(cloudflare: State<'_, CloudflareState>) {
+    *cloudflare.session.lock().unwrap() = None;
+}
+
+/// Every R2 bucket in the account, across all jurisdictions.
+#[tauri::command]
+pub async fn cloudflare_list_buckets(cloudflare: State<'_, CloudflareState>) -> Result<Vec<CloudflareBucket>, AppError> {
+    let mut buckets = Vec::new();
+    for jurisdiction in JURISDICTIONS {
+        buckets.extend(cloudflare.list_buckets(jurisdiction).await?);
+    }
+    Ok(buckets)
+}
+
+#[tauri::command]
+pub async fn cloudflare_create_bucket(
+    name: String,
+    jurisdiction: Option<String>,
+    location_hint: Option<String>,
+    storage_class: Option<String>,
+    cloudflare: State<'_, CloudflareState>,
+) -> Result<CloudflareBucket, AppError> {
+    let jurisdiction = check_jurisdiction(jurisdiction)?;
+    let (account_id, token) = cloudflare.session()?;
+
+    let mut body = json!({ "name": name });
+    if let Some(hint) = location_hint.filter(|h| !h.is_empty()) {
+        body["locationHint"] = json!(hint);
+    }
+    if let Some(class) = storage_class.filter(|c| !c.is_empty()) {
+        body["storageClass"] = json!(class);
+    }
+
+    let request = cloudflare.http
+        .post(format!("{}/accounts/{}/r2/buckets", API_BASE, account_id))
+        .header("cf-r2-jurisdiction", &jurisdiction)
+        .json(&body);
+    let created: serde_json::Value = cloudflare.call(request, &token).await?;
+
+    let field = |f: &str| created.get(f).and_then(|v| v.as_str()).map(|s| s.to_string());
+    Ok(CloudflareBucket {
+        name: field("name").unwrap_or(name),
+        creation_date: field("creation_date"),
+        location: field("location"),
+        jurisdiction,
+        storage_class: field("storage_class"),
+    })
+}
+
+#[tauri::command]
+pub async fn cloudflare_delete_bucket(
+    name: String,
+    jurisdiction: Option<String>,
+    cloudflare: State<'_, CloudflareState>,
+) -> Result<(), AppError> {
+    let jurisdiction = check_jurisdiction(jurisdiction)?;
+    let (account_id, token) = cloudflare.session()?;
+
+    let request = cloudflare.http
+        .delete(format!("{}/accounts/{}/r2/buckets/{}", API_BASE, account_id, name))
+        .header("cf-r2-jurisdiction", &jurisdiction);
+    let _: serde_json::Value = cloudflare.call(request, &token).await?;
+
+    Ok(())
+}
+
+/// Stored bytes and object counts as billed by Cloudflare, per bucket and for the whole account.
+/// Warns when the totals go over the limits in the notification settings.
+#[tauri::command]
+pub async fn cloudflare_get_usage(app: AppHandle, cloudflare: State<'_, CloudflareState>) -> Result<AccountUsage, AppError> {
+    let usage = cloudflare.usage().await?;
+    check_limits(&app, &cloudflare, &usage);
+    Ok(usage)
+}
+
+/// Warns once when account usage goes over a limit, and again only after it has dropped back
+/// under it.
+fn check_limits(app: &AppHandle, cloudflare: &CloudflareState, usage: &AccountUsage) {
+    let settings = app.state::<AppState>().settings.lock().unwrap().notifications.clone();
+    let checks = [
+        ("storage", settings.storage_limit, usage.total_payload_size),
+        ("objects", settings.object_limit, usage.total_object_count),
+    ];
+
+    let mut over = cloudflare.over_limit.lock().unwrap();
+    for (name, limit, used) in checks {
+        let Some(limit) = limit.filter(|limit| used > *limit) else {
+            over.remove(name);
+            continue;
+        };
+        if !over.insert(name) {
+            continue;
+        }
+        let body = match name {
+            "storage" => format!("R2 storage is at {:.1} GB, over the {:.1} GB limit", used as f64 / 1e9, limit as f64 / 1e9),
+            _ => format!("R2 holds {} objects, over the limit of {}", used, limit),
+        };
+        notifications::warning(app, "Usage limit reached", &body);
+    }
+}
+
+/// Background task started at launch. Checks account usage against the limits while an API
+/// token is set and a limit is configured.
+pub async fn watch_usage(app: AppHandle) {
+    loop {
+        tokio::time::sleep(USAGE_TICK).await;
+
+        let settings = app.state::<AppState>().settings.lock().unwrap().notifications.clone();
+        if settings.storage_limit.is_none() && settings.object_limit.is_none() {
+            continue;
+        }
+        let cloudflare = app.state::<CloudflareState>();
+        if cloudflare.session().is_err() {
+            continue;
+        }
+        // A failed check is retried on the next tick
+        if let Ok(usage) = cloudflare.usage().await {
+            check_limits(&app, &cloudflare, &usage);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CustomDomain {
+    pub domain: String,
+    pub enabled: bool,
+    pub zone_id: Option<String>,
+    pub ownership_status: Option<String>,
+    pub ssl_status: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PublicAccess {
+    pub bucket: String,
+    pub r2_dev_enabled: bool,
+    pub r2_dev_domain: Option<String>,
+    pub custom_domains: Vec<CustomDomain>,
+    /// Base URL for public object links: the first active custom domain, else the r2.dev URL.
+    pub public_base_url: Option<String>,
+}
+
+fn domains_url(account_id: &str, bucket: &str) -> String {
+    format!("{}/accounts/{}/r2/buckets/{}/domains", API_BASE, account_id, bucket)
+}
+
+async fn load_public_access(cloudflare: &CloudflareState, bucket: &str, jurisdiction: &str) -> Result<PublicAccess, String> {
+    #[derive(Deserialize)]
+    struct Managed {
+        domain: Option<String>,
+        enabled: bool,
+    }
+    #[derive(Deserialize, Default)]
+    struct Status {
+        ownership: Option<String>,
+        ssl: Option<String>,
+    }
+    #[derive(Deserialize)]
+    struct Custom {
+        domain: String,
+        enabled: bool,
+        #[serde(rename = "zoneId")]
+        zone_id: Option<String>,
+        #[serde(default)]
+        status: Status,
+    }
+    #[derive(Deserialize)]
+    struct CustomList {
+        domains: Vec<Custom>,
+    }
+
+    let (account_id, token) = cloudflare.session()?;
+    let base = domains_url(&account_id, bucket);
+
+    let managed: Managed = cloudflare
+        .call(cloudflare.http.get(format!("{}/managed", base)).header("cf-r2-jurisdiction", jurisdiction), &token)
+        .await?;
+    let custom: CustomList = cloudflare
+        .call(cloudflare.http.get(format!("{}/custom", base)).header("cf-r2-jurisdiction", jurisdiction), &token)
+        .await?;
+
+    let custom_domains: Vec<CustomDomain> = custom.domains.into_iter().map(|d| CustomDomain {
+        domain: d.domain,
+        enabled: d.enabled,
+        zone_id: d.zone_id,
+        ownership_status: d.status.ownership,
+        ssl_status: d.status.ssl,
+    }).collect();
+
+    let active_custom = custom_domains.iter().find(|d| d.enabled && d.ownership_status.as_deref() == Some("active"));
+    let public_base_url = match (active_custom, managed.enabled, &managed.domain) {
+        (Some(d), _, _) => Some(format!("https://{}", d.domain)),
+        (None, true, Some(domain)) => Some(format!("https://{}", domain)),
+        _ => None,
+    };
+
+    Ok(PublicAccess {
+        bucket: bucket.to_string(),
+        r2_dev_enabled: managed.enabled,
+        r2_dev_domain: managed.domain,
+        custom_domains,
+        public_base_url,
+    })
+}
+
+#[tauri::command]
+pub async fn cloudflare_get_public_access(
+    bucket: String,
+    jurisdiction: Option<String>,
+    cloudflare: State<'_, CloudflareState>,
+) -> Result<PublicAccess, AppError> {
+    let jurisdiction = check_jurisdiction(jurisdiction)?;
+    Ok(load_public_access(&cloudflare, &bucket, &jurisdiction).await?)
+}
+
+// R2 endpoints carry the jurisdiction as a subdomain: <account>.eu.r2.cloudflarestorage.com
+fn jurisdiction_of(endpoint: &str) -> Option<&'static str> {
+    let host = endpoint.split("://").last().unwrap_or(endpoint).trim_end_matches('/');
+    let rest = host.strip_suffix(".r2.cloudflarestorage.com")?;
+    Some(match rest.rsplit('.').next() {
+        Some("eu") => "eu",
+        Some("fedramp") => "fedramp",
+        _ => "default",
+    })
+}
+
+/// The permanent public link for `key`: from the base URL set on the connection's profile, else
+/// from the bucket's public access when a Cloudflare token is set. Fails when the bucket isn't
+/// public. Recorded as a share without an expiry.
+#[tauri::command]
+pub async fn get_public_url(
+    bucket: String,
+    key: String,
+    app: AppHandle,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+    cloudflare: State<'_, CloudflareState>,
+) -> Result<String, AppError> {
+    let id = connection_key(connection_id.as_deref());
+    let mut base = profiles::public_url_for(&app, id, &bucket);
+    if base.is_none() && cloudflare.session().is_ok() {
+        if let Some(jurisdiction) = jurisdiction_of(&connection(&state, Some(id))?.endpoint) {
+            base = load_public_access(&cloudflare, &bucket, jurisdiction).await?.public_base_url;
+        }
+    }
+    let base = base.ok_or_else(|| {
+        AppError::new(ErrorKind::NotFound, format!("Bucket '{}' has no public URL configured", bucket))
+    })?;
+
+    let path: Vec<String> = key.split('/').map(|segment| encode(segment).into_owned()).collect();
+    let url = format!("{}/{}", base.trim_end_matches('/'), path.join("/"));
+    shares::record(&app, &bucket, &key, &url, "GET", None);
+    Ok(url)
+}
+
+/// Turns the bucket's `*.r2.dev` public URL on or off.
+#[tauri::command]
+pub async fn cloudflare_set_r2_dev(
+    bucket: String,
+    enabled: bool,
+    jurisdiction: Option<String>,
+    cloudflare: State<'_, CloudflareState>,
+) -> Result<PublicAccess, AppError> {
+    let jurisdiction = check_jurisdiction(jurisdiction)?;
+    let (account_id, token) = cloudflare.session()?;
+
+    let request = cloudflare.http
+        .put(format!("{}/managed", domains_url(&account_id, &bucket)))
+        .header("cf-r2-jurisdiction", &jurisdiction)
+        .json(&json!({ "enabled": enabled }));
+    let _: serde_json::Value = cloudflare.call(request, &token).await?;
+
+    Ok(load_public_access(&cloudflare, &bucket, &jurisdiction).await?)
+}
+
+/// Attaches a domain from one of the account's zones. It only serves traffic once Cloudflare
+/// reports ownership and SSL as active.
+#[tauri::command]
+pub async fn cloudflare_add_custom_domain(
+    bucket: String,
+    domain: String,
+    zone_id: String,
+    jurisdiction: Option<String>,
+    cloudflare: State<'_, CloudflareState>,
+) -> Result<PublicAccess, AppError> {
+    let jurisdiction = check_jurisdiction(jurisdiction)?;
+    let (account_id, token) = cloudflare.session()?;
+
+    let request = cloudflare.http
+        .post(format!("{}/custom", domains_url(&account_id, &bucket)))
+        .header("cf-r2-jurisdiction", &jurisdiction)
+        .json(&json!({ "domain": domain, "zoneId": zone_id, "enabled": true }));
+    let _: serde_json::Value = cloudflare.call(request, &token).await?;
+
+    Ok(load_public_access(&cloudflare, &bucket, &jurisdiction).await?)
+}
+
+#[tauri::command]
+pub async fn cloudflare_remove_custom_domain(
+    bucket: String,
+    domain: String,
+    jurisdiction: Option<String>,
+    cloudflare: State<'_, CloudflareState>,
+) -> Result<PublicAccess, AppError> {
+    let jurisdiction = check_jurisdiction(jurisdiction)?;
+    let (account_id, token) = cloudflare.session()?;
+
+    let request = cloudflare.http
+        .delete(format!("{}/custom/{}", domains_url(&account_id, &bucket), domain))
+        .header("cf-r2-jurisdiction", &jurisdiction);
+    let _: serde_json::Value = cloudflare.call(request, &token).await?;
+
+    Ok(load_public_access(&cloudflare, &bucket, &jurisdiction).await?)
+}
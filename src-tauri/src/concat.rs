@@ -0,0 +1,145 @@
+use aws_sdk_s3::types::{CompletedMultipartUpload, CompletedPart};
+use aws_sdk_s3::Client;
+use serde_json::json;
+use tauri::{AppHandle, State};
+use urlencoding::encode;
+
+use crate::error::{AppError, ErrorKind};
+use crate::jobs;
+use crate::s3::{acquire_write_client, AppState};
+
+// S3 multipart limits: every part but the last must be at least 5 MiB, and a single
+// UploadPartCopy can copy at most 5 GiB.
+const MIN_PART_SIZE: i64 = 5 * 1024 * 1024;
+const MAX_PART_SIZE: i64 = 5 * 1024 * 1024 * 1024;
+
+/// One UploadPartCopy: the source key and an inclusive byte range within it.
+struct PartSource {
+    key: String,
+    start: i64,
+    end: i64,
+}
+
+async fn plan_parts(client: &Client, bucket: &str, sources: &[String]) -> Result<Vec<PartSource>, String> {
+    let mut parts = Vec::new();
+    for (i, key) in sources.iter().enumerate() {
+        let head = client.head_object()
+            .bucket(bucket)
+            .key(key)
+            .send()
+            .await
+            .map_err(|e| format!("Failed to read {}: {}", key, e))?;
+        let size = head.content_length().unwrap_or(0);
+
+        let is_last = i == sources.len() - 1;
+        if size == 0 {
+            return Err(format!("{} is empty and cannot be used as a part", key));
+        }
+        if size < MIN_PART_SIZE && !is_last {
+            return Err(format!(
+                "{} is smaller than 5 MiB; only the last object may be below the multipart minimum",
+                key
+            ));
+        }
+
+        // Objects over 5 GiB are split into equal pieces, so no piece ends up below the minimum
+        let count = (size + MAX_PART_SIZE - 1) / MAX_PART_SIZE;
+        let piece = (size + count - 1) / count;
+        let mut start = 0;
+        while start < size {
+            let end = (start + piece).min(size) - 1;
+            parts.push(PartSource { key: key.clone(), start, end });
+            start = end + 1;
+        }
+    }
+    Ok(parts)
+}
+
+async fn copy_parts(
+    client: &Client,
+    bucket: &str,
+    destination: &str,
+    upload_id: &str,
+    parts: &[PartSource],
+) -> Result<Vec<CompletedPart>, String> {
+    let mut completed = Vec::new();
+    for (i, part) in parts.iter().enumerate() {
+        let part_number = i as i32 + 1;
+        let resp = client.upload_part_copy()
+            .bucket(bucket)
+            .key(destination)
+            .upload_id(upload_id)
+            .part_number(part_number)
+            .copy_source(format!("{}/{}", bucket, encode(&part.key)))
+            .copy_source_range(format!("bytes={}-{}", part.start, part.end))
+            .send()
+            .await
+            .map_err(|e| format!("Failed to copy {}: {}", part.key, e))?;
+
+        let etag = resp.copy_part_result().and_then(|r| r.e_tag()).unwrap_or_default();
+        completed.push(CompletedPart::builder().part_number(part_number).e_tag(etag).build());
+    }
+    Ok(completed)
+}
+
+/// Concatenates existing objects, in order, into `destination` without downloading them.
+#[tauri::command]
+pub async fn concatenate_objects(
+    bucket: String,
+    sources: Vec<String>,
+    destination: String,
+    content_type: Option<String>,
+    app: AppHandle,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    let job = jobs::start("concatenate", Some(&bucket), json!({ "sources": sources, "destination": destination }));
+    let result: Result<(), AppError> = async {
+        if sources.is_empty() {
+            return Err(AppError::new(ErrorKind::InvalidInput, "No source objects given"));
+        }
+        if sources.contains(&destination) {
+            return Err(AppError::new(ErrorKind::InvalidInput, "Destination cannot be one of the sources"));
+        }
+
+        let client = acquire_write_client(&state, connection_id.as_deref())?;
+
+        let parts = plan_parts(&client, &bucket, &sources).await?;
+
+        let upload = client.create_multipart_upload()
+            .bucket(&bucket)
+            .key(&destination)
+            .set_content_type(content_type)
+            .send()
+            .await?;
+        let upload_id = upload.upload_id().ok_or("Missing upload id")?.to_string();
+
+        let completed = match copy_parts(&client, &bucket, &destination, &upload_id, &parts).await {
+            Ok(c) => c,
+            Err(e) => {
+                // Don't leave billable orphaned parts behind
+                let _ = client.abort_multipart_upload()
+                    .bucket(&bucket)
+                    .key(&destination)
+                    .upload_id(&upload_id)
+                    .send()
+                    .await;
+                return Err(e.into());
+            }
+        };
+
+        client.complete_multipart_upload()
+            .bucket(&bucket)
+            .key(&destination)
+            .upload_id(&upload_id)
+            .multipart_upload(CompletedMultipartUpload::builder().set_parts(Some(completed)).build())
+            .send()
+            .await?;
+
+        Ok(())
+    }
+    .await;
+    job.finish(&app, &result, |_| format!("Created {}", destination));
+
+    result
+}
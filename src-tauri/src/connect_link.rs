@@ -0,0 +1,82 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use qrcode::render::svg;
+use qrcode::QrCode;
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::error::{AppError, ErrorKind};
+use crate::s3::{connection, AppState};
+
+pub const LINK_PREFIX: &str = "r2drive:connect:";
+const DESCRIPTOR_VERSION: u8 = 1;
+
+/// Everything a teammate needs to connect except their own credentials. Never carries secrets.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConnectDescriptor {
+    pub version: u8,
+    pub label: Option<String>,
+    pub account_id: String,
+    pub endpoint: String,
+    pub bucket: Option<String>,
+    pub prefix: Option<String>,
+    /// Name of the API token the teammate should ask for, shown as a hint when importing.
+    pub token_name: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConnectLink {
+    pub link: String,
+    pub qr_svg: String,
+}
+
+fn encode_descriptor(descriptor: &ConnectDescriptor) -> Result<ConnectLink, String> {
+    let json = serde_json::to_vec(descriptor).map_err(|e| e.to_string())?;
+    let link = format!("{}{}", LINK_PREFIX, URL_SAFE_NO_PAD.encode(json));
+    let qr_svg = QrCode::new(link.as_bytes())
+        .map_err(|e| e.to_string())?
+        .render::<svg::Color>()
+        .min_dimensions(240, 240)
+        .build();
+    Ok(ConnectLink { link, qr_svg })
+}
+
+/// Builds a quick-connect link for an open connection.
+#[tauri::command]
+pub fn create_connect_link(
+    label: Option<String>,
+    bucket: Option<String>,
+    prefix: Option<String>,
+    token_name: Option<String>,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<ConnectLink, AppError> {
+    let connection = connection(&state, connection_id.as_deref())?;
+
+    Ok(encode_descriptor(&ConnectDescriptor {
+        version: DESCRIPTOR_VERSION,
+        label,
+        account_id: connection.credentials.account_id,
+        endpoint: connection.endpoint,
+        bucket,
+        prefix,
+        token_name,
+    })?)
+}
+
+/// Decodes a link (with or without the `r2drive:connect:` prefix) so the UI can pre-fill the
+/// connection form.
+#[tauri::command]
+pub fn import_connect_link(link: String) -> Result<ConnectDescriptor, AppError> {
+    let encoded = link.trim();
+    let encoded = encoded.strip_prefix(LINK_PREFIX).unwrap_or(encoded);
+    let json = URL_SAFE_NO_PAD
+        .decode(encoded.trim_end_matches('='))
+        .map_err(|_| "Not a valid connection link".to_string())?;
+    let descriptor: ConnectDescriptor =
+        serde_json::from_slice(&json).map_err(|_| "Not a valid connection link".to_string())?;
+    if descriptor.version > DESCRIPTOR_VERSION {
+        return Err(AppError::new(ErrorKind::InvalidInput, "This link was created by a newer version of R2Drive"));
+    }
+    Ok(descriptor)
+}
@@ -0,0 +1,132 @@
+use aws_config::meta::region::RegionProviderChain;
+use aws_sdk_s3::config::Region;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::error::AppError;
+use crate::jobs;
+use crate::network;
+use crate::notifications;
+use crate::s3::{self, build_client, connection, connection_key, AppState, Credentials};
+
+// How often open connections are checked, and how early the UI is warned before temporary
+// credentials lapse
+const TICK: Duration = Duration::from_secs(30);
+const WARN_BEFORE_SECS: i64 = 5 * 60;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AssumeRoleRequest {
+    pub role_arn: String,
+    pub session_name: Option<String>,
+    pub duration_seconds: Option<i32>,
+    pub external_id: Option<String>,
+    /// Region for STS and the resulting S3 client; `us-east-1` when unset.
+    pub region: Option<String>,
+    /// STS endpoint for non-AWS backends (MinIO serves STS on its S3 endpoint). AWS's regional
+    /// endpoint is used when unset.
+    pub sts_endpoint: Option<String>,
+    #[serde(default)]
+    pub force_path_style: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CredentialsExpiring {
+    pub connection_id: String,
+    pub expires_at: i64,
+    pub seconds_left: i64,
+}
+
+/// Exchanges the credentials of `connection_id` for a role's temporary credentials and opens them
+/// as `target_connection_id` (the source connection itself when unset). Returns the expiry.
+#[tauri::command]
+pub async fn assume_role(
+    request: AssumeRoleRequest,
+    connection_id: Option<String>,
+    target_connection_id: Option<String>,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<i64, AppError> {
+    let source = connection(&state, connection_id.as_deref())?;
+    let region = request.region.clone().unwrap_or_else(|| "us-east-1".to_string());
+
+    let base = aws_credential_types::Credentials::new(
+        &source.credentials.access_key,
+        &source.credentials.secret_key,
+        source.credentials.session_token.clone(),
+        None,
+        "Static",
+    );
+    let (http_client, timeouts) = {
+        let settings = state.settings.lock().unwrap();
+        (network::http_client(&settings), network::timeout_config(&settings))
+    };
+    let mut loader = aws_config::defaults(aws_config::BehaviorVersion::latest())
+        .region(RegionProviderChain::first_try(Region::new(region.clone())))
+        .credentials_provider(base)
+        .http_client(http_client)
+        .timeout_config(timeouts);
+    if let Some(endpoint) = request.sts_endpoint.as_deref().filter(|e| !e.is_empty()) {
+        loader = loader.endpoint_url(endpoint);
+    }
+    let sts = aws_sdk_sts::Client::new(&loader.load().await);
+
+    let resp = sts.assume_role()
+        .role_arn(&request.role_arn)
+        .role_session_name(request.session_name.unwrap_or_else(|| format!("r2drive-{}", jobs::now_secs())))
+        .set_duration_seconds(request.duration_seconds)
+        .set_external_id(request.external_id)
+        .send()
+        .await
+        .map_err(|e| aws_sdk_sts::error::DisplayErrorContext(e).to_string())?;
+    let issued = resp.credentials().ok_or("STS returned no credentials")?;
+
+    let expires_at = issued.expiration().secs();
+    let credentials = Credentials {
+        session_token: Some(issued.session_token().to_string()),
+        expires_at: Some(expires_at),
+        sse_c: source.credentials.sse_c.clone(),
+        ..Credentials::new(
+            source.credentials.account_id.clone(),
+            issued.access_key_id().to_string(),
+            issued.secret_access_key().to_string(),
+        )
+    };
+    let client = build_client(&state, &source.endpoint, Some(region), &credentials, request.force_path_style).await;
+
+    let target = connection_key(target_connection_id.as_deref().or(connection_id.as_deref())).to_string();
+    s3::activate(&app, &state, &target, client, &source.endpoint, credentials, source.read_only);
+    Ok(expires_at)
+}
+
+/// Background task started at launch. Emits `credentials://expiring` once per set of temporary
+/// credentials when they are about to lapse (or already have).
+pub async fn watch_expiry(app: AppHandle) {
+    let mut warned: HashSet<(String, i64)> = HashSet::new();
+    loop {
+        tokio::time::sleep(TICK).await;
+
+        let now = jobs::now_secs();
+        let expiring: Vec<(String, i64)> = app.state::<AppState>().connections.lock().unwrap()
+            .iter()
+            .filter_map(|(id, c)| c.credentials.expires_at.map(|t| (id.clone(), t)))
+            .filter(|(_, t)| t - now <= WARN_BEFORE_SECS)
+            .collect();
+
+        for (connection_id, expires_at) in expiring {
+            if warned.insert((connection_id.clone(), expires_at)) {
+                let body = match (expires_at - now) / 60 {
+                    minutes if minutes > 0 => format!("Credentials for {} expire in {} min", connection_id, minutes),
+                    _ => format!("Credentials for {} have expired", connection_id),
+                };
+                notifications::warning(&app, "Credentials expiring", &body);
+                let _ = app.emit("credentials://expiring", CredentialsExpiring {
+                    connection_id,
+                    expires_at,
+                    seconds_left: (expires_at - now).max(0),
+                });
+            }
+        }
+    }
+}
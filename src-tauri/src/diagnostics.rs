@@ -0,0 +1,212 @@
+use aws_sdk_s3::config::http::HttpResponse;
+use aws_sdk_s3::error::{DisplayErrorContext, ProvideErrorMetadata, SdkError};
+use serde::Serialize;
+use std::time::{Duration, Instant};
+use tauri::State;
+
+use crate::error::AppError;
+use crate::s3::{acquire_client, connection, AppState};
+
+const TEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConnectionIssue {
+    Dns,
+    Tls,
+    Timeout,
+    Network,
+    InvalidCredentials,
+    ClockSkew,
+    PermissionDenied,
+    NoSuchBucket,
+    Unknown,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConnectionTest {
+    pub ok: bool,
+    pub endpoint: String,
+    pub latency_ms: u64,
+    pub issue: Option<ConnectionIssue>,
+    pub message: Option<String>,
+    /// What the user can do about it.
+    pub hint: Option<String>,
+}
+
+fn classify<E>(err: &SdkError<E, HttpResponse>) -> (ConnectionIssue, String)
+where
+    E: ProvideErrorMetadata + std::error::Error + 'static,
+{
+    let detail = DisplayErrorContext(err).to_string();
+    let lower = detail.to_lowercase();
+
+    let issue = match err {
+        SdkError::TimeoutError(_) => ConnectionIssue::Timeout,
+        SdkError::DispatchFailure(failure) => {
+            if failure.is_timeout() {
+                ConnectionIssue::Timeout
+            } else if lower.contains("dns") || lower.contains("lookup") || lower.contains("name or service not known") {
+                ConnectionIssue::Dns
+            } else if lower.contains("tls") || lower.contains("certificate") || lower.contains("handshake") {
+                ConnectionIssue::Tls
+            } else {
+                ConnectionIssue::Network
+            }
+        }
+        _ => match err.code() {
+            Some("InvalidAccessKeyId") | Some("SignatureDoesNotMatch") | Some("Unauthorized") => ConnectionIssue::InvalidCredentials,
+            Some("RequestTimeTooSkewed") => ConnectionIssue::ClockSkew,
+            Some("AccessDenied") => ConnectionIssue::PermissionDenied,
+            Some("NoSuchBucket") => ConnectionIssue::NoSuchBucket,
+            _ => match err.raw_response().map(|r| r.status().as_u16()) {
+                Some(401) => ConnectionIssue::InvalidCredentials,
+                Some(403) => ConnectionIssue::PermissionDenied,
+                Some(404) => ConnectionIssue::NoSuchBucket,
+                _ => ConnectionIssue::Unknown,
+            },
+        },
+    };
+
+    (issue, detail)
+}
+
+fn hint(issue: ConnectionIssue) -> &'static str {
+    match issue {
+        ConnectionIssue::Dns => "The endpoint host could not be resolved. Check the account ID and jurisdiction.",
+        ConnectionIssue::Tls => "A secure connection could not be established. A proxy or firewall may be intercepting traffic.",
+        ConnectionIssue::Timeout => "The endpoint did not answer in time. Check your network connection.",
+        ConnectionIssue::Network => "The endpoint could not be reached. Check your network connection.",
+        ConnectionIssue::InvalidCredentials => "The access key or secret key is wrong, or the token has been revoked.",
+        ConnectionIssue::ClockSkew => "Your system clock is too far off. Sync the clock and try again.",
+        ConnectionIssue::PermissionDenied => "The credentials are valid but the token is not allowed to do this.",
+        ConnectionIssue::NoSuchBucket => "The bucket does not exist, or it lives in a different jurisdiction.",
+        ConnectionIssue::Unknown => "The endpoint rejected the request.",
+    }
+}
+
+/// Checks a connection with a HeadBucket (when `bucket` is given) or a ListBuckets,
+/// and explains any failure.
+#[tauri::command]
+pub async fn test_connection(
+    bucket: Option<String>,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<ConnectionTest, AppError> {
+    let client = acquire_client(&state, connection_id.as_deref())?;
+    let endpoint = connection(&state, connection_id.as_deref())?.endpoint;
+
+    let started = Instant::now();
+    let outcome = match &bucket {
+        Some(b) => tokio::time::timeout(TEST_TIMEOUT, client.head_bucket().bucket(b).send())
+            .await
+            .map(|r| r.map(|_| ()).map_err(|e| classify(&e))),
+        None => tokio::time::timeout(TEST_TIMEOUT, client.list_buckets().send())
+            .await
+            .map(|r| r.map(|_| ()).map_err(|e| classify(&e))),
+    };
+    let latency_ms = started.elapsed().as_millis() as u64;
+
+    let failure = match outcome {
+        Ok(Ok(())) => None,
+        Ok(Err(failure)) => Some(failure),
+        Err(_) => Some((ConnectionIssue::Timeout, format!("No response within {} seconds", TEST_TIMEOUT.as_secs()))),
+    };
+
+    Ok(match failure {
+        None => ConnectionTest { ok: true, endpoint, latency_ms, issue: None, message: None, hint: None },
+        Some((issue, message)) => ConnectionTest {
+            ok: false,
+            endpoint,
+            latency_ms,
+            issue: Some(issue),
+            message: Some(message),
+            hint: Some(hint(issue).to_string()),
+        },
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Permission {
+    Allowed,
+    Denied,
+    /// Not tested, e.g. write probes on a read-only profile.
+    Skipped,
+    /// The probe failed for a reason unrelated to permissions.
+    Unknown,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PermissionProbe {
+    pub bucket: String,
+    pub list: Permission,
+    pub read: Permission,
+    pub write: Permission,
+    pub delete: Permission,
+    pub errors: Vec<String>,
+}
+
+/// Maps a probe outcome to a permission. `ok_codes` are error codes that still prove access,
+/// like NoSuchKey when reading a key that doesn't exist.
+fn permission<T, E>(
+    action: &str,
+    result: Result<T, SdkError<E, HttpResponse>>,
+    ok_codes: &[&str],
+    errors: &mut Vec<String>,
+) -> Permission
+where
+    E: ProvideErrorMetadata + std::error::Error + 'static,
+{
+    match result {
+        Ok(_) => Permission::Allowed,
+        Err(e) if e.code().is_some_and(|c| ok_codes.contains(&c)) => Permission::Allowed,
+        Err(e) => {
+            let (issue, detail) = classify(&e);
+            errors.push(format!("{}: {}", action, detail));
+            if matches!(issue, ConnectionIssue::PermissionDenied | ConnectionIssue::InvalidCredentials) {
+                Permission::Denied
+            } else {
+                Permission::Unknown
+            }
+        }
+    }
+}
+
+/// Works out what the connection's token may do in `bucket` by trying cheap operations. The write
+/// probe creates a tiny object under a random key and deletes it straight away.
+#[tauri::command]
+pub async fn probe_permissions(
+    bucket: String,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<PermissionProbe, AppError> {
+    let client = acquire_client(&state, connection_id.as_deref())?;
+    let read_only = connection(&state, connection_id.as_deref())?.read_only;
+    let probe_key = format!(".r2drive-probe-{:016x}", rand::random::<u64>());
+    let mut errors = Vec::new();
+
+    let list = permission("list", client.list_objects_v2().bucket(&bucket).max_keys(1).send().await, &[], &mut errors);
+    let read = permission(
+        "read",
+        client.get_object().bucket(&bucket).key(&probe_key).send().await,
+        &["NoSuchKey"],
+        &mut errors,
+    );
+
+    let (write, delete) = if read_only {
+        (Permission::Skipped, Permission::Skipped)
+    } else {
+        let write = permission(
+            "write",
+            client.put_object().bucket(&bucket).key(&probe_key).body(Vec::new().into()).send().await,
+            &[],
+            &mut errors,
+        );
+        // Deleting a missing key still succeeds when allowed, so this works even if the put failed
+        let delete = permission("delete", client.delete_object().bucket(&bucket).key(&probe_key).send().await, &[], &mut errors);
+        (write, delete)
+    };
+
+    Ok(PermissionProbe { bucket, list, read, write, delete, errors })
+}
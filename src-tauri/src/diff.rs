@@ -0,0 +1,277 @@
+use aws_sdk_s3::primitives::ByteStream;
+use aws_sdk_s3::Client;
+use serde::Serialize;
+use similar::{ChangeTag, TextDiff};
+use tauri::State;
+use tokio::io::AsyncReadExt;
+
+use crate::error::AppError;
+use crate::s3::{acquire_client, AppState};
+
+// Both sides must fit under this to get a line diff; anything bigger is compared bytewise
+const TEXT_DIFF_LIMIT: u64 = 2 * 1024 * 1024;
+const MAX_RANGES: usize = 1000;
+const CONTEXT_LINES: usize = 3;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DiffLine {
+    pub tag: String, // "equal" | "insert" | "delete"
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DiffHunk {
+    pub old_start: usize,
+    pub old_len: usize,
+    pub new_start: usize,
+    pub new_len: usize,
+    pub lines: Vec<DiffLine>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ByteRange {
+    pub start: u64,
+    pub end: u64, // exclusive
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ObjectDiff {
+    Text {
+        identical: bool,
+        added: usize,
+        removed: usize,
+        hunks: Vec<DiffHunk>,
+    },
+    Binary {
+        identical: bool,
+        size_a: u64,
+        size_b: u64,
+        /// Differing byte ranges within the common length, capped at 1000 entries.
+        differing_ranges: Vec<ByteRange>,
+        truncated: bool,
+    },
+}
+
+enum Source {
+    Remote(ByteStream),
+    Local(tokio::fs::File),
+}
+
+impl Source {
+    async fn next_chunk(&mut self) -> Result<Option<Vec<u8>>, String> {
+        match self {
+            Source::Remote(body) => Ok(body.try_next().await.map_err(|e| e.to_string())?.map(|b| b.to_vec())),
+            Source::Local(file) => {
+                let mut buf = vec![0u8; 256 * 1024];
+                let n = file.read(&mut buf).await.map_err(|e| e.to_string())?;
+                buf.truncate(n);
+                Ok(if n == 0 { None } else { Some(buf) })
+            }
+        }
+    }
+
+    async fn read_all(&mut self) -> Result<Vec<u8>, String> {
+        let mut data = Vec::new();
+        while let Some(chunk) = self.next_chunk().await? {
+            data.extend_from_slice(&chunk);
+        }
+        Ok(data)
+    }
+}
+
+async fn open_remote(client: &Client, bucket: &str, key: &str) -> Result<(Source, u64), String> {
+    let resp = client.get_object()
+        .bucket(bucket)
+        .key(key)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to read {}: {}", key, e))?;
+    let size = resp.content_length().unwrap_or(0) as u64;
+    Ok((Source::Remote(resp.body), size))
+}
+
+async fn open_local(path: &str) -> Result<(Source, u64), String> {
+    let file = tokio::fs::File::open(path).await.map_err(|e| format!("{}: {}", path, e))?;
+    let size = file.metadata().await.map_err(|e| e.to_string())?.len();
+    Ok((Source::Local(file), size))
+}
+
+fn looks_like_text(data: &[u8]) -> bool {
+    !data[..data.len().min(8192)].contains(&0) && std::str::from_utf8(data).is_ok()
+}
+
+fn text_diff(a: &str, b: &str) -> ObjectDiff {
+    let diff = TextDiff::from_lines(a, b);
+    let mut added = 0;
+    let mut removed = 0;
+    let mut hunks = Vec::new();
+
+    for group in diff.grouped_ops(CONTEXT_LINES) {
+        let (Some(first), Some(last)) = (group.first(), group.last()) else { continue };
+        let old_range = first.old_range().start..last.old_range().end;
+        let new_range = first.new_range().start..last.new_range().end;
+
+        let mut lines = Vec::new();
+        for op in &group {
+            for change in diff.iter_changes(op) {
+                let tag = match change.tag() {
+                    ChangeTag::Equal => "equal",
+                    ChangeTag::Insert => {
+                        added += 1;
+                        "insert"
+                    }
+                    ChangeTag::Delete => {
+                        removed += 1;
+                        "delete"
+                    }
+                };
+                lines.push(DiffLine {
+                    tag: tag.to_string(),
+                    text: change.value().trim_end_matches(['\n', '\r']).to_string(),
+                });
+            }
+        }
+
+        hunks.push(DiffHunk {
+            old_start: old_range.start + 1,
+            old_len: old_range.len(),
+            new_start: new_range.start + 1,
+            new_len: new_range.len(),
+            lines,
+        });
+    }
+
+    ObjectDiff::Text { identical: hunks.is_empty(), added, removed, hunks }
+}
+
+#[derive(Default)]
+struct RangeCollector {
+    ranges: Vec<ByteRange>,
+    truncated: bool,
+}
+
+impl RangeCollector {
+    fn mark(&mut self, offset: u64) {
+        if let Some(last) = self.ranges.last_mut() {
+            if last.end == offset {
+                last.end += 1;
+                return;
+            }
+        }
+        if self.ranges.len() >= MAX_RANGES {
+            self.truncated = true;
+            return;
+        }
+        self.ranges.push(ByteRange { start: offset, end: offset + 1 });
+    }
+
+    fn compare(&mut self, base: u64, a: &[u8], b: &[u8]) {
+        for (i, (x, y)) in a.iter().zip(b).enumerate() {
+            if x != y {
+                self.mark(base + i as u64);
+            }
+        }
+    }
+}
+
+/// Streams both sources side by side so arbitrarily large objects can be compared.
+async fn binary_diff(mut a: Source, size_a: u64, mut b: Source, size_b: u64) -> Result<ObjectDiff, String> {
+    let mut collector = RangeCollector::default();
+    let mut buf_a: Vec<u8> = Vec::new();
+    let mut buf_b: Vec<u8> = Vec::new();
+    let mut offset = 0u64;
+    let (mut done_a, mut done_b) = (false, false);
+
+    while !(done_a && done_b) {
+        if buf_a.is_empty() && !done_a {
+            match a.next_chunk().await? {
+                Some(c) => buf_a = c,
+                None => done_a = true,
+            }
+        }
+        if buf_b.is_empty() && !done_b {
+            match b.next_chunk().await? {
+                Some(c) => buf_b = c,
+                None => done_b = true,
+            }
+        }
+        let n = buf_a.len().min(buf_b.len());
+        if n == 0 {
+            if done_a || done_b {
+                break;
+            }
+            continue;
+        }
+        collector.compare(offset, &buf_a[..n], &buf_b[..n]);
+        buf_a.drain(..n);
+        buf_b.drain(..n);
+        offset += n as u64;
+    }
+
+    Ok(ObjectDiff::Binary {
+        identical: size_a == size_b && collector.ranges.is_empty(),
+        size_a,
+        size_b,
+        differing_ranges: collector.ranges,
+        truncated: collector.truncated,
+    })
+}
+
+async fn diff_sources(mut a: Source, size_a: u64, mut b: Source, size_b: u64) -> Result<ObjectDiff, String> {
+    if size_a > TEXT_DIFF_LIMIT || size_b > TEXT_DIFF_LIMIT {
+        return binary_diff(a, size_a, b, size_b).await;
+    }
+
+    let data_a = a.read_all().await?;
+    let data_b = b.read_all().await?;
+    if looks_like_text(&data_a) && looks_like_text(&data_b) {
+        let text_a = String::from_utf8_lossy(&data_a);
+        let text_b = String::from_utf8_lossy(&data_b);
+        return Ok(text_diff(&text_a, &text_b));
+    }
+
+    let mut collector = RangeCollector::default();
+    let n = data_a.len().min(data_b.len());
+    collector.compare(0, &data_a[..n], &data_b[..n]);
+    Ok(ObjectDiff::Binary {
+        identical: data_a == data_b,
+        size_a: data_a.len() as u64,
+        size_b: data_b.len() as u64,
+        differing_ranges: collector.ranges,
+        truncated: collector.truncated,
+    })
+}
+
+#[tauri::command]
+pub async fn diff_objects(
+    bucket: String,
+    key_a: String,
+    key_b: String,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<ObjectDiff, AppError> {
+    let client = acquire_client(&state, connection_id.as_deref())?;
+
+    let (a, size_a) = open_remote(&client, &bucket, &key_a).await?;
+    let (b, size_b) = open_remote(&client, &bucket, &key_b).await?;
+
+    Ok(diff_sources(a, size_a, b, size_b).await?)
+}
+
+/// Compares a local file (old side) with a remote object (new side).
+#[tauri::command]
+pub async fn diff_local_remote(
+    local_path: String,
+    bucket: String,
+    key: String,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<ObjectDiff, AppError> {
+    let client = acquire_client(&state, connection_id.as_deref())?;
+
+    let (a, size_a) = open_local(&local_path).await?;
+    let (b, size_b) = open_remote(&client, &bucket, &key).await?;
+
+    Ok(diff_sources(a, size_a, b, size_b).await?)
+}
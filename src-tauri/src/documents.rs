@@ -0,0 +1,101 @@
+use image::DynamicImage;
+use pdfium_render::prelude::*;
+use std::io::{Cursor, Read};
+use std::sync::OnceLock;
+use tauri::{AppHandle, Manager, State};
+
+use crate::error::{AppError, ErrorKind};
+use crate::s3::{acquire_client, AppState};
+use crate::thumbnails::{self, Thumbnail, MAX_SOURCE_BYTES};
+
+// PDFs are rendered with PDFium, loaded at runtime from the app's resources or the system. Office
+// documents aren't rendered; the thumbnail their editor embedded on save is used instead.
+static PDFIUM: OnceLock<Result<Pdfium, String>> = OnceLock::new();
+
+// Where OOXML (docx/xlsx/pptx) and OpenDocument files keep their embedded thumbnail
+const EMBEDDED_THUMBNAILS: &[&str] = &["docProps/thumbnail.jpeg", "docProps/thumbnail.jpg", "docProps/thumbnail.png", "Thumbnails/thumbnail.png"];
+const OFFICE_EXTENSIONS: &[&str] = &["docx", "xlsx", "pptx", "docm", "xlsm", "pptm", "odt", "ods", "odp", "odg"];
+
+fn pdfium(app: &AppHandle) -> Result<&'static Pdfium, AppError> {
+    let loaded = PDFIUM.get_or_init(|| {
+        let bundled = app.path().resource_dir().ok().map(|dir| Pdfium::pdfium_platform_library_name_at_path(&dir));
+        bundled
+            .and_then(|path| Pdfium::bind_to_library(path).ok())
+            .map_or_else(Pdfium::bind_to_system_library, Ok)
+            .map(Pdfium::new)
+            .map_err(|e| format!("PDFium isn't available: {}", e))
+    });
+    loaded.as_ref().map_err(|e| AppError::new(ErrorKind::Unsupported, e.clone()))
+}
+
+fn render_pdf(pdfium: &Pdfium, bytes: Vec<u8>, max_dimension: u32) -> Result<DynamicImage, AppError> {
+    let unsupported = |e: PdfiumError| AppError::new(ErrorKind::Unsupported, format!("Cannot render PDF: {}", e));
+    let document = pdfium.load_pdf_from_byte_vec(bytes, None).map_err(unsupported)?;
+    let page = document.pages().first().map_err(unsupported)?;
+    let config = PdfRenderConfig::new()
+        .set_maximum_width(max_dimension as i32)
+        .set_maximum_height(max_dimension as i32);
+    page.render_with_config(&config).and_then(|bitmap| bitmap.as_image()).map_err(unsupported)
+}
+
+fn embedded_thumbnail(bytes: Vec<u8>) -> Result<DynamicImage, AppError> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(bytes))
+        .map_err(|e| AppError::new(ErrorKind::Unsupported, format!("Not an Office document: {}", e)))?;
+    for name in EMBEDDED_THUMBNAILS {
+        let Ok(mut entry) = archive.by_name(name) else { continue };
+        let mut data = Vec::new();
+        entry.read_to_end(&mut data).map_err(|e| e.to_string())?;
+        if let Ok(image) = image::load_from_memory(&data) {
+            return Ok(image);
+        }
+    }
+    Err(AppError::new(ErrorKind::Unsupported, "The document has no embedded preview"))
+}
+
+/// A preview image of a document: the first page of a PDF, or the thumbnail embedded in an
+/// Office/OpenDocument file. Cached alongside image thumbnails.
+#[tauri::command]
+pub async fn get_document_preview(
+    bucket: String,
+    key: String,
+    max_dimension: Option<u32>,
+    app: AppHandle,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Thumbnail, AppError> {
+    let extension = key.rsplit_once('.').map(|(_, e)| e.to_lowercase()).unwrap_or_default();
+    let is_pdf = extension == "pdf";
+    if !is_pdf && !OFFICE_EXTENSIONS.contains(&extension.as_str()) {
+        return Err(AppError::new(ErrorKind::Unsupported, format!("No document preview for .{} files", extension)));
+    }
+    let max_dimension = thumbnails::clamp_dimension(max_dimension);
+    let client = acquire_client(&state, connection_id.as_deref())?;
+
+    let head = client.head_object().bucket(&bucket).key(&key).send().await?;
+    let etag = head.e_tag().unwrap_or_default().trim_matches('"').to_string();
+    let path = thumbnails::cache_dir(&app, "thumbnails")?
+        .join(thumbnails::cache_name(&bucket, &key, &etag, &format!("document-{}", max_dimension)));
+    if let Ok(bytes) = tokio::fs::read(&path).await {
+        return Ok(thumbnails::from_encoded(path, &bytes, true)?);
+    }
+
+    if head.content_length().unwrap_or(0) > MAX_SOURCE_BYTES {
+        return Err(AppError::new(ErrorKind::InvalidInput, "Document too large to preview"));
+    }
+    let pdfium = if is_pdf { Some(pdfium(&app)?) } else { None };
+    let resp = client.get_object().bucket(&bucket).key(&key).send().await?;
+    let source = resp.body.collect().await.map_err(|e| e.to_string())?.into_bytes().to_vec();
+
+    let encoded = tauri::async_runtime::spawn_blocking(move || {
+        let image = match pdfium {
+            Some(pdfium) => render_pdf(pdfium, source, max_dimension)?,
+            None => embedded_thumbnail(source)?,
+        };
+        Ok::<_, AppError>(thumbnails::encode_thumbnail(image, max_dimension)?)
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+
+    tokio::fs::write(&path, &encoded).await.map_err(|e| e.to_string())?;
+    Ok(thumbnails::from_encoded(path, &encoded, false)?)
+}
@@ -0,0 +1,112 @@
+use aws_sdk_s3::Client;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use tauri::State;
+
+use crate::error::AppError;
+use crate::s3::{acquire_client, list_all_objects, AppState};
+
+// Only objects up to this size are downloaded to settle ambiguous multipart ETags
+const HASH_SIZE_LIMIT: i64 = 16 * 1024 * 1024;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DuplicateObject {
+    pub key: String,
+    pub etag: String,
+    pub last_modified: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DuplicateSet {
+    pub size: i64,
+    pub fingerprint: String,
+    pub objects: Vec<DuplicateObject>,
+    /// Bytes freed by keeping only one copy.
+    pub reclaimable: i64,
+}
+
+fn trim_etag(etag: Option<&str>) -> String {
+    etag.unwrap_or_default().trim_matches('"').to_string()
+}
+
+async fn sha256_object(client: &Client, bucket: &str, key: &str) -> Result<String, String> {
+    let resp = client.get_object()
+        .bucket(bucket)
+        .key(key)
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    let data = resp.body.collect().await.map_err(|e| e.to_string())?.into_bytes();
+    Ok(format!("{:x}", Sha256::digest(&data)))
+}
+
+#[tauri::command]
+pub async fn find_duplicates(
+    bucket: String,
+    prefix: Option<String>,
+    hash_ambiguous: Option<bool>,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<DuplicateSet>, AppError> {
+    let client = acquire_client(&state, connection_id.as_deref())?;
+
+    let objects = list_all_objects(&client, &bucket, prefix.as_deref()).await?;
+
+    // Empty objects (including folder markers) cost nothing to keep
+    let mut by_size: HashMap<i64, Vec<DuplicateObject>> = HashMap::new();
+    for obj in objects {
+        let size = obj.size().unwrap_or(0);
+        if size == 0 {
+            continue;
+        }
+        by_size.entry(size).or_default().push(DuplicateObject {
+            key: obj.key().unwrap_or_default().to_string(),
+            etag: trim_etag(obj.e_tag()),
+            last_modified: obj.last_modified().map(|d| d.secs()),
+        });
+    }
+
+    let mut sets = Vec::new();
+    for (size, candidates) in by_size {
+        if candidates.len() < 2 {
+            continue;
+        }
+
+        // Multipart ETags depend on the part size used, so identical content can carry
+        // different ETags. Hash those groups when asked to and they are small enough.
+        let mut etags: Vec<&str> = candidates.iter().map(|c| c.etag.as_str()).collect();
+        etags.sort();
+        etags.dedup();
+        let ambiguous = etags.len() > 1 && etags.iter().any(|e| e.contains('-'));
+
+        let mut groups: HashMap<String, Vec<DuplicateObject>> = HashMap::new();
+        if ambiguous && hash_ambiguous.unwrap_or(false) && size <= HASH_SIZE_LIMIT {
+            for c in candidates {
+                let digest = sha256_object(&client, &bucket, &c.key).await?;
+                groups.entry(format!("sha256:{}", digest)).or_default().push(c);
+            }
+        } else {
+            for c in candidates {
+                groups.entry(format!("etag:{}", c.etag)).or_default().push(c);
+            }
+        }
+
+        for (fingerprint, mut objects) in groups {
+            if objects.len() < 2 {
+                continue;
+            }
+            objects.sort_by(|a, b| a.key.cmp(&b.key));
+            sets.push(DuplicateSet {
+                size,
+                fingerprint,
+                reclaimable: size * (objects.len() as i64 - 1),
+                objects,
+            });
+        }
+    }
+
+    sets.sort_by_key(|s| std::cmp::Reverse(s.reclaimable));
+
+    Ok(sets)
+}
@@ -0,0 +1,259 @@
+use aws_sdk_s3::primitives::ByteStream;
+use notify::{EventKind, RecursiveMode, Watcher};
+use serde::Serialize;
+use serde_json::json;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tauri_plugin_opener::OpenerExt;
+use tokio::sync::mpsc;
+
+use crate::encryption::{self, Decryptor};
+use crate::error::{AppError, ErrorKind};
+use crate::jobs;
+use crate::s3::{acquire_write_client, download_to_path, wait_if_paused, AppState};
+use crate::thumbnails;
+
+// Objects opened in the OS default application: the object is downloaded to a folder of its own
+// under the cache dir, the folder is watched, and every save is uploaded back once the file has
+// been quiet for a moment. Uploads are conditional on the ETag of the last version seen, so a
+// change made meanwhile by someone else stops the round trip instead of being overwritten.
+const QUIET: Duration = Duration::from_millis(1000);
+static SEQUENCE: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EditingFile {
+    pub id: String,
+    pub bucket: String,
+    pub key: String,
+    pub connection_id: Option<String>,
+    pub local_path: String,
+    /// ETag of the version the local copy was last downloaded as or uploaded to.
+    pub etag: String,
+    pub uploads: u32,
+    /// The object changed in the bucket since it was opened; saves are no longer uploaded.
+    pub conflict: bool,
+    /// Why the last upload failed, if it did.
+    pub error: Option<String>,
+}
+
+struct Editing {
+    file: EditingFile,
+    /// Client-side encrypted objects are encrypted again before each upload.
+    encrypted: bool,
+    /// Modification time and size of the local copy when it last matched the object.
+    synced: Option<(SystemTime, u64)>,
+    task: JoinHandle<()>,
+}
+
+#[derive(Default)]
+pub struct EditingState {
+    sessions: Mutex<HashMap<String, Editing>>,
+}
+
+fn stamp(path: &Path) -> Option<(SystemTime, u64)> {
+    let meta = std::fs::metadata(path).ok()?;
+    Some((meta.modified().ok()?, meta.len()))
+}
+
+/// Updates a session and tells the UI through `editing://status`.
+fn update(app: &AppHandle, id: &str, change: impl FnOnce(&mut Editing)) {
+    let state = app.state::<EditingState>();
+    let mut sessions = state.sessions.lock().unwrap();
+    if let Some(editing) = sessions.get_mut(id) {
+        change(editing);
+        let _ = app.emit("editing://status", &editing.file);
+    }
+}
+
+/// Uploads the local copy if it changed since it last matched the object. With `force` a
+/// conflicting change in the bucket is overwritten.
+async fn upload_changes(app: &AppHandle, id: &str, force: bool) -> Result<(), AppError> {
+    let (file, encrypted, synced) = {
+        let state = app.state::<EditingState>();
+        let sessions = state.sessions.lock().unwrap();
+        let editing = sessions.get(id).ok_or_else(|| AppError::new(ErrorKind::NotFound, format!("No file being edited with id {}", id)))?;
+        (editing.file.clone(), editing.encrypted, editing.synced)
+    };
+    let path = PathBuf::from(&file.local_path);
+    let current = stamp(&path);
+    // Unchanged, or gone mid-save (the rename that completes it brings another event)
+    if current.is_none() || current == synced {
+        return Ok(());
+    }
+    if file.conflict && !force {
+        return Err(AppError::new(ErrorKind::PreconditionFailed, "The object was changed in the bucket since it was opened"));
+    }
+
+    let job = jobs::start("edit_upload", Some(&file.bucket), json!({ "key": file.key, "path": file.local_path }));
+    let state = app.state::<AppState>();
+    let result: Result<String, AppError> = async {
+        wait_if_paused(&state).await;
+        let client = acquire_write_client(&state, file.connection_id.as_deref())?;
+        let mut metadata = match client.head_object().bucket(&file.bucket).key(&file.key).send().await {
+            Ok(head) => head.metadata().cloned().unwrap_or_default(),
+            Err(e) if e.as_service_error().is_some_and(|se| se.is_not_found()) => HashMap::new(),
+            Err(e) => return Err(e.into()),
+        };
+        let sealed = if encrypted {
+            let (sealed, sealed_metadata) = encryption::encrypt_file(&encryption::unlocked_key(app)?, &path).await?;
+            metadata.extend(sealed_metadata);
+            Some(sealed)
+        } else {
+            None
+        };
+        let body = ByteStream::from_path(sealed.as_ref().map_or(path.as_path(), |s| s.0.as_path())).await.map_err(|e| e.to_string())?;
+        let resp = client.put_object()
+            .bucket(&file.bucket)
+            .key(&file.key)
+            .set_if_match((!force).then(|| format!("\"{}\"", file.etag)))
+            .content_type(mime_guess::from_path(&path).first_or_octet_stream().to_string())
+            .set_metadata(Some(metadata))
+            .body(body)
+            .send()
+            .await;
+        match resp {
+            Ok(out) => Ok(out.e_tag().unwrap_or_default().trim_matches('"').to_string()),
+            Err(e) if e.raw_response().is_some_and(|r| r.status().as_u16() == 412) => {
+                Err(AppError::new(ErrorKind::PreconditionFailed, "The object was changed in the bucket since it was opened"))
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+    .await;
+    job.finish(app, &result, |_| format!("Uploaded edits to {}", file.key));
+
+    update(app, id, |editing| match &result {
+        Ok(etag) => {
+            editing.file.etag = etag.clone();
+            editing.file.uploads += 1;
+            editing.file.conflict = false;
+            editing.file.error = None;
+            editing.synced = current;
+        }
+        Err(e) => {
+            editing.file.conflict = e.kind == ErrorKind::PreconditionFailed;
+            editing.file.error = Some(e.message.clone());
+        }
+    });
+    result.map(|_| ())
+}
+
+/// Watches the copy's folder rather than the file itself, since many editors save by writing a
+/// new file and renaming it over the old one.
+fn watch(app: &AppHandle, id: String, path: PathBuf) -> Result<JoinHandle<()>, String> {
+    let dir = path.parent().ok_or("Not a valid local path")?.to_path_buf();
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        if let Ok(event) = event {
+            let _ = tx.send(event);
+        }
+    })
+    .map_err(|e| e.to_string())?;
+    watcher.watch(&dir, RecursiveMode::NonRecursive).map_err(|e| format!("Cannot watch {}: {}", dir.display(), e))?;
+
+    let app = app.clone();
+    Ok(tauri::async_runtime::spawn(async move {
+        // Dropping the watcher stops the notifications
+        let _watcher = watcher;
+        let touches = |event: &notify::Event| {
+            matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) && event.paths.iter().any(|p| p == &path)
+        };
+        while let Some(event) = rx.recv().await {
+            if !touches(&event) {
+                continue;
+            }
+            while let Ok(Some(_)) = tokio::time::timeout(QUIET, rx.recv()).await {}
+            // Failures are recorded on the session; the next save tries again
+            let _ = upload_changes(&app, &id, false).await;
+        }
+    }))
+}
+
+/// Downloads an object and opens it in the default application for its type. Saves made there
+/// are uploaded back until `stop_editing`. Opening an object that is already being edited
+/// reopens the existing copy, keeping its edits.
+#[tauri::command]
+pub async fn open_for_editing(
+    bucket: String,
+    key: String,
+    app: AppHandle,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+    editing: State<'_, EditingState>,
+) -> Result<EditingFile, AppError> {
+    let existing = editing.sessions.lock().unwrap()
+        .values()
+        .find(|e| e.file.bucket == bucket && e.file.key == key && e.file.connection_id == connection_id)
+        .map(|e| e.file.clone());
+    if let Some(file) = existing {
+        app.opener().open_path(&file.local_path, None::<&str>).map_err(|e| e.to_string())?;
+        return Ok(file);
+    }
+
+    let name = key.trim_end_matches('/').rsplit('/').next().unwrap_or_default();
+    if name.is_empty() || name == "." || name == ".." {
+        return Err(AppError::new(ErrorKind::InvalidInput, "Only files can be opened for editing"));
+    }
+    let client = acquire_write_client(&state, connection_id.as_deref())?;
+    let head = client.head_object().bucket(&bucket).key(&key).send().await?;
+    let encrypted = Decryptor::for_object(&app, head.metadata())?.is_some();
+
+    let id = format!("edit-{}-{}", jobs::now_secs(), SEQUENCE.fetch_add(1, Ordering::Relaxed));
+    // A folder per session keeps the object's own file name, which the default app goes by
+    let dir = thumbnails::cache_dir(&app, "editing")?.join(&id);
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let path = dir.join(name);
+    download_to_path(&app, &client, &bucket, &key, &path).await?;
+
+    let file = EditingFile {
+        id: id.clone(),
+        bucket,
+        key,
+        connection_id,
+        local_path: path.to_string_lossy().to_string(),
+        etag: head.e_tag().unwrap_or_default().trim_matches('"').to_string(),
+        uploads: 0,
+        conflict: false,
+        error: None,
+    };
+    let task = watch(&app, id.clone(), path.clone())?;
+    editing.sessions.lock().unwrap().insert(id, Editing { file: file.clone(), encrypted, synced: stamp(&path), task });
+
+    app.opener().open_path(&file.local_path, None::<&str>).map_err(|e| e.to_string())?;
+    Ok(file)
+}
+
+#[tauri::command]
+pub fn list_editing_files(editing: State<'_, EditingState>) -> Vec<EditingFile> {
+    let mut files: Vec<EditingFile> = editing.sessions.lock().unwrap().values().map(|e| e.file.clone()).collect();
+    files.sort_by(|a, b| a.id.cmp(&b.id));
+    files
+}
+
+/// Uploads any edits not sent yet, then stops watching and removes the local copy. A conflicting
+/// change in the bucket fails with PreconditionFailed and leaves the session running, unless
+/// `overwrite` replaces it or `discard` drops the local edits.
+#[tauri::command]
+pub async fn stop_editing(
+    id: String,
+    overwrite: Option<bool>,
+    discard: Option<bool>,
+    app: AppHandle,
+) -> Result<(), AppError> {
+    if !discard.unwrap_or(false) {
+        upload_changes(&app, &id, overwrite.unwrap_or(false)).await?;
+    }
+    let Some(editing) = app.state::<EditingState>().sessions.lock().unwrap().remove(&id) else {
+        return Err(AppError::new(ErrorKind::NotFound, format!("No file being edited with id {}", id)));
+    };
+    editing.task.abort();
+    if let Some(dir) = Path::new(&editing.file.local_path).parent() {
+        let _ = std::fs::remove_dir_all(dir);
+    }
+    Ok(())
+}
@@ -0,0 +1,453 @@
+use aes_gcm::aead::Aead;
+use aes_gcm::{Aes256Gcm, KeyInit, Nonce};
+use argon2::Argon2;
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use base64::Engine;
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, State};
+
+use crate::error::{AppError, ErrorKind};
+use crate::s3::AppState;
+use crate::settings;
+use r2drive_core::client::BodyFilter;
+
+// Client-side encryption. File contents are split into 64 KiB chunks, each sealed with
+// AES-256-GCM under a key derived for the object from the master key and a random salt. A chunk's
+// nonce is a random prefix, its index and a last-chunk flag, so chunks can't be reordered,
+// dropped or cut off unnoticed. Everything needed to decrypt except the master key goes in the
+// object's metadata, which is how downloads recognize encrypted objects.
+const CHUNK_SIZE: usize = 64 * 1024;
+const TAG_SIZE: usize = 16;
+const NONCE_PREFIX_SIZE: usize = 7;
+const SALT_SIZE: usize = 16;
+const KEYFILE_SIZE: usize = 32;
+
+const META_VERSION: &str = r2drive_core::client::ENCRYPTED_META;
+const META_SALT: &str = "r2drive-enc-salt";
+const META_NONCE: &str = "r2drive-enc-nonce";
+const META_KEY_ID: &str = "r2drive-enc-key";
+const META_SIZE: &str = "r2drive-enc-size";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EncryptionSettings {
+    /// Encrypt uploaded files: `upload_file`, edits, syncs, snapshots, folder watches and
+    /// context-menu and clipboard uploads. Downloads decrypt whenever an object is encrypted,
+    /// whether or not this is on.
+    pub enabled: bool,
+    /// Also encrypt the file name part of the key; folder names stay as they are.
+    pub encrypt_names: bool,
+    /// Argon2 salt for the passphrase, base64. Set on first unlock.
+    pub kdf_salt: Option<String>,
+    /// Id of the key files were encrypted with, so a mistyped passphrase is caught on unlock.
+    pub key_id: Option<String>,
+}
+
+/// The unlocked master key. Only ever held in memory.
+#[derive(Default)]
+pub struct EncryptionState {
+    key: Mutex<Option<MasterKey>>,
+}
+
+#[derive(Clone)]
+pub struct MasterKey([u8; 32]);
+
+impl MasterKey {
+    /// Short public fingerprint recorded on objects and in settings.
+    pub fn id(&self) -> String {
+        let digest = Sha256::new().chain_update(b"r2drive key id").chain_update(self.0).finalize();
+        digest[..8].iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    fn derive(&self, salt: &[u8], info: &[u8]) -> [u8; 32] {
+        let mut out = [0u8; 32];
+        Hkdf::<Sha256>::new(Some(salt), &self.0).expand(info, &mut out).expect("32 bytes is a valid HKDF length");
+        out
+    }
+}
+
+fn random<const N: usize>() -> [u8; N] {
+    let mut bytes = [0u8; N];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    bytes
+}
+
+fn nonce(prefix: &[u8], index: u32, last: bool) -> [u8; 12] {
+    let mut nonce = [0u8; 12];
+    nonce[..NONCE_PREFIX_SIZE].copy_from_slice(prefix);
+    nonce[NONCE_PREFIX_SIZE..11].copy_from_slice(&index.to_be_bytes());
+    nonce[11] = last as u8;
+    nonce
+}
+
+/// The unlocked key, or an error telling the user to unlock first.
+pub fn unlocked_key(app: &AppHandle) -> Result<MasterKey, String> {
+    app.try_state::<EncryptionState>()
+        .and_then(|state| state.key.lock().unwrap().clone())
+        .ok_or_else(|| "Encryption is locked; unlock it with your passphrase or keyfile first".to_string())
+}
+
+/// A temporary file removed when dropped.
+pub struct TempFile(pub PathBuf);
+
+impl TempFile {
+    /// A fresh path in the temp dir, ending in `extension`.
+    pub fn new(extension: &str) -> Self {
+        TempFile(std::env::temp_dir().join(format!("r2drive-{}.{}", URL_SAFE_NO_PAD.encode(random::<12>()), extension)))
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+/// Encrypts `path` into a temporary file. Returns it with the metadata to store on the object.
+pub async fn encrypt_file(key: &MasterKey, path: &Path) -> Result<(TempFile, HashMap<String, String>), String> {
+    let salt = random::<SALT_SIZE>();
+    let prefix = random::<NONCE_PREFIX_SIZE>();
+    let cipher = Aes256Gcm::new_from_slice(&key.derive(&salt, b"r2drive content v1")).map_err(|e| e.to_string())?;
+    let temp = TempFile::new("enc");
+    let (source, target) = (path.to_path_buf(), temp.0.clone());
+
+    let size = tokio::task::spawn_blocking(move || -> Result<u64, String> {
+        let mut input = std::fs::File::open(&source).map_err(|e| e.to_string())?;
+        let mut output = std::io::BufWriter::new(std::fs::File::create(&target).map_err(|e| e.to_string())?);
+        let mut buf = vec![0u8; CHUNK_SIZE];
+        let mut size = 0u64;
+        // A short chunk is the last one; a file that fills its last chunk gets an empty one after it
+        for index in 0u32.. {
+            let mut filled = 0;
+            while filled < CHUNK_SIZE {
+                match input.read(&mut buf[filled..]).map_err(|e| e.to_string())? {
+                    0 => break,
+                    n => filled += n,
+                }
+            }
+            let last = filled < CHUNK_SIZE;
+            let sealed = cipher
+                .encrypt(Nonce::from_slice(&nonce(&prefix, index, last)), &buf[..filled])
+                .map_err(|_| "Encryption failed".to_string())?;
+            output.write_all(&sealed).map_err(|e| e.to_string())?;
+            size += filled as u64;
+            if last {
+                break;
+            }
+        }
+        output.flush().map_err(|e| e.to_string())?;
+        Ok(size)
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+
+    let metadata = HashMap::from([
+        (META_VERSION.to_string(), "1".to_string()),
+        (META_SALT.to_string(), STANDARD.encode(salt)),
+        (META_NONCE.to_string(), STANDARD.encode(prefix)),
+        (META_KEY_ID.to_string(), key.id()),
+        (META_SIZE.to_string(), size.to_string()),
+    ]);
+    Ok((temp, metadata))
+}
+
+/// Size of the plaintext an object decrypts to, or None if the object isn't encrypted.
+pub fn plaintext_size(metadata: Option<&HashMap<String, String>>) -> Option<u64> {
+    metadata.filter(|m| m.contains_key(META_VERSION))?.get(META_SIZE)?.parse().ok()
+}
+
+/// Decrypts a download chunk by chunk as it streams in.
+pub struct Decryptor {
+    cipher: Aes256Gcm,
+    prefix: Vec<u8>,
+    index: u32,
+    buffer: Vec<u8>,
+}
+
+impl Decryptor {
+    /// A decryptor for an object with this metadata, or None if the object isn't encrypted.
+    pub fn for_object(app: &AppHandle, metadata: Option<&HashMap<String, String>>) -> Result<Option<Self>, String> {
+        let Some(metadata) = metadata.filter(|m| m.contains_key(META_VERSION)) else {
+            return Ok(None);
+        };
+        if metadata[META_VERSION] != "1" {
+            return Err(format!("Unsupported encryption version {}", metadata[META_VERSION]));
+        }
+        let field = |name: &str| -> Result<Vec<u8>, String> {
+            let value = metadata.get(name).ok_or_else(|| format!("Encrypted object is missing {}", name))?;
+            STANDARD.decode(value).map_err(|e| e.to_string())
+        };
+        let (salt, prefix) = (field(META_SALT)?, field(META_NONCE)?);
+        if prefix.len() != NONCE_PREFIX_SIZE {
+            return Err("Encrypted object has an invalid nonce".to_string());
+        }
+
+        let key = unlocked_key(app)?;
+        if let Some(id) = metadata.get(META_KEY_ID).filter(|id| **id != key.id()) {
+            return Err(format!("Encrypted with a different key ({}) than the unlocked one ({})", id, key.id()));
+        }
+        Self::new(&key, &salt, prefix).map(Some)
+    }
+
+    fn new(key: &MasterKey, salt: &[u8], prefix: Vec<u8>) -> Result<Self, String> {
+        let cipher = Aes256Gcm::new_from_slice(&key.derive(salt, b"r2drive content v1")).map_err(|e| e.to_string())?;
+        Ok(Self { cipher, prefix, index: 0, buffer: Vec::new() })
+    }
+
+    fn open(&mut self, frame: &[u8], last: bool) -> Result<Vec<u8>, String> {
+        let plain = self.cipher
+            .decrypt(Nonce::from_slice(&nonce(&self.prefix, self.index, last)), frame)
+            .map_err(|_| "Decryption failed: the object is corrupt or was modified".to_string())?;
+        self.index += 1;
+        Ok(plain)
+    }
+
+    /// Takes the next piece of ciphertext and returns whatever plaintext it completes.
+    pub fn update(&mut self, data: &[u8]) -> Result<Vec<u8>, String> {
+        self.buffer.extend_from_slice(data);
+        let mut plain = Vec::new();
+        let frame = CHUNK_SIZE + TAG_SIZE;
+        // Only the last chunk is short, so a full frame always has another after it
+        let full = self.buffer.len() / frame * frame;
+        let pending: Vec<u8> = self.buffer.drain(..full).collect();
+        for chunk in pending.chunks(frame) {
+            plain.extend(self.open(chunk, false)?);
+        }
+        Ok(plain)
+    }
+
+    /// Decrypts the last chunk, failing if the download was cut short.
+    pub fn finish(mut self) -> Result<Vec<u8>, String> {
+        if self.buffer.len() < TAG_SIZE {
+            return Err("Decryption failed: the object is truncated".to_string());
+        }
+        let last = std::mem::take(&mut self.buffer);
+        self.open(&last, true)
+    }
+}
+
+impl BodyFilter for Decryptor {
+    fn update(&mut self, data: &[u8]) -> Result<Vec<u8>, String> {
+        Decryptor::update(self, data)
+    }
+
+    fn finish(self: Box<Self>) -> Result<Vec<u8>, String> {
+        Decryptor::finish(*self)
+    }
+}
+
+fn name_keys(key: &MasterKey) -> ([u8; 32], [u8; 32]) {
+    (key.derive(b"names", b"r2drive name cipher v1"), key.derive(b"names", b"r2drive name mac v1"))
+}
+
+/// Encrypts a name deterministically, so the same name always gives the same key and uploads
+/// still overwrite. The nonce is an HMAC of the name.
+pub fn encrypt_name(key: &MasterKey, name: &str) -> Result<String, String> {
+    let (cipher_key, mac_key) = name_keys(key);
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(&mac_key).map_err(|e| e.to_string())?;
+    mac.update(name.as_bytes());
+    let nonce = mac.finalize().into_bytes();
+    let cipher = Aes256Gcm::new_from_slice(&cipher_key).map_err(|e| e.to_string())?;
+    let sealed = cipher
+        .encrypt(Nonce::from_slice(&nonce[..12]), name.as_bytes())
+        .map_err(|_| "Encryption failed".to_string())?;
+    Ok(URL_SAFE_NO_PAD.encode([&nonce[..12], &sealed[..]].concat()))
+}
+
+/// The plaintext of an encrypted name, or None if it isn't one made with this key.
+pub fn decrypt_name(key: &MasterKey, name: &str) -> Option<String> {
+    let raw = URL_SAFE_NO_PAD.decode(name).ok()?;
+    if raw.len() < 12 + TAG_SIZE {
+        return None;
+    }
+    let cipher = Aes256Gcm::new_from_slice(&name_keys(key).0).ok()?;
+    let plain = cipher.decrypt(Nonce::from_slice(&raw[..12]), &raw[12..]).ok()?;
+    String::from_utf8(plain).ok()
+}
+
+/// Encrypts the last segment of `key` if name encryption is on.
+pub fn object_key(settings: &EncryptionSettings, master: &MasterKey, key: &str) -> Result<String, String> {
+    if !settings.encrypt_names {
+        return Ok(key.to_string());
+    }
+    let (folder, name) = key.rsplit_once('/').map(|(f, n)| (format!("{}/", f), n)).unwrap_or((String::new(), key));
+    Ok(format!("{}{}", folder, encrypt_name(master, name)?))
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EncryptionStatus {
+    pub enabled: bool,
+    pub encrypt_names: bool,
+    pub unlocked: bool,
+    /// Id of the unlocked key, or of the key last used if locked.
+    pub key_id: Option<String>,
+}
+
+#[tauri::command]
+pub fn encryption_status(state: State<'_, AppState>, encryption: State<'_, EncryptionState>) -> EncryptionStatus {
+    let settings = state.settings.lock().unwrap().encryption.clone();
+    let key = encryption.key.lock().unwrap().clone();
+    EncryptionStatus {
+        enabled: settings.enabled,
+        encrypt_names: settings.encrypt_names,
+        unlocked: key.is_some(),
+        key_id: key.map(|k| k.id()).or(settings.key_id),
+    }
+}
+
+/// Derives the master key from a passphrase (Argon2id) or a keyfile and keeps it in memory until
+/// locked or the app exits. Fails if it isn't the key used before unless `replace` is set, which
+/// makes it the key for new uploads; objects encrypted with the old key then need the old key.
+#[tauri::command]
+pub async fn unlock_encryption(
+    passphrase: Option<String>,
+    keyfile: Option<String>,
+    replace: Option<bool>,
+    app: AppHandle,
+    state: State<'_, AppState>,
+    encryption: State<'_, EncryptionState>,
+) -> Result<EncryptionStatus, AppError> {
+    let kdf_salt = state.settings.lock().unwrap().encryption.kdf_salt.clone();
+    let (key, salt) = match (passphrase, keyfile) {
+        (Some(passphrase), None) if !passphrase.is_empty() => {
+            let salt = match &kdf_salt {
+                Some(salt) => STANDARD.decode(salt).map_err(|e| e.to_string())?,
+                None => random::<SALT_SIZE>().to_vec(),
+            };
+            let derived = tokio::task::spawn_blocking({
+                let salt = salt.clone();
+                move || -> Result<MasterKey, String> {
+                    let mut key = [0u8; 32];
+                    Argon2::default().hash_password_into(passphrase.as_bytes(), &salt, &mut key).map_err(|e| e.to_string())?;
+                    Ok(MasterKey(key))
+                }
+            })
+            .await
+            .map_err(|e| e.to_string())??;
+            (derived, Some(STANDARD.encode(&salt)))
+        }
+        (None, Some(keyfile)) => {
+            let contents = std::fs::read(&keyfile).map_err(|e| format!("Can't read keyfile: {}", e))?;
+            if contents.len() < KEYFILE_SIZE {
+                return Err(AppError::new(ErrorKind::InvalidInput, "Keyfile must be at least 32 bytes"));
+            }
+            (MasterKey(Sha256::digest(&contents).into()), kdf_salt)
+        }
+        _ => return Err(AppError::new(ErrorKind::InvalidInput, "Give either a passphrase or a keyfile")),
+    };
+
+    let mut settings = state.settings.lock().unwrap().clone();
+    if settings.encryption.key_id.as_ref().is_some_and(|id| *id != key.id()) && !replace.unwrap_or(false) {
+        return Err(AppError::new(ErrorKind::PreconditionFailed, "This isn't the key files were encrypted with"));
+    }
+    settings.encryption.key_id = Some(key.id());
+    settings.encryption.kdf_salt = salt;
+    settings::save(&app, &settings)?;
+    state.apply_settings(settings);
+    *encryption.key.lock().unwrap() = Some(key);
+
+    Ok(encryption_status(state, encryption))
+}
+
+#[tauri::command]
+pub fn lock_encryption(encryption: State<'_, EncryptionState>) {
+    encryption.key.lock().unwrap().take();
+}
+
+/// Writes a new random keyfile to `path`. Losing it means losing everything encrypted with it.
+#[tauri::command]
+pub fn create_keyfile(path: String) -> Result<(), AppError> {
+    if Path::new(&path).exists() {
+        return Err(AppError::new(ErrorKind::AlreadyExists, format!("{} already exists", path)));
+    }
+    std::fs::write(&path, random::<KEYFILE_SIZE>()).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Plaintext names for listed keys, by last segment; None for keys whose name isn't encrypted
+/// with the unlocked key.
+#[tauri::command]
+pub fn decrypt_key_names(keys: Vec<String>, app: AppHandle) -> Result<Vec<Option<String>>, AppError> {
+    let key = unlocked_key(&app)?;
+    Ok(keys
+        .iter()
+        .map(|k| {
+            let name = k.trim_end_matches('/').rsplit('/').next().unwrap_or_default();
+            decrypt_name(&key, name)
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn round_trip(size: usize) {
+        let key = MasterKey([7u8; 32]);
+        let plain: Vec<u8> = (0..size).map(|i| (i % 251) as u8).collect();
+        let source = TempFile::new("bin");
+        std::fs::write(&source.0, &plain).unwrap();
+
+        let (sealed, metadata) = encrypt_file(&key, &source.0).await.unwrap();
+        let ciphertext = std::fs::read(&sealed.0).unwrap();
+        assert_eq!(ciphertext.len(), size + (size / CHUNK_SIZE + 1) * TAG_SIZE);
+        assert_eq!(plaintext_size(Some(&metadata)), Some(size as u64));
+
+        let salt = STANDARD.decode(&metadata[META_SALT]).unwrap();
+        let prefix = STANDARD.decode(&metadata[META_NONCE]).unwrap();
+        let mut decryptor = Decryptor::new(&key, &salt, prefix).unwrap();
+        // Pieces that don't line up with frames, as a download delivers them
+        let mut decrypted = Vec::new();
+        for piece in ciphertext.chunks(1000) {
+            decrypted.extend(decryptor.update(piece).unwrap());
+        }
+        decrypted.extend(decryptor.finish().unwrap());
+        assert_eq!(decrypted, plain);
+    }
+
+    #[tokio::test]
+    async fn round_trips_any_size() {
+        for size in [0, 1, CHUNK_SIZE - 1, CHUNK_SIZE + 1, 3 * CHUNK_SIZE + 17] {
+            round_trip(size).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn round_trips_exact_chunk_multiples() {
+        for size in [CHUNK_SIZE, 2 * CHUNK_SIZE] {
+            round_trip(size).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_truncated_and_modified_objects() {
+        let key = MasterKey([7u8; 32]);
+        let source = TempFile::new("bin");
+        std::fs::write(&source.0, vec![1u8; 2 * CHUNK_SIZE + 10]).unwrap();
+        let (sealed, metadata) = encrypt_file(&key, &source.0).await.unwrap();
+        let ciphertext = std::fs::read(&sealed.0).unwrap();
+        let salt = STANDARD.decode(&metadata[META_SALT]).unwrap();
+        let prefix = STANDARD.decode(&metadata[META_NONCE]).unwrap();
+
+        // Cut after whole frames: there is no last chunk left to open
+        let mut decryptor = Decryptor::new(&key, &salt, prefix.clone()).unwrap();
+        decryptor.update(&ciphertext[..2 * (CHUNK_SIZE + TAG_SIZE)]).unwrap();
+        assert!(decryptor.finish().is_err());
+
+        let mut modified = ciphertext.clone();
+        modified[10] ^= 1;
+        let mut decryptor = Decryptor::new(&key, &salt, prefix.clone()).unwrap();
+        assert!(decryptor.update(&modified).is_err());
+
+        let mut decryptor = Decryptor::new(&MasterKey([8u8; 32]), &salt, prefix).unwrap();
+        assert!(decryptor.update(&ciphertext).is_err());
+    }
+}
@@ -0,0 +1,135 @@
+use aws_sdk_s3::config::http::HttpResponse;
+use aws_sdk_s3::error::{DisplayErrorContext, ProvideErrorMetadata, SdkError};
+use aws_sdk_s3::operation::RequestId;
+use serde::Serialize;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    NotConnected,
+    ReadOnly,
+    InvalidInput,
+    NotFound,
+    AlreadyExists,
+    /// An If-Match / If-None-Match condition failed, e.g. the object changed since it was read.
+    PreconditionFailed,
+    AccessDenied,
+    InvalidCredentials,
+    ClockSkew,
+    Throttled,
+    Timeout,
+    Network,
+    Unsupported,
+    Cancelled,
+    /// The endpoint failed on its side (5xx).
+    Service,
+    /// Anything raised by the app itself rather than the endpoint.
+    Internal,
+}
+
+impl ErrorKind {
+    fn retryable(self) -> bool {
+        matches!(self, ErrorKind::Throttled | ErrorKind::Timeout | ErrorKind::Network | ErrorKind::Service)
+    }
+}
+
+/// Error returned by every command. `kind` is stable and meant for the UI to branch on (and
+/// localize); `message` is for display.
+#[derive(Debug, Clone, Serialize)]
+pub struct AppError {
+    pub kind: ErrorKind,
+    pub message: String,
+    pub retryable: bool,
+    pub http_status: Option<u16>,
+    pub request_id: Option<String>,
+}
+
+impl AppError {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        Self { kind, message: message.into(), retryable: kind.retryable(), http_status: None, request_id: None }
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for AppError {}
+
+// Helpers that still report plain strings surface as internal errors
+impl From<String> for AppError {
+    fn from(message: String) -> Self {
+        AppError::new(ErrorKind::Internal, message)
+    }
+}
+
+impl From<&str> for AppError {
+    fn from(message: &str) -> Self {
+        AppError::new(ErrorKind::Internal, message)
+    }
+}
+
+impl From<AppError> for String {
+    fn from(err: AppError) -> Self {
+        err.message
+    }
+}
+
+fn kind_for(code: Option<&str>, status: Option<u16>) -> ErrorKind {
+    match code {
+        Some("NoSuchKey" | "NoSuchBucket" | "NoSuchUpload" | "NoSuchVersion" | "NotFound") => ErrorKind::NotFound,
+        Some("AccessDenied" | "AllAccessDisabled") => ErrorKind::AccessDenied,
+        Some("InvalidAccessKeyId" | "SignatureDoesNotMatch" | "Unauthorized" | "ExpiredToken" | "InvalidToken") => {
+            ErrorKind::InvalidCredentials
+        }
+        Some("RequestTimeTooSkewed") => ErrorKind::ClockSkew,
+        Some("SlowDown" | "TooManyRequests" | "Throttling" | "ThrottlingException") => ErrorKind::Throttled,
+        Some("PreconditionFailed") => ErrorKind::PreconditionFailed,
+        Some("BucketAlreadyExists" | "BucketAlreadyOwnedByYou") => ErrorKind::AlreadyExists,
+        Some("BucketNotEmpty" | "InvalidBucketName" | "InvalidArgument" | "InvalidRequest" | "EntityTooLarge" | "InvalidRange") => {
+            ErrorKind::InvalidInput
+        }
+        Some("NotImplemented") => ErrorKind::Unsupported,
+        Some("InternalError" | "ServiceUnavailable") => ErrorKind::Service,
+        _ => match status {
+            Some(400) => ErrorKind::InvalidInput,
+            Some(401) => ErrorKind::InvalidCredentials,
+            Some(403) => ErrorKind::AccessDenied,
+            Some(404) => ErrorKind::NotFound,
+            Some(409) => ErrorKind::AlreadyExists,
+            Some(412) => ErrorKind::PreconditionFailed,
+            Some(429) | Some(503) => ErrorKind::Throttled,
+            Some(501) => ErrorKind::Unsupported,
+            Some(s) if s >= 500 => ErrorKind::Service,
+            _ => ErrorKind::Internal,
+        },
+    }
+}
+
+impl<E> From<SdkError<E, HttpResponse>> for AppError
+where
+    E: ProvideErrorMetadata + std::error::Error + 'static,
+{
+    fn from(err: SdkError<E, HttpResponse>) -> Self {
+        let http_status = err.raw_response().map(|r| r.status().as_u16());
+        let request_id = err.request_id().map(|id| id.to_string());
+
+        let kind = match &err {
+            SdkError::TimeoutError(_) => ErrorKind::Timeout,
+            SdkError::DispatchFailure(failure) if failure.is_timeout() => ErrorKind::Timeout,
+            SdkError::DispatchFailure(_) => ErrorKind::Network,
+            SdkError::ResponseError(_) => ErrorKind::Service,
+            _ => kind_for(err.code(), http_status),
+        };
+        let message = match (err.code(), err.message()) {
+            (_, Some(message)) if !message.is_empty() => message.to_string(),
+            (Some(code), _) => code.to_string(),
+            _ => DisplayErrorContext(&err).to_string(),
+        };
+
+        AppError { kind, message, retryable: kind.retryable(), http_status, request_id }
+    }
+}
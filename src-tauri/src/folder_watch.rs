@@ -0,0 +1,242 @@
+use notify::{EventKind, RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tokio::sync::mpsc;
+
+use crate::error::{AppError, ErrorKind};
+use crate::jobs;
+use crate::s3::{acquire_write_client, upload_path, wait_if_paused, AppState};
+use crate::settings;
+use crate::sync::{self, SyncFailure};
+
+// Local folders mapped to a prefix: changed files are uploaded once the folder has been quiet
+// for `debounce_ms`, so a burst of saves becomes one batch. Deletions are not propagated.
+const DEFAULT_DEBOUNCE_MS: u64 = 2000;
+const MIN_DEBOUNCE_MS: u64 = 250;
+static SEQUENCE: AtomicU64 = AtomicU64::new(0);
+
+fn default_debounce_ms() -> u64 {
+    DEFAULT_DEBOUNCE_MS
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FolderWatch {
+    /// Assigned on first save.
+    #[serde(default)]
+    pub id: String,
+    pub local_root: String,
+    pub bucket: String,
+    #[serde(default)]
+    pub prefix: String,
+    #[serde(default)]
+    pub connection_id: Option<String>,
+    /// Gitignore-style patterns for files to leave alone, on top of the root's `.r2ignore`.
+    #[serde(default)]
+    pub ignore: Vec<String>,
+    #[serde(default = "default_debounce_ms")]
+    pub debounce_ms: u64,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FolderWatchStatus {
+    #[serde(flatten)]
+    pub watch: FolderWatch,
+    pub running: bool,
+}
+
+/// Payload of `folder-watch://synced`.
+#[derive(Debug, Clone, Serialize)]
+pub struct FolderWatchBatch {
+    pub watch_id: String,
+    pub uploaded: Vec<String>,
+    pub failed: Vec<SyncFailure>,
+}
+
+#[derive(Default)]
+pub struct FolderWatchState {
+    running: Mutex<HashMap<String, JoinHandle<()>>>,
+}
+
+fn relative_to(root: &Path, path: &Path) -> Option<String> {
+    let relative = path.strip_prefix(root).ok()?;
+    let parts: Vec<String> = relative.components().map(|c| c.as_os_str().to_string_lossy().to_string()).collect();
+    (!parts.is_empty()).then(|| parts.join("/"))
+}
+
+async fn upload_batch(app: &AppHandle, watch: &FolderWatch, root: &Path, relatives: BTreeSet<String>) {
+    let prefix = sync::normalize_prefix(&watch.prefix);
+    let mut batch = FolderWatchBatch { watch_id: watch.id.clone(), uploaded: Vec::new(), failed: Vec::new() };
+    let job = jobs::start("folder_watch", Some(&watch.bucket), json!({
+        "local_root": watch.local_root,
+        "prefix": prefix,
+        "files": relatives.len(),
+    }));
+
+    let state = app.state::<AppState>();
+    let result: Result<(), AppError> = async {
+        let client = acquire_write_client(&state, watch.connection_id.as_deref())?;
+        for relative in relatives {
+            let Some(path) = sync::local_path(root, &relative) else { continue };
+            // Gone again, or a folder
+            if !path.is_file() {
+                continue;
+            }
+            wait_if_paused(&state).await;
+            let key = format!("{}{}", prefix, relative);
+            match upload_path(app, &client, &watch.bucket, &key, &path).await {
+                Ok(_) => batch.uploaded.push(relative),
+                Err(error) => batch.failed.push(SyncFailure { relative, error }),
+            }
+        }
+        Ok(())
+    }
+    .await;
+    job.finish(app, &result, |_| format!("Uploaded {} changed files, {} failed", batch.uploaded.len(), batch.failed.len()));
+
+    if let Err(e) = result {
+        batch.failed.push(SyncFailure { relative: String::new(), error: e.message });
+    }
+    let _ = app.emit("folder-watch://synced", &batch);
+}
+
+/// Starts watching `watch.local_root`, replacing a running watcher with the same id.
+fn start(app: &AppHandle, watch: FolderWatch) -> Result<(), String> {
+    let root = PathBuf::from(&watch.local_root);
+    let mut rules = sync::ignore_rules(&root, &watch.ignore)?;
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        if let Ok(event) = event {
+            let _ = tx.send(event);
+        }
+    })
+    .map_err(|e| e.to_string())?;
+    watcher.watch(&root, RecursiveMode::Recursive).map_err(|e| format!("Cannot watch {}: {}", root.display(), e))?;
+
+    let debounce = Duration::from_millis(watch.debounce_ms.max(MIN_DEBOUNCE_MS));
+    let id = watch.id.clone();
+    let task_app = app.clone();
+    let handle = tauri::async_runtime::spawn(async move {
+        // Dropping the watcher stops the notifications
+        let _watcher = watcher;
+        let mut pending = BTreeSet::new();
+        let mut collect = |event: notify::Event, pending: &mut BTreeSet<String>| {
+            if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+                for path in event.paths {
+                    let Some(relative) = relative_to(&root, &path) else { continue };
+                    if relative == sync::IGNORE_FILE {
+                        // Keep the old rules while the file is half-written or invalid
+                        if let Ok(updated) = sync::ignore_rules(&root, &watch.ignore) {
+                            rules = updated;
+                        }
+                    }
+                    if !sync::is_ignored(&rules, &relative, path.is_dir()) {
+                        pending.insert(relative);
+                    }
+                }
+            }
+        };
+        while let Some(event) = rx.recv().await {
+            collect(event, &mut pending);
+            // Wait for the folder to go quiet before uploading
+            while let Ok(Some(event)) = tokio::time::timeout(debounce, rx.recv()).await {
+                collect(event, &mut pending);
+            }
+            if !pending.is_empty() {
+                upload_batch(&task_app, &watch, &root, std::mem::take(&mut pending)).await;
+            }
+        }
+    });
+
+    if let Some(previous) = app.state::<FolderWatchState>().running.lock().unwrap().insert(id, handle) {
+        previous.abort();
+    }
+    Ok(())
+}
+
+fn stop(app: &AppHandle, id: &str) -> bool {
+    match app.state::<FolderWatchState>().running.lock().unwrap().remove(id) {
+        Some(handle) => {
+            handle.abort();
+            true
+        }
+        None => false,
+    }
+}
+
+/// Starts the enabled watches at launch. One that can't start (e.g. its folder is gone) is
+/// reported as `folder-watch://error`.
+pub fn restore(app: &AppHandle) {
+    let watches = app.state::<AppState>().settings.lock().unwrap().folder_watches.clone();
+    for watch in watches.into_iter().filter(|w| w.enabled) {
+        if let Err(e) = start(app, watch.clone()) {
+            let _ = app.emit("folder-watch://error", (&watch.id, e));
+        }
+    }
+}
+
+#[tauri::command]
+pub fn list_folder_watches(state: State<'_, AppState>, watch_state: State<'_, FolderWatchState>) -> Vec<FolderWatchStatus> {
+    let running = watch_state.running.lock().unwrap();
+    state.settings.lock().unwrap()
+        .folder_watches
+        .iter()
+        .map(|w| FolderWatchStatus { running: running.contains_key(&w.id), watch: w.clone() })
+        .collect()
+}
+
+/// Adds or updates a watched folder and starts or stops it to match `enabled`.
+#[tauri::command]
+pub fn save_folder_watch(mut watch: FolderWatch, app: AppHandle, state: State<'_, AppState>) -> Result<FolderWatch, AppError> {
+    if watch.bucket.is_empty() {
+        return Err(AppError::new(ErrorKind::InvalidInput, "A bucket is required"));
+    }
+    if !Path::new(&watch.local_root).is_dir() {
+        return Err(AppError::new(ErrorKind::InvalidInput, format!("{} is not a folder", watch.local_root)));
+    }
+    sync::ignore_rules(Path::new(&watch.local_root), &watch.ignore).map_err(|e| AppError::new(ErrorKind::InvalidInput, e))?;
+    if watch.id.is_empty() {
+        watch.id = format!("{}-{}", jobs::now_secs(), SEQUENCE.fetch_add(1, Ordering::Relaxed));
+    }
+
+    let mut settings = state.settings.lock().unwrap().clone();
+    match settings.folder_watches.iter_mut().find(|w| w.id == watch.id) {
+        Some(existing) => *existing = watch.clone(),
+        None => settings.folder_watches.push(watch.clone()),
+    }
+    settings::save(&app, &settings)?;
+    state.apply_settings(settings);
+
+    if watch.enabled {
+        start(&app, watch.clone())?;
+    } else {
+        stop(&app, &watch.id);
+    }
+    Ok(watch)
+}
+
+#[tauri::command]
+pub fn delete_folder_watch(id: String, app: AppHandle, state: State<'_, AppState>) -> Result<(), AppError> {
+    let mut settings = state.settings.lock().unwrap().clone();
+    let before = settings.folder_watches.len();
+    settings.folder_watches.retain(|w| w.id != id);
+    if settings.folder_watches.len() == before {
+        return Err(AppError::new(ErrorKind::NotFound, format!("No folder watch with id '{}'", id)));
+    }
+    settings::save(&app, &settings)?;
+    state.apply_settings(settings);
+    stop(&app, &id);
+    Ok(())
+}
@@ -0,0 +1,193 @@
+use md5::Md5;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::UNIX_EPOCH;
+use tauri::{AppHandle, Emitter, Manager};
+use walkdir::WalkDir;
+
+use crate::error::AppError;
+
+// Persisted size+mtime → digest cache, so unchanged files are never re-read
+static CACHE_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CacheEntry {
+    size: u64,
+    modified_ms: i64,
+    md5: String,
+    sha256: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LocalFileHash {
+    pub path: String,
+    /// Path relative to the hashed root, always '/'-separated so it maps onto object keys.
+    pub relative_path: String,
+    pub size: u64,
+    pub modified_ms: i64,
+    /// Hex MD5, comparable with single-part R2 ETags.
+    pub md5: String,
+    pub sha256: String,
+    pub cached: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HashProgress {
+    pub total: u64,
+    pub done: u64,
+    pub bytes_hashed: u64,
+}
+
+fn cache_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    Ok(dir.join("hash_cache.json"))
+}
+
+fn load_cache(app: &AppHandle) -> HashMap<String, CacheEntry> {
+    cache_path(app)
+        .ok()
+        .and_then(|p| std::fs::read_to_string(p).ok())
+        .and_then(|raw| serde_json::from_str(&raw).ok())
+        .unwrap_or_default()
+}
+
+fn save_cache(app: &AppHandle, cache: &HashMap<String, CacheEntry>) -> Result<(), String> {
+    let path = cache_path(app)?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let raw = serde_json::to_string(cache).map_err(|e| e.to_string())?;
+    std::fs::write(path, raw).map_err(|e| e.to_string())
+}
+
+pub fn modified_ms(meta: &std::fs::Metadata) -> i64 {
+    meta.modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+pub fn relative_key(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().to_string())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Reads a file once, feeding both digests.
+pub fn hash_file(path: &Path) -> Result<(String, String), String> {
+    let mut file = std::fs::File::open(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let mut md5 = Md5::new();
+    let mut sha = Sha256::new();
+    let mut buf = vec![0u8; 1024 * 1024];
+    loop {
+        let n = file.read(&mut buf).map_err(|e| format!("{}: {}", path.display(), e))?;
+        if n == 0 {
+            break;
+        }
+        md5.update(&buf[..n]);
+        sha.update(&buf[..n]);
+    }
+    Ok((format!("{:x}", md5.finalize()), format!("{:x}", sha.finalize())))
+}
+
+/// Lists regular files below `root`.
+pub fn walk_files(root: &Path) -> Vec<PathBuf> {
+    WalkDir::new(root)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .map(|e| e.into_path())
+        .collect()
+}
+
+/// Hashes `files` on the rayon pool, reusing cached digests when size and mtime are unchanged.
+/// Emits `hash://progress` while working.
+pub fn hash_files(app: &AppHandle, root: &Path, files: &[PathBuf]) -> Result<Vec<LocalFileHash>, String> {
+    let cache = {
+        let _guard = CACHE_LOCK.lock().unwrap();
+        load_cache(app)
+    };
+
+    let total = files.len() as u64;
+    let done = AtomicU64::new(0);
+    let bytes_hashed = AtomicU64::new(0);
+
+    let results: Vec<Result<LocalFileHash, String>> = files
+        .par_iter()
+        .map(|path| {
+            let meta = std::fs::metadata(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+            let size = meta.len();
+            let modified_ms = modified_ms(&meta);
+            let cache_key = path.to_string_lossy().to_string();
+
+            let cached = cache
+                .get(&cache_key)
+                .filter(|c| c.size == size && c.modified_ms == modified_ms);
+            let (md5, sha256, was_cached) = match cached {
+                Some(c) => (c.md5.clone(), c.sha256.clone(), true),
+                None => {
+                    let (md5, sha256) = hash_file(path)?;
+                    bytes_hashed.fetch_add(size, Ordering::Relaxed);
+                    (md5, sha256, false)
+                }
+            };
+
+            let n = done.fetch_add(1, Ordering::Relaxed) + 1;
+            if n.is_multiple_of(50) || n == total {
+                let _ = app.emit("hash://progress", HashProgress {
+                    total,
+                    done: n,
+                    bytes_hashed: bytes_hashed.load(Ordering::Relaxed),
+                });
+            }
+
+            Ok(LocalFileHash {
+                path: cache_key,
+                relative_path: relative_key(root, path),
+                size,
+                modified_ms,
+                md5,
+                sha256,
+                cached: was_cached,
+            })
+        })
+        .collect();
+
+    let hashes: Vec<LocalFileHash> = results.into_iter().collect::<Result<_, _>>()?;
+
+    let _guard = CACHE_LOCK.lock().unwrap();
+    let mut cache = load_cache(app);
+    for h in hashes.iter().filter(|h| !h.cached) {
+        cache.insert(h.path.clone(), CacheEntry {
+            size: h.size,
+            modified_ms: h.modified_ms,
+            md5: h.md5.clone(),
+            sha256: h.sha256.clone(),
+        });
+    }
+    save_cache(app, &cache)?;
+
+    Ok(hashes)
+}
+
+#[tauri::command]
+pub async fn hash_local_files(root: String, app: AppHandle) -> Result<Vec<LocalFileHash>, AppError> {
+    let hashes = tauri::async_runtime::spawn_blocking(move || {
+        let root = PathBuf::from(root);
+        let files = walk_files(&root);
+        hash_files(&app, &root, &files)
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+    Ok(hashes)
+}
@@ -0,0 +1,118 @@
+use serde::Serialize;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::error::AppError;
+use crate::jobs;
+use crate::keychain;
+
+// Reachability probe for the Cloudflare edge; R2 endpoints are per account, so we can't test
+// the real one until the user connects
+const CONNECTIVITY_HOST: &str = "api.cloudflare.com:443";
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Default)]
+pub struct HealthState {
+    report: Mutex<Option<HealthReport>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthStatus {
+    Ok,
+    Degraded,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthCheck {
+    pub name: String,
+    pub status: HealthStatus,
+    pub reason: Option<String>,
+    /// Features that won't work while this check is failing.
+    pub affects: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthReport {
+    pub status: HealthStatus,
+    pub checked_at: i64,
+    pub checks: Vec<HealthCheck>,
+}
+
+fn check(name: &str, result: Result<(), String>, affects: &[&str]) -> HealthCheck {
+    let (status, reason) = match result {
+        Ok(()) => (HealthStatus::Ok, None),
+        Err(e) => (HealthStatus::Degraded, Some(e)),
+    };
+    HealthCheck {
+        name: name.to_string(),
+        status,
+        reason,
+        affects: affects.iter().map(|a| a.to_string()).collect(),
+    }
+}
+
+/// Creates `dir` if needed and round-trips a small file through it.
+fn probe_writable(dir: &Path) -> Result<(), String> {
+    std::fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    let probe = dir.join(".r2drive-health");
+    std::fs::write(&probe, b"ok").map_err(|e| format!("{} is not writable: {}", dir.display(), e))?;
+    let _ = std::fs::remove_file(probe);
+    Ok(())
+}
+
+async fn probe_connectivity() -> Result<(), String> {
+    match tokio::time::timeout(CONNECT_TIMEOUT, tokio::net::TcpStream::connect(CONNECTIVITY_HOST)).await {
+        Ok(Ok(_)) => Ok(()),
+        Ok(Err(e)) => Err(format!("Cannot reach Cloudflare: {}", e)),
+        Err(_) => Err("Timed out reaching Cloudflare".to_string()),
+    }
+}
+
+pub async fn run_checks(app: &AppHandle) -> HealthReport {
+    let paths = app.path();
+    let config_dir = paths.app_config_dir().map_err(|e| e.to_string());
+    let data_dir = paths.app_data_dir().map_err(|e| e.to_string());
+    let temp_dir = paths.temp_dir().map_err(|e| e.to_string());
+
+    let checks = vec![
+        check("connectivity", probe_connectivity().await, &["connections", "transfers"]),
+        check("config_dir", config_dir.and_then(|d| probe_writable(&d)), &["settings", "capability overrides", "api grants"]),
+        check("data_dir", data_dir.and_then(|d| probe_writable(&d)), &["job history", "hash cache", "resumable downloads", "request log", "search index", "share links"]),
+        check("temp_dir", temp_dir.and_then(|d| probe_writable(&d)), &["previews"]),
+        check("keychain", keychain::probe(), &["saved profiles", "session restore"]),
+    ];
+
+    let status = if checks.iter().all(|c| c.status == HealthStatus::Ok) {
+        HealthStatus::Ok
+    } else {
+        HealthStatus::Degraded
+    };
+
+    HealthReport { status, checked_at: jobs::now_secs(), checks }
+}
+
+/// Runs every check, stores the report and emits it as `health://report`. Called at startup.
+pub async fn refresh(app: AppHandle) -> HealthReport {
+    let report = run_checks(&app).await;
+    *app.state::<HealthState>().report.lock().unwrap() = Some(report.clone());
+    let _ = app.emit("health://report", &report);
+    report
+}
+
+/// The startup report, or a fresh one if the startup checks haven't finished yet.
+#[tauri::command]
+pub async fn get_health_report(app: AppHandle, health: State<'_, HealthState>) -> Result<HealthReport, AppError> {
+    let cached = health.report.lock().unwrap().clone();
+    match cached {
+        Some(report) => Ok(report),
+        None => Ok(refresh(app).await),
+    }
+}
+
+#[tauri::command]
+pub async fn run_health_check(app: AppHandle) -> Result<HealthReport, AppError> {
+    Ok(refresh(app).await)
+}
@@ -0,0 +1,357 @@
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::error::{AppError, ErrorKind};
+use crate::jobs;
+use crate::profiles;
+use crate::s3::{acquire_client, connection_key, is_folder_marker, AppState, ObjectEntry};
+use crate::search::{Matcher, SearchOptions};
+
+// Indexes live in index/<profile>.sqlite under the app data dir, one database per profile (or
+// per connection id for ad-hoc connections), holding every bucket indexed through it.
+const DEFAULT_INTERVAL_SECS: u64 = 15 * 60;
+const MIN_INTERVAL_SECS: u64 = 60;
+const DEFAULT_MAX_RESULTS: usize = 10_000;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS objects (
+        bucket TEXT NOT NULL,
+        key TEXT NOT NULL,
+        size INTEGER NOT NULL,
+        last_modified INTEGER NOT NULL,
+        etag TEXT NOT NULL,
+        storage_class TEXT,
+        generation INTEGER NOT NULL,
+        PRIMARY KEY (bucket, key)
+    ) WITHOUT ROWID;
+    CREATE TABLE IF NOT EXISTS buckets (
+        bucket TEXT PRIMARY KEY,
+        generation INTEGER NOT NULL,
+        indexed_at INTEGER
+    );
+";
+
+/// Running indexers, keyed by (database name, bucket).
+#[derive(Default)]
+pub struct IndexState {
+    indexers: Mutex<HashMap<(String, String), JoinHandle<()>>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IndexStatus {
+    pub bucket: String,
+    pub objects: u64,
+    pub total_size: u64,
+    /// When the last full pass finished; unset while the first one is still running.
+    pub indexed_at: Option<i64>,
+    pub running: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IndexProgress {
+    pub bucket: String,
+    pub indexed: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IndexDone {
+    pub bucket: String,
+    pub objects: u64,
+    pub removed: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IndexedFolderSize {
+    pub prefix: String,
+    pub objects: u64,
+    pub total_size: u64,
+    pub indexed_at: Option<i64>,
+}
+
+/// File stem for `connection_id`'s database: its profile name, or the connection id itself.
+pub(crate) fn db_name(app: &AppHandle, connection_id: Option<&str>) -> String {
+    let id = connection_key(connection_id);
+    let name = profiles::profile_for(app, id).unwrap_or_else(|| format!("connection-{}", id));
+    name.chars().map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' }).collect()
+}
+
+fn db_path(app: &AppHandle, name: &str) -> Result<PathBuf, String> {
+    let dir = app.path().app_data_dir().map_err(|e| e.to_string())?.join("index");
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    Ok(dir.join(format!("{}.sqlite", name)))
+}
+
+fn open(app: &AppHandle, name: &str) -> Result<Connection, String> {
+    let db = Connection::open(db_path(app, name)?).map_err(|e| e.to_string())?;
+    db.busy_timeout(Duration::from_secs(5)).map_err(|e| e.to_string())?;
+    db.execute_batch(SCHEMA).map_err(|e| e.to_string())?;
+    Ok(db)
+}
+
+/// Bounds matching every key under `prefix`. Keys compare bytewise, and no UTF-8 sequence sorts
+/// after `char::MAX`.
+fn prefix_range(prefix: &str) -> (String, String) {
+    (prefix.to_string(), format!("{}{}", prefix, char::MAX))
+}
+
+/// One pass over the bucket. Rows are upserted page by page under a new generation, then any row
+/// the pass didn't touch is gone from the bucket and gets dropped.
+async fn refresh(app: &AppHandle, connection_id: Option<&str>, name: &str, bucket: &str) -> Result<IndexDone, AppError> {
+    // Opened per pass; a Connection can't be shared across the awaits below
+    let db = open(app, name)?;
+    let state = app.state::<AppState>();
+    let client = acquire_client(&state, connection_id)?;
+
+    let generation: i64 = db
+        .query_row("SELECT generation FROM buckets WHERE bucket = ?1", [bucket], |r| r.get(0))
+        .unwrap_or(0)
+        + 1;
+
+    let mut indexed = 0u64;
+    let mut continuation_token = None;
+    loop {
+        let resp = client.list_objects_v2()
+            .bucket(bucket)
+            .set_continuation_token(continuation_token)
+            .send()
+            .await?;
+
+        let tx = db.unchecked_transaction().map_err(|e| e.to_string())?;
+        {
+            let mut upsert = tx.prepare_cached(
+                "INSERT INTO objects (bucket, key, size, last_modified, etag, storage_class, generation)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+                 ON CONFLICT (bucket, key) DO UPDATE SET size = excluded.size,
+                    last_modified = excluded.last_modified, etag = excluded.etag,
+                    storage_class = excluded.storage_class, generation = excluded.generation",
+            ).map_err(|e| e.to_string())?;
+            for obj in resp.contents() {
+                if is_folder_marker(obj.key().unwrap_or_default(), obj.size().unwrap_or_default()) {
+                    continue;
+                }
+                let entry = ObjectEntry::from(obj);
+                upsert.execute(params![
+                    bucket,
+                    entry.key,
+                    entry.size as i64,
+                    entry.last_modified,
+                    entry.etag,
+                    entry.storage_class,
+                    generation,
+                ]).map_err(|e| e.to_string())?;
+                indexed += 1;
+            }
+        }
+        tx.commit().map_err(|e| e.to_string())?;
+        let _ = app.emit("index://progress", IndexProgress { bucket: bucket.to_string(), indexed });
+
+        if !resp.is_truncated().unwrap_or(false) {
+            break;
+        }
+        continuation_token = resp.next_continuation_token().map(|t| t.to_string());
+    }
+
+    let tx = db.unchecked_transaction().map_err(|e| e.to_string())?;
+    let removed = tx
+        .execute("DELETE FROM objects WHERE bucket = ?1 AND generation <> ?2", params![bucket, generation])
+        .map_err(|e| e.to_string())?;
+    tx.execute(
+        "INSERT INTO buckets (bucket, generation, indexed_at) VALUES (?1, ?2, ?3)
+         ON CONFLICT (bucket) DO UPDATE SET generation = excluded.generation, indexed_at = excluded.indexed_at",
+        params![bucket, generation, jobs::now_secs()],
+    ).map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())?;
+
+    Ok(IndexDone { bucket: bucket.to_string(), objects: indexed, removed: removed as u64 })
+}
+
+/// Starts indexing `bucket` in the background: a full pass now, then a re-listing every
+/// `interval_secs` that applies only what changed. Progress arrives as `index://progress`, each
+/// pass ends with `index://done` or `index://error`. Restarts the indexer if one is running.
+#[tauri::command]
+pub fn start_indexing(
+    bucket: String,
+    interval_secs: Option<u64>,
+    connection_id: Option<String>,
+    app: AppHandle,
+    index_state: State<'_, IndexState>,
+) -> Result<(), AppError> {
+    let name = db_name(&app, connection_id.as_deref());
+    // Fail here rather than in the background when the database can't be opened
+    open(&app, &name)?;
+    let interval = Duration::from_secs(interval_secs.unwrap_or(DEFAULT_INTERVAL_SECS).max(MIN_INTERVAL_SECS));
+
+    let task_app = app.clone();
+    let (task_name, task_bucket) = (name.clone(), bucket.clone());
+    let handle = tauri::async_runtime::spawn(async move {
+        loop {
+            match refresh(&task_app, connection_id.as_deref(), &task_name, &task_bucket).await {
+                Ok(done) => {
+                    let _ = task_app.emit("index://done", done);
+                }
+                Err(e) => {
+                    let _ = task_app.emit("index://error", (&task_bucket, e));
+                }
+            }
+            tokio::time::sleep(interval).await;
+        }
+    });
+
+    if let Some(previous) = index_state.indexers.lock().unwrap().insert((name, bucket), handle) {
+        previous.abort();
+    }
+    Ok(())
+}
+
+/// Stops refreshing `bucket`. The index stays on disk and can still be queried.
+#[tauri::command]
+pub fn stop_indexing(
+    bucket: String,
+    connection_id: Option<String>,
+    app: AppHandle,
+    index_state: State<'_, IndexState>,
+) -> bool {
+    let name = db_name(&app, connection_id.as_deref());
+    match index_state.indexers.lock().unwrap().remove(&(name, bucket)) {
+        Some(handle) => {
+            handle.abort();
+            true
+        }
+        None => false,
+    }
+}
+
+/// Stops indexing `bucket` and deletes what was indexed for it.
+#[tauri::command]
+pub fn drop_index(
+    bucket: String,
+    connection_id: Option<String>,
+    app: AppHandle,
+    index_state: State<'_, IndexState>,
+) -> Result<(), AppError> {
+    let name = db_name(&app, connection_id.as_deref());
+    if let Some(handle) = index_state.indexers.lock().unwrap().remove(&(name.clone(), bucket.clone())) {
+        handle.abort();
+    }
+    let db = open(&app, &name)?;
+    db.execute("DELETE FROM objects WHERE bucket = ?1", [&bucket]).map_err(|e| e.to_string())?;
+    db.execute("DELETE FROM buckets WHERE bucket = ?1", [&bucket]).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Every bucket indexed for the connection's profile.
+#[tauri::command]
+pub fn index_status(
+    connection_id: Option<String>,
+    app: AppHandle,
+    index_state: State<'_, IndexState>,
+) -> Result<Vec<IndexStatus>, AppError> {
+    let name = db_name(&app, connection_id.as_deref());
+    let db = open(&app, &name)?;
+    let indexers = index_state.indexers.lock().unwrap();
+
+    let mut stmt = db.prepare(
+        "SELECT b.bucket, b.indexed_at, COUNT(o.key), COALESCE(SUM(o.size), 0)
+         FROM buckets b LEFT JOIN objects o ON o.bucket = b.bucket
+         GROUP BY b.bucket ORDER BY b.bucket",
+    ).map_err(|e| e.to_string())?;
+    let mut statuses: Vec<IndexStatus> = stmt
+        .query_map([], |r| {
+            Ok(IndexStatus {
+                bucket: r.get(0)?,
+                indexed_at: r.get(1)?,
+                objects: r.get::<_, i64>(2)? as u64,
+                total_size: r.get::<_, i64>(3)? as u64,
+                running: false,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<_, _>>()
+        .map_err(|e| e.to_string())?;
+
+    // Buckets whose first pass hasn't finished yet have no row in `buckets`
+    for (_, bucket) in indexers.keys().filter(|(n, _)| *n == name) {
+        if !statuses.iter().any(|s| &s.bucket == bucket) {
+            statuses.push(IndexStatus { bucket: bucket.clone(), objects: 0, total_size: 0, indexed_at: None, running: true });
+        }
+    }
+    for status in &mut statuses {
+        status.running = indexers.contains_key(&(name.clone(), status.bucket.clone()));
+    }
+    Ok(statuses)
+}
+
+/// Same matching as `search_objects`, answered from the index instead of the bucket.
+#[tauri::command]
+pub fn search_index(
+    bucket: String,
+    query: String,
+    options: Option<SearchOptions>,
+    connection_id: Option<String>,
+    app: AppHandle,
+) -> Result<Vec<ObjectEntry>, AppError> {
+    let options = options.unwrap_or_default();
+    let matcher = Matcher::new(&query, options.mode, options.case_sensitive)?;
+    let max_results = options.max_results.unwrap_or(DEFAULT_MAX_RESULTS).max(1);
+    let (from, to) = prefix_range(options.prefix.as_deref().unwrap_or(""));
+
+    let db = open(&app, &db_name(&app, connection_id.as_deref()))?;
+    let mut stmt = db.prepare(
+        "SELECT key, size, last_modified, etag, storage_class FROM objects
+         WHERE bucket = ?1 AND key >= ?2 AND key < ?3 ORDER BY key",
+    ).map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(params![bucket, from, to], |r| {
+            Ok(ObjectEntry {
+                key: r.get(0)?,
+                size: r.get::<_, i64>(1)? as u64,
+                last_modified: r.get(2)?,
+                etag: r.get(3)?,
+                storage_class: r.get(4)?,
+            })
+        })
+        .map_err(|e| e.to_string())?;
+
+    let mut matches = Vec::new();
+    for row in rows {
+        let entry = row.map_err(|e| e.to_string())?;
+        if matcher.is_match(&entry.key) && options.filter.matches_file(&entry) {
+            matches.push(entry);
+            if matches.len() == max_results {
+                break;
+            }
+        }
+    }
+    Ok(matches)
+}
+
+/// Object count and total size under `prefix`, from the index.
+#[tauri::command]
+pub fn index_folder_size(
+    bucket: String,
+    prefix: String,
+    connection_id: Option<String>,
+    app: AppHandle,
+) -> Result<IndexedFolderSize, AppError> {
+    let db = open(&app, &db_name(&app, connection_id.as_deref()))?;
+    let indexed_at: Option<i64> = db
+        .query_row("SELECT indexed_at FROM buckets WHERE bucket = ?1", [&bucket], |r| r.get(0))
+        .map_err(|_| AppError::new(ErrorKind::NotFound, format!("Bucket '{}' hasn't been indexed", bucket)))?;
+
+    let (from, to) = prefix_range(&prefix);
+    let (objects, total_size): (i64, i64) = db
+        .query_row(
+            "SELECT COUNT(*), COALESCE(SUM(size), 0) FROM objects WHERE bucket = ?1 AND key >= ?2 AND key < ?3",
+            params![bucket, from, to],
+            |r| Ok((r.get(0)?, r.get(1)?)),
+        )
+        .map_err(|e| e.to_string())?;
+
+    Ok(IndexedFolderSize { prefix, objects: objects as u64, total_size: total_size as u64, indexed_at })
+}
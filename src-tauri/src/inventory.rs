@@ -0,0 +1,252 @@
+use aws_smithy_types::date_time::Format;
+use aws_smithy_types::DateTime;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use tauri::{AppHandle, State};
+
+use crate::error::{AppError, ErrorKind};
+use crate::jobs;
+use crate::s3::{acquire_client, is_folder_marker, list_all_objects, AppState, ObjectEntry};
+
+const CSV_HEADER: &str = "key,size,etag,last_modified,storage_class";
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InventoryFormat {
+    Csv,
+    Json,
+}
+
+/// A JSON inventory file. CSV inventories hold just the objects, one row each.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Inventory {
+    pub bucket: String,
+    pub prefix: String,
+    pub generated_at: i64,
+    pub objects: Vec<ObjectEntry>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct InventorySummary {
+    pub path: String,
+    pub objects: u64,
+    pub total_size: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ChangedObject {
+    pub key: String,
+    pub before: ObjectEntry,
+    pub after: ObjectEntry,
+}
+
+/// What changed going from the base inventory to the target. Lists are sorted by key.
+#[derive(Debug, Clone, Serialize)]
+pub struct InventoryDiff {
+    pub added: Vec<ObjectEntry>,
+    pub removed: Vec<ObjectEntry>,
+    pub changed: Vec<ChangedObject>,
+    pub unchanged: u64,
+}
+
+pub fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn to_csv(objects: &[ObjectEntry]) -> String {
+    let mut out = String::from(CSV_HEADER);
+    out.push('\n');
+    for o in objects {
+        let modified = DateTime::from_secs(o.last_modified).fmt(Format::DateTime).unwrap_or_default();
+        out.push_str(&format!(
+            "{},{},{},{},{}\n",
+            csv_field(&o.key),
+            o.size,
+            csv_field(&o.etag),
+            modified,
+            csv_field(o.storage_class.as_deref().unwrap_or("")),
+        ));
+    }
+    out
+}
+
+/// Splits CSV text into records, handling quoted fields with embedded commas, quotes and newlines.
+pub fn parse_csv(text: &str, delimiter: char) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (quoted, c) {
+            (true, '"') if chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            (true, '"') => quoted = false,
+            (true, c) => field.push(c),
+            (false, '"') if field.is_empty() => quoted = true,
+            (false, c) if c == delimiter => record.push(std::mem::take(&mut field)),
+            (false, '\r') => {}
+            (false, '\n') => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            (false, c) => field.push(c),
+        }
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    records
+}
+
+fn from_csv(text: &str) -> Result<Vec<ObjectEntry>, String> {
+    let mut records = parse_csv(text, ',').into_iter();
+    if records.next().map(|h| h.join(",")) != Some(CSV_HEADER.to_string()) {
+        return Err(format!("Not an inventory CSV (expected header '{}')", CSV_HEADER));
+    }
+    records
+        .enumerate()
+        .map(|(i, r)| {
+            let [key, size, etag, modified, storage_class] = <[String; 5]>::try_from(r)
+                .map_err(|_| format!("Row {} doesn't have 5 columns", i + 2))?;
+            Ok(ObjectEntry {
+                key,
+                size: size.parse().map_err(|_| format!("Row {}: invalid size '{}'", i + 2, size))?,
+                etag,
+                last_modified: DateTime::from_str(&modified, Format::DateTime).map(|d| d.secs()).unwrap_or(0),
+                storage_class: Some(storage_class).filter(|s| !s.is_empty()),
+            })
+        })
+        .collect()
+}
+
+/// Reads an inventory written by `export_inventory`, detecting the format from its contents.
+async fn read_inventory(path: &str) -> Result<(Option<Inventory>, Vec<ObjectEntry>), AppError> {
+    let text = tokio::fs::read_to_string(path)
+        .await
+        .map_err(|e| AppError::new(ErrorKind::NotFound, format!("Cannot read {}: {}", path, e)))?;
+    let invalid = |e: String| AppError::new(ErrorKind::InvalidInput, format!("{}: {}", path, e));
+
+    if text.trim_start().starts_with('{') {
+        let inventory: Inventory = serde_json::from_str(&text).map_err(|e| invalid(e.to_string()))?;
+        let objects = inventory.objects.clone();
+        Ok((Some(inventory), objects))
+    } else {
+        Ok((None, from_csv(&text).map_err(invalid)?))
+    }
+}
+
+fn diff(base: Vec<ObjectEntry>, mut target: Vec<ObjectEntry>) -> InventoryDiff {
+    let mut base: BTreeMap<String, ObjectEntry> = base.into_iter().map(|o| (o.key.clone(), o)).collect();
+    let mut result = InventoryDiff { added: Vec::new(), removed: Vec::new(), changed: Vec::new(), unchanged: 0 };
+
+    target.sort_by(|a, b| a.key.cmp(&b.key));
+    for after in target {
+        match base.remove(&after.key) {
+            None => result.added.push(after),
+            // Fall back to the modification time for endpoints that don't report ETags
+            Some(before) if before.etag != after.etag || before.size != after.size
+                || (before.etag.is_empty() && before.last_modified != after.last_modified) => {
+                result.changed.push(ChangedObject { key: after.key.clone(), before, after });
+            }
+            Some(_) => result.unchanged += 1,
+        }
+    }
+    result.removed = base.into_values().collect();
+    result
+}
+
+/// Compares the inventory at `base_path` with the one at `target_path`, or with a live listing
+/// when `target_path` is unset. The live listing covers `bucket`/`prefix`, which default to those
+/// recorded in a JSON base inventory.
+#[tauri::command]
+pub async fn diff_inventory(
+    base_path: String,
+    target_path: Option<String>,
+    bucket: Option<String>,
+    prefix: Option<String>,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<InventoryDiff, AppError> {
+    let (base_meta, base) = read_inventory(&base_path).await?;
+
+    let target = match target_path {
+        Some(path) => read_inventory(&path).await?.1,
+        None => {
+            let bucket = bucket.or_else(|| base_meta.as_ref().map(|m| m.bucket.clone())).ok_or_else(|| {
+                AppError::new(ErrorKind::InvalidInput, "A bucket is needed to compare a CSV inventory with the live bucket")
+            })?;
+            let prefix = prefix.or_else(|| base_meta.as_ref().map(|m| m.prefix.clone())).unwrap_or_default();
+
+            let client = acquire_client(&state, connection_id.as_deref())?;
+            list_all_objects(&client, &bucket, Some(&prefix))
+                .await?
+                .iter()
+                .filter(|o| !is_folder_marker(o.key().unwrap_or_default(), o.size().unwrap_or_default()))
+                .map(ObjectEntry::from)
+                .collect()
+        }
+    };
+
+    Ok(diff(base, target))
+}
+
+/// Lists every object under `prefix` and writes the inventory to `path` as CSV (with an RFC 3339
+/// `last_modified`) or JSON.
+#[tauri::command]
+pub async fn export_inventory(
+    bucket: String,
+    prefix: Option<String>,
+    format: InventoryFormat,
+    path: String,
+    app: AppHandle,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<InventorySummary, AppError> {
+    let prefix = prefix.unwrap_or_default();
+    let job = jobs::start("export_inventory", Some(&bucket), json!({ "prefix": prefix, "path": path }));
+    let result: Result<InventorySummary, AppError> = async {
+        let client = acquire_client(&state, connection_id.as_deref())?;
+        let objects: Vec<ObjectEntry> = list_all_objects(&client, &bucket, Some(&prefix))
+            .await?
+            .iter()
+            .filter(|o| !is_folder_marker(o.key().unwrap_or_default(), o.size().unwrap_or_default()))
+            .map(ObjectEntry::from)
+            .collect();
+
+        let summary = InventorySummary {
+            path: path.clone(),
+            objects: objects.len() as u64,
+            total_size: objects.iter().map(|o| o.size).sum(),
+        };
+        let contents = match format {
+            InventoryFormat::Csv => to_csv(&objects),
+            InventoryFormat::Json => {
+                let inventory = Inventory { bucket: bucket.clone(), prefix: prefix.clone(), generated_at: jobs::now_secs(), objects };
+                serde_json::to_string_pretty(&inventory).map_err(|e| e.to_string())?
+            }
+        };
+
+        let path = PathBuf::from(&path);
+        if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            tokio::fs::create_dir_all(dir).await.map_err(|e| e.to_string())?;
+        }
+        tokio::fs::write(&path, contents)
+            .await
+            .map_err(|e| AppError::new(ErrorKind::InvalidInput, format!("Cannot write {}: {}", path.display(), e)))?;
+        Ok(summary)
+    }.await;
+
+    job.finish(&app, &result, |s| format!("Exported {} objects", s.objects));
+    result
+}
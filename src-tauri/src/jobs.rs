@@ -0,0 +1,168 @@
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager};
+
+// Completed jobs are appended to a JSON-lines archive under the app data dir
+static ARCHIVE_LOCK: Mutex<()> = Mutex::new(());
+static SEQUENCE: AtomicU64 = AtomicU64::new(0);
+
+/// Job kinds that move files, as listed in the tray and announced by notifications.
+pub const TRANSFER_KINDS: &[&str] = &[
+    "upload",
+    "download",
+    "download_as_zip",
+    "upload_archive",
+    "sync",
+    "scheduled_sync",
+    "folder_watch",
+    "mount_upload",
+    "edit_upload",
+    "shell_upload",
+    "clipboard_upload",
+    "replicate",
+    "relay_transfer",
+    "transfer_between_accounts",
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobRecord {
+    pub id: String,
+    pub kind: String,
+    pub bucket: Option<String>,
+    pub params: serde_json::Value,
+    pub started_at: i64,
+    pub finished_at: i64,
+    pub succeeded: bool,
+    pub summary: String,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct JobQuery {
+    pub bucket: Option<String>,
+    pub kind: Option<String>,
+    pub since: Option<i64>,
+    pub until: Option<i64>,
+    /// Case-insensitive match against the summary, error and parameters.
+    pub text: Option<String>,
+    pub succeeded: Option<bool>,
+    pub limit: Option<usize>,
+}
+
+pub fn now_secs() -> i64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs() as i64).unwrap_or(0)
+}
+
+fn archive_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    Ok(dir.join("jobs.jsonl"))
+}
+
+/// Captures a job's parameters and start time; call `finish` with the outcome.
+pub struct JobTimer {
+    kind: String,
+    bucket: Option<String>,
+    params: serde_json::Value,
+    started_at: i64,
+}
+
+pub fn start(kind: &str, bucket: Option<&str>, params: serde_json::Value) -> JobTimer {
+    JobTimer {
+        kind: kind.to_string(),
+        bucket: bucket.map(|b| b.to_string()),
+        params,
+        started_at: now_secs(),
+    }
+}
+
+impl JobTimer {
+    /// Archives the outcome. `summary` describes a success; failures record the error instead.
+    /// Archiving is best effort and never fails the job itself.
+    pub fn finish<T, E: std::fmt::Display>(self, app: &AppHandle, result: &Result<T, E>, summary: impl FnOnce(&T) -> String) {
+        let finished_at = now_secs();
+        let (succeeded, summary, error) = match result {
+            Ok(value) => (true, summary(value), None),
+            Err(e) => (false, format!("{} failed", self.kind), Some(e.to_string())),
+        };
+        let record = JobRecord {
+            id: format!("{}-{}", finished_at, SEQUENCE.fetch_add(1, Ordering::Relaxed)),
+            kind: self.kind,
+            bucket: self.bucket,
+            params: self.params,
+            started_at: self.started_at,
+            finished_at,
+            succeeded,
+            summary,
+            error,
+        };
+        let _ = append(app, &record);
+        crate::tray::record(app, &record);
+        crate::notifications::job_finished(app, &record);
+    }
+}
+
+fn append(app: &AppHandle, record: &JobRecord) -> Result<(), String> {
+    let _guard = ARCHIVE_LOCK.lock().unwrap();
+    let path = archive_path(app)?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| e.to_string())?;
+    let line = serde_json::to_string(record).map_err(|e| e.to_string())?;
+    writeln!(file, "{}", line).map_err(|e| e.to_string())
+}
+
+/// Every archived job, oldest first. Unparseable lines are skipped.
+pub fn load_all(app: &AppHandle) -> Vec<JobRecord> {
+    let _guard = ARCHIVE_LOCK.lock().unwrap();
+    archive_path(app)
+        .ok()
+        .and_then(|p| std::fs::read_to_string(p).ok())
+        .map(|raw| raw.lines().filter_map(|l| serde_json::from_str(l).ok()).collect())
+        .unwrap_or_default()
+}
+
+fn matches(job: &JobRecord, query: &JobQuery, text: &Option<String>) -> bool {
+    if query.bucket.as_ref().is_some_and(|b| job.bucket.as_ref() != Some(b)) {
+        return false;
+    }
+    if query.kind.as_ref().is_some_and(|k| &job.kind != k) {
+        return false;
+    }
+    if query.since.is_some_and(|t| job.finished_at < t) || query.until.is_some_and(|t| job.finished_at > t) {
+        return false;
+    }
+    if query.succeeded.is_some_and(|s| job.succeeded != s) {
+        return false;
+    }
+    if let Some(text) = text {
+        let haystack = format!("{} {} {}", job.summary, job.error.as_deref().unwrap_or_default(), job.params)
+            .to_lowercase();
+        if !haystack.contains(text) {
+            return false;
+        }
+    }
+    true
+}
+
+/// Newest-first search over the job archive.
+#[tauri::command]
+pub fn search_jobs(query: JobQuery, app: AppHandle) -> Vec<JobRecord> {
+    let text = query.text.as_ref().map(|t| t.to_lowercase()).filter(|t| !t.is_empty());
+    let limit = query.limit.unwrap_or(200);
+
+    load_all(&app)
+        .into_iter()
+        .rev()
+        .filter(|job| matches(job, &query, &text))
+        .take(limit)
+        .collect()
+}
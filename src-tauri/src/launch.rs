@@ -0,0 +1,208 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager, State, Url};
+use tauri_plugin_deep_link::DeepLinkExt;
+
+use crate::connect_link;
+use crate::shell_menu;
+use crate::sync::normalize_prefix;
+use crate::tray;
+
+// Arguments the app was launched with: files to upload ("Open with R2Drive") and r2:// URLs to
+// browse. A second launch hands its arguments to the running instance and exits, so there is
+// only ever one window and one set of connections. Requests wait here until the UI takes them,
+// since the first one arrives before the UI is listening.
+//
+// r2drive:// links come through the deep-link plugin instead:
+//   r2drive://open?bucket=assets&prefix=logos/&profile=work
+//   r2drive://download?bucket=assets&key=logos/mark.svg
+// plus the r2drive:connect: links made by `create_connect_link`. Links can come from anywhere,
+// so they only ever fill in a request; the UI shows it and the user decides.
+const R2_SCHEME: &str = "r2://";
+const DEEP_LINK_SCHEME: &str = "r2drive";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BrowseTarget {
+    /// Profile to connect with first; the current connection when unset.
+    pub profile: Option<String>,
+    pub bucket: String,
+    /// Folder to open, ending in '/' (empty for the bucket root).
+    pub prefix: String,
+    /// Object to select in it, when the URL named one.
+    pub key: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DownloadTarget {
+    pub profile: Option<String>,
+    pub bucket: String,
+    pub key: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct LaunchRequest {
+    /// Absolute paths of existing files and folders.
+    pub files: Vec<String>,
+    pub browse: Option<BrowseTarget>,
+    /// Paths sent with "Upload to R2Drive" while no default destination is set; the UI asks
+    /// where they go and passes them to `upload_shell_files`.
+    pub upload: Vec<String>,
+    pub download: Option<DownloadTarget>,
+    /// An r2drive:connect: link to import.
+    pub connect_link: Option<String>,
+    /// Arguments that were neither, reported rather than dropped.
+    pub unrecognized: Vec<String>,
+}
+
+impl LaunchRequest {
+    fn is_empty(&self) -> bool {
+        self.files.is_empty()
+            && self.browse.is_none()
+            && self.upload.is_empty()
+            && self.download.is_none()
+            && self.connect_link.is_none()
+            && self.unrecognized.is_empty()
+    }
+}
+
+#[derive(Default)]
+pub struct LaunchState {
+    pending: Mutex<Option<LaunchRequest>>,
+}
+
+/// Parses `r2://bucket/folder/` or `r2://bucket/folder/file`.
+pub fn parse_r2_url(url: &str) -> Option<BrowseTarget> {
+    let rest = url.strip_prefix(R2_SCHEME)?;
+    let (bucket, path) = rest.split_once('/').unwrap_or((rest, ""));
+    if bucket.is_empty() {
+        return None;
+    }
+    let path = urlencoding::decode(path).ok()?.into_owned();
+    let (prefix, key) = match path.rfind('/') {
+        _ if path.is_empty() || path.ends_with('/') => (path, None),
+        Some(slash) => (path[..=slash].to_string(), Some(path)),
+        None => (String::new(), Some(path)),
+    };
+    Some(BrowseTarget { profile: None, bucket: bucket.to_string(), prefix, key })
+}
+
+/// Reads an r2drive: link into a request.
+fn parse_deep_link(url: &Url) -> LaunchRequest {
+    let mut request = LaunchRequest::default();
+    if url.as_str().starts_with(connect_link::LINK_PREFIX) {
+        request.connect_link = Some(url.to_string());
+        return request;
+    }
+
+    let query: HashMap<String, String> = url.query_pairs().into_owned().collect();
+    let param = |name: &str| query.get(name).filter(|v| !v.is_empty()).cloned();
+    let profile = param("profile");
+    match (url.host_str(), param("bucket")) {
+        (Some("open"), Some(bucket)) => {
+            let prefix = normalize_prefix(&param("prefix").unwrap_or_default());
+            request.browse = Some(BrowseTarget { profile, bucket, prefix, key: param("key") });
+        }
+        (Some("download"), Some(bucket)) => match param("key").filter(|k| !k.ends_with('/')) {
+            Some(key) => request.download = Some(DownloadTarget { profile, bucket, key }),
+            None => request.unrecognized.push(url.to_string()),
+        },
+        _ => request.unrecognized.push(url.to_string()),
+    }
+    request
+}
+
+/// Reads a command line (without the program name), resolving relative paths against `cwd`.
+/// Flags are skipped, except that `--upload` sends the paths to the context-menu upload.
+fn parse_args(args: &[String], cwd: &Path) -> LaunchRequest {
+    let mut request = LaunchRequest::default();
+    let upload = args.iter().any(|a| a == shell_menu::UPLOAD_FLAG);
+    // r2drive: links are picked up by the deep-link plugin; files merely named r2drive... are not links
+    let is_deep_link = |a: &str| Url::parse(a).is_ok_and(|u| u.scheme() == DEEP_LINK_SCHEME);
+    for arg in args.iter().filter(|a| !a.starts_with('-') && !is_deep_link(a)) {
+        if arg.starts_with(R2_SCHEME) {
+            match parse_r2_url(arg) {
+                Some(target) => request.browse = Some(target),
+                None => request.unrecognized.push(arg.clone()),
+            }
+            continue;
+        }
+        // Absolute arguments replace `cwd` entirely
+        let path = cwd.join(arg);
+        if path.exists() && upload {
+            request.upload.push(path.to_string_lossy().to_string());
+        } else if path.exists() {
+            request.files.push(path.to_string_lossy().to_string());
+        } else {
+            request.unrecognized.push(arg.clone());
+        }
+    }
+    request
+}
+
+/// Queues a request for the UI and announces it with `launch://request`. Files from several
+/// launches before the UI gets to them add up; the latest browse target wins.
+fn deliver(app: &AppHandle, request: LaunchRequest) {
+    let request = shell_menu::upload_to_default(app, request);
+    if request.is_empty() {
+        return;
+    }
+    {
+        let state = app.state::<LaunchState>();
+        let mut pending = state.pending.lock().unwrap();
+        let merged = pending.get_or_insert_with(LaunchRequest::default);
+        merged.files.extend(request.files);
+        merged.upload.extend(request.upload);
+        merged.unrecognized.extend(request.unrecognized);
+        if request.browse.is_some() {
+            merged.browse = request.browse;
+        }
+        if request.download.is_some() {
+            merged.download = request.download;
+        }
+        if request.connect_link.is_some() {
+            merged.connect_link = request.connect_link;
+        }
+    }
+    let _ = app.emit("launch://request", ());
+}
+
+/// Picks up this instance's own command line and the link that launched it, if any, and starts
+/// listening for links. Called once at startup.
+pub fn init(app: &AppHandle) {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let cwd = std::env::current_dir().unwrap_or_default();
+    deliver(app, parse_args(&args, &cwd));
+
+    let deep_link = app.deep_link();
+    // Installs that skipped the OS registration (AppImages, dev builds) still get their links
+    #[cfg(any(windows, target_os = "linux"))]
+    let _ = deep_link.register_all();
+    if let Ok(Some(urls)) = deep_link.get_current() {
+        open_urls(app, urls);
+    }
+    let handle = app.clone();
+    deep_link.on_open_url(move |event| open_urls(&handle, event.urls()));
+}
+
+/// Runs in the first instance when another one is launched; `argv` and `cwd` are the second
+/// instance's.
+pub fn forwarded(app: &AppHandle, argv: Vec<String>, cwd: String) {
+    tray::show_window(app);
+    deliver(app, parse_args(argv.get(1..).unwrap_or_default(), Path::new(&cwd)));
+}
+
+/// Handles r2drive: links, whether they launched the app or arrived while it was running.
+pub fn open_urls(app: &AppHandle, urls: Vec<Url>) {
+    tray::show_window(app);
+    for url in urls.iter().filter(|u| u.scheme() == DEEP_LINK_SCHEME) {
+        deliver(app, parse_deep_link(url));
+    }
+}
+
+/// The launch request waiting for the UI, if any. Each request is handed out once.
+#[tauri::command]
+pub fn take_launch_request(state: State<'_, LaunchState>) -> Option<LaunchRequest> {
+    state.pending.lock().unwrap().take()
+}
@@ -1,6 +1,73 @@
-use std::sync::Mutex;
+use r2drive_core::keychain;
+use tauri::Manager;
 
+mod activity;
+mod api_grants;
+mod archive;
+mod audit;
+mod buckets;
+mod capabilities;
+mod checksums;
+mod clipboard;
+mod cloudflare;
+mod concat;
+mod connect_link;
+mod credentials;
+mod diagnostics;
+mod diff;
+mod documents;
+mod duplicates;
+mod editing;
+mod encryption;
+mod error;
+mod folder_watch;
+mod hashing;
+mod health;
+mod index;
+mod inventory;
+mod jobs;
+mod launch;
+mod listing;
+mod local_api;
+mod logging;
+mod maintenance;
+mod media;
+mod media_info;
+mod mount;
+#[cfg(all(any(unix, windows), feature = "mount"))]
+mod mount_fs;
+#[cfg(all(unix, feature = "mount"))]
+mod mount_fuse;
+#[cfg(all(windows, feature = "mount"))]
+mod mount_winfsp;
+mod network;
+mod notifications;
+mod partial;
+mod presign;
+mod profiles;
+mod relay;
+mod rename;
+mod replicate;
 mod s3;
+mod search;
+mod settings;
+mod shares;
+mod shell_menu;
+mod signatures;
+mod snapshot;
+mod sse_c;
+mod sync;
+mod sync_schedule;
+mod sync_state;
+mod table;
+mod text;
+mod thumbnails;
+mod tray;
+mod updates;
+mod validate;
+mod verify;
+mod versions;
+mod watch;
 
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
 #[tauri::command]
@@ -10,13 +77,59 @@ fn greet(name: &str) -> String {
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
-    tauri::Builder::default()
+    let mut builder = tauri::Builder::default();
+    // Registered first, so a second launch exits before setting anything else up
+    #[cfg(desktop)]
+    {
+        builder = builder.plugin(tauri_plugin_single_instance::init(launch::forwarded));
+        if let Some(pubkey) = updates::PUBKEY {
+            builder = builder.plugin(tauri_plugin_updater::Builder::new().pubkey(pubkey).build());
+        }
+    }
+    builder
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
-        .manage(s3::AppState {
-            client: Mutex::new(None),
-            credentials: Mutex::new(None),
+        .plugin(tauri_plugin_notification::init())
+        .plugin(tauri_plugin_deep_link::init())
+        .register_asynchronous_uri_scheme_protocol(media::SCHEME, media::handle)
+        .setup(|app| {
+            logging::init(app.handle());
+            partial::cleanup_stale(app.handle());
+            let settings = settings::load(app.handle());
+            app.manage(s3::AppState::new(settings));
+            app.manage(watch::WatchState::default());
+            app.manage(maintenance::MaintenanceState::default());
+            app.manage(cloudflare::CloudflareState::default());
+            app.manage(health::HealthState::default());
+            app.manage(search::SearchState::default());
+            app.manage(index::IndexState::default());
+            app.manage(text::TailState::default());
+            app.manage(folder_watch::FolderWatchState::default());
+            folder_watch::restore(app.handle());
+            app.manage(sync_schedule::ScheduleState::default());
+            app.manage(encryption::EncryptionState::default());
+            app.manage(mount::MountState::default());
+            app.manage(editing::EditingState::default());
+            app.manage(updates::UpdateState::default());
+            updates::init(app.handle());
+            app.manage(local_api::LocalApiState::default());
+            local_api::init(app.handle());
+            let _ = tray::init(app.handle());
+            app.manage(launch::LaunchState::default());
+            launch::init(app.handle());
+            tauri::async_runtime::spawn(maintenance::run_scheduler(app.handle().clone()));
+            tauri::async_runtime::spawn(health::refresh(app.handle().clone()));
+            tauri::async_runtime::spawn(profiles::restore_session(app.handle().clone()));
+            tauri::async_runtime::spawn(credentials::watch_expiry(app.handle().clone()));
+            tauri::async_runtime::spawn(cloudflare::watch_usage(app.handle().clone()));
+            tauri::async_runtime::spawn(sync_schedule::run_scheduler(app.handle().clone()));
+            Ok(())
+        })
+        .on_window_event(|window, event| {
+            if let tauri::WindowEvent::CloseRequested { api, .. } = event {
+                tray::close_requested(window, api);
+            }
         })
         .invoke_handler(tauri::generate_handler![
             greet,
@@ -29,10 +142,170 @@ pub fn run() {
             s3::create_folder,
             s3::upload_file,
             s3::download_file,
-            s3::read_text_file,
-            s3::get_presigned_url,
+            text::read_text_file,
+            presign::get_presigned_url,
             s3::copy_object,
-            s3::rename_folder
+            s3::rename_folder,
+            rename::bulk_rename,
+            settings::get_settings,
+            settings::update_settings,
+            duplicates::find_duplicates,
+            buckets::empty_bucket,
+            watch::watch_prefix,
+            watch::unwatch_prefix,
+            s3::delete_objects_if_unchanged,
+            s3::is_empty_prefix,
+            hashing::hash_local_files,
+            s3::get_prefix_stats,
+            concat::concatenate_objects,
+            s3::create_text_object,
+            validate::validate_text,
+            s3::create_empty_object,
+            s3::append_text,
+            relay::relay_transfer,
+            diff::diff_objects,
+            diff::diff_local_remote,
+            capabilities::get_capabilities,
+            capabilities::set_capability,
+            maintenance::cleanup_multipart_uploads,
+            maintenance::get_last_cleanup_report,
+            buckets::create_bucket,
+            buckets::delete_bucket,
+            buckets::get_bucket_cors,
+            buckets::put_bucket_cors,
+            jobs::search_jobs,
+            buckets::get_bucket_lifecycle,
+            buckets::put_bucket_lifecycle,
+            connect_link::create_connect_link,
+            connect_link::import_connect_link,
+            buckets::get_bucket_info,
+            verify::verify_uploads,
+            s3::get_bucket_breakdown,
+            api_grants::create_api_grant,
+            api_grants::list_api_grants,
+            api_grants::revoke_api_grant,
+            activity::get_activity,
+            cloudflare::set_cloudflare_token,
+            cloudflare::clear_cloudflare_token,
+            cloudflare::cloudflare_list_buckets,
+            cloudflare::cloudflare_create_bucket,
+            cloudflare::cloudflare_delete_bucket,
+            cloudflare::cloudflare_get_usage,
+            cloudflare::cloudflare_get_public_access,
+            cloudflare::cloudflare_set_r2_dev,
+            cloudflare::cloudflare_add_custom_domain,
+            cloudflare::cloudflare_remove_custom_domain,
+            health::get_health_report,
+            health::run_health_check,
+            profiles::list_profiles,
+            profiles::create_profile,
+            profiles::update_profile,
+            profiles::delete_profile,
+            profiles::connect_profile,
+            diagnostics::test_connection,
+            diagnostics::probe_permissions,
+            s3::list_connections,
+            s3::close_connection,
+            relay::transfer_between_accounts,
+            credentials::assume_role,
+            logging::get_recent_logs,
+            s3::list_objects_page,
+            search::search_objects,
+            search::cancel_search,
+            search::list_saved_searches,
+            search::save_search,
+            search::delete_saved_search,
+            search::run_saved_search,
+            index::start_indexing,
+            index::stop_indexing,
+            index::drop_index,
+            index::index_status,
+            index::search_index,
+            index::index_folder_size,
+            inventory::export_inventory,
+            inventory::diff_inventory,
+            presign::get_presigned_upload_url,
+            presign::presign_object,
+            presign::get_presigned_urls,
+            presign::presign_multipart_upload,
+            presign::complete_presigned_upload,
+            presign::abort_presigned_upload,
+            shares::list_shares,
+            shares::expire_share,
+            shares::delete_share,
+            shares::prune_expired_shares,
+            cloudflare::get_public_url,
+            thumbnails::get_thumbnail,
+            thumbnails::clear_thumbnail_cache,
+            media::get_media_url,
+            documents::get_document_preview,
+            text::tail_object,
+            text::stop_tail,
+            text::read_binary_range,
+            text::write_text_file,
+            archive::list_archive,
+            archive::extract_archive_entry,
+            archive::download_as_zip,
+            archive::upload_archive,
+            table::preview_table,
+            media_info::get_media_info,
+            sync::plan_sync,
+            sync::apply_sync_plan,
+            sync::sync_folder,
+            folder_watch::list_folder_watches,
+            folder_watch::save_folder_watch,
+            folder_watch::delete_folder_watch,
+            sync_schedule::list_sync_schedules,
+            sync_schedule::save_sync_schedule,
+            sync_schedule::set_sync_schedule_enabled,
+            sync_schedule::delete_sync_schedule,
+            sync_schedule::run_sync_schedule_now,
+            sync_schedule::sync_schedule_history,
+            sync_state::reset_sync_state,
+            replicate::replicate,
+            snapshot::create_snapshot,
+            snapshot::list_snapshots,
+            snapshot::prune_snapshots,
+            snapshot::snapshot_restore_size,
+            snapshot::restore_snapshot,
+            versions::set_bucket_versioning,
+            versions::list_object_versions,
+            versions::download_object_version,
+            versions::restore_version,
+            versions::delete_object_version,
+            versions::clean_up_versions,
+            encryption::encryption_status,
+            encryption::unlock_encryption,
+            encryption::lock_encryption,
+            encryption::create_keyfile,
+            encryption::decrypt_key_names,
+            sse_c::generate_sse_c_key,
+            audit::audit,
+            audit::clear_audit_records,
+            checksums::create_checksum_manifest,
+            checksums::verify_checksum_manifest,
+            signatures::verify_signature,
+            signatures::add_trusted_key,
+            signatures::remove_trusted_key,
+            mount::mount_bucket,
+            mount::unmount_bucket,
+            mount::list_mounts,
+            tray::set_transfers_paused,
+            tray::transfers_paused,
+            launch::take_launch_request,
+            editing::open_for_editing,
+            editing::list_editing_files,
+            editing::stop_editing,
+            shell_menu::upload_shell_files,
+            shell_menu::shell_integration_installed,
+            shell_menu::install_shell_integration,
+            shell_menu::uninstall_shell_integration,
+            updates::check_for_update,
+            updates::download_update,
+            updates::install_update,
+            clipboard::paste_to_bucket,
+            local_api::local_api_status,
+            local_api::set_local_api
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,105 @@
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+
+use crate::s3::{FolderEntry, ListObjectsResult, ObjectEntry};
+
+/// Narrows a folder listing. Every condition that is set must match. Only `name_contains`
+/// applies to folders; the rest only look at files.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ListingFilter {
+    /// Case-insensitive substring of the entry's name (the last path segment).
+    pub name_contains: Option<String>,
+    /// Extensions without the dot, case-insensitive; empty allows any.
+    pub extensions: Vec<String>,
+    pub min_size: Option<u64>,
+    pub max_size: Option<u64>,
+    /// Unix seconds, inclusive.
+    pub modified_after: Option<i64>,
+    pub modified_before: Option<i64>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortField {
+    #[default]
+    Name,
+    Size,
+    LastModified,
+    Extension,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortDirection {
+    #[default]
+    Asc,
+    Desc,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(default)]
+pub struct ListingSort {
+    pub field: SortField,
+    pub direction: SortDirection,
+}
+
+fn name(key: &str) -> &str {
+    key.trim_end_matches('/').rsplit('/').next().unwrap_or(key)
+}
+
+fn extension(key: &str) -> String {
+    match name(key).rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() => ext.to_lowercase(),
+        _ => String::new(),
+    }
+}
+
+impl ListingFilter {
+    fn name_matches(&self, key: &str) -> bool {
+        match &self.name_contains {
+            Some(needle) if !needle.is_empty() => name(key).to_lowercase().contains(&needle.to_lowercase()),
+            _ => true,
+        }
+    }
+
+    pub fn matches_file(&self, entry: &ObjectEntry) -> bool {
+        self.name_matches(&entry.key)
+            && (self.extensions.is_empty() || {
+                let ext = extension(&entry.key);
+                self.extensions.iter().any(|e| e.trim_start_matches('.').eq_ignore_ascii_case(&ext))
+            })
+            && self.min_size.is_none_or(|min| entry.size >= min)
+            && self.max_size.is_none_or(|max| entry.size <= max)
+            && self.modified_after.is_none_or(|t| entry.last_modified >= t)
+            && self.modified_before.is_none_or(|t| entry.last_modified <= t)
+    }
+
+    pub fn matches_folder(&self, entry: &FolderEntry) -> bool {
+        self.name_matches(&entry.prefix)
+    }
+}
+
+/// Sorts files by the chosen field (ties broken by key) and folders by name.
+pub fn sort(result: &mut ListObjectsResult, sort: ListingSort) {
+    let directed = |ordering: Ordering| match sort.direction {
+        SortDirection::Asc => ordering,
+        SortDirection::Desc => ordering.reverse(),
+    };
+
+    result.files.sort_by(|a, b| {
+        let primary = match sort.field {
+            SortField::Name => Ordering::Equal,
+            SortField::Size => a.size.cmp(&b.size),
+            SortField::LastModified => a.last_modified.cmp(&b.last_modified),
+            SortField::Extension => extension(&a.key).cmp(&extension(&b.key)),
+        };
+        directed(primary.then_with(|| a.key.cmp(&b.key)))
+    });
+
+    if sort.field == SortField::Name {
+        result.folders.sort_by(|a, b| directed(a.prefix.cmp(&b.prefix)));
+    } else {
+        result.folders.sort_by(|a, b| a.prefix.cmp(&b.prefix));
+    }
+}
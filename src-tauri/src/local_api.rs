@@ -0,0 +1,377 @@
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::net::{Ipv4Addr, SocketAddr};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Manager, State};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+
+use crate::api_grants::{self, ApiGrant, GrantAccess};
+use crate::error::{AppError, ErrorKind};
+use crate::s3::{self, AppState};
+use crate::settings;
+use crate::sync::{self, normalize_prefix, SyncDirection, SyncOptions, SyncPlan};
+
+// Automation API for scripts and editors while the app sits in the tray: JSON-RPC 2.0 over a
+// TCP socket on 127.0.0.1, one message per line. A connection starts with `authenticate` and an
+// API grant token (see api_grants); every call after that goes through the grant's connection
+// and is held to its bucket, prefix and access, rechecked each time so revoking or expiring a
+// grant takes effect at once. The job history isn't offered: jobs don't record the connection
+// or every key they touched, so a grant couldn't be held to it. The port in use is written to
+// local-api.json in the config dir for clients to find.
+const DISCOVERY_FILE: &str = "local-api.json";
+
+// JSON-RPC error codes. App errors carry the AppError as `data`.
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const APP_ERROR: i64 = -32000;
+const UNAUTHENTICATED: i64 = -32001;
+const FORBIDDEN: i64 = -32002;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LocalApiSettings {
+    pub enabled: bool,
+    /// 0 picks a free port on each start.
+    pub port: u16,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LocalApiStatus {
+    pub enabled: bool,
+    pub running: bool,
+    pub port: Option<u16>,
+    /// Why the server couldn't start, if it didn't.
+    pub error: Option<String>,
+}
+
+struct Server {
+    port: u16,
+    task: JoinHandle<()>,
+}
+
+#[derive(Default)]
+pub struct LocalApiState {
+    server: Mutex<Option<Server>>,
+    error: Mutex<Option<String>>,
+}
+
+#[derive(Debug, Serialize)]
+struct RpcError {
+    code: i64,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data: Option<AppError>,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self { code, message: message.into(), data: None }
+    }
+}
+
+impl From<AppError> for RpcError {
+    fn from(err: AppError) -> Self {
+        Self { code: APP_ERROR, message: err.message.clone(), data: Some(err) }
+    }
+}
+
+/// Bucket a call applies to, defaulting to the grant's. The connection always comes from the grant.
+#[derive(Deserialize)]
+struct Target {
+    bucket: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TokenParams {
+    token: String,
+}
+
+#[derive(Deserialize)]
+struct ListParams {
+    prefix: Option<String>,
+    delimiter: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct UploadParams {
+    key: String,
+    path: String,
+}
+
+#[derive(Deserialize)]
+struct DownloadParams {
+    key: String,
+    save_path: String,
+}
+
+#[derive(Deserialize)]
+struct DeleteParams {
+    keys: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct SyncParams {
+    local_root: String,
+    prefix: Option<String>,
+    direction: SyncDirection,
+    options: Option<SyncOptions>,
+}
+
+#[derive(Deserialize)]
+struct ApplyParams {
+    plan: SyncPlan,
+}
+
+fn discovery_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = app.path().app_config_dir().map_err(|e| e.to_string())?;
+    Ok(dir.join(DISCOVERY_FILE))
+}
+
+fn params<T: DeserializeOwned>(params: &Value) -> Result<T, RpcError> {
+    serde_json::from_value(params.clone()).map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))
+}
+
+/// The grant behind the connection's token, as it stands now.
+fn current_grant(app: &AppHandle, token: Option<&str>) -> Result<ApiGrant, RpcError> {
+    let token = token.ok_or_else(|| RpcError::new(UNAUTHENTICATED, "Call authenticate with an API token first"))?;
+    api_grants::grant_for_token(app, token).ok_or_else(|| RpcError::new(UNAUTHENTICATED, "The API token has expired or been revoked"))
+}
+
+fn check_write(grant: &ApiGrant) -> Result<(), RpcError> {
+    match grant.access {
+        GrantAccess::ReadWrite => Ok(()),
+        GrantAccess::ReadOnly => Err(RpcError::new(FORBIDDEN, "The API token is read-only")),
+    }
+}
+
+fn check_covers(grant: &ApiGrant, bucket: &str, key: &str) -> Result<(), RpcError> {
+    if grant.covers(bucket, key) {
+        Ok(())
+    } else {
+        Err(RpcError::new(FORBIDDEN, format!("The API token doesn't cover {}/{}", bucket, key)))
+    }
+}
+
+/// Runs one call for a connection holding `grant`. Parameters are named as in the matching
+/// command, minus `bucket`, which defaults to the grant's, and `connection_id`, which is the
+/// grant's and can't be overridden.
+async fn call(app: &AppHandle, grant: &ApiGrant, method: &str, raw: &Value) -> Result<Value, RpcError> {
+    if raw.get("connection_id").is_some() {
+        return Err(RpcError::new(INVALID_PARAMS, "connection_id is fixed by the API token"));
+    }
+    let target: Target = params(raw)?;
+    let bucket = target.bucket.unwrap_or_else(|| grant.bucket.clone());
+    let connection_id = grant.connection_id.clone();
+    let grant_prefix = grant.prefix.clone().unwrap_or_default();
+    let state = app.state::<AppState>();
+
+    let value = match method {
+        "list_objects" => {
+            let p: ListParams = params(raw)?;
+            let prefix = p.prefix.unwrap_or(grant_prefix);
+            check_covers(grant, &bucket, &prefix)?;
+            let listing = s3::list_objects(bucket, Some(prefix), p.delimiter, None, None, None, app.clone(), connection_id, state).await?;
+            json!(listing)
+        }
+        "upload_file" => {
+            let p: UploadParams = params(raw)?;
+            check_write(grant)?;
+            check_covers(grant, &bucket, &p.key)?;
+            s3::upload_file(bucket, p.key, p.path, None, app.clone(), connection_id, state).await?;
+            Value::Null
+        }
+        "download_file" => {
+            let p: DownloadParams = params(raw)?;
+            check_covers(grant, &bucket, &p.key)?;
+            s3::download_file(bucket, p.key, p.save_path, None, app.clone(), connection_id, state).await?;
+            Value::Null
+        }
+        "delete_objects" => {
+            let p: DeleteParams = params(raw)?;
+            check_write(grant)?;
+            for key in &p.keys {
+                check_covers(grant, &bucket, key)?;
+            }
+            s3::delete_objects(bucket, p.keys, app.clone(), connection_id, state).await?;
+            Value::Null
+        }
+        "plan_sync" | "sync_folder" => {
+            let p: SyncParams = params(raw)?;
+            let prefix = normalize_prefix(&p.prefix.unwrap_or(grant_prefix));
+            check_covers(grant, &bucket, &prefix)?;
+            if method == "plan_sync" {
+                json!(sync::plan_sync(p.local_root, bucket, prefix, p.direction, p.options, app.clone(), connection_id, state).await?)
+            } else {
+                if p.direction != SyncDirection::Download {
+                    check_write(grant)?;
+                }
+                json!(sync::sync_folder(p.local_root, bucket, prefix, p.direction, p.options, app.clone(), connection_id).await?)
+            }
+        }
+        "apply_sync_plan" => {
+            let p: ApplyParams = params(raw)?;
+            check_covers(grant, &p.plan.bucket, &p.plan.prefix)?;
+            if sync::writes_remote(&p.plan) {
+                check_write(grant)?;
+            }
+            json!(sync::apply_sync_plan(p.plan, app.clone(), connection_id, state).await?)
+        }
+        _ => return Err(RpcError::new(METHOD_NOT_FOUND, format!("Unknown method '{}'", method))),
+    };
+    Ok(value)
+}
+
+fn response(id: Value, outcome: Result<Value, RpcError>) -> String {
+    let message = match outcome {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(error) => json!({ "jsonrpc": "2.0", "id": id, "error": error }),
+    };
+    format!("{}\n", message)
+}
+
+/// Serves one client. Calls run concurrently and answer as they finish, so a long sync doesn't
+/// hold up the rest; `authenticate` is handled in order so the calls after it see the token.
+async fn serve(app: AppHandle, stream: TcpStream) {
+    let (reader, mut writer) = stream.into_split();
+    let (tx, mut rx) = mpsc::unbounded_channel::<String>();
+    let write_task = tauri::async_runtime::spawn(async move {
+        while let Some(line) = rx.recv().await {
+            if writer.write_all(line.as_bytes()).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    let token: Arc<Mutex<Option<String>>> = Arc::default();
+    let mut lines = BufReader::new(reader).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let Ok(message) = serde_json::from_str::<Value>(&line) else {
+            // Before authenticating this is most likely not a client at all (a browser, say)
+            if token.lock().unwrap().is_none() {
+                break;
+            }
+            let _ = tx.send(response(Value::Null, Err(RpcError::new(PARSE_ERROR, "Invalid JSON"))));
+            continue;
+        };
+        // Requests without an id are notifications and get no answer
+        let id = message.get("id").cloned();
+        let method = message.get("method").and_then(Value::as_str).map(str::to_string);
+        let Some(method) = method.filter(|_| message.get("jsonrpc") == Some(&json!("2.0"))) else {
+            let _ = tx.send(response(id.unwrap_or(Value::Null), Err(RpcError::new(INVALID_REQUEST, "Not a JSON-RPC 2.0 request"))));
+            continue;
+        };
+        let raw = message.get("params").cloned().unwrap_or_else(|| json!({}));
+
+        if method == "authenticate" {
+            let outcome = params::<TokenParams>(&raw).and_then(|p| {
+                let grant = current_grant(&app, Some(&p.token))?;
+                *token.lock().unwrap() = Some(p.token);
+                Ok(json!(grant))
+            });
+            if let Some(id) = id {
+                let _ = tx.send(response(id, outcome));
+            }
+            continue;
+        }
+        let (app, tx, token) = (app.clone(), tx.clone(), token.lock().unwrap().clone());
+        tauri::async_runtime::spawn(async move {
+            let outcome = match current_grant(&app, token.as_deref()) {
+                Ok(grant) => call(&app, &grant, &method, &raw).await,
+                Err(e) => Err(e),
+            };
+            if let Some(id) = id {
+                let _ = tx.send(response(id, outcome));
+            }
+        });
+    }
+    drop(tx);
+    let _ = write_task.await;
+}
+
+fn stop(app: &AppHandle) {
+    if let Some(server) = app.state::<LocalApiState>().server.lock().unwrap().take() {
+        server.task.abort();
+    }
+    if let Ok(path) = discovery_path(app) {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+/// (Re)starts the server on the configured port, or stops it when disabled.
+fn restart(app: &AppHandle) -> Result<(), String> {
+    stop(app);
+    let config = app.state::<AppState>().settings.lock().unwrap().local_api.clone();
+    if !config.enabled {
+        return Ok(());
+    }
+    // Bound here rather than in the task so a taken port is reported to the caller
+    let listener = std::net::TcpListener::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, config.port)))
+        .map_err(|e| format!("Cannot listen on port {}: {}", config.port, e))?;
+    listener.set_nonblocking(true).map_err(|e| e.to_string())?;
+    let port = listener.local_addr().map_err(|e| e.to_string())?.port();
+
+    let path = discovery_path(app)?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    std::fs::write(&path, json!({ "port": port, "pid": std::process::id() }).to_string()).map_err(|e| e.to_string())?;
+
+    let handle = app.clone();
+    let task = tauri::async_runtime::spawn(async move {
+        let Ok(listener) = TcpListener::from_std(listener) else { return };
+        while let Ok((stream, _)) = listener.accept().await {
+            tauri::async_runtime::spawn(serve(handle.clone(), stream));
+        }
+    });
+    *app.state::<LocalApiState>().server.lock().unwrap() = Some(Server { port, task });
+    Ok(())
+}
+
+fn restart_recording(app: &AppHandle) -> Result<(), String> {
+    let result = restart(app);
+    *app.state::<LocalApiState>().error.lock().unwrap() = result.as_ref().err().cloned();
+    result
+}
+
+/// Starts the server at launch when it's enabled.
+pub fn init(app: &AppHandle) {
+    let _ = restart_recording(app);
+}
+
+#[tauri::command]
+pub fn local_api_status(state: State<'_, AppState>, api: State<'_, LocalApiState>) -> LocalApiStatus {
+    let server = api.server.lock().unwrap();
+    LocalApiStatus {
+        enabled: state.settings.lock().unwrap().local_api.enabled,
+        running: server.is_some(),
+        port: server.as_ref().map(|s| s.port),
+        error: api.error.lock().unwrap().clone(),
+    }
+}
+
+/// Turns the automation API on or off, saving the choice. The server restarts on the new port.
+#[tauri::command]
+pub fn set_local_api(
+    enabled: bool,
+    port: Option<u16>,
+    app: AppHandle,
+    state: State<'_, AppState>,
+    api: State<'_, LocalApiState>,
+) -> Result<LocalApiStatus, AppError> {
+    let mut settings = state.settings.lock().unwrap().clone();
+    settings.local_api = LocalApiSettings { enabled, port: port.unwrap_or(settings.local_api.port) };
+    settings::save(&app, &settings)?;
+    state.apply_settings(settings);
+    restart_recording(&app).map_err(|e| AppError::new(ErrorKind::Internal, e))?;
+    Ok(local_api_status(state, api))
+}
@@ -0,0 +1,187 @@
+use aws_sdk_s3::config::interceptors::{BeforeSerializationInterceptorContextRef, FinalizerInterceptorContextRef};
+use aws_sdk_s3::config::{ConfigBag, Intercept, RuntimeComponents};
+use aws_sdk_s3::error::DisplayErrorContext;
+use aws_smithy_runtime_api::box_error::BoxError;
+use aws_smithy_runtime_api::client::orchestrator::Metadata;
+use aws_smithy_types::config_bag::{Storable, StoreReplace};
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+use std::time::Instant;
+use tauri::{AppHandle, Manager};
+
+use crate::jobs;
+
+// Every S3 call is appended to logs/requests.log under the app data dir. The file rolls over to
+// requests.log.1, .2, ... once it reaches MAX_LOG_BYTES.
+const MAX_LOG_BYTES: u64 = 5 * 1024 * 1024;
+const KEEP_ROTATED: usize = 3;
+
+static LOG: OnceLock<Mutex<RequestLog>> = OnceLock::new();
+
+struct RequestLog {
+    path: PathBuf,
+    file: Option<File>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogEntry {
+    pub timestamp: i64,
+    pub operation: String,
+    pub endpoint: String,
+    pub bucket: Option<String>,
+    pub key: Option<String>,
+    pub duration_ms: u64,
+    pub succeeded: bool,
+    pub status: Option<u16>,
+    pub request_id: Option<String>,
+    pub error: Option<String>,
+}
+
+impl RequestLog {
+    fn rotated(&self, n: usize) -> PathBuf {
+        self.path.with_extension(format!("log.{}", n))
+    }
+
+    fn rotate(&mut self) {
+        self.file = None;
+        for n in (1..KEEP_ROTATED).rev() {
+            let _ = std::fs::rename(self.rotated(n), self.rotated(n + 1));
+        }
+        let _ = std::fs::rename(&self.path, self.rotated(1));
+    }
+
+    fn append(&mut self, entry: &LogEntry) -> std::io::Result<()> {
+        if std::fs::metadata(&self.path).map(|m| m.len() >= MAX_LOG_BYTES).unwrap_or(false) {
+            self.rotate();
+        }
+        if self.file.is_none() {
+            self.file = Some(OpenOptions::new().create(true).append(true).open(&self.path)?);
+        }
+        let line = serde_json::to_string(entry).map_err(std::io::Error::other)?;
+        writeln!(self.file.as_mut().unwrap(), "{}", line)
+    }
+}
+
+/// Opens the request log. Called once at startup; until then calls simply aren't logged.
+pub fn init(app: &AppHandle) {
+    let Ok(dir) = app.path().app_data_dir().map(|d| d.join("logs")) else { return };
+    if std::fs::create_dir_all(&dir).is_err() {
+        return;
+    }
+    let _ = LOG.set(Mutex::new(RequestLog { path: dir.join("requests.log"), file: None }));
+}
+
+fn record(entry: &LogEntry) {
+    if let Some(log) = LOG.get() {
+        // Logging is best effort and must never fail the request itself
+        let _ = log.lock().unwrap().append(entry);
+    }
+}
+
+#[derive(Debug, Clone)]
+struct StartedAt(Instant);
+
+impl Storable for StartedAt {
+    type Storer = StoreReplace<Self>;
+}
+
+/// SDK interceptor that logs every call made by the client it's attached to.
+#[derive(Debug)]
+pub struct RequestLogger {
+    endpoint_host: String,
+}
+
+impl RequestLogger {
+    pub fn new(endpoint: &str) -> Self {
+        let host = endpoint.split("://").last().unwrap_or(endpoint);
+        Self { endpoint_host: host.trim_end_matches('/').to_string() }
+    }
+
+    /// Splits a request URI into bucket and key, for both virtual-hosted and path-style URLs.
+    fn locate(&self, uri: &str) -> (Option<String>, Option<String>) {
+        let rest = uri.split("://").last().unwrap_or(uri);
+        let rest = rest.split('?').next().unwrap_or(rest);
+        let (host, path) = rest.split_once('/').unwrap_or((rest, ""));
+        let decode = |s: &str| urlencoding::decode(s).map(|d| d.into_owned()).unwrap_or_else(|_| s.to_string());
+        let non_empty = |s: String| Some(s).filter(|s| !s.is_empty());
+
+        match host.strip_suffix(&self.endpoint_host).and_then(|b| b.strip_suffix('.')) {
+            Some(bucket) => (non_empty(bucket.to_string()), non_empty(decode(path))),
+            None => {
+                let (bucket, key) = path.split_once('/').unwrap_or((path, ""));
+                (non_empty(decode(bucket)), non_empty(decode(key)))
+            }
+        }
+    }
+}
+
+impl Intercept for RequestLogger {
+    fn name(&self) -> &'static str {
+        "RequestLogger"
+    }
+
+    fn read_before_execution(
+        &self,
+        _context: &BeforeSerializationInterceptorContextRef<'_>,
+        cfg: &mut ConfigBag,
+    ) -> Result<(), BoxError> {
+        cfg.interceptor_state().store_put(StartedAt(Instant::now()));
+        Ok(())
+    }
+
+    fn read_after_execution(
+        &self,
+        context: &FinalizerInterceptorContextRef<'_>,
+        _runtime_components: &RuntimeComponents,
+        cfg: &mut ConfigBag,
+    ) -> Result<(), BoxError> {
+        let duration_ms = cfg.load::<StartedAt>().map(|s| s.0.elapsed().as_millis() as u64).unwrap_or(0);
+        let operation = cfg.load::<Metadata>().map(|m| m.name().to_string()).unwrap_or_default();
+        let (bucket, key) = context.request().map(|r| self.locate(r.uri())).unwrap_or((None, None));
+        let response = context.response();
+        let request_id = response.and_then(|r| {
+            r.headers().get("x-amz-request-id").or_else(|| r.headers().get("cf-ray")).map(|id| id.to_string())
+        });
+        let error = match context.output_or_error() {
+            Some(Err(e)) => Some(DisplayErrorContext(e).to_string()),
+            _ => None,
+        };
+
+        record(&LogEntry {
+            timestamp: jobs::now_secs(),
+            operation,
+            endpoint: self.endpoint_host.clone(),
+            bucket,
+            key,
+            duration_ms,
+            succeeded: error.is_none(),
+            status: response.map(|r| r.status().as_u16()),
+            request_id,
+            error,
+        });
+        Ok(())
+    }
+}
+
+/// The last `n` logged calls, newest first. Reads into rotated files when the current one holds
+/// fewer than `n`.
+#[tauri::command]
+pub fn get_recent_logs(n: usize) -> Vec<LogEntry> {
+    let Some(log) = LOG.get() else { return Vec::new() };
+    let log = log.lock().unwrap();
+
+    let mut entries = Vec::new();
+    let files = std::iter::once(log.path.clone()).chain((1..=KEEP_ROTATED).map(|i| log.rotated(i)));
+    for path in files {
+        let Ok(raw) = std::fs::read_to_string(path) else { break };
+        entries.extend(raw.lines().rev().filter_map(|line| serde_json::from_str::<LogEntry>(line).ok()));
+        if entries.len() >= n {
+            break;
+        }
+    }
+    entries.truncate(n);
+    entries
+}
@@ -0,0 +1,201 @@
+use aws_sdk_s3::Client;
+use serde::Serialize;
+use serde_json::json;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::error::AppError;
+use crate::jobs;
+use crate::s3::{acquire_client, acquire_write_client, AppState};
+
+// How often the background task wakes up to see whether a cleanup run is due
+const TICK: Duration = Duration::from_secs(15 * 60);
+
+#[derive(Default)]
+pub struct MaintenanceState {
+    last_run: Mutex<Option<Instant>>,
+    last_report: Mutex<Option<CleanupReport>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StaleUpload {
+    pub bucket: String,
+    pub key: String,
+    pub upload_id: String,
+    pub initiated: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CleanupReport {
+    pub started_at: i64,
+    pub older_than_days: u32,
+    pub dry_run: bool,
+    pub aborted: Vec<StaleUpload>,
+    pub errors: Vec<String>,
+}
+
+async fn stale_uploads(client: &Client, bucket: &str, cutoff: i64) -> Result<Vec<StaleUpload>, String> {
+    let mut found = Vec::new();
+    let mut key_marker = None;
+    let mut upload_id_marker = None;
+
+    loop {
+        let resp = client.list_multipart_uploads()
+            .bucket(bucket)
+            .set_key_marker(key_marker)
+            .set_upload_id_marker(upload_id_marker)
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+
+        for upload in resp.uploads() {
+            let initiated = upload.initiated().map(|d| d.secs());
+            if initiated.is_some_and(|t| t < cutoff) {
+                found.push(StaleUpload {
+                    bucket: bucket.to_string(),
+                    key: upload.key().unwrap_or_default().to_string(),
+                    upload_id: upload.upload_id().unwrap_or_default().to_string(),
+                    initiated,
+                });
+            }
+        }
+
+        if resp.is_truncated().unwrap_or(false) {
+            key_marker = resp.next_key_marker().map(|s| s.to_string());
+            upload_id_marker = resp.next_upload_id_marker().map(|s| s.to_string());
+        } else {
+            break;
+        }
+    }
+
+    Ok(found)
+}
+
+/// Aborts incomplete multipart uploads older than `older_than_days` in every bucket.
+/// Per-bucket failures are collected in the report instead of stopping the run.
+pub async fn cleanup_multipart(client: &Client, older_than_days: u32, dry_run: bool) -> Result<CleanupReport, String> {
+    let started_at = jobs::now_secs();
+    let cutoff = started_at - older_than_days as i64 * 86_400;
+    let mut report = CleanupReport {
+        started_at,
+        older_than_days,
+        dry_run,
+        aborted: Vec::new(),
+        errors: Vec::new(),
+    };
+
+    let buckets = client.list_buckets().send().await.map_err(|e| e.to_string())?;
+    for bucket in buckets.buckets().iter().filter_map(|b| b.name()) {
+        let uploads = match stale_uploads(client, bucket, cutoff).await {
+            Ok(u) => u,
+            Err(e) => {
+                report.errors.push(format!("{}: {}", bucket, e));
+                continue;
+            }
+        };
+
+        for upload in uploads {
+            if !dry_run {
+                let aborted = client.abort_multipart_upload()
+                    .bucket(&upload.bucket)
+                    .key(&upload.key)
+                    .upload_id(&upload.upload_id)
+                    .send()
+                    .await;
+                if let Err(e) = aborted {
+                    report.errors.push(format!("{}/{}: {}", upload.bucket, upload.key, e));
+                    continue;
+                }
+            }
+            report.aborted.push(upload);
+        }
+    }
+
+    Ok(report)
+}
+
+/// Background loop for the opt-in cleanup policy. Runs once a client is connected and then
+/// every `interval_hours` over each writable connection, emitting `maintenance://multipart-cleanup`
+/// with each report.
+pub async fn run_scheduler(app: AppHandle) {
+    loop {
+        let policy = app.state::<AppState>().settings.lock().unwrap().multipart_cleanup.clone();
+        let maintenance = app.state::<MaintenanceState>();
+        let due = {
+            let last_run = maintenance.last_run.lock().unwrap();
+            last_run.is_none_or(|t| t.elapsed() >= Duration::from_secs(policy.interval_hours as u64 * 3600))
+        };
+        let state = app.state::<AppState>();
+        let writable: Vec<String> = state.connections.lock().unwrap()
+            .iter()
+            .filter(|(_, c)| !c.read_only)
+            .map(|(id, _)| id.clone())
+            .collect();
+
+        if policy.enabled && due && !writable.is_empty() {
+            *maintenance.last_run.lock().unwrap() = Some(Instant::now());
+            for connection_id in writable {
+                let job = jobs::start("multipart_cleanup", None, json!({
+                    "older_than_days": policy.older_than_days,
+                    "connection_id": connection_id,
+                    "scheduled": true,
+                }));
+                let result = match acquire_client(&state, Some(&connection_id)) {
+                    Ok(client) => cleanup_multipart(&client, policy.older_than_days, false).await,
+                    Err(e) => Err(e.into()),
+                };
+                job.finish(&app, &result, |r| format!("Aborted {} uploads", r.aborted.len()));
+                match result {
+                    Ok(report) => {
+                        let _ = app.emit("maintenance://multipart-cleanup", &report);
+                        *maintenance.last_report.lock().unwrap() = Some(report);
+                    }
+                    Err(e) => {
+                        let _ = app.emit("maintenance://error", e);
+                    }
+                }
+            }
+        }
+
+        tokio::time::sleep(TICK).await;
+    }
+}
+
+#[tauri::command]
+pub async fn cleanup_multipart_uploads(
+    older_than_days: u32,
+    dry_run: bool,
+    app: AppHandle,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+    maintenance: State<'_, MaintenanceState>,
+) -> Result<CleanupReport, AppError> {
+    let job = jobs::start("multipart_cleanup", None, json!({
+        "older_than_days": older_than_days,
+        "dry_run": dry_run,
+    }));
+    let result: Result<CleanupReport, AppError> = async {
+        let client = if dry_run {
+            acquire_client(&state, connection_id.as_deref())?
+        } else {
+            acquire_write_client(&state, connection_id.as_deref())?
+        };
+
+        let report = cleanup_multipart(&client, older_than_days, dry_run).await?;
+        if !dry_run {
+            *maintenance.last_report.lock().unwrap() = Some(report.clone());
+        }
+
+        Ok(report)
+    }
+    .await;
+    job.finish(&app, &result, |r| format!("Aborted {} uploads", r.aborted.len()));
+
+    result
+}
+
+#[tauri::command]
+pub fn get_last_cleanup_report(maintenance: State<'_, MaintenanceState>) -> Option<CleanupReport> {
+    maintenance.last_report.lock().unwrap().clone()
+}
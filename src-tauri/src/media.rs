@@ -0,0 +1,112 @@
+use tauri::http::{header, Request, Response, StatusCode};
+use tauri::{AppHandle, Manager, UriSchemeContext, UriSchemeResponder, Wry};
+use urlencoding::{decode, encode};
+
+use crate::error::AppError;
+use crate::s3::{acquire_client, connection_key, AppState};
+
+// Media objects are served to the webview through the r2media:// scheme so <video>/<audio> can
+// seek with range requests instead of downloading the whole object. Open-ended ranges are cut
+// to CHUNK_BYTES per response; players simply ask for the next range.
+pub const SCHEME: &str = "r2media";
+const CHUNK_BYTES: u64 = 4 * 1024 * 1024;
+
+/// Turns a Range header into one the endpoint is asked for, never spanning more than a chunk.
+fn bounded_range(range: Option<&str>) -> Result<String, String> {
+    let Some(spec) = range.map(str::trim) else {
+        return Ok(format!("bytes=0-{}", CHUNK_BYTES - 1));
+    };
+    let spec = spec.strip_prefix("bytes=").ok_or("Only byte ranges are supported")?;
+    // Players only ever send a single range
+    let spec = spec.split(',').next().unwrap_or(spec).trim();
+    let (start, end) = spec.split_once('-').ok_or("Malformed range")?;
+
+    if start.is_empty() {
+        let suffix: u64 = end.parse().map_err(|_| "Malformed range")?;
+        return Ok(format!("bytes=-{}", suffix.min(CHUNK_BYTES)));
+    }
+    let start: u64 = start.parse().map_err(|_| "Malformed range")?;
+    let last = start + CHUNK_BYTES - 1;
+    let end = match end {
+        "" => last,
+        e => e.parse::<u64>().map_err(|_| "Malformed range")?.min(last),
+    };
+    Ok(format!("bytes={}-{}", start, end))
+}
+
+fn error_response(status: StatusCode, message: String) -> Response<Vec<u8>> {
+    Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, "text/plain")
+        .body(message.into_bytes())
+        .unwrap()
+}
+
+async fn serve(app: AppHandle, request: Request<Vec<u8>>) -> Response<Vec<u8>> {
+    // Path is /<connection id>/<bucket>/<key>, each segment percent-encoded
+    let path = request.uri().path().trim_start_matches('/').to_string();
+    let mut segments = path.splitn(3, '/').map(|s| decode(s).map(|d| d.into_owned()).unwrap_or_default());
+    let (Some(connection_id), Some(bucket), Some(key)) = (segments.next(), segments.next(), segments.next()) else {
+        return error_response(StatusCode::BAD_REQUEST, "Expected /<connection>/<bucket>/<key>".to_string());
+    };
+    let range_header = request.headers().get(header::RANGE).and_then(|v| v.to_str().ok());
+    let range = match bounded_range(range_header) {
+        Ok(r) => r,
+        Err(e) => return error_response(StatusCode::RANGE_NOT_SATISFIABLE, e),
+    };
+
+    let state = app.state::<AppState>();
+    let result: Result<_, AppError> = async {
+        let client = acquire_client(&state, Some(&connection_id))?;
+        let resp = client.get_object().bucket(&bucket).key(&key).range(&range).send().await?;
+        let content_type = resp.content_type().unwrap_or("application/octet-stream").to_string();
+        let content_range = resp.content_range().map(|r| r.to_string());
+        let data = resp.body.collect().await.map_err(|e| e.to_string())?.into_bytes().to_vec();
+        Ok((content_type, content_range, data))
+    }.await;
+
+    match result {
+        Ok((content_type, content_range, data)) => {
+            // A request without a Range header that got the whole object is a plain 200
+            let total = content_range.as_deref().and_then(|r| r.rsplit('/').next()).and_then(|t| t.parse::<usize>().ok());
+            let partial = range_header.is_some() || total.is_some_and(|t| t > data.len());
+            let mut builder = Response::builder()
+                .header(header::CONTENT_TYPE, content_type)
+                .header(header::ACCEPT_RANGES, "bytes")
+                .header(header::CONTENT_LENGTH, data.len())
+                .header(header::ACCESS_CONTROL_ALLOW_ORIGIN, "*");
+            if partial {
+                builder = builder.status(StatusCode::PARTIAL_CONTENT);
+                if let Some(r) = content_range {
+                    builder = builder.header(header::CONTENT_RANGE, r);
+                }
+            }
+            builder.body(data).unwrap()
+        }
+        Err(e) => {
+            let status = e.http_status.and_then(|s| StatusCode::from_u16(s).ok()).unwrap_or(StatusCode::BAD_GATEWAY);
+            error_response(status, e.message)
+        }
+    }
+}
+
+/// Handler for the r2media:// scheme, registered on the builder.
+pub fn handle(ctx: UriSchemeContext<'_, Wry>, request: Request<Vec<u8>>, responder: UriSchemeResponder) {
+    let app = ctx.app_handle().clone();
+    tauri::async_runtime::spawn(async move {
+        responder.respond(serve(app, request).await);
+    });
+}
+
+/// A URL the webview can stream `key` from, e.g. as a <video> source.
+#[tauri::command]
+pub fn get_media_url(bucket: String, key: String, connection_id: Option<String>) -> String {
+    // Windows and Android webviews only reach custom schemes through http://<scheme>.localhost
+    let base = if cfg!(any(windows, target_os = "android")) {
+        format!("http://{}.localhost", SCHEME)
+    } else {
+        format!("{}://localhost", SCHEME)
+    };
+    let key: Vec<String> = key.split('/').map(|s| encode(s).into_owned()).collect();
+    format!("{}/{}/{}/{}", base, encode(connection_key(connection_id.as_deref())), encode(&bucket), key.join("/"))
+}
@@ -0,0 +1,282 @@
+use aws_sdk_s3::Client;
+use bytes::Bytes;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io::Cursor;
+use tauri::State;
+
+use crate::error::{AppError, ErrorKind};
+use crate::s3::{acquire_client, AppState};
+use crate::text;
+
+// Everything here is read from the start of the object, plus the `moov` box for MP4s that keep
+// it at the end. JPEG EXIF always fits in the first 64 KB segment.
+const HEAD_BYTES: u64 = 128 * 1024;
+const MAX_MOOV_BYTES: u64 = 16 * 1024 * 1024;
+const MAX_TOP_LEVEL_BOXES: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MediaKind {
+    Image,
+    Video,
+    Audio,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MediaInfo {
+    pub kind: Option<MediaKind>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub duration_secs: Option<f64>,
+    /// Codec identifiers as the container names them, e.g. "avc1", "mp4a", "pcm", "flac".
+    pub codecs: Vec<String>,
+    pub sample_rate: Option<u32>,
+    pub channels: Option<u16>,
+    /// EXIF tags of the primary image by name, formatted for display with units.
+    pub exif: BTreeMap<String, String>,
+}
+
+fn be_u16(b: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(b.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn be_u32(b: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(b.get(at..at + 4)?.try_into().ok()?))
+}
+
+fn be_u64(b: &[u8], at: usize) -> Option<u64> {
+    Some(u64::from_be_bytes(b.get(at..at + 8)?.try_into().ok()?))
+}
+
+fn le_u16(b: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(b.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn le_u32(b: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(b.get(at..at + 4)?.try_into().ok()?))
+}
+
+async fn fetch(client: &Client, bucket: &str, key: &str, range: String) -> Result<(Bytes, Option<u64>), AppError> {
+    let resp = client.get_object().bucket(bucket).key(key).range(range).send().await?;
+    let size = text::object_size(resp.content_range());
+    Ok((resp.body.collect().await.map_err(|e| e.to_string())?.into_bytes(), size))
+}
+
+fn read_exif(head: &[u8], info: &mut MediaInfo) {
+    let Ok(exif) = exif::Reader::new().read_from_container(&mut Cursor::new(head)) else {
+        return;
+    };
+    for field in exif.fields().filter(|f| f.ifd_num == exif::In::PRIMARY) {
+        info.exif.insert(field.tag.to_string(), field.display_value().with_unit(&exif).to_string());
+    }
+    // Fallback for when the image header lies past what was fetched
+    let dimension = |tag| exif.get_field(tag, exif::In::PRIMARY).and_then(|f| f.value.get_uint(0));
+    info.width = info.width.or_else(|| dimension(exif::Tag::PixelXDimension));
+    info.height = info.height.or_else(|| dimension(exif::Tag::PixelYDimension));
+}
+
+fn read_image(head: &[u8], info: &mut MediaInfo) {
+    info.kind = Some(MediaKind::Image);
+    if let Ok(reader) = image::ImageReader::new(Cursor::new(head)).with_guessed_format() {
+        if let Some(format) = reader.format() {
+            info.codecs.push(format.extensions_str().first().copied().unwrap_or_default().to_string());
+        }
+        if let Ok((width, height)) = reader.into_dimensions() {
+            info.width = Some(width);
+            info.height = Some(height);
+        }
+    }
+    read_exif(head, info);
+}
+
+/// ISO BMFF boxes in `data` as (type, body). A size of 0 runs to the end; 1 means a 64-bit size.
+fn boxes(data: &[u8]) -> Vec<(&[u8], &[u8])> {
+    let mut out = Vec::new();
+    let mut at = 0;
+    while let (Some(size), Some(kind)) = (be_u32(data, at), data.get(at + 4..at + 8)) {
+        let (header, size) = match size {
+            0 => (8, (data.len() - at) as u64),
+            1 => (16, be_u64(data, at + 8).unwrap_or(0)),
+            n => (8, n as u64),
+        };
+        let end = at.saturating_add(size as usize);
+        if size < header as u64 || end > data.len() {
+            break;
+        }
+        out.push((kind, &data[at + header..end]));
+        at = end;
+    }
+    out
+}
+
+fn child<'a>(data: &'a [u8], kind: &[u8]) -> Option<&'a [u8]> {
+    boxes(data).into_iter().find(|(k, _)| *k == kind).map(|(_, body)| body)
+}
+
+fn parse_moov(moov: &[u8], info: &mut MediaInfo) {
+    if let Some(mvhd) = child(moov, b"mvhd") {
+        let (timescale, duration) = match mvhd.first() {
+            Some(1) => (be_u32(mvhd, 20), be_u64(mvhd, 24)),
+            _ => (be_u32(mvhd, 12), be_u32(mvhd, 16).map(u64::from)),
+        };
+        if let (Some(timescale), Some(duration)) = (timescale.filter(|t| *t > 0), duration) {
+            info.duration_secs = Some(duration as f64 / timescale as f64);
+        }
+    }
+
+    for (_, trak) in boxes(moov).into_iter().filter(|(k, _)| *k == b"trak") {
+        let Some(mdia) = child(trak, b"mdia") else { continue };
+        let handler = child(mdia, b"hdlr").and_then(|h| h.get(8..12));
+        let stsd = child(mdia, b"minf").and_then(|m| child(m, b"stbl")).and_then(|s| child(s, b"stsd"));
+        // First sample entry: size, format, then the format-specific fields
+        let entry = stsd.and_then(|s| s.get(8..));
+        if let Some(codec) = entry.and_then(|e| e.get(4..8)) {
+            info.codecs.push(String::from_utf8_lossy(codec).trim().to_string());
+        }
+
+        match handler {
+            Some(b"vide") => {
+                info.kind = Some(MediaKind::Video);
+                if let Some(tkhd) = child(trak, b"tkhd") {
+                    // 16.16 fixed point, after the matrix
+                    let at = if tkhd.first() == Some(&1) { 88 } else { 76 };
+                    if let (Some(w), Some(h)) = (be_u32(tkhd, at), be_u32(tkhd, at + 4)) {
+                        info.width = info.width.or(Some(w >> 16));
+                        info.height = info.height.or(Some(h >> 16));
+                    }
+                }
+            }
+            Some(b"soun") => {
+                info.kind = info.kind.or(Some(MediaKind::Audio));
+                if let Some(entry) = entry {
+                    info.channels = info.channels.or(be_u16(entry, 24));
+                    info.sample_rate = info.sample_rate.or(be_u32(entry, 32).map(|r| r >> 16));
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Walks the top-level boxes with small ranged reads until `moov` turns up.
+async fn find_moov(client: &Client, bucket: &str, key: &str, head: &Bytes, size: u64) -> Result<Option<Bytes>, AppError> {
+    let mut at = 0u64;
+    for _ in 0..MAX_TOP_LEVEL_BOXES {
+        if at + 8 > size {
+            break;
+        }
+        let header = match head.get(at as usize..at as usize + 16) {
+            Some(h) => Bytes::copy_from_slice(h),
+            None => fetch(client, bucket, key, format!("bytes={}-{}", at, (at + 15).min(size - 1))).await?.0,
+        };
+        let (Some(len), Some(kind)) = (be_u32(&header, 0), header.get(4..8)) else { break };
+        let (header_len, len) = match len {
+            0 => (8, size - at),
+            1 => (16, be_u64(&header, 8).unwrap_or(0)),
+            n => (8, n as u64),
+        };
+        if len < header_len {
+            break;
+        }
+        if kind == b"moov" {
+            if len > MAX_MOOV_BYTES {
+                return Err(AppError::new(ErrorKind::Unsupported, "Media index is too large to read"));
+            }
+            let (start, end) = (at + header_len, at + len);
+            return match head.get(start as usize..end as usize) {
+                Some(body) => Ok(Some(Bytes::copy_from_slice(body))),
+                None => Ok(Some(fetch(client, bucket, key, format!("bytes={}-{}", start, end - 1)).await?.0)),
+            };
+        }
+        at += len;
+    }
+    Ok(None)
+}
+
+fn parse_wav(head: &[u8], size: u64, info: &mut MediaInfo) {
+    info.kind = Some(MediaKind::Audio);
+    let mut byte_rate = None;
+    let mut at = 12;
+    while let (Some(id), Some(len)) = (head.get(at..at + 4), le_u32(head, at + 4)) {
+        let body = at + 8;
+        match id {
+            b"fmt " => {
+                info.codecs.push(match le_u16(head, body) {
+                    Some(1) => "pcm".to_string(),
+                    Some(3) => "pcm-float".to_string(),
+                    Some(tag) => format!("0x{:04x}", tag),
+                    None => break,
+                });
+                info.channels = le_u16(head, body + 2);
+                info.sample_rate = le_u32(head, body + 4);
+                byte_rate = le_u32(head, body + 8).filter(|r| *r > 0);
+            }
+            b"data" => {
+                // The data chunk may claim more than is there in a truncated file
+                let data = (len as u64).min(size.saturating_sub(body as u64));
+                info.duration_secs = byte_rate.map(|r| data as f64 / r as f64);
+                break;
+            }
+            _ => {}
+        }
+        // Chunks are padded to an even length
+        at = body + len as usize + (len as usize & 1);
+    }
+}
+
+fn parse_flac(head: &[u8], info: &mut MediaInfo) {
+    info.kind = Some(MediaKind::Audio);
+    info.codecs.push("flac".to_string());
+    // STREAMINFO is always the first metadata block, right after "fLaC" and its 4-byte header
+    let Some(packed) = be_u64(head, 18) else { return };
+    let sample_rate = (packed >> 44) as u32;
+    let channels = ((packed >> 41) & 0x7) as u16 + 1;
+    let samples = packed & 0xF_FFFF_FFFF;
+    info.sample_rate = Some(sample_rate);
+    info.channels = Some(channels);
+    if sample_rate > 0 && samples > 0 {
+        info.duration_secs = Some(samples as f64 / sample_rate as f64);
+    }
+}
+
+/// EXIF tags and basic media properties for the properties panel: dimensions for images,
+/// duration, resolution and codecs for MP4/MOV/M4A video and audio, and the stream format of WAV
+/// and FLAC. Only the byte ranges holding that information are fetched.
+#[tauri::command]
+pub async fn get_media_info(
+    bucket: String,
+    key: String,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<MediaInfo, AppError> {
+    let client = acquire_client(&state, connection_id.as_deref())?;
+    let (head, size) = match fetch(&client, &bucket, &key, format!("bytes=0-{}", HEAD_BYTES - 1)).await {
+        Ok(fetched) => fetched,
+        Err(e) if e.http_status == Some(416) => return Ok(MediaInfo::default()),
+        Err(e) => return Err(e),
+    };
+    let size = size.unwrap_or(head.len() as u64);
+
+    let mut info = MediaInfo::default();
+    if head.get(4..8) == Some(b"ftyp") {
+        // HEIF/AVIF images are ISO BMFF too, without a moov
+        let brand = head.get(8..12).unwrap_or_default();
+        if matches!(brand, b"heic" | b"heix" | b"mif1" | b"avif") {
+            info.kind = Some(MediaKind::Image);
+            info.codecs.push(String::from_utf8_lossy(brand).to_string());
+            read_exif(&head, &mut info);
+        } else if let Some(moov) = find_moov(&client, &bucket, &key, &head, size).await? {
+            parse_moov(&moov, &mut info);
+        }
+    } else if head.starts_with(b"RIFF") && head.get(8..12) == Some(b"WAVE") {
+        parse_wav(&head, size, &mut info);
+    } else if head.starts_with(b"fLaC") {
+        parse_flac(&head, &mut info);
+    } else if image::guess_format(&head).is_ok() {
+        read_image(&head, &mut info);
+    } else {
+        return Err(AppError::new(ErrorKind::Unsupported, "No media information available for this file type"));
+    }
+    Ok(info)
+}
@@ -0,0 +1,146 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use tauri::{AppHandle, State};
+
+use crate::error::{AppError, ErrorKind};
+use crate::s3::{acquire_client, connection, AppState};
+use crate::sync::normalize_prefix;
+
+// A bucket, or a prefix of one, mounted as a local folder so any application can open its
+// files. Reads go through a block cache; writes land in a staged local copy that is uploaded
+// when the file is closed. Built with the `mount` feature, on top of FUSE (fusermount on Linux,
+// macFUSE on macOS) or WinFsp on Windows.
+static SEQUENCE: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MountInfo {
+    pub id: String,
+    pub bucket: String,
+    pub prefix: String,
+    pub mountpoint: String,
+    pub connection_id: Option<String>,
+    pub read_only: bool,
+}
+
+struct Mounted {
+    info: MountInfo,
+    // Unmounts when dropped
+    #[cfg(all(unix, feature = "mount"))]
+    _session: fuser::BackgroundSession,
+    #[cfg(all(windows, feature = "mount"))]
+    _session: crate::mount_winfsp::Session,
+}
+
+#[derive(Default)]
+pub struct MountState {
+    mounts: Mutex<HashMap<String, Mounted>>,
+}
+
+#[cfg(all(unix, feature = "mount"))]
+fn start(app: &AppHandle, info: MountInfo) -> Result<Mounted, AppError> {
+    let session = crate::mount_fuse::mount(app, &info)?;
+    Ok(Mounted { info, _session: session })
+}
+
+#[cfg(all(windows, feature = "mount"))]
+fn start(app: &AppHandle, info: MountInfo) -> Result<Mounted, AppError> {
+    let session = crate::mount_winfsp::mount(app, &info)?;
+    Ok(Mounted { info, _session: session })
+}
+
+#[cfg(not(all(any(unix, windows), feature = "mount")))]
+fn start(_app: &AppHandle, _info: MountInfo) -> Result<Mounted, AppError> {
+    Err(AppError::new(ErrorKind::Unsupported, "Mounting buckets isn't available in this build"))
+}
+
+/// FUSE mounts over an existing empty folder.
+#[cfg(not(windows))]
+fn check_mountpoint(mountpoint: &str) -> Result<(), AppError> {
+    let path = Path::new(mountpoint);
+    if !path.is_dir() {
+        return Err(AppError::new(ErrorKind::InvalidInput, format!("{} is not a folder", mountpoint)));
+    }
+    if path.read_dir().map_err(|e| e.to_string())?.next().is_some() {
+        return Err(AppError::new(ErrorKind::InvalidInput, "Mount points must be empty folders"));
+    }
+    Ok(())
+}
+
+/// WinFsp mounts on a free drive letter, or creates the mount folder itself.
+#[cfg(windows)]
+fn check_mountpoint(mountpoint: &str) -> Result<(), AppError> {
+    let drive = mountpoint.len() == 2 && mountpoint.ends_with(':') && mountpoint.as_bytes()[0].is_ascii_alphabetic();
+    if drive {
+        if Path::new(&format!("{}\\", mountpoint)).exists() {
+            return Err(AppError::new(ErrorKind::AlreadyExists, format!("Drive {} is already in use", mountpoint)));
+        }
+        return Ok(());
+    }
+    let path = Path::new(mountpoint);
+    if path.exists() {
+        return Err(AppError::new(ErrorKind::InvalidInput, "Mount points must be a free drive letter or a folder that doesn't exist yet"));
+    }
+    if !path.parent().is_some_and(Path::is_dir) {
+        return Err(AppError::new(ErrorKind::InvalidInput, format!("The folder holding {} doesn't exist", mountpoint)));
+    }
+    Ok(())
+}
+
+/// Mounts `bucket` (or just `prefix` in it) at `mountpoint`: an existing empty folder, or on
+/// Windows a free drive letter such as `R:` or a folder that doesn't exist yet. Mounts are
+/// read-only when asked or when the connection is.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn mount_bucket(
+    bucket: String,
+    prefix: Option<String>,
+    mountpoint: String,
+    read_only: Option<bool>,
+    app: AppHandle,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+    mounts: State<'_, MountState>,
+) -> Result<MountInfo, AppError> {
+    check_mountpoint(&mountpoint)?;
+    if mounts.mounts.lock().unwrap().values().any(|m| m.info.mountpoint == mountpoint) {
+        return Err(AppError::new(ErrorKind::AlreadyExists, format!("Something is already mounted at {}", mountpoint)));
+    }
+
+    let read_only = read_only.unwrap_or(false) || connection(&state, connection_id.as_deref())?.read_only;
+    {
+        let client = acquire_client(&state, connection_id.as_deref())?;
+        client.head_bucket().bucket(&bucket).send().await?;
+    }
+
+    let info = MountInfo {
+        id: format!("mount-{}", SEQUENCE.fetch_add(1, Ordering::Relaxed) + 1),
+        bucket,
+        prefix: normalize_prefix(prefix.as_deref().unwrap_or_default()),
+        mountpoint,
+        connection_id,
+        read_only,
+    };
+    let mounted = start(&app, info.clone())?;
+    mounts.mounts.lock().unwrap().insert(info.id.clone(), mounted);
+
+    Ok(info)
+}
+
+/// Unmounts a mount. Uploads of files already closed still finish in the background.
+#[tauri::command]
+pub fn unmount_bucket(id: String, mounts: State<'_, MountState>) -> Result<(), AppError> {
+    match mounts.mounts.lock().unwrap().remove(&id) {
+        Some(_) => Ok(()),
+        None => Err(AppError::new(ErrorKind::NotFound, format!("No mount with id {}", id))),
+    }
+}
+
+#[tauri::command]
+pub fn list_mounts(mounts: State<'_, MountState>) -> Vec<MountInfo> {
+    let mut list: Vec<MountInfo> = mounts.mounts.lock().unwrap().values().map(|m| m.info.clone()).collect();
+    list.sort_by(|a, b| a.mountpoint.cmp(&b.mountpoint));
+    list
+}
@@ -0,0 +1,734 @@
+use aws_sdk_s3::Client;
+use serde::Serialize;
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fs::{File, OpenOptions};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter, Manager};
+use urlencoding::encode;
+
+use crate::error::{AppError, ErrorKind};
+use crate::jobs;
+use crate::mount::MountInfo;
+use crate::s3::{self, acquire_client, acquire_write_client, AppState};
+use crate::thumbnails;
+use r2drive_core::sync;
+
+// What a mount does, whichever driver it runs on (FUSE in mount_fuse, WinFsp in mount_winfsp).
+// Folders are listed lazily with a delimiter and re-listed once they are a few seconds old.
+// Files are read in ranged blocks kept in a shared in-memory cache keyed by ETag, so a changed
+// object is never served from stale blocks. Opening a file for writing stages a local copy; it
+// is uploaded when the last handle closes, and reads come from it until then. Objects are shown
+// as stored: client-side encrypted ones stay encrypted.
+const LISTING_TTL: Duration = Duration::from_secs(5);
+pub const BLOCK_SIZE: u64 = 4 * 1024 * 1024;
+/// 256 MiB of cached blocks per mount.
+const CACHE_BLOCKS: usize = 64;
+/// Largest object `rename` moves with a server-side copy; bigger ones are left to the caller's
+/// copy-and-delete fallback.
+const MAX_COPY_SIZE: u64 = 5 * 1024 * 1024 * 1024;
+pub const ROOT: u64 = 1;
+
+/// Payload of `mount://uploaded`, sent when a file written through a mount has been uploaded
+/// (or failed to).
+#[derive(Debug, Clone, Serialize)]
+pub struct MountUpload {
+    pub mount_id: String,
+    pub key: String,
+    pub error: Option<String>,
+}
+
+/// Why a mount operation failed, for each driver to turn into its own error codes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FsError {
+    NotFound,
+    NotDir,
+    IsDir,
+    Exists,
+    NotEmpty,
+    Busy,
+    /// Can't be done in place; the caller should copy instead.
+    CrossDevice,
+    InvalidName,
+    BadHandle,
+    ReadOnly,
+    AccessDenied,
+    /// The object changed on the endpoint while it was being read.
+    Stale,
+    Io,
+}
+
+impl From<AppError> for FsError {
+    fn from(e: AppError) -> Self {
+        match e.kind {
+            ErrorKind::NotFound => FsError::NotFound,
+            ErrorKind::AccessDenied | ErrorKind::InvalidCredentials => FsError::AccessDenied,
+            ErrorKind::ReadOnly => FsError::ReadOnly,
+            ErrorKind::PreconditionFailed => FsError::Stale,
+            _ => FsError::Io,
+        }
+    }
+}
+
+impl From<std::io::Error> for FsError {
+    fn from(e: std::io::Error) -> Self {
+        match e.kind() {
+            std::io::ErrorKind::NotFound => FsError::NotFound,
+            std::io::ErrorKind::PermissionDenied => FsError::AccessDenied,
+            _ => FsError::Io,
+        }
+    }
+}
+
+pub struct Node {
+    /// Path under the mount root without leading or trailing slash; empty for the root.
+    pub path: String,
+    pub parent: u64,
+    pub dir: bool,
+    size: u64,
+    pub mtime: SystemTime,
+    etag: Option<String>,
+    /// Folder entries by name, as of `listed_at`.
+    pub children: BTreeMap<String, u64>,
+    listed_at: Option<Instant>,
+    /// Local copy holding writes not uploaded yet.
+    staged: Option<PathBuf>,
+    writers: usize,
+    /// Bumped on every change to the staged copy, so an upload only discards what it sent.
+    generation: u64,
+}
+
+impl Node {
+    fn new(path: String, parent: u64, dir: bool, size: u64, mtime: SystemTime, etag: Option<String>) -> Self {
+        Self {
+            path,
+            parent,
+            dir,
+            size,
+            mtime,
+            etag,
+            children: BTreeMap::new(),
+            listed_at: None,
+            staged: None,
+            writers: 0,
+            generation: 0,
+        }
+    }
+
+    /// Current size, counting writes that haven't been uploaded yet.
+    pub fn size(&self) -> u64 {
+        match &self.staged {
+            Some(path) => std::fs::metadata(path).map(|m| m.len()).unwrap_or(self.size),
+            None => self.size,
+        }
+    }
+}
+
+/// A folder entry as listed by the endpoint.
+struct Listed {
+    name: String,
+    dir: bool,
+    size: u64,
+    mtime: SystemTime,
+    etag: Option<String>,
+}
+
+pub struct Inodes {
+    pub nodes: HashMap<u64, Node>,
+    next: u64,
+}
+
+impl Inodes {
+    /// Inode of `name` in `parent`, refreshed with what the endpoint reports, or a new one.
+    fn child(&mut self, parent: u64, name: &str, dir: bool, size: u64, mtime: SystemTime, etag: Option<String>) -> u64 {
+        let existing = self.nodes.get(&parent).and_then(|p| p.children.get(name).copied());
+        if let Some(node) = existing.and_then(|ino| self.nodes.get_mut(&ino)).filter(|n| n.dir == dir) {
+            if node.staged.is_none() {
+                node.size = size;
+                node.mtime = mtime;
+                node.etag = etag;
+            }
+            return existing.unwrap_or_default();
+        }
+
+        let ino = self.next;
+        self.next += 1;
+        let path = join(&self.nodes[&parent].path, name);
+        self.nodes.insert(ino, Node::new(path, parent, dir, size, mtime, etag));
+        if let Some(parent) = self.nodes.get_mut(&parent) {
+            parent.children.insert(name.to_string(), ino);
+        }
+        ino
+    }
+}
+
+struct Handle {
+    ino: u64,
+    write: bool,
+    dirty: bool,
+}
+
+/// Cached file blocks by (inode, ETag, block index), dropped oldest first.
+#[derive(Default)]
+struct BlockCache {
+    blocks: HashMap<(u64, String, u64), Arc<Vec<u8>>>,
+    order: VecDeque<(u64, String, u64)>,
+}
+
+impl BlockCache {
+    fn get(&self, key: &(u64, String, u64)) -> Option<Arc<Vec<u8>>> {
+        self.blocks.get(key).cloned()
+    }
+
+    fn insert(&mut self, key: (u64, String, u64), block: Arc<Vec<u8>>) {
+        if self.blocks.insert(key.clone(), block).is_none() {
+            self.order.push_back(key);
+        }
+        while self.order.len() > CACHE_BLOCKS {
+            if let Some(oldest) = self.order.pop_front() {
+                self.blocks.remove(&oldest);
+            }
+        }
+    }
+}
+
+fn join(parent: &str, name: &str) -> String {
+    if parent.is_empty() { name.to_string() } else { format!("{}/{}", parent, name) }
+}
+
+fn modified(time: Option<&aws_sdk_s3::primitives::DateTime>) -> SystemTime {
+    time.and_then(|t| u64::try_from(t.secs()).ok()).map(|secs| UNIX_EPOCH + Duration::from_secs(secs)).unwrap_or(UNIX_EPOCH)
+}
+
+#[cfg(unix)]
+fn read_at(file: &File, data: &mut [u8], offset: u64) -> std::io::Result<()> {
+    std::os::unix::fs::FileExt::read_exact_at(file, data, offset)
+}
+
+#[cfg(windows)]
+fn read_at(file: &File, mut data: &mut [u8], mut offset: u64) -> std::io::Result<()> {
+    while !data.is_empty() {
+        match std::os::windows::fs::FileExt::seek_read(file, data, offset)? {
+            0 => return Err(std::io::ErrorKind::UnexpectedEof.into()),
+            read => {
+                data = &mut data[read..];
+                offset += read as u64;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn write_at(file: &File, data: &[u8], offset: u64) -> std::io::Result<()> {
+    std::os::unix::fs::FileExt::write_all_at(file, data, offset)
+}
+
+#[cfg(windows)]
+fn write_at(file: &File, mut data: &[u8], mut offset: u64) -> std::io::Result<()> {
+    while !data.is_empty() {
+        match std::os::windows::fs::FileExt::seek_write(file, data, offset)? {
+            0 => return Err(std::io::ErrorKind::WriteZero.into()),
+            written => {
+                data = &data[written..];
+                offset += written as u64;
+            }
+        }
+    }
+    Ok(())
+}
+
+pub struct MountFs {
+    app: AppHandle,
+    pub info: MountInfo,
+    staging: PathBuf,
+    pub inodes: Arc<Mutex<Inodes>>,
+    handles: Mutex<HashMap<u64, Handle>>,
+    next_handle: AtomicU64,
+    blocks: Mutex<BlockCache>,
+    /// One upload at a time, so the last one to land carries the latest content.
+    uploads: Arc<tokio::sync::Mutex<()>>,
+}
+
+impl MountFs {
+    pub fn new(app: &AppHandle, info: &MountInfo) -> Result<Self, AppError> {
+        let staging = thumbnails::cache_dir(app, "mounts")?.join(&info.id);
+        std::fs::create_dir_all(&staging).map_err(|e| e.to_string())?;
+
+        let mut nodes = HashMap::new();
+        nodes.insert(ROOT, Node::new(String::new(), ROOT, true, 0, SystemTime::now(), None));
+        Ok(Self {
+            app: app.clone(),
+            info: info.clone(),
+            staging,
+            inodes: Arc::new(Mutex::new(Inodes { nodes, next: ROOT + 1 })),
+            handles: Mutex::new(HashMap::new()),
+            next_handle: AtomicU64::new(0),
+            blocks: Mutex::new(BlockCache::default()),
+            uploads: Arc::new(tokio::sync::Mutex::new(())),
+        })
+    }
+
+    fn key(&self, path: &str, dir: bool) -> String {
+        if dir && !path.is_empty() {
+            format!("{}{}/", self.info.prefix, path)
+        } else {
+            format!("{}{}", self.info.prefix, path)
+        }
+    }
+
+    fn client(&self, write: bool) -> Result<Client, FsError> {
+        let state = self.app.state::<AppState>();
+        let connection_id = self.info.connection_id.as_deref();
+        let client = if write {
+            acquire_write_client(&state, connection_id)
+        } else {
+            acquire_client(&state, connection_id)
+        };
+        Ok(client?)
+    }
+
+    /// Lists a folder again if its entries are older than `LISTING_TTL`. New files that haven't
+    /// been uploaded yet are kept.
+    pub fn refresh(&self, ino: u64) -> Result<(), FsError> {
+        let path = {
+            let inodes = self.inodes.lock().unwrap();
+            let node = inodes.nodes.get(&ino).ok_or(FsError::NotFound)?;
+            if !node.dir {
+                return Err(FsError::NotDir);
+            }
+            if node.listed_at.is_some_and(|at| at.elapsed() < LISTING_TTL) {
+                return Ok(());
+            }
+            node.path.clone()
+        };
+
+        let prefix = self.key(&path, true);
+        let client = self.client(false)?;
+        let listed: Result<Vec<Listed>, AppError> = tauri::async_runtime::block_on(async {
+            let mut entries = Vec::new();
+            let mut continuation_token = None;
+            loop {
+                let resp = client.list_objects_v2()
+                    .bucket(&self.info.bucket)
+                    .prefix(&prefix)
+                    .delimiter("/")
+                    .set_continuation_token(continuation_token)
+                    .send()
+                    .await?;
+                for folder in resp.common_prefixes() {
+                    let name = folder.prefix().unwrap_or_default()[prefix.len()..].trim_end_matches('/');
+                    if !name.is_empty() {
+                        entries.push(Listed { name: name.to_string(), dir: true, size: 0, mtime: UNIX_EPOCH, etag: None });
+                    }
+                }
+                for object in resp.contents() {
+                    let name = &object.key().unwrap_or_default()[prefix.len()..];
+                    if name.is_empty() || name.ends_with('/') {
+                        continue;
+                    }
+                    entries.push(Listed {
+                        name: name.to_string(),
+                        dir: false,
+                        size: object.size().unwrap_or(0).max(0) as u64,
+                        mtime: modified(object.last_modified()),
+                        etag: object.e_tag().map(str::to_string),
+                    });
+                }
+                if resp.is_truncated().unwrap_or(false) {
+                    continuation_token = resp.next_continuation_token;
+                } else {
+                    return Ok(entries);
+                }
+            }
+        });
+        let listed = listed?;
+
+        let mut inodes = self.inodes.lock().unwrap();
+        let mut children = BTreeMap::new();
+        for entry in listed {
+            let child = inodes.child(ino, &entry.name, entry.dir, entry.size, entry.mtime, entry.etag);
+            children.insert(entry.name, child);
+        }
+        let Some(node) = inodes.nodes.get(&ino) else { return Err(FsError::NotFound) };
+        let unsent: Vec<(String, u64)> = node.children
+            .iter()
+            .filter(|(name, child)| !children.contains_key(*name) && inodes.nodes.get(child).is_some_and(|c| c.staged.is_some()))
+            .map(|(name, child)| (name.clone(), *child))
+            .collect();
+        children.extend(unsent);
+        if let Some(node) = inodes.nodes.get_mut(&ino) {
+            node.children = children;
+            node.listed_at = Some(Instant::now());
+        }
+        Ok(())
+    }
+
+    pub fn find(&self, parent: u64, name: &str) -> Result<u64, FsError> {
+        self.refresh(parent)?;
+        let inodes = self.inodes.lock().unwrap();
+        inodes.nodes.get(&parent).and_then(|p| p.children.get(name).copied()).ok_or(FsError::NotFound)
+    }
+
+    /// Entries of a folder as (name, inode, is folder), listing it again if needed.
+    pub fn entries(&self, ino: u64) -> Result<Vec<(String, u64, bool)>, FsError> {
+        self.refresh(ino)?;
+        let inodes = self.inodes.lock().unwrap();
+        let node = inodes.nodes.get(&ino).ok_or(FsError::NotFound)?;
+        Ok(node.children
+            .iter()
+            .filter_map(|(name, child)| Some((name.clone(), *child, inodes.nodes.get(child)?.dir)))
+            .collect())
+    }
+
+    fn block(&self, ino: u64, key: &str, etag: &str, size: u64, index: u64) -> Result<Arc<Vec<u8>>, FsError> {
+        let cache_key = (ino, etag.to_string(), index);
+        if let Some(block) = self.blocks.lock().unwrap().get(&cache_key) {
+            return Ok(block);
+        }
+
+        let start = index * BLOCK_SIZE;
+        let last = (start + BLOCK_SIZE).min(size) - 1;
+        let client = self.client(false)?;
+        let data: Result<Vec<u8>, AppError> = tauri::async_runtime::block_on(async {
+            let resp = client.get_object()
+                .bucket(&self.info.bucket)
+                .key(key)
+                .range(format!("bytes={}-{}", start, last))
+                .set_if_match((!etag.is_empty()).then(|| etag.to_string()))
+                .send()
+                .await?;
+            Ok(resp.body.collect().await.map_err(|e| e.to_string())?.into_bytes().to_vec())
+        });
+        let block = Arc::new(data?);
+        self.blocks.lock().unwrap().insert(cache_key, block.clone());
+        Ok(block)
+    }
+
+    fn read_remote(&self, ino: u64, offset: u64, len: u64) -> Result<Vec<u8>, FsError> {
+        let (key, etag, size) = {
+            let inodes = self.inodes.lock().unwrap();
+            let node = inodes.nodes.get(&ino).ok_or(FsError::NotFound)?;
+            (self.key(&node.path, false), node.etag.clone().unwrap_or_default(), node.size)
+        };
+        let end = offset.saturating_add(len).min(size);
+        let mut data = Vec::with_capacity(end.saturating_sub(offset) as usize);
+        let mut index = offset / BLOCK_SIZE;
+        while index * BLOCK_SIZE < end {
+            let block = self.block(ino, &key, &etag, size, index)?;
+            let base = index * BLOCK_SIZE;
+            let from = (offset.max(base) - base) as usize;
+            let to = ((end - base) as usize).min(block.len());
+            data.extend_from_slice(&block[from.min(to)..to]);
+            index += 1;
+        }
+        Ok(data)
+    }
+
+    /// The staged copy of `ino`, made on first use from the object (or empty when truncating).
+    fn stage(&self, ino: u64, truncate: bool) -> Result<PathBuf, FsError> {
+        let (path, key) = {
+            let mut inodes = self.inodes.lock().unwrap();
+            let node = inodes.nodes.get_mut(&ino).ok_or(FsError::NotFound)?;
+            if node.dir {
+                return Err(FsError::IsDir);
+            }
+            if let Some(staged) = node.staged.clone() {
+                if truncate {
+                    File::create(&staged)?;
+                    node.generation += 1;
+                }
+                return Ok(staged);
+            }
+            (self.staging.join(ino.to_string()), self.key(&node.path, false))
+        };
+
+        if truncate {
+            File::create(&path)?;
+        } else {
+            let client = self.client(false)?;
+            let saved: Result<u64, AppError> = tauri::async_runtime::block_on(async {
+                let resp = client.get_object().bucket(&self.info.bucket).key(&key).send().await?;
+                Ok(s3::save_body(&self.app, resp.body, None, &path).await?)
+            });
+            saved?;
+        }
+
+        let mut inodes = self.inodes.lock().unwrap();
+        let node = inodes.nodes.get_mut(&ino).ok_or(FsError::NotFound)?;
+        node.staged = Some(path.clone());
+        node.generation += 1;
+        Ok(path)
+    }
+
+    fn open_handle(&self, ino: u64, write: bool, dirty: bool) -> u64 {
+        let fh = self.next_handle.fetch_add(1, Ordering::Relaxed) + 1;
+        self.handles.lock().unwrap().insert(fh, Handle { ino, write, dirty });
+        fh
+    }
+
+    /// Uploads the staged copy of `ino` in the background. The copy is dropped afterwards
+    /// unless it changed or is open for writing again.
+    fn upload(&self, ino: u64) {
+        let (key, path, generation) = {
+            let inodes = self.inodes.lock().unwrap();
+            let Some(node) = inodes.nodes.get(&ino) else { return };
+            let Some(path) = node.staged.clone() else { return };
+            (self.key(&node.path, false), path, node.generation)
+        };
+        let app = self.app.clone();
+        let info = self.info.clone();
+        let inodes = self.inodes.clone();
+        let uploads = self.uploads.clone();
+
+        tauri::async_runtime::spawn(async move {
+            let _turn = uploads.lock().await;
+            let job = jobs::start("mount_upload", Some(&info.bucket), json!({ "key": key, "mount_id": info.id }));
+            let state = app.state::<AppState>();
+            let result: Result<Option<String>, AppError> = async {
+                let client = acquire_write_client(&state, info.connection_id.as_deref())?;
+                Ok(sync::upload(&client, &info.bucket, &key, &path).await?)
+            }
+            .await;
+            job.finish(&app, &result, |_| format!("Uploaded {}", key));
+
+            if let Ok(etag) = &result {
+                let mut inodes = inodes.lock().unwrap();
+                if let Some(node) = inodes.nodes.get_mut(&ino).filter(|n| n.generation == generation && n.writers == 0) {
+                    node.size = std::fs::metadata(&path).map(|m| m.len()).unwrap_or(node.size);
+                    node.mtime = SystemTime::now();
+                    node.etag = etag.clone();
+                    node.staged = None;
+                    let _ = std::fs::remove_file(&path);
+                }
+            }
+            let _ = app.emit("mount://uploaded", &MountUpload { mount_id: info.id.clone(), key, error: result.err().map(|e| e.message) });
+        });
+    }
+
+    pub fn writable(&self) -> Result<(), FsError> {
+        if self.info.read_only { Err(FsError::ReadOnly) } else { Ok(()) }
+    }
+
+    fn remove_child(&self, parent: u64, name: &str) {
+        let mut inodes = self.inodes.lock().unwrap();
+        if let Some(parent) = inodes.nodes.get_mut(&parent) {
+            parent.children.remove(name);
+        }
+    }
+
+    fn delete_key(&self, key: &str) -> Result<(), FsError> {
+        let client = self.client(true)?;
+        tauri::async_runtime::block_on(client.delete_object().bucket(&self.info.bucket).key(key).send())
+            .map_err(AppError::from)?;
+        Ok(())
+    }
+
+    /// Opens `ino` and returns the handle. Opening for writing stages a copy first, emptied
+    /// when `truncate` is set.
+    pub fn open(&self, ino: u64, write: bool, truncate: bool) -> Result<u64, FsError> {
+        if !write {
+            return Ok(self.open_handle(ino, false, false));
+        }
+        self.writable()?;
+        self.stage(ino, truncate)?;
+        if let Some(node) = self.inodes.lock().unwrap().nodes.get_mut(&ino) {
+            node.writers += 1;
+        }
+        Ok(self.open_handle(ino, true, truncate))
+    }
+
+    /// Creates an empty file in `parent` and opens it for writing.
+    pub fn create(&self, parent: u64, name: &str) -> Result<(u64, u64), FsError> {
+        self.writable()?;
+        self.refresh(parent)?;
+        let ino = self.inodes.lock().unwrap().child(parent, name, false, 0, SystemTime::now(), None);
+        self.stage(ino, true)?;
+        if let Some(node) = self.inodes.lock().unwrap().nodes.get_mut(&ino) {
+            node.writers += 1;
+        }
+        // Dirty from the start, so an empty file still gets uploaded
+        Ok((ino, self.open_handle(ino, true, true)))
+    }
+
+    /// Closes a handle, uploading what was written through it.
+    pub fn release(&self, fh: u64) {
+        let handle = self.handles.lock().unwrap().remove(&fh);
+        if let Some(handle) = handle.filter(|h| h.write) {
+            if let Some(node) = self.inodes.lock().unwrap().nodes.get_mut(&handle.ino) {
+                node.writers = node.writers.saturating_sub(1);
+            }
+            if handle.dirty {
+                self.upload(handle.ino);
+            }
+        }
+    }
+
+    pub fn read(&self, ino: u64, offset: u64, len: u64) -> Result<Vec<u8>, FsError> {
+        let staged = self.inodes.lock().unwrap().nodes.get(&ino).and_then(|n| n.staged.clone());
+        let Some(path) = staged else { return self.read_remote(ino, offset, len) };
+        let file = File::open(path)?;
+        let available = file.metadata()?.len().saturating_sub(offset);
+        let mut data = vec![0u8; available.min(len) as usize];
+        read_at(&file, &mut data, offset)?;
+        Ok(data)
+    }
+
+    pub fn write(&self, ino: u64, fh: u64, offset: u64, data: &[u8]) -> Result<(), FsError> {
+        let path = {
+            let mut inodes = self.inodes.lock().unwrap();
+            let node = inodes.nodes.get_mut(&ino).ok_or(FsError::NotFound)?;
+            node.generation += 1;
+            node.staged.clone().ok_or(FsError::BadHandle)?
+        };
+        write_at(&OpenOptions::new().write(true).open(path)?, data, offset)?;
+        match self.handles.lock().unwrap().get_mut(&fh) {
+            Some(handle) if handle.write => handle.dirty = true,
+            _ => return Err(FsError::BadHandle),
+        }
+        Ok(())
+    }
+
+    /// Changes the size of `ino`. The change goes up when `fh` closes, or right away when it
+    /// isn't a handle open for writing.
+    pub fn resize(&self, ino: u64, size: u64, fh: Option<u64>) -> Result<(), FsError> {
+        self.writable()?;
+        let path = self.stage(ino, size == 0)?;
+        OpenOptions::new().write(true).open(&path)?.set_len(size)?;
+        if let Some(node) = self.inodes.lock().unwrap().nodes.get_mut(&ino) {
+            node.generation += 1;
+        }
+        let marked = fh.and_then(|fh| {
+            let mut handles = self.handles.lock().unwrap();
+            handles.get_mut(&fh).filter(|h| h.write).map(|h| h.dirty = true)
+        });
+        if marked.is_none() {
+            self.upload(ino);
+        }
+        Ok(())
+    }
+
+    /// Creates a folder in `parent` by putting its marker object.
+    pub fn mkdir(&self, parent: u64, name: &str) -> Result<u64, FsError> {
+        self.writable()?;
+        self.refresh(parent)?;
+        let path = {
+            let inodes = self.inodes.lock().unwrap();
+            let parent = inodes.nodes.get(&parent).ok_or(FsError::NotFound)?;
+            if parent.children.contains_key(name) {
+                return Err(FsError::Exists);
+            }
+            join(&parent.path, name)
+        };
+        let marker = self.key(&path, true);
+        let client = self.client(true)?;
+        tauri::async_runtime::block_on(client.put_object().bucket(&self.info.bucket).key(&marker).send())
+            .map_err(AppError::from)?;
+
+        let mut inodes = self.inodes.lock().unwrap();
+        let ino = inodes.child(parent, name, true, 0, SystemTime::now(), None);
+        if let Some(node) = inodes.nodes.get_mut(&ino) {
+            node.listed_at = Some(Instant::now());
+        }
+        Ok(ino)
+    }
+
+    pub fn unlink(&self, parent: u64, name: &str) -> Result<(), FsError> {
+        self.writable()?;
+        let ino = self.find(parent, name)?;
+        let (key, uploaded, staged) = {
+            let mut inodes = self.inodes.lock().unwrap();
+            let node = inodes.nodes.get_mut(&ino).ok_or(FsError::NotFound)?;
+            if node.dir {
+                return Err(FsError::IsDir);
+            }
+            // A pending upload then finds nothing staged and leaves the object alone
+            node.generation += 1;
+            (self.key(&node.path, false), node.etag.is_some(), node.staged.take())
+        };
+        if let Some(staged) = staged {
+            let _ = std::fs::remove_file(staged);
+        }
+        if uploaded {
+            self.delete_key(&key)?;
+        }
+        self.remove_child(parent, name);
+        Ok(())
+    }
+
+    /// Whether `ino` is a folder with nothing in it.
+    pub fn is_empty_dir(&self, ino: u64) -> Result<bool, FsError> {
+        self.refresh(ino)?;
+        let inodes = self.inodes.lock().unwrap();
+        Ok(inodes.nodes.get(&ino).ok_or(FsError::NotFound)?.children.is_empty())
+    }
+
+    pub fn rmdir(&self, parent: u64, name: &str) -> Result<(), FsError> {
+        self.writable()?;
+        let ino = self.find(parent, name)?;
+        if !self.is_empty_dir(ino)? {
+            return Err(FsError::NotEmpty);
+        }
+        let key = {
+            let inodes = self.inodes.lock().unwrap();
+            self.key(&inodes.nodes.get(&ino).ok_or(FsError::NotFound)?.path, true)
+        };
+        self.delete_key(&key)?;
+        self.remove_child(parent, name);
+        Ok(())
+    }
+
+    pub fn rename(&self, parent: u64, name: &str, new_parent: u64, new_name: &str) -> Result<(), FsError> {
+        self.writable()?;
+        let ino = self.find(parent, name)?;
+        self.refresh(new_parent)?;
+        let (key, size, new_path) = {
+            let inodes = self.inodes.lock().unwrap();
+            let node = inodes.nodes.get(&ino).ok_or(FsError::NotFound)?;
+            let new_parent = inodes.nodes.get(&new_parent).ok_or(FsError::NotFound)?;
+            // Folders would need every object under them copied; file managers fall back to
+            // copying file by file.
+            if node.dir || node.size > MAX_COPY_SIZE {
+                return Err(FsError::CrossDevice);
+            }
+            if node.staged.is_some() {
+                return Err(FsError::Busy);
+            }
+            if new_parent.children.get(new_name).and_then(|c| inodes.nodes.get(c)).is_some_and(|c| c.dir) {
+                return Err(FsError::IsDir);
+            }
+            (self.key(&node.path, false), node.size, join(&new_parent.path, new_name))
+        };
+
+        let new_key = self.key(&new_path, false);
+        let client = self.client(true)?;
+        let copied: Result<(), AppError> = tauri::async_runtime::block_on(async {
+            client.copy_object()
+                .bucket(&self.info.bucket)
+                .key(&new_key)
+                .copy_source(format!("{}/{}", self.info.bucket, encode(&key)))
+                .send()
+                .await?;
+            client.delete_object().bucket(&self.info.bucket).key(&key).send().await?;
+            Ok(())
+        });
+        copied?;
+
+        let mut inodes = self.inodes.lock().unwrap();
+        if let Some(old_parent) = inodes.nodes.get_mut(&parent) {
+            old_parent.children.remove(name);
+        }
+        if let Some(new_parent) = inodes.nodes.get_mut(&new_parent) {
+            new_parent.children.insert(new_name.to_string(), ino);
+        }
+        if let Some(node) = inodes.nodes.get_mut(&ino) {
+            node.path = new_path;
+            node.parent = new_parent;
+            node.size = size;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,284 @@
+use fuser::{
+    BsdFileFlags, Config, Errno, FileAttr, FileHandle, FileType, Filesystem, FopenFlags, Generation, INodeNo, LockOwner, MountOption,
+    OpenAccMode, OpenFlags, RenameFlags, ReplyAttr, ReplyCreate, ReplyData, ReplyDirectory, ReplyEmpty, ReplyEntry,
+    ReplyOpen, ReplyWrite, Request, TimeOrNow, WriteFlags,
+};
+use std::ffi::OsStr;
+use std::time::{Duration, SystemTime};
+use tauri::AppHandle;
+
+use crate::error::{AppError, ErrorKind};
+use crate::mount::MountInfo;
+use crate::mount_fs::{FsError, MountFs, Node, BLOCK_SIZE};
+
+// FUSE side of `mount`, on Linux and macOS. Everything but the FUSE plumbing is in mount_fs.
+const ATTR_TTL: Duration = Duration::from_secs(1);
+
+fn errno(e: FsError) -> Errno {
+    match e {
+        FsError::NotFound => Errno::ENOENT,
+        FsError::NotDir => Errno::ENOTDIR,
+        FsError::IsDir => Errno::EISDIR,
+        FsError::Exists => Errno::EEXIST,
+        FsError::NotEmpty => Errno::ENOTEMPTY,
+        FsError::Busy => Errno::EBUSY,
+        // Makes `mv` and file managers fall back to copying file by file
+        FsError::CrossDevice => Errno::EXDEV,
+        FsError::InvalidName => Errno::EINVAL,
+        FsError::BadHandle => Errno::EBADF,
+        FsError::ReadOnly => Errno::EROFS,
+        FsError::AccessDenied => Errno::EACCES,
+        FsError::Stale => Errno::ESTALE,
+        FsError::Io => Errno::EIO,
+    }
+}
+
+fn name_str(name: &OsStr) -> Result<&str, FsError> {
+    name.to_str().filter(|n| !n.contains('/')).ok_or(FsError::InvalidName)
+}
+
+struct FuseFs {
+    fs: MountFs,
+    uid: u32,
+    gid: u32,
+}
+
+impl FuseFs {
+    fn attr(&self, ino: u64, node: &Node) -> FileAttr {
+        let size = node.size();
+        let writable = if self.fs.info.read_only { 0 } else { 0o200 };
+        FileAttr {
+            ino: INodeNo(ino),
+            size,
+            blocks: size.div_ceil(512),
+            atime: node.mtime,
+            mtime: node.mtime,
+            ctime: node.mtime,
+            crtime: node.mtime,
+            kind: if node.dir { FileType::Directory } else { FileType::RegularFile },
+            perm: if node.dir { 0o555 | writable } else { 0o444 | writable },
+            nlink: if node.dir { 2 } else { 1 },
+            uid: self.uid,
+            gid: self.gid,
+            rdev: 0,
+            blksize: BLOCK_SIZE as u32,
+            flags: 0,
+        }
+    }
+
+    fn attr_of(&self, ino: u64) -> Result<FileAttr, FsError> {
+        let inodes = self.fs.inodes.lock().unwrap();
+        inodes.nodes.get(&ino).map(|node| self.attr(ino, node)).ok_or(FsError::NotFound)
+    }
+}
+
+impl Filesystem for FuseFs {
+    fn lookup(&self, _req: &Request, parent: INodeNo, name: &OsStr, reply: ReplyEntry) {
+        match name_str(name).and_then(|name| self.fs.find(parent.0, name)).and_then(|ino| self.attr_of(ino)) {
+            Ok(attr) => reply.entry(&ATTR_TTL, &attr, Generation(0)),
+            Err(e) => reply.error(errno(e)),
+        }
+    }
+
+    fn getattr(&self, _req: &Request, ino: INodeNo, _fh: Option<FileHandle>, reply: ReplyAttr) {
+        match self.attr_of(ino.0) {
+            Ok(attr) => reply.attr(&ATTR_TTL, &attr),
+            Err(e) => reply.error(errno(e)),
+        }
+    }
+
+    // Only size changes mean anything here; modes, owners and times are reported as they are
+    fn setattr(
+        &self,
+        _req: &Request,
+        ino: INodeNo,
+        _mode: Option<u32>,
+        _uid: Option<u32>,
+        _gid: Option<u32>,
+        size: Option<u64>,
+        _atime: Option<TimeOrNow>,
+        _mtime: Option<TimeOrNow>,
+        _ctime: Option<SystemTime>,
+        fh: Option<FileHandle>,
+        _crtime: Option<SystemTime>,
+        _chgtime: Option<SystemTime>,
+        _bkuptime: Option<SystemTime>,
+        _flags: Option<BsdFileFlags>,
+        reply: ReplyAttr,
+    ) {
+        let resized = match size {
+            Some(size) => self.fs.resize(ino.0, size, fh.map(|fh| fh.0)),
+            None => Ok(()),
+        };
+        match resized.and_then(|_| self.attr_of(ino.0)) {
+            Ok(attr) => reply.attr(&ATTR_TTL, &attr),
+            Err(e) => reply.error(errno(e)),
+        }
+    }
+
+    fn mkdir(&self, _req: &Request, parent: INodeNo, name: &OsStr, _mode: u32, _umask: u32, reply: ReplyEntry) {
+        match name_str(name).and_then(|name| self.fs.mkdir(parent.0, name)).and_then(|ino| self.attr_of(ino)) {
+            Ok(attr) => reply.entry(&ATTR_TTL, &attr, Generation(0)),
+            Err(e) => reply.error(errno(e)),
+        }
+    }
+
+    fn unlink(&self, _req: &Request, parent: INodeNo, name: &OsStr, reply: ReplyEmpty) {
+        match name_str(name).and_then(|name| self.fs.unlink(parent.0, name)) {
+            Ok(()) => reply.ok(),
+            Err(e) => reply.error(errno(e)),
+        }
+    }
+
+    fn rmdir(&self, _req: &Request, parent: INodeNo, name: &OsStr, reply: ReplyEmpty) {
+        match name_str(name).and_then(|name| self.fs.rmdir(parent.0, name)) {
+            Ok(()) => reply.ok(),
+            Err(e) => reply.error(errno(e)),
+        }
+    }
+
+    fn rename(
+        &self,
+        _req: &Request,
+        parent: INodeNo,
+        name: &OsStr,
+        newparent: INodeNo,
+        newname: &OsStr,
+        _flags: RenameFlags,
+        reply: ReplyEmpty,
+    ) {
+        let renamed = name_str(name)
+            .and_then(|name| Ok((name, name_str(newname)?)))
+            .and_then(|(name, newname)| self.fs.rename(parent.0, name, newparent.0, newname));
+        match renamed {
+            Ok(()) => reply.ok(),
+            Err(e) => reply.error(errno(e)),
+        }
+    }
+
+    fn open(&self, _req: &Request, ino: INodeNo, flags: OpenFlags, reply: ReplyOpen) {
+        let write = !matches!(flags.acc_mode(), OpenAccMode::O_RDONLY);
+        match self.fs.open(ino.0, write, flags.0 & libc::O_TRUNC != 0) {
+            Ok(fh) => reply.opened(FileHandle(fh), FopenFlags::empty()),
+            Err(e) => reply.error(errno(e)),
+        }
+    }
+
+    fn read(
+        &self,
+        _req: &Request,
+        ino: INodeNo,
+        _fh: FileHandle,
+        offset: u64,
+        size: u32,
+        _flags: OpenFlags,
+        _lock_owner: Option<LockOwner>,
+        reply: ReplyData,
+    ) {
+        match self.fs.read(ino.0, offset, size as u64) {
+            Ok(data) => reply.data(&data),
+            Err(e) => reply.error(errno(e)),
+        }
+    }
+
+    fn write(
+        &self,
+        _req: &Request,
+        ino: INodeNo,
+        fh: FileHandle,
+        offset: u64,
+        data: &[u8],
+        _write_flags: WriteFlags,
+        _flags: OpenFlags,
+        _lock_owner: Option<LockOwner>,
+        reply: ReplyWrite,
+    ) {
+        match self.fs.write(ino.0, fh.0, offset, data) {
+            Ok(()) => reply.written(data.len() as u32),
+            Err(e) => reply.error(errno(e)),
+        }
+    }
+
+    fn flush(&self, _req: &Request, _ino: INodeNo, _fh: FileHandle, _lock_owner: LockOwner, reply: ReplyEmpty) {
+        reply.ok();
+    }
+
+    fn release(
+        &self,
+        _req: &Request,
+        _ino: INodeNo,
+        fh: FileHandle,
+        _flags: OpenFlags,
+        _lock_owner: Option<LockOwner>,
+        _flush: bool,
+        reply: ReplyEmpty,
+    ) {
+        self.fs.release(fh.0);
+        reply.ok();
+    }
+
+    // Writes are uploaded on close
+    fn fsync(&self, _req: &Request, _ino: INodeNo, _fh: FileHandle, _datasync: bool, reply: ReplyEmpty) {
+        reply.ok();
+    }
+
+    fn readdir(&self, _req: &Request, ino: INodeNo, _fh: FileHandle, offset: u64, mut reply: ReplyDirectory) {
+        let children = match self.fs.entries(ino.0) {
+            Ok(children) => children,
+            Err(e) => return reply.error(errno(e)),
+        };
+        let Some(parent) = self.fs.inodes.lock().unwrap().nodes.get(&ino.0).map(|n| n.parent) else {
+            return reply.error(Errno::ENOENT);
+        };
+        let mut entries = vec![(ino.0, FileType::Directory, ".".to_string()), (parent, FileType::Directory, "..".to_string())];
+        entries.extend(children.into_iter().map(|(name, child, dir)| {
+            (child, if dir { FileType::Directory } else { FileType::RegularFile }, name)
+        }));
+        for (index, (child, kind, name)) in entries.into_iter().enumerate().skip(offset as usize) {
+            if reply.add(INodeNo(child), index as u64 + 1, kind, name) {
+                break;
+            }
+        }
+        reply.ok();
+    }
+
+    fn create(
+        &self,
+        _req: &Request,
+        parent: INodeNo,
+        name: &OsStr,
+        _mode: u32,
+        _umask: u32,
+        _flags: i32,
+        reply: ReplyCreate,
+    ) {
+        let created = name_str(name)
+            .and_then(|name| self.fs.create(parent.0, name))
+            .and_then(|(ino, fh)| Ok((self.attr_of(ino)?, fh)));
+        match created {
+            Ok((attr, fh)) => reply.created(&ATTR_TTL, &attr, Generation(0), FileHandle(fh), FopenFlags::empty()),
+            Err(e) => reply.error(errno(e)),
+        }
+    }
+}
+
+/// Mounts `info` in the background. The mount lasts until the returned session is dropped.
+pub fn mount(app: &AppHandle, info: &MountInfo) -> Result<fuser::BackgroundSession, AppError> {
+    let fs = FuseFs {
+        fs: MountFs::new(app, info)?,
+        // SAFETY: getuid and getgid have no preconditions and cannot fail
+        uid: unsafe { libc::getuid() },
+        gid: unsafe { libc::getgid() },
+    };
+
+    let mut config = Config::default();
+    config.mount_options = vec![
+        MountOption::FSName(format!("r2drive:{}", info.bucket)),
+        MountOption::Subtype("r2drive".to_string()),
+        MountOption::NoDev,
+        MountOption::NoSuid,
+        if info.read_only { MountOption::RO } else { MountOption::RW },
+    ];
+    fuser::spawn_mount(fs, &info.mountpoint, &config)
+        .map_err(|e| AppError::new(ErrorKind::Internal, format!("Cannot mount at {}: {}", info.mountpoint, e)))
+}
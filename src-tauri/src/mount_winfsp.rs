@@ -0,0 +1,425 @@
+use std::ffi::c_void;
+use std::sync::OnceLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::AppHandle;
+use windows::core::w;
+use windows::Win32::Foundation::{
+    LocalFree, HLOCAL, NTSTATUS, STATUS_ACCESS_DENIED, STATUS_BUFFER_OVERFLOW, STATUS_DIRECTORY_NOT_EMPTY, STATUS_END_OF_FILE,
+    STATUS_FILE_INVALID, STATUS_FILE_IS_A_DIRECTORY, STATUS_INVALID_HANDLE, STATUS_MEDIA_WRITE_PROTECTED, STATUS_NOT_A_DIRECTORY,
+    STATUS_NOT_SAME_DEVICE, STATUS_OBJECT_NAME_COLLISION, STATUS_OBJECT_NAME_INVALID, STATUS_OBJECT_NAME_NOT_FOUND,
+    STATUS_SHARING_VIOLATION, STATUS_UNEXPECTED_IO_ERROR,
+};
+use windows::Win32::Security::Authorization::{ConvertStringSecurityDescriptorToSecurityDescriptorW, SDDL_REVISION_1};
+use windows::Win32::Security::PSECURITY_DESCRIPTOR;
+use windows::Win32::Storage::FileSystem::{
+    FILE_APPEND_DATA, FILE_ATTRIBUTE_ARCHIVE, FILE_ATTRIBUTE_DIRECTORY, FILE_ATTRIBUTE_READONLY, FILE_WRITE_DATA,
+};
+use winfsp::constants::FspCleanupFlags;
+use winfsp::filesystem::{
+    DirBuffer, DirInfo, DirMarker, FileInfo, FileSecurity, FileSystemContext, OpenFileInfo, VolumeInfo, WideNameInfo,
+};
+use winfsp::host::{FileSystemHost, VolumeParams};
+use winfsp::{FspError, U16CStr};
+
+use crate::error::{AppError, ErrorKind};
+use crate::mount::MountInfo;
+use crate::mount_fs::{FsError, MountFs, Node, BLOCK_SIZE, ROOT};
+
+// WinFsp side of `mount`, on Windows. Everything but the WinFsp plumbing is in mount_fs. WinFsp
+// names files by path, so each request walks the path from the root through the same inode
+// table the FUSE side uses.
+/// `FILE_DIRECTORY_FILE` create option: the caller asked for a folder.
+const FILE_DIRECTORY_FILE: u32 = 0x1;
+/// Seconds between 1601-01-01, where Windows file times start, and the Unix epoch.
+const FILETIME_EPOCH: u64 = 11_644_473_600;
+/// Size reported for the volume; buckets have no fixed capacity.
+const VOLUME_SIZE: u64 = 1 << 50;
+
+fn status(e: FsError) -> FspError {
+    let status: NTSTATUS = match e {
+        FsError::NotFound => STATUS_OBJECT_NAME_NOT_FOUND,
+        FsError::NotDir => STATUS_NOT_A_DIRECTORY,
+        FsError::IsDir => STATUS_FILE_IS_A_DIRECTORY,
+        FsError::Exists => STATUS_OBJECT_NAME_COLLISION,
+        FsError::NotEmpty => STATUS_DIRECTORY_NOT_EMPTY,
+        FsError::Busy => STATUS_SHARING_VIOLATION,
+        // Makes Explorer and `move` fall back to copying file by file
+        FsError::CrossDevice => STATUS_NOT_SAME_DEVICE,
+        FsError::InvalidName => STATUS_OBJECT_NAME_INVALID,
+        FsError::BadHandle => STATUS_INVALID_HANDLE,
+        FsError::ReadOnly => STATUS_MEDIA_WRITE_PROTECTED,
+        FsError::AccessDenied => STATUS_ACCESS_DENIED,
+        FsError::Stale => STATUS_FILE_INVALID,
+        FsError::Io => STATUS_UNEXPECTED_IO_ERROR,
+    };
+    status.into()
+}
+
+/// Components of a path as WinFsp sends it, `\folder\file`.
+fn components(path: &U16CStr) -> Result<Vec<String>, FsError> {
+    let path = path.to_string().map_err(|_| FsError::InvalidName)?;
+    Ok(path.split('\\').filter(|c| !c.is_empty()).map(str::to_string).collect())
+}
+
+fn filetime(time: SystemTime) -> u64 {
+    let since = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    (since.as_secs() + FILETIME_EPOCH) * 10_000_000 + since.subsec_nanos() as u64 / 100
+}
+
+/// The one security descriptor every file and folder gets: full access for everyone, since
+/// what a mount may do is decided by the connection, not by Windows accounts.
+fn security() -> &'static [u8] {
+    static SECURITY: OnceLock<Vec<u8>> = OnceLock::new();
+    SECURITY.get_or_init(|| {
+        let mut descriptor = PSECURITY_DESCRIPTOR::default();
+        let mut len = 0u32;
+        // SAFETY: the SDDL string is a valid constant, and the descriptor it returns is copied
+        // out before being freed
+        unsafe {
+            let sddl = w!("O:BAG:BAD:P(A;;FA;;;SY)(A;;FA;;;BA)(A;;FA;;;WD)");
+            if ConvertStringSecurityDescriptorToSecurityDescriptorW(sddl, SDDL_REVISION_1, &mut descriptor, Some(&mut len)).is_err() {
+                return Vec::new();
+            }
+            let bytes = std::slice::from_raw_parts(descriptor.0 as *const u8, len as usize).to_vec();
+            LocalFree(Some(HLOCAL(descriptor.0)));
+            bytes
+        }
+    })
+}
+
+/// Copies the security descriptor into `buffer` when there is one, returning its length.
+fn copy_security(buffer: Option<&mut [c_void]>) -> winfsp::Result<u64> {
+    let security = security();
+    if let Some(buffer) = buffer {
+        if buffer.len() < security.len() {
+            return Err(STATUS_BUFFER_OVERFLOW.into());
+        }
+        // SAFETY: the buffer holds at least `security.len()` bytes
+        unsafe { std::ptr::copy_nonoverlapping(security.as_ptr(), buffer.as_mut_ptr().cast::<u8>(), security.len()) };
+    }
+    Ok(security.len() as u64)
+}
+
+fn attributes(node: &Node, read_only: bool) -> u32 {
+    match (node.dir, read_only) {
+        (true, _) => FILE_ATTRIBUTE_DIRECTORY.0,
+        (false, true) => FILE_ATTRIBUTE_ARCHIVE.0 | FILE_ATTRIBUTE_READONLY.0,
+        (false, false) => FILE_ATTRIBUTE_ARCHIVE.0,
+    }
+}
+
+fn fill(info: &mut FileInfo, ino: u64, node: &Node, read_only: bool) {
+    let size = if node.dir { 0 } else { node.size() };
+    let time = filetime(node.mtime);
+    info.file_attributes = attributes(node, read_only);
+    info.reparse_tag = 0;
+    info.file_size = size;
+    info.allocation_size = size.div_ceil(BLOCK_SIZE) * BLOCK_SIZE;
+    info.creation_time = time;
+    info.last_access_time = time;
+    info.last_write_time = time;
+    info.change_time = time;
+    info.index_number = ino;
+    info.hard_links = 0;
+    info.ea_size = 0;
+}
+
+struct WinFile {
+    ino: u64,
+    /// Handle from `MountFs::open`; folders have none.
+    fh: Option<u64>,
+    entries: DirBuffer,
+}
+
+struct WinFs {
+    fs: MountFs,
+}
+
+impl WinFs {
+    fn resolve(&self, path: &U16CStr) -> Result<u64, FsError> {
+        components(path)?.iter().try_fold(ROOT, |ino, name| self.fs.find(ino, name))
+    }
+
+    /// Folder holding `path` and the last component's name.
+    fn resolve_parent(&self, path: &U16CStr) -> Result<(u64, String), FsError> {
+        let mut components = components(path)?;
+        let name = components.pop().ok_or(FsError::InvalidName)?;
+        let parent = components.iter().try_fold(ROOT, |ino, name| self.fs.find(ino, name))?;
+        Ok((parent, name))
+    }
+
+    fn is_dir(&self, ino: u64) -> Result<bool, FsError> {
+        let inodes = self.fs.inodes.lock().unwrap();
+        Ok(inodes.nodes.get(&ino).ok_or(FsError::NotFound)?.dir)
+    }
+
+    fn file_info(&self, ino: u64, info: &mut FileInfo) -> Result<(), FsError> {
+        let inodes = self.fs.inodes.lock().unwrap();
+        fill(info, ino, inodes.nodes.get(&ino).ok_or(FsError::NotFound)?, self.fs.info.read_only);
+        Ok(())
+    }
+
+    /// Lists a folder into its handle's buffer, with `.` and `..` for anything but the root.
+    fn list(&self, file: &WinFile, marker: &DirMarker) -> Result<(), FsError> {
+        let Ok(buffer) = file.entries.acquire(marker.is_none(), None) else { return Ok(()) };
+        let children = self.fs.entries(file.ino)?;
+        let read_only = self.fs.info.read_only;
+        let inodes = self.fs.inodes.lock().unwrap();
+        let node = inodes.nodes.get(&file.ino).ok_or(FsError::NotFound)?;
+
+        let mut entries = Vec::new();
+        if file.ino != ROOT {
+            entries.push((".".to_string(), file.ino));
+            entries.push(("..".to_string(), node.parent));
+        }
+        entries.extend(children.into_iter().map(|(name, child, _)| (name, child)));
+
+        let mut info: DirInfo = DirInfo::new();
+        for (name, ino) in entries {
+            let Some(node) = inodes.nodes.get(&ino) else { continue };
+            info.reset();
+            // Names longer than Windows allows can't be listed
+            if info.set_name(&name).is_err() {
+                continue;
+            }
+            fill(info.file_info_mut(), ino, node, read_only);
+            if buffer.write(&mut info).is_err() {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FileSystemContext for WinFs {
+    type FileContext = WinFile;
+
+    fn get_security_by_name(
+        &self,
+        file_name: &U16CStr,
+        security_descriptor: Option<&mut [c_void]>,
+        _reparse_point_resolver: impl FnOnce(&U16CStr) -> Option<FileSecurity>,
+    ) -> winfsp::Result<FileSecurity> {
+        let ino = self.resolve(file_name).map_err(status)?;
+        let attributes = {
+            let inodes = self.fs.inodes.lock().unwrap();
+            attributes(inodes.nodes.get(&ino).ok_or_else(|| status(FsError::NotFound))?, self.fs.info.read_only)
+        };
+        Ok(FileSecurity { reparse: false, sz_security_descriptor: copy_security(security_descriptor)?, attributes })
+    }
+
+    fn open(&self, file_name: &U16CStr, _create_options: u32, granted_access: u32, file_info: &mut OpenFileInfo) -> winfsp::Result<WinFile> {
+        let mut opened = || -> Result<WinFile, FsError> {
+            let ino = self.resolve(file_name)?;
+            let fh = match self.is_dir(ino)? {
+                true => None,
+                false => Some(self.fs.open(ino, granted_access & (FILE_WRITE_DATA.0 | FILE_APPEND_DATA.0) != 0, false)?),
+            };
+            let file = WinFile { ino, fh, entries: DirBuffer::new() };
+            if let Err(e) = self.file_info(ino, file_info.as_mut()) {
+                self.close(file);
+                return Err(e);
+            }
+            Ok(file)
+        };
+        opened().map_err(status)
+    }
+
+    fn close(&self, file: WinFile) {
+        if let Some(fh) = file.fh {
+            self.fs.release(fh);
+        }
+    }
+
+    fn create(
+        &self,
+        file_name: &U16CStr,
+        create_options: u32,
+        _granted_access: u32,
+        _file_attributes: u32,
+        _security_descriptor: Option<&[c_void]>,
+        _allocation_size: u64,
+        _extra_buffer: Option<&[u8]>,
+        _extra_buffer_is_reparse_point: bool,
+        file_info: &mut OpenFileInfo,
+    ) -> winfsp::Result<WinFile> {
+        let mut created = || -> Result<WinFile, FsError> {
+            let (parent, name) = self.resolve_parent(file_name)?;
+            let file = if create_options & FILE_DIRECTORY_FILE != 0 {
+                WinFile { ino: self.fs.mkdir(parent, &name)?, fh: None, entries: DirBuffer::new() }
+            } else {
+                let (ino, fh) = self.fs.create(parent, &name)?;
+                WinFile { ino, fh: Some(fh), entries: DirBuffer::new() }
+            };
+            self.file_info(file.ino, file_info.as_mut())?;
+            Ok(file)
+        };
+        created().map_err(status)
+    }
+
+    // Windows deletes in two steps: `set_delete` checks the file can go, and the last handle's
+    // cleanup removes it. Cleanup can't report errors, so a failed delete just leaves the object.
+    fn cleanup(&self, file: &WinFile, file_name: Option<&U16CStr>, flags: u32) {
+        let Some(file_name) = file_name.filter(|_| FspCleanupFlags::FspCleanupDelete.is_flagged(flags)) else { return };
+        let _ = self.resolve_parent(file_name).and_then(|(parent, name)| match file.fh {
+            Some(_) => self.fs.unlink(parent, &name),
+            None => self.fs.rmdir(parent, &name),
+        });
+    }
+
+    fn set_delete(&self, file: &WinFile, _file_name: &U16CStr, delete_file: bool) -> winfsp::Result<()> {
+        if !delete_file {
+            return Ok(());
+        }
+        self.fs.writable().map_err(status)?;
+        if file.fh.is_none() && !self.fs.is_empty_dir(file.ino).map_err(status)? {
+            return Err(status(FsError::NotEmpty));
+        }
+        Ok(())
+    }
+
+    // Writes are uploaded on close
+    fn flush(&self, file: Option<&WinFile>, file_info: &mut FileInfo) -> winfsp::Result<()> {
+        match file {
+            Some(file) => self.file_info(file.ino, file_info).map_err(status),
+            None => Ok(()),
+        }
+    }
+
+    fn get_file_info(&self, file: &WinFile, file_info: &mut FileInfo) -> winfsp::Result<()> {
+        self.file_info(file.ino, file_info).map_err(status)
+    }
+
+    fn get_security(&self, _file: &WinFile, security_descriptor: Option<&mut [c_void]>) -> winfsp::Result<u64> {
+        copy_security(security_descriptor)
+    }
+
+    fn overwrite(
+        &self,
+        file: &WinFile,
+        _file_attributes: u32,
+        _replace_file_attributes: bool,
+        _allocation_size: u64,
+        _extra_buffer: Option<&[u8]>,
+        file_info: &mut FileInfo,
+    ) -> winfsp::Result<()> {
+        self.fs.resize(file.ino, 0, file.fh).and_then(|_| self.file_info(file.ino, file_info)).map_err(status)
+    }
+
+    fn read_directory(&self, file: &WinFile, _pattern: Option<&U16CStr>, marker: DirMarker, buffer: &mut [u8]) -> winfsp::Result<u32> {
+        self.list(file, &marker).map_err(status)?;
+        Ok(file.entries.read(marker, buffer))
+    }
+
+    fn rename(&self, _file: &WinFile, file_name: &U16CStr, new_file_name: &U16CStr, replace_if_exists: bool) -> winfsp::Result<()> {
+        let renamed = || -> Result<(), FsError> {
+            let (parent, name) = self.resolve_parent(file_name)?;
+            let (new_parent, new_name) = self.resolve_parent(new_file_name)?;
+            if !replace_if_exists && self.fs.find(new_parent, &new_name).is_ok() {
+                return Err(FsError::Exists);
+            }
+            self.fs.rename(parent, &name, new_parent, &new_name)
+        };
+        renamed().map_err(status)
+    }
+
+    // Attributes and times are reported as they are
+    fn set_basic_info(
+        &self,
+        file: &WinFile,
+        _file_attributes: u32,
+        _creation_time: u64,
+        _last_access_time: u64,
+        _last_write_time: u64,
+        _last_change_time: u64,
+        file_info: &mut FileInfo,
+    ) -> winfsp::Result<()> {
+        self.file_info(file.ino, file_info).map_err(status)
+    }
+
+    fn set_file_size(&self, file: &WinFile, new_size: u64, set_allocation_size: bool, file_info: &mut FileInfo) -> winfsp::Result<()> {
+        // Allocation sizes mean nothing to an object store
+        if !set_allocation_size {
+            self.fs.resize(file.ino, new_size, file.fh).map_err(status)?;
+        }
+        self.file_info(file.ino, file_info).map_err(status)
+    }
+
+    fn read(&self, file: &WinFile, buffer: &mut [u8], offset: u64) -> winfsp::Result<u32> {
+        let data = self.fs.read(file.ino, offset, buffer.len() as u64).map_err(status)?;
+        if data.is_empty() && !buffer.is_empty() {
+            return Err(STATUS_END_OF_FILE.into());
+        }
+        buffer[..data.len()].copy_from_slice(&data);
+        Ok(data.len() as u32)
+    }
+
+    fn write(
+        &self,
+        file: &WinFile,
+        buffer: &[u8],
+        offset: u64,
+        write_to_eof: bool,
+        constrained_io: bool,
+        file_info: &mut FileInfo,
+    ) -> winfsp::Result<u32> {
+        let mut written = || -> Result<u32, FsError> {
+            let fh = file.fh.ok_or(FsError::BadHandle)?;
+            let size = {
+                let inodes = self.fs.inodes.lock().unwrap();
+                inodes.nodes.get(&file.ino).ok_or(FsError::NotFound)?.size()
+            };
+            let offset = if write_to_eof { size } else { offset };
+            // Paging writes never grow the file
+            let data = match constrained_io {
+                true => &buffer[..buffer.len().min(size.saturating_sub(offset) as usize)],
+                false => buffer,
+            };
+            if !data.is_empty() {
+                self.fs.write(file.ino, fh, offset, data)?;
+            }
+            self.file_info(file.ino, file_info)?;
+            Ok(data.len() as u32)
+        };
+        written().map_err(status)
+    }
+
+    fn get_volume_info(&self, volume_info: &mut VolumeInfo) -> winfsp::Result<()> {
+        volume_info.total_size = VOLUME_SIZE;
+        volume_info.free_size = VOLUME_SIZE;
+        volume_info.set_volume_label(&self.fs.info.bucket);
+        Ok(())
+    }
+}
+
+/// A running WinFsp file system. It unmounts when dropped.
+pub struct Session {
+    _host: FileSystemHost<WinFs>,
+}
+
+/// Mounts `info` on its own dispatcher threads. `info.mountpoint` is a drive letter such as `R:`
+/// or a folder that doesn't exist yet.
+pub fn mount(app: &AppHandle, info: &MountInfo) -> Result<Session, AppError> {
+    winfsp::winfsp_init().map_err(|_| AppError::new(ErrorKind::Unsupported, "Mounting buckets needs WinFsp, which isn't installed"))?;
+    let fs = WinFs { fs: MountFs::new(app, info)? };
+
+    let mut params = VolumeParams::new();
+    params
+        .filesystem_name("r2drive")
+        .sector_size(4096)
+        .sectors_per_allocation_unit((BLOCK_SIZE / 4096) as u16)
+        .max_component_length(255)
+        .case_sensitive_search(true)
+        .case_preserved_names(true)
+        .unicode_on_disk(true)
+        .persistent_acls(false)
+        .post_cleanup_when_modified_only(true)
+        .file_info_timeout(1000)
+        .read_only_volume(info.read_only);
+
+    let failed = |e: windows::core::Error| AppError::new(ErrorKind::Internal, format!("Cannot mount at {}: {}", info.mountpoint, e));
+    let mut host: FileSystemHost<WinFs> = FileSystemHost::new(params, fs).map_err(failed)?;
+    host.mount(&info.mountpoint).map_err(failed)?;
+    host.start().map_err(failed)?;
+    Ok(Session { _host: host })
+}
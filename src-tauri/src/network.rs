@@ -0,0 +1,69 @@
+use aws_config::timeout::TimeoutConfig;
+use aws_sdk_s3::config::SharedHttpClient;
+use aws_smithy_runtime_api::client::connector_metadata::ConnectorMetadata;
+use aws_smithy_runtime_api::client::http::{
+    HttpClient, HttpConnector, HttpConnectorFuture, HttpConnectorSettings, SharedHttpConnector,
+};
+use aws_smithy_runtime_api::client::orchestrator::HttpRequest;
+use aws_smithy_runtime_api::client::result::ConnectorError;
+use aws_smithy_runtime_api::client::runtime_components::RuntimeComponents;
+use r2drive_core::network;
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+
+use crate::settings::Settings;
+
+pub use r2drive_core::network::proxy_config;
+
+/// Connect and read timeouts for SDK clients, from the app's settings.
+pub fn timeout_config(settings: &Settings) -> TimeoutConfig {
+    network::timeout_config(&settings.timeouts)
+}
+
+/// HTTP client for SDK clients (S3, STS), routed through the configured proxy.
+pub fn http_client(settings: &Settings) -> SharedHttpClient {
+    network::http_client(&settings.proxy, &settings.timeouts)
+}
+
+/// Wraps an HTTP client so every request it sends, retries included, first takes a permit from
+/// `limiter`. The permit is held until the response headers arrive; bodies stream outside the
+/// cap, so a transfer reading one object while writing another can't starve itself.
+pub fn limited(inner: SharedHttpClient, limiter: Arc<Semaphore>) -> SharedHttpClient {
+    SharedHttpClient::new(Limited { inner, limiter })
+}
+
+#[derive(Debug)]
+struct Limited {
+    inner: SharedHttpClient,
+    limiter: Arc<Semaphore>,
+}
+
+impl HttpClient for Limited {
+    fn http_connector(&self, settings: &HttpConnectorSettings, components: &RuntimeComponents) -> SharedHttpConnector {
+        SharedHttpConnector::new(LimitedConnector {
+            inner: self.inner.http_connector(settings, components),
+            limiter: self.limiter.clone(),
+        })
+    }
+
+    fn connector_metadata(&self) -> Option<ConnectorMetadata> {
+        self.inner.connector_metadata()
+    }
+}
+
+#[derive(Debug)]
+struct LimitedConnector {
+    inner: SharedHttpConnector,
+    limiter: Arc<Semaphore>,
+}
+
+impl HttpConnector for LimitedConnector {
+    fn call(&self, request: HttpRequest) -> HttpConnectorFuture {
+        let inner = self.inner.clone();
+        let limiter = self.limiter.clone();
+        HttpConnectorFuture::new(async move {
+            let _permit = limiter.acquire_owned().await.map_err(|e| ConnectorError::other(e.into(), None))?;
+            inner.call(request).await
+        })
+    }
+}
@@ -0,0 +1,71 @@
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+use tauri_plugin_notification::NotificationExt;
+
+use crate::jobs::{self, JobRecord};
+use crate::s3::AppState;
+
+// Native notifications for finished transfer jobs and for warnings that need the user before
+// something stops working, such as temporary credentials about to lapse or usage going over a
+// limit.
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationLevel {
+    Off,
+    /// Failures and warnings only.
+    #[default]
+    Problems,
+    /// Completed transfers as well, while the window isn't in front.
+    All,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotificationSettings {
+    pub level: NotificationLevel,
+    /// Warn when the Cloudflare account stores more than this many bytes.
+    pub storage_limit: Option<u64>,
+    /// Warn when the Cloudflare account holds more than this many objects.
+    pub object_limit: Option<u64>,
+}
+
+fn level(app: &AppHandle) -> NotificationLevel {
+    app.state::<AppState>().settings.lock().unwrap().notifications.level
+}
+
+fn show(app: &AppHandle, title: &str, body: &str) {
+    // Best effort: a missing notification daemon must never fail the job that triggered it
+    let _ = app.notification().builder().title(title).body(body).show();
+}
+
+fn window_in_front(app: &AppHandle) -> bool {
+    app.get_webview_window("main").is_some_and(|w| w.is_visible().unwrap_or(false) && w.is_focused().unwrap_or(false))
+}
+
+/// "scheduled_sync" -> "Scheduled sync".
+fn job_title(kind: &str) -> String {
+    let words = kind.replace('_', " ");
+    let mut chars = words.chars();
+    chars.next().map(|c| c.to_uppercase().chain(chars).collect()).unwrap_or_default()
+}
+
+/// Notifies about a finished transfer job, as far as the notification level allows.
+pub fn job_finished(app: &AppHandle, job: &JobRecord) {
+    if !jobs::TRANSFER_KINDS.contains(&job.kind.as_str()) {
+        return;
+    }
+    match (level(app), &job.error) {
+        (NotificationLevel::Off, _) => {}
+        (_, Some(error)) => show(app, &format!("{} failed", job_title(&job.kind)), error),
+        (NotificationLevel::All, None) if !window_in_front(app) => show(app, &format!("{} finished", job_title(&job.kind)), &job.summary),
+        _ => {}
+    }
+}
+
+/// Notifies about something the user should act on, unless notifications are off.
+pub fn warning(app: &AppHandle, title: &str, body: &str) {
+    if level(app) != NotificationLevel::Off {
+        show(app, title, body);
+    }
+}
@@ -0,0 +1,30 @@
+use r2drive_core::partial;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+
+// Partial downloads and their journal live in r2drive_core::partial, shared with r2drive-cli;
+// these resolve the journal to the app's data dir.
+
+fn data_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path().app_data_dir().map_err(|e| e.to_string())
+}
+
+/// Records a partial file as in flight and returns its path.
+pub fn begin(app: &AppHandle, target: &Path) -> Result<PathBuf, String> {
+    partial::begin(&data_dir(app)?, target)
+}
+
+/// Drops a partial from the journal once it has been renamed into place or removed.
+pub fn finish(app: &AppHandle, part: &Path) {
+    if let Ok(dir) = data_dir(app) {
+        partial::finish(&dir, part);
+    }
+}
+
+/// Removes partial files left behind by downloads that never finished, the command line's
+/// included. Called once at startup.
+pub fn cleanup_stale(app: &AppHandle) {
+    if let Ok(dir) = data_dir(app) {
+        partial::cleanup_stale(&dir);
+    }
+}
@@ -0,0 +1,375 @@
+use aws_sdk_s3::presigning::{PresignedRequest, PresigningConfig};
+use aws_sdk_s3::types::{CompletedMultipartUpload, CompletedPart};
+use aws_sdk_s3::Client;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::Duration;
+use tauri::{AppHandle, State};
+use tokio::task::JoinSet;
+
+use crate::error::{AppError, ErrorKind};
+use crate::inventory::csv_field;
+use crate::jobs;
+use crate::s3::{acquire_client, acquire_write_client, AppState};
+use crate::shares;
+
+const MIN_EXPIRY_SECS: u64 = 60;
+// SigV4 presigned URLs can't outlive a week
+const MAX_EXPIRY_SECS: u64 = 7 * 24 * 3600;
+const DEFAULT_EXPIRY_SECS: u64 = 3600;
+
+const MIN_PART_SIZE: u64 = 5 * 1024 * 1024;
+const MAX_PART_SIZE: u64 = 5 * 1024 * 1024 * 1024;
+const DEFAULT_PART_SIZE: u64 = 64 * 1024 * 1024;
+const MAX_PARTS: u64 = 10_000;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum PresignMethod {
+    #[default]
+    Get,
+    Head,
+    Put,
+    Delete,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct PresignOptions {
+    /// Seconds, from a minute up to 7 days; an hour when unset.
+    pub expires_in: Option<u64>,
+    pub method: PresignMethod,
+    /// Content-Type the uploader must send (PUT only).
+    pub content_type: Option<String>,
+    /// Overrides the Content-Type the download is served with (GET/HEAD only).
+    pub response_content_type: Option<String>,
+    /// Overrides the Content-Disposition, e.g. `attachment` to force a download (GET/HEAD only).
+    pub response_content_disposition: Option<String>,
+}
+
+/// A presigned request. Whoever uses it must send `headers` exactly as given, since they're part
+/// of the signature.
+#[derive(Debug, Clone, Serialize)]
+pub struct PresignedLink {
+    pub url: String,
+    pub method: String,
+    pub headers: BTreeMap<String, String>,
+    pub expires_at: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct KeyedLink {
+    pub key: String,
+    #[serde(flatten)]
+    pub link: PresignedLink,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PresignedPart {
+    pub part_number: i32,
+    /// Byte range of the file this part carries, end exclusive.
+    pub start: u64,
+    pub end: u64,
+    #[serde(flatten)]
+    pub link: PresignedLink,
+}
+
+/// What an external tool needs to upload a large file without credentials: PUT each part to its
+/// link and report the returned ETag headers back. The SDK can't presign the completion call, so
+/// the upload is finished here with `complete_presigned_upload` (or dropped with
+/// `abort_presigned_upload`); abandoned ones are swept by the multipart cleanup.
+#[derive(Debug, Clone, Serialize)]
+pub struct PresignedMultipartUpload {
+    pub upload_id: String,
+    pub part_size: u64,
+    pub parts: Vec<PresignedPart>,
+    pub expires_at: i64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct UploadedPart {
+    pub part_number: i32,
+    pub etag: String,
+}
+
+fn config(expires_in: Option<u64>) -> Result<(PresigningConfig, i64), AppError> {
+    let secs = expires_in.unwrap_or(DEFAULT_EXPIRY_SECS);
+    if !(MIN_EXPIRY_SECS..=MAX_EXPIRY_SECS).contains(&secs) {
+        return Err(AppError::new(
+            ErrorKind::InvalidInput,
+            format!("Expiry must be between 1 minute and 7 days, got {} seconds", secs),
+        ));
+    }
+    let config = PresigningConfig::expires_in(Duration::from_secs(secs)).map_err(|e| e.to_string())?;
+    Ok((config, jobs::now_secs() + secs as i64))
+}
+
+fn link(request: PresignedRequest, expires_at: i64) -> PresignedLink {
+    PresignedLink {
+        url: request.uri().to_string(),
+        method: request.method().to_string(),
+        headers: request.headers().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+        expires_at,
+    }
+}
+
+async fn sign(
+    client: &Client,
+    bucket: String,
+    key: String,
+    options: PresignOptions,
+    config: PresigningConfig,
+) -> Result<PresignedRequest, AppError> {
+    Ok(match options.method {
+        PresignMethod::Get => client.get_object()
+            .bucket(bucket)
+            .key(key)
+            .set_response_content_type(options.response_content_type)
+            .set_response_content_disposition(options.response_content_disposition)
+            .presigned(config)
+            .await?,
+        PresignMethod::Head => client.head_object()
+            .bucket(bucket)
+            .key(key)
+            .set_response_content_type(options.response_content_type)
+            .set_response_content_disposition(options.response_content_disposition)
+            .presigned(config)
+            .await?,
+        PresignMethod::Put => client.put_object()
+            .bucket(bucket)
+            .key(key)
+            .set_content_type(options.content_type)
+            .presigned(config)
+            .await?,
+        PresignMethod::Delete => client.delete_object()
+            .bucket(bucket)
+            .key(key)
+            .presigned(config)
+            .await?,
+    })
+}
+
+async fn presign(
+    state: &AppState,
+    bucket: String,
+    key: String,
+    options: PresignOptions,
+    connection_id: Option<&str>,
+) -> Result<PresignedLink, AppError> {
+    let writes = matches!(options.method, PresignMethod::Put | PresignMethod::Delete);
+    if writes && (options.response_content_type.is_some() || options.response_content_disposition.is_some()) {
+        return Err(AppError::new(ErrorKind::InvalidInput, "Response header overrides only apply to GET and HEAD links"));
+    }
+    if options.method != PresignMethod::Put && options.content_type.is_some() {
+        return Err(AppError::new(ErrorKind::InvalidInput, "A content type only applies to PUT links"));
+    }
+
+    // Signing is local, but a read-only connection shouldn't hand out write access either
+    let client = if writes {
+        acquire_write_client(state, connection_id)?
+    } else {
+        acquire_client(state, connection_id)?
+    };
+    let (config, expires_at) = config(options.expires_in)?;
+    let request = sign(&client, bucket, key, options, config).await?;
+    Ok(link(request, expires_at))
+}
+
+/// Presigns any supported request for `key` and records it as a share.
+#[tauri::command]
+pub async fn presign_object(
+    bucket: String,
+    key: String,
+    options: PresignOptions,
+    app: AppHandle,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<PresignedLink, AppError> {
+    let link = presign(&state, bucket.clone(), key.clone(), options, connection_id.as_deref()).await?;
+    shares::record(&app, &bucket, &key, &link.url, &link.method, Some(link.expires_at));
+    Ok(link)
+}
+
+/// A one-hour GET link for `key`. Used for in-app previews, so it isn't recorded as a share.
+#[tauri::command]
+pub async fn get_presigned_url(
+    bucket: String,
+    key: String,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<String, AppError> {
+    let link = presign(&state, bucket, key, PresignOptions::default(), connection_id.as_deref()).await?;
+    Ok(link.url)
+}
+
+/// A one-shot PUT link for `key`, for collaborators without credentials. When `content_type` is
+/// given the upload must be sent with that Content-Type.
+#[tauri::command]
+pub async fn get_presigned_upload_url(
+    bucket: String,
+    key: String,
+    expires_in: Option<u64>,
+    content_type: Option<String>,
+    app: AppHandle,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<PresignedLink, AppError> {
+    let options = PresignOptions { expires_in, method: PresignMethod::Put, content_type, ..Default::default() };
+    presign_object(bucket, key, options, app, connection_id, state).await
+}
+
+/// GET links for many keys at once, in the order given. With `csv_path` they're also written
+/// there as `key,url,expires_at` rows.
+#[tauri::command]
+pub async fn get_presigned_urls(
+    bucket: String,
+    keys: Vec<String>,
+    expires_in: Option<u64>,
+    csv_path: Option<String>,
+    app: AppHandle,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<KeyedLink>, AppError> {
+    let client = acquire_client(&state, connection_id.as_deref())?;
+    let (config, expires_at) = config(expires_in)?;
+
+    let mut tasks = JoinSet::new();
+    for (index, key) in keys.into_iter().enumerate() {
+        let (client, bucket, config) = (client.clone(), bucket.clone(), config.clone());
+        tasks.spawn(async move {
+            let request = sign(&client, bucket, key.clone(), PresignOptions::default(), config).await?;
+            Ok::<_, AppError>((index, KeyedLink { key, link: link(request, expires_at) }))
+        });
+    }
+    let mut links = Vec::with_capacity(tasks.len());
+    while let Some(joined) = tasks.join_next().await {
+        links.push(joined.map_err(|e| e.to_string())??);
+    }
+    links.sort_by_key(|(index, _)| *index);
+    let links: Vec<KeyedLink> = links.into_iter().map(|(_, l)| l).collect();
+    shares::record_all(
+        &app,
+        &bucket,
+        links.iter().map(|l| (l.key.as_str(), l.link.url.as_str(), l.link.method.as_str(), Some(l.link.expires_at))),
+    );
+
+    if let Some(path) = csv_path {
+        let mut out = String::from("key,url,expires_at\n");
+        for l in &links {
+            out.push_str(&format!("{},{},{}\n", csv_field(&l.key), csv_field(&l.link.url), l.link.expires_at));
+        }
+        tokio::fs::write(&path, out)
+            .await
+            .map_err(|e| AppError::new(ErrorKind::InvalidInput, format!("Cannot write {}: {}", path, e)))?;
+    }
+    Ok(links)
+}
+
+/// Starts a multipart upload of `size` bytes to `key` and presigns every part. The part size defaults to 64 MiB, grown as needed to stay within
+/// 10,000 parts.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn presign_multipart_upload(
+    bucket: String,
+    key: String,
+    size: u64,
+    part_size: Option<u64>,
+    expires_in: Option<u64>,
+    content_type: Option<String>,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<PresignedMultipartUpload, AppError> {
+    let part_size = part_size.unwrap_or_else(|| DEFAULT_PART_SIZE.max(size.div_ceil(MAX_PARTS)));
+    if !(MIN_PART_SIZE..=MAX_PART_SIZE).contains(&part_size) {
+        return Err(AppError::new(ErrorKind::InvalidInput, "Part size must be between 5 MiB and 5 GiB"));
+    }
+    let part_count = size.div_ceil(part_size).max(1);
+    if part_count > MAX_PARTS {
+        return Err(AppError::new(
+            ErrorKind::InvalidInput,
+            format!("{} parts of {} bytes exceed the 10,000 part limit", part_count, part_size),
+        ));
+    }
+
+    let client = acquire_write_client(&state, connection_id.as_deref())?;
+    let (config, expires_at) = config(expires_in)?;
+
+    let upload = client.create_multipart_upload()
+        .bucket(&bucket)
+        .key(&key)
+        .set_content_type(content_type)
+        .send()
+        .await?;
+    let upload_id = upload.upload_id().ok_or("Missing upload id")?.to_string();
+
+    let signed: Result<PresignedMultipartUpload, AppError> = async {
+        let mut parts = Vec::with_capacity(part_count as usize);
+        for n in 0..part_count {
+            let part_number = n as i32 + 1;
+            let request = client.upload_part()
+                .bucket(&bucket)
+                .key(&key)
+                .upload_id(&upload_id)
+                .part_number(part_number)
+                .presigned(config.clone())
+                .await?;
+            let start = n * part_size;
+            parts.push(PresignedPart { part_number, start, end: (start + part_size).min(size), link: link(request, expires_at) });
+        }
+        Ok(PresignedMultipartUpload { upload_id: upload_id.clone(), part_size, parts, expires_at })
+    }.await;
+
+    if signed.is_err() {
+        // Don't leave an upload behind that nobody has the links to finish
+        let _ = client.abort_multipart_upload().bucket(&bucket).key(&key).upload_id(&upload_id).send().await;
+    }
+    signed
+}
+
+/// Finishes an upload started by `presign_multipart_upload` once every part is in.
+#[tauri::command]
+pub async fn complete_presigned_upload(
+    bucket: String,
+    key: String,
+    upload_id: String,
+    mut parts: Vec<UploadedPart>,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    if parts.is_empty() {
+        return Err(AppError::new(ErrorKind::InvalidInput, "No parts were uploaded"));
+    }
+    let client = acquire_write_client(&state, connection_id.as_deref())?;
+
+    parts.sort_by_key(|p| p.part_number);
+    let completed = parts
+        .into_iter()
+        .map(|p| CompletedPart::builder().part_number(p.part_number).e_tag(p.etag).build())
+        .collect();
+    client.complete_multipart_upload()
+        .bucket(&bucket)
+        .key(&key)
+        .upload_id(&upload_id)
+        .multipart_upload(CompletedMultipartUpload::builder().set_parts(Some(completed)).build())
+        .send()
+        .await?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn abort_presigned_upload(
+    bucket: String,
+    key: String,
+    upload_id: String,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    let client = acquire_write_client(&state, connection_id.as_deref())?;
+    client.abort_multipart_upload()
+        .bucket(&bucket)
+        .key(&key)
+        .upload_id(&upload_id)
+        .send()
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,206 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::error::{AppError, ErrorKind};
+use crate::keychain;
+use crate::s3::{self, build_client, AppState};
+use r2drive_core::profiles as core;
+
+pub use r2drive_core::profiles::{secret_for, sse_c_account, sse_c_key_for, Profile};
+
+// Saved connections (see r2drive_core::profiles) are edited here. The profile behind each open
+// connection is remembered in session.json.
+static PROFILES_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Session {
+    /// Connection id -> profile name.
+    #[serde(default)]
+    connections: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RestoredConnection {
+    pub connection_id: String,
+    pub profile: String,
+    pub endpoint: String,
+}
+
+fn config_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path().app_config_dir().map_err(|e| e.to_string())
+}
+
+fn load(app: &AppHandle) -> Vec<Profile> {
+    config_dir(app).map(|dir| core::load(&dir)).unwrap_or_default()
+}
+
+/// Writes the profile list. Secrets held in memory (new profiles, or ones saved before secrets
+/// moved to the keychain) are stored in the keychain first.
+fn save(app: &AppHandle, profiles: &[Profile]) -> Result<(), String> {
+    for profile in profiles.iter().filter(|p| !p.secret_key.is_empty()) {
+        keychain::store_secret(&profile.name, &profile.secret_key)?;
+    }
+    for profile in profiles.iter().filter(|p| p.sse_c && !p.sse_c_key.is_empty()) {
+        keychain::store_secret(&sse_c_account(&profile.name), &profile.sse_c_key)?;
+    }
+    let path = core::profiles_path(&config_dir(app)?);
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let raw = serde_json::to_string_pretty(profiles).map_err(|e| e.to_string())?;
+    std::fs::write(path, raw).map_err(|e| e.to_string())
+}
+
+fn session_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = app.path().app_config_dir().map_err(|e| e.to_string())?;
+    Ok(dir.join("session.json"))
+}
+
+fn load_session(app: &AppHandle) -> Session {
+    session_path(app)
+        .ok()
+        .and_then(|p| std::fs::read_to_string(p).ok())
+        .and_then(|raw| serde_json::from_str(&raw).ok())
+        .unwrap_or_default()
+}
+
+fn update_session(app: &AppHandle, connection_id: &str, profile: Option<&str>) -> Result<(), String> {
+    let _guard = PROFILES_LOCK.lock().unwrap();
+    let mut session = load_session(app);
+    match profile {
+        Some(p) => session.connections.insert(connection_id.to_string(), p.to_string()),
+        None => session.connections.remove(connection_id),
+    };
+
+    let path = session_path(app)?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let raw = serde_json::to_string_pretty(&session).map_err(|e| e.to_string())?;
+    std::fs::write(path, raw).map_err(|e| e.to_string())
+}
+
+/// The profile `connection_id` was opened from, if any.
+pub fn profile_for(app: &AppHandle, connection_id: &str) -> Option<String> {
+    let _guard = PROFILES_LOCK.lock().unwrap();
+    load_session(app).connections.remove(connection_id)
+}
+
+/// The public base URL set for `bucket` on the profile `connection_id` was opened from.
+pub fn public_url_for(app: &AppHandle, connection_id: &str, bucket: &str) -> Option<String> {
+    let name = profile_for(app, connection_id)?;
+    let _guard = PROFILES_LOCK.lock().unwrap();
+    load(app).into_iter().find(|p| p.name == name)?.public_urls.remove(bucket)
+}
+
+/// Stops restoring `connection_id` on launch, e.g. once it's closed or reconnected with ad-hoc
+/// credentials.
+pub fn forget_session(app: &AppHandle, connection_id: &str) {
+    let _ = update_session(app, connection_id, None);
+}
+
+#[tauri::command]
+pub fn list_profiles(app: AppHandle) -> Vec<Profile> {
+    let _guard = PROFILES_LOCK.lock().unwrap();
+    load(&app)
+}
+
+#[tauri::command]
+pub fn create_profile(profile: Profile, app: AppHandle) -> Result<Profile, AppError> {
+    profile.validate()?;
+    let _guard = PROFILES_LOCK.lock().unwrap();
+    let mut profiles = load(&app);
+    if profiles.iter().any(|p| p.name == profile.name) {
+        return Err(AppError::new(ErrorKind::AlreadyExists, format!("A profile named '{}' already exists", profile.name)));
+    }
+    profiles.push(profile.clone());
+    save(&app, &profiles)?;
+    Ok(profile)
+}
+
+/// Replaces the profile called `name` (which may be renamed via `profile.name`).
+#[tauri::command]
+pub fn update_profile(name: String, mut profile: Profile, app: AppHandle) -> Result<Profile, AppError> {
+    let _guard = PROFILES_LOCK.lock().unwrap();
+    let mut profiles = load(&app);
+    let index = profiles.iter().position(|p| p.name == name).ok_or_else(|| format!("No profile named '{}'", name))?;
+
+    if profile.secret_key.is_empty() {
+        profile.secret_key = secret_for(&profiles[index])?;
+    }
+    if profile.sse_c && profile.sse_c_key.is_empty() && profiles[index].sse_c {
+        profile.sse_c_key = sse_c_key_for(&profiles[index])?;
+    }
+    profile.validate()?;
+    if profile.name != name && profiles.iter().any(|p| p.name == profile.name) {
+        return Err(AppError::new(ErrorKind::AlreadyExists, format!("A profile named '{}' already exists", profile.name)));
+    }
+
+    profiles[index] = profile.clone();
+    save(&app, &profiles)?;
+    if profile.name != name {
+        let _ = keychain::delete_secret(&name);
+    }
+    if profile.name != name || !profile.sse_c {
+        let _ = keychain::delete_secret(&sse_c_account(&name));
+    }
+    Ok(profile)
+}
+
+#[tauri::command]
+pub fn delete_profile(name: String, app: AppHandle) -> Result<(), AppError> {
+    let _guard = PROFILES_LOCK.lock().unwrap();
+    let profiles = load(&app);
+    if !profiles.iter().any(|p| p.name == name) {
+        return Err(AppError::new(ErrorKind::NotFound, format!("No profile named '{}'", name)));
+    }
+    let remaining: Vec<Profile> = profiles.into_iter().filter(|p| p.name != name).collect();
+    save(&app, &remaining)?;
+    keychain::delete_secret(&sse_c_account(&name))?;
+    Ok(keychain::delete_secret(&name)?)
+}
+
+async fn connect(app: &AppHandle, state: &AppState, connection_id: &str, name: &str) -> Result<String, String> {
+    let profile = {
+        let _guard = PROFILES_LOCK.lock().unwrap();
+        load(app).into_iter().find(|p| p.name == name).ok_or_else(|| format!("No profile named '{}'", name))?
+    };
+    let endpoint = profile.endpoint()?;
+    let credentials = profile.credentials()?;
+    let client = build_client(state, &endpoint, profile.region.clone(), &credentials, profile.force_path_style).await;
+    s3::activate(app, state, connection_id, client, &endpoint, credentials, profile.read_only);
+    update_session(app, connection_id, Some(name))?;
+
+    Ok(endpoint)
+}
+
+/// Opens a saved profile as `connection_id` and remembers it for the next launch.
+#[tauri::command]
+pub async fn connect_profile(
+    name: String,
+    app: AppHandle,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<String, AppError> {
+    connect(&app, &state, s3::connection_key(connection_id.as_deref()), &name).await?;
+    Ok("Initialized".to_string())
+}
+
+/// Reconnects every profile that was open in the previous session. Called once at startup;
+/// emits `connection://restored` or `connection://restore-failed` per connection.
+pub async fn restore_session(app: AppHandle) {
+    let state = app.state::<AppState>();
+    for (connection_id, profile) in load_session(&app).connections {
+        match connect(&app, &state, &connection_id, &profile).await {
+            Ok(endpoint) => {
+                let _ = app.emit("connection://restored", RestoredConnection { connection_id, profile, endpoint });
+            }
+            Err(e) => {
+                let _ = app.emit("connection://restore-failed", (connection_id, profile, e));
+            }
+        }
+    }
+}
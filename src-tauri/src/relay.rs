@@ -0,0 +1,230 @@
+use aws_sdk_s3::primitives::ByteStream;
+use aws_sdk_s3::types::{CompletedMultipartUpload, CompletedPart};
+use aws_sdk_s3::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tauri::{AppHandle, Emitter, State};
+
+use crate::error::AppError;
+use crate::jobs;
+use crate::s3::{acquire_client, acquire_write_client, build_client, AppState, Credentials};
+
+// Parts are buffered in memory one at a time, so this bounds the relay's memory use
+const PART_SIZE: usize = 8 * 1024 * 1024;
+
+/// A destination on any S3-compatible provider (AWS S3, MinIO, another R2 account...).
+#[derive(Debug, Clone, Deserialize)]
+pub struct RemoteEndpoint {
+    pub endpoint: String,
+    pub region: Option<String>,
+    pub access_key: String,
+    pub secret_key: String,
+    #[serde(default)]
+    pub session_token: Option<String>,
+    #[serde(default)]
+    pub force_path_style: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RelayProgress {
+    pub src_key: String,
+    pub dst_key: String,
+    pub transferred: u64,
+    pub total: Option<u64>,
+}
+
+async fn upload_part(
+    dst: &Client,
+    bucket: &str,
+    key: &str,
+    upload_id: &str,
+    part_number: i32,
+    data: Vec<u8>,
+) -> Result<CompletedPart, String> {
+    let resp = dst.upload_part()
+        .bucket(bucket)
+        .key(key)
+        .upload_id(upload_id)
+        .part_number(part_number)
+        .body(ByteStream::from(data))
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(CompletedPart::builder()
+        .part_number(part_number)
+        .set_e_tag(resp.e_tag().map(|s| s.to_string()))
+        .build())
+}
+
+/// Streams one object from `src` to `dst` without touching disk, emitting `relay://progress`.
+/// Objects that fit in a single part are sent with one PutObject; larger ones use multipart
+/// on the destination, aborted if anything fails.
+pub async fn relay_object(
+    app: &AppHandle,
+    src: &Client,
+    src_bucket: &str,
+    src_key: &str,
+    dst: &Client,
+    dst_bucket: &str,
+    dst_key: &str,
+) -> Result<u64, String> {
+    let resp = src.get_object()
+        .bucket(src_bucket)
+        .key(src_key)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to read {}: {}", src_key, e))?;
+
+    let total = resp.content_length().map(|l| l as u64);
+    let content_type = resp.content_type().map(|c| c.to_string());
+    let mut body = resp.body;
+
+    let mut progress = RelayProgress {
+        src_key: src_key.to_string(),
+        dst_key: dst_key.to_string(),
+        transferred: 0,
+        total,
+    };
+
+    let mut buffer: Vec<u8> = Vec::with_capacity(PART_SIZE);
+    let mut upload_id: Option<String> = None;
+    let mut parts = Vec::new();
+
+    let result: Result<(), String> = async {
+        while let Some(chunk) = body.try_next().await.map_err(|e| e.to_string())? {
+            buffer.extend_from_slice(&chunk);
+            progress.transferred += chunk.len() as u64;
+
+            if buffer.len() >= PART_SIZE {
+                if upload_id.is_none() {
+                    let created = dst.create_multipart_upload()
+                        .bucket(dst_bucket)
+                        .key(dst_key)
+                        .set_content_type(content_type.clone())
+                        .send()
+                        .await
+                        .map_err(|e| e.to_string())?;
+                    upload_id = Some(created.upload_id().ok_or("Missing upload id")?.to_string());
+                }
+                let data = std::mem::replace(&mut buffer, Vec::with_capacity(PART_SIZE));
+                let id = upload_id.as_deref().unwrap_or_default();
+                parts.push(upload_part(dst, dst_bucket, dst_key, id, parts.len() as i32 + 1, data).await?);
+                let _ = app.emit("relay://progress", &progress);
+            }
+        }
+
+        match &upload_id {
+            None => {
+                dst.put_object()
+                    .bucket(dst_bucket)
+                    .key(dst_key)
+                    .set_content_type(content_type.clone())
+                    .body(ByteStream::from(std::mem::take(&mut buffer)))
+                    .send()
+                    .await
+                    .map_err(|e| e.to_string())?;
+            }
+            Some(id) => {
+                if !buffer.is_empty() {
+                    let data = std::mem::take(&mut buffer);
+                    parts.push(upload_part(dst, dst_bucket, dst_key, id, parts.len() as i32 + 1, data).await?);
+                }
+                dst.complete_multipart_upload()
+                    .bucket(dst_bucket)
+                    .key(dst_key)
+                    .upload_id(id)
+                    .multipart_upload(CompletedMultipartUpload::builder().set_parts(Some(parts.clone())).build())
+                    .send()
+                    .await
+                    .map_err(|e| e.to_string())?;
+            }
+        }
+        Ok(())
+    }
+    .await;
+
+    if let Err(e) = result {
+        if let Some(id) = &upload_id {
+            let _ = dst.abort_multipart_upload()
+                .bucket(dst_bucket)
+                .key(dst_key)
+                .upload_id(id)
+                .send()
+                .await;
+        }
+        return Err(e);
+    }
+
+    let _ = app.emit("relay://progress", &progress);
+    Ok(progress.transferred)
+}
+
+/// Copies an object from an open connection to a bucket on another provider.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn relay_transfer(
+    src_bucket: String,
+    src_key: String,
+    destination: RemoteEndpoint,
+    dst_bucket: String,
+    dst_key: String,
+    app: AppHandle,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<u64, AppError> {
+    let job = jobs::start("relay_transfer", Some(&src_bucket), json!({
+        "src_key": src_key,
+        "endpoint": destination.endpoint,
+        "dst_bucket": dst_bucket,
+        "dst_key": dst_key,
+    }));
+    let result: Result<u64, AppError> = async {
+        let src = acquire_client(&state, connection_id.as_deref())?;
+        let credentials = Credentials {
+            session_token: destination.session_token,
+            ..Credentials::new(String::new(), destination.access_key, destination.secret_key)
+        };
+        let dst = build_client(&state, &destination.endpoint, destination.region, &credentials, destination.force_path_style).await;
+
+        Ok(relay_object(&app, &src, &src_bucket, &src_key, &dst, &dst_bucket, &dst_key).await?)
+    }
+    .await;
+    job.finish(&app, &result, |n| format!("Transferred {} bytes", n));
+
+    result
+}
+
+/// Streams an object between two open connections (e.g. two R2 accounts) without touching disk,
+/// emitting `relay://progress`.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn transfer_between_accounts(
+    src_conn: String,
+    src_bucket: String,
+    src_key: String,
+    dst_conn: String,
+    dst_bucket: String,
+    dst_key: String,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<u64, AppError> {
+    let job = jobs::start("transfer_between_accounts", Some(&src_bucket), json!({
+        "src_conn": src_conn,
+        "src_key": src_key,
+        "dst_conn": dst_conn,
+        "dst_bucket": dst_bucket,
+        "dst_key": dst_key,
+    }));
+    let result: Result<u64, AppError> = async {
+        // Requests on either side take their own permits from the shared limiter; the source
+        // body streams while a part is uploaded, so both sides can have a request in flight
+        let dst = acquire_write_client(&state, Some(&dst_conn))?;
+        let src = acquire_client(&state, Some(&src_conn))?;
+
+        Ok(relay_object(&app, &src, &src_bucket, &src_key, &dst, &dst_bucket, &dst_key).await?)
+    }
+    .await;
+    job.finish(&app, &result, |n| format!("Transferred {} bytes", n));
+
+    result
+}
@@ -0,0 +1,183 @@
+use aws_sdk_s3::types::{Delete, ObjectIdentifier};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use serde_json::json;
+use tauri::{AppHandle, State};
+use urlencoding::encode;
+
+use crate::error::{AppError, ErrorKind};
+use crate::jobs;
+use crate::s3::{acquire_write_client, AppState};
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SequenceOptions {
+    pub start: u32,
+    pub padding: usize,
+    pub separator: Option<String>,
+}
+
+/// Rename template applied to the file name part of each key (everything after the last '/').
+/// Steps run in order: find/replace, prefix, suffix (before the extension), sequence number.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct RenamePattern {
+    pub find: Option<String>,
+    pub replace: Option<String>,
+    pub prefix: Option<String>,
+    pub suffix: Option<String>,
+    pub sequence: Option<SequenceOptions>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RenameMapping {
+    pub old_key: String,
+    pub new_key: String,
+}
+
+/// An object copied to its new name whose old key could not be deleted.
+#[derive(Debug, Clone, Serialize)]
+pub struct RenameFailure {
+    pub key: String,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RenameResult {
+    pub mappings: Vec<RenameMapping>,
+    pub failed: Vec<RenameFailure>,
+}
+
+fn split_extension(name: &str) -> (&str, &str) {
+    match name.rfind('.') {
+        // Dotfiles like ".env" have no extension
+        Some(idx) if idx > 0 => (&name[..idx], &name[idx..]),
+        _ => (name, ""),
+    }
+}
+
+fn apply_pattern(key: &str, pattern: &RenamePattern, index: usize) -> String {
+    let (dir, name) = match key.rfind('/') {
+        Some(idx) => (&key[..=idx], &key[idx + 1..]),
+        None => ("", key),
+    };
+
+    let mut name = name.to_string();
+    if let Some(find) = pattern.find.as_deref().filter(|f| !f.is_empty()) {
+        name = name.replace(find, pattern.replace.as_deref().unwrap_or(""));
+    }
+
+    let (stem, ext) = split_extension(&name);
+    let mut stem = stem.to_string();
+    let ext = ext.to_string();
+
+    if let Some(prefix) = &pattern.prefix {
+        stem = format!("{}{}", prefix, stem);
+    }
+    if let Some(suffix) = &pattern.suffix {
+        stem.push_str(suffix);
+    }
+    if let Some(seq) = &pattern.sequence {
+        let number = seq.start as usize + index;
+        let separator = seq.separator.as_deref().unwrap_or("_");
+        stem = format!("{}{}{:0width$}", stem, separator, number, width = seq.padding);
+    }
+
+    format!("{}{}{}", dir, stem, ext)
+}
+
+/// Computes old→new mappings, dropping unchanged keys and rejecting collisions.
+fn plan_renames(keys: &[String], pattern: &RenamePattern) -> Result<Vec<RenameMapping>, String> {
+    let sources: HashSet<&str> = keys.iter().map(|k| k.as_str()).collect();
+    let mut targets = HashSet::new();
+    let mut mappings = Vec::new();
+
+    for (i, key) in keys.iter().enumerate() {
+        let new_key = apply_pattern(key, pattern, i);
+        if new_key == *key {
+            continue;
+        }
+        if new_key.ends_with('/') || new_key.is_empty() {
+            return Err(format!("Pattern produces an empty name for {}", key));
+        }
+        if !targets.insert(new_key.clone()) || sources.contains(new_key.as_str()) {
+            return Err(format!("Rename collision: {} -> {}", key, new_key));
+        }
+        mappings.push(RenameMapping { old_key: key.clone(), new_key });
+    }
+
+    Ok(mappings)
+}
+
+/// Renames `keys` by `pattern`, or only plans it with `dry_run`. Targets that already exist are
+/// refused unless `overwrite` is set.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn bulk_rename(
+    bucket: String,
+    keys: Vec<String>,
+    pattern: RenamePattern,
+    dry_run: bool,
+    overwrite: Option<bool>,
+    app: AppHandle,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<RenameResult, AppError> {
+    let mappings = plan_renames(&keys, &pattern)?;
+    if dry_run || mappings.is_empty() {
+        return Ok(RenameResult { mappings, failed: Vec::new() });
+    }
+
+    let job = jobs::start("bulk_rename", Some(&bucket), json!({ "count": mappings.len() }));
+    let result: Result<RenameResult, AppError> = async {
+        let client = acquire_write_client(&state, connection_id.as_deref())?;
+
+        if !overwrite.unwrap_or(false) {
+            for m in &mappings {
+                match client.head_object().bucket(&bucket).key(&m.new_key).send().await {
+                    Ok(_) => return Err(AppError::new(ErrorKind::AlreadyExists, format!("{} already exists", m.new_key))),
+                    Err(e) if e.as_service_error().is_some_and(|se| se.is_not_found()) => {}
+                    Err(e) => return Err(format!("Failed to check {}: {}", m.new_key, e).into()),
+                }
+            }
+        }
+
+        // Copy everything first so a failure part-way never loses data
+        for m in &mappings {
+            let copy_source = format!("{}/{}", bucket, encode(&m.old_key));
+            client.copy_object()
+                .bucket(&bucket)
+                .copy_source(copy_source)
+                .key(&m.new_key)
+                .send()
+                .await
+                .map_err(|e| format!("Failed to copy {}: {}", m.old_key, e))?;
+        }
+
+        let object_ids: Vec<ObjectIdentifier> = mappings
+            .iter()
+            .map(|m| ObjectIdentifier::builder().key(&m.old_key).build().unwrap())
+            .collect();
+
+        let mut failed = Vec::new();
+        for chunk in object_ids.chunks(1000) {
+            let delete = Delete::builder().set_objects(Some(chunk.to_vec())).quiet(true).build().unwrap();
+            let resp = client.delete_objects()
+                .bucket(&bucket)
+                .delete(delete)
+                .send()
+                .await?;
+            failed.extend(resp.errors().iter().map(|e| RenameFailure {
+                key: e.key().unwrap_or_default().to_string(),
+                error: e.message().unwrap_or_default().to_string(),
+            }));
+        }
+
+        Ok(RenameResult { mappings, failed })
+    }
+    .await;
+    job.finish(&app, &result, |r| match r.failed.len() {
+        0 => format!("Renamed {} objects", r.mappings.len()),
+        n => format!("Renamed {} objects, {} old keys not deleted", r.mappings.len(), n),
+    });
+
+    result
+}
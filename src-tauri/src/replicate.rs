@@ -0,0 +1,284 @@
+use aws_sdk_s3::types::{CompletedMultipartUpload, CompletedPart, Object};
+use aws_sdk_s3::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tokio::task::JoinSet;
+use urlencoding::encode;
+
+use crate::error::{AppError, ErrorKind};
+use crate::jobs;
+use crate::s3::{self, acquire_write_client, is_folder_marker, list_all_objects, wait_if_paused, AppState};
+use crate::sync::normalize_prefix;
+
+// Copies objects between buckets of one account without downloading them. Each run lists both
+// sides and copies only what is missing or different, so repeated runs are incremental.
+const COPY_CONCURRENCY: usize = 8;
+// Largest single CopyObject; bigger objects are copied in parts of this size
+const MAX_COPY_SIZE: i64 = 5 * 1024 * 1024 * 1024;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Replication {
+    pub src_bucket: String,
+    #[serde(default)]
+    pub src_prefix: String,
+    pub dst_bucket: String,
+    #[serde(default)]
+    pub dst_prefix: String,
+    /// Delete objects under the destination prefix that the source doesn't have.
+    #[serde(default)]
+    pub delete: bool,
+}
+
+/// Payload of `replicate://progress`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReplicateProgress {
+    pub src_bucket: String,
+    pub dst_bucket: String,
+    pub done: usize,
+    pub total: usize,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplicateFailure {
+    pub key: String,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReplicateResult {
+    pub copied: usize,
+    pub deleted: usize,
+    /// Already identical on the destination.
+    pub unchanged: usize,
+    pub bytes: u64,
+    /// Failed keys (source keys for copies, destination keys for deletes); the rest still run.
+    pub failed: Vec<ReplicateFailure>,
+}
+
+enum Step {
+    Copy { key: String, size: i64 },
+    Delete { key: String },
+}
+
+/// Whether `dst` already holds `src`. Plain ETags are content hashes and compare directly; a
+/// multipart ETag changes when copied, so then a destination at least as new and the same size
+/// counts as a copy.
+fn same(src: &Object, dst: &Object) -> bool {
+    if src.size() != dst.size() {
+        return false;
+    }
+    match (src.e_tag(), dst.e_tag()) {
+        (Some(a), Some(b)) if !a.contains('-') && !b.contains('-') => a == b,
+        _ => dst.last_modified().map(|d| d.secs()) >= src.last_modified().map(|d| d.secs()),
+    }
+}
+
+async fn list(client: &Client, bucket: &str, prefix: &str) -> Result<HashMap<String, Object>, String> {
+    let objects = list_all_objects(client, bucket, Some(prefix)).await?;
+    Ok(objects
+        .into_iter()
+        .filter(|o| !is_folder_marker(o.key().unwrap_or_default(), o.size().unwrap_or(0)))
+        .filter_map(|o| Some((o.key()?.strip_prefix(prefix)?.to_string(), o)))
+        .collect())
+}
+
+/// Copies an object over 5 GiB with UploadPartCopy, carrying its content type and metadata.
+async fn copy_large(client: &Client, copy_source: &str, src: (&str, &str), dst: (&str, &str), size: i64) -> Result<(), String> {
+    let head = client.head_object().bucket(src.0).key(src.1).send().await.map_err(|e| AppError::from(e).message)?;
+    let upload = client.create_multipart_upload()
+        .bucket(dst.0)
+        .key(dst.1)
+        .set_content_type(head.content_type().map(str::to_string))
+        .set_metadata(head.metadata().cloned())
+        .send()
+        .await
+        .map_err(|e| AppError::from(e).message)?;
+    let upload_id = upload.upload_id().ok_or("Missing upload id")?.to_string();
+
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let copied: Result<(), String> = async {
+        while start < size {
+            let end = (start + MAX_COPY_SIZE).min(size) - 1;
+            let part_number = parts.len() as i32 + 1;
+            let resp = client.upload_part_copy()
+                .bucket(dst.0)
+                .key(dst.1)
+                .upload_id(&upload_id)
+                .part_number(part_number)
+                .copy_source(copy_source)
+                .copy_source_range(format!("bytes={}-{}", start, end))
+                .send()
+                .await
+                .map_err(|e| AppError::from(e).message)?;
+            let etag = resp.copy_part_result().and_then(|r| r.e_tag()).unwrap_or_default();
+            parts.push(CompletedPart::builder().part_number(part_number).e_tag(etag).build());
+            start = end + 1;
+        }
+        client.complete_multipart_upload()
+            .bucket(dst.0)
+            .key(dst.1)
+            .upload_id(&upload_id)
+            .multipart_upload(CompletedMultipartUpload::builder().set_parts(Some(parts)).build())
+            .send()
+            .await
+            .map_err(|e| AppError::from(e).message)?;
+        Ok(())
+    }
+    .await;
+    if copied.is_err() {
+        // Don't leave billable orphaned parts behind
+        let _ = client.abort_multipart_upload().bucket(dst.0).key(dst.1).upload_id(&upload_id).send().await;
+    }
+    copied
+}
+
+/// Server-side copies one object of `size` bytes, in parts if it is over 5 GiB.
+pub async fn copy(client: &Client, src: (&str, &str), dst: (&str, &str), size: i64) -> Result<(), String> {
+    let copy_source = format!("{}/{}", src.0, encode(src.1));
+    if size > MAX_COPY_SIZE {
+        return copy_large(client, &copy_source, src, dst, size).await;
+    }
+    client.copy_object()
+        .bucket(dst.0)
+        .key(dst.1)
+        .copy_source(copy_source)
+        .send()
+        .await
+        .map(|_| ())
+        .map_err(|e| AppError::from(e).message)
+}
+
+async fn run_step(client: &Client, replication: &Replication, step: &Step) -> Result<(), String> {
+    match step {
+        Step::Copy { key, size } => {
+            let src_key = format!("{}{}", replication.src_prefix, key);
+            let dst_key = format!("{}{}", replication.dst_prefix, key);
+            copy(client, (&replication.src_bucket, &src_key), (&replication.dst_bucket, &dst_key), *size).await
+        }
+        Step::Delete { key } => client.delete_object()
+            .bucket(&replication.dst_bucket)
+            .key(format!("{}{}", replication.dst_prefix, key))
+            .send()
+            .await
+            .map(|_| ())
+            .map_err(|e| AppError::from(e).message),
+    }
+}
+
+/// Brings the destination prefix up to date with the source prefix. Emits `replicate://progress`.
+pub async fn replicate_prefix(app: &AppHandle, client: &Client, replication: Replication) -> Result<ReplicateResult, AppError> {
+    let replication = Arc::new(Replication {
+        src_prefix: normalize_prefix(&replication.src_prefix),
+        dst_prefix: normalize_prefix(&replication.dst_prefix),
+        ..replication
+    });
+    if replication.src_bucket == replication.dst_bucket
+        && (replication.dst_prefix.starts_with(&replication.src_prefix) || replication.src_prefix.starts_with(&replication.dst_prefix))
+    {
+        return Err(AppError::new(ErrorKind::InvalidInput, "Source and destination overlap"));
+    }
+
+    let source = list(client, &replication.src_bucket, &replication.src_prefix).await?;
+    let destination = list(client, &replication.dst_bucket, &replication.dst_prefix).await?;
+    let mut result = ReplicateResult::default();
+    let mut steps = Vec::new();
+    for (key, src) in &source {
+        match destination.get(key) {
+            Some(dst) if same(src, dst) => result.unchanged += 1,
+            _ => steps.push(Step::Copy { key: key.clone(), size: src.size().unwrap_or(0) }),
+        }
+    }
+    if replication.delete {
+        steps.extend(destination.keys().filter(|k| !source.contains_key(*k)).map(|key| Step::Delete { key: key.clone() }));
+    }
+    let steps = Arc::new(steps);
+
+    let mut progress = ReplicateProgress {
+        src_bucket: replication.src_bucket.clone(),
+        dst_bucket: replication.dst_bucket.clone(),
+        done: 0,
+        total: steps.len(),
+        bytes: 0,
+    };
+    let _ = app.emit("replicate://progress", &progress);
+    // Each copy's requests take their own limiter permits; no more copies run than it allows
+    let concurrency = s3::concurrency(&app.state::<AppState>(), COPY_CONCURRENCY);
+
+    let mut finish = |progress: &mut ReplicateProgress, step: &Step, outcome: Result<(), String>| {
+        match (step, outcome) {
+            (Step::Copy { size, .. }, Ok(())) => {
+                result.copied += 1;
+                result.bytes += *size as u64;
+                progress.bytes += *size as u64;
+            }
+            (Step::Delete { .. }, Ok(())) => result.deleted += 1,
+            (Step::Copy { key, .. }, Err(error)) => {
+                result.failed.push(ReplicateFailure { key: format!("{}{}", replication.src_prefix, key), error })
+            }
+            (Step::Delete { key }, Err(error)) => {
+                result.failed.push(ReplicateFailure { key: format!("{}{}", replication.dst_prefix, key), error })
+            }
+        }
+        progress.done += 1;
+        let _ = app.emit("replicate://progress", &*progress);
+    };
+
+    let mut tasks: JoinSet<(usize, Result<(), String>)> = JoinSet::new();
+    for index in 0..steps.len() {
+        while tasks.len() >= concurrency {
+            if let Some(Ok((index, outcome))) = tasks.join_next().await {
+                finish(&mut progress, &steps[index], outcome);
+            }
+        }
+        wait_if_paused(&app.state::<AppState>()).await;
+        let (client, replication, steps) = (client.clone(), replication.clone(), steps.clone());
+        tasks.spawn(async move { (index, run_step(&client, &replication, &steps[index]).await) });
+    }
+    while let Some(joined) = tasks.join_next().await {
+        if let Ok((index, outcome)) = joined {
+            finish(&mut progress, &steps[index], outcome);
+        }
+    }
+    Ok(result)
+}
+
+pub fn summary(result: &ReplicateResult) -> String {
+    format!("Replicated: {} copied, {} deleted, {} unchanged, {} failed", result.copied, result.deleted, result.unchanged, result.failed.len())
+}
+
+/// Server-side copies every object under `src_prefix` that is missing or different under
+/// `dst_prefix`, compared by ETag and size. Both buckets must be reachable with the same
+/// credentials.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn replicate(
+    src_bucket: String,
+    src_prefix: String,
+    dst_bucket: String,
+    dst_prefix: String,
+    delete: Option<bool>,
+    app: AppHandle,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<ReplicateResult, AppError> {
+    let replication = Replication { src_bucket, src_prefix, dst_bucket, dst_prefix, delete: delete.unwrap_or(false) };
+    let job = jobs::start("replicate", Some(&replication.dst_bucket), json!({
+        "src_bucket": replication.src_bucket,
+        "src_prefix": replication.src_prefix,
+        "dst_prefix": replication.dst_prefix,
+        "delete": replication.delete,
+    }));
+    let result: Result<ReplicateResult, AppError> = async {
+        let client = acquire_write_client(&state, connection_id.as_deref())?;
+        replicate_prefix(&app, &client, replication).await
+    }
+    .await;
+    job.finish(&app, &result, summary);
+
+    result
+}
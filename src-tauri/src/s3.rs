@@ -1,55 +1,240 @@
-use aws_config::meta::region::RegionProviderChain;
-use aws_sdk_s3::{Client, config::Region};
+use aws_sdk_s3::Client;
 use aws_sdk_s3::primitives::ByteStream;
-use std::collections::HashMap;
-use std::sync::Mutex;
-use tauri::State;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Emitter, Manager, State};
+use tokio::sync::{watch, Semaphore};
 use urlencoding::encode;
 
+use crate::capabilities::{self, Capability, CapabilityMap};
+use crate::encryption::{self, Decryptor};
+use crate::error::{AppError, ErrorKind};
+use crate::jobs;
+use crate::listing::{self, ListingFilter, ListingSort};
+use crate::logging;
+use crate::network;
+use crate::profiles;
+use crate::settings::Settings;
+use crate::signatures;
+use crate::sse_c::{self, SseCKey};
+use crate::validate;
+use r2drive_core::client;
+
+pub use r2drive_core::client::{folder_marker_key, is_folder_marker, list_all_objects, r2_endpoint, Credentials};
+
+/// Connection used by commands that don't name one.
+pub const DEFAULT_CONNECTION: &str = "default";
+
+/// One open account or endpoint.
+#[derive(Clone)]
+pub struct Connection {
+    pub client: Client,
+    pub endpoint: String,
+    pub credentials: Credentials,
+    pub capabilities: CapabilityMap,
+    /// Set from the connected profile; mutating commands refuse to run while it's on.
+    pub read_only: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConnectionInfo {
+    pub connection_id: String,
+    pub endpoint: String,
+    pub account_id: String,
+    pub provider: String,
+    pub read_only: bool,
+    pub expires_at: Option<i64>,
+}
+
 pub struct AppState {
-    pub client: Mutex<Option<Client>>,
-    pub credentials: Mutex<Option<(String, String, String)>>, // account_id, access_key, secret_key
+    // Open connections by id, so several accounts can be used side by side
+    pub connections: Mutex<HashMap<String, Connection>>,
+    pub settings: Mutex<Settings>,
+    // Shared cap on simultaneous requests to the endpoint, taken per request by every client's
+    // HTTP connector. Resized in place when the limit changes.
+    pub limiter: Arc<Semaphore>,
+    /// Set while transfers are paused from the tray or `set_transfers_paused`; transfers hold
+    /// before their next file or chunk.
+    pub paused: watch::Sender<bool>,
+}
+
+impl AppState {
+    pub fn new(settings: Settings) -> Self {
+        let limiter = Arc::new(Semaphore::new(settings.max_connections));
+        Self {
+            connections: Mutex::new(HashMap::new()),
+            settings: Mutex::new(settings),
+            limiter,
+            paused: watch::Sender::new(false),
+        }
+    }
+
+    pub fn apply_settings(&self, settings: Settings) {
+        let mut current = self.settings.lock().unwrap();
+        let (old, new) = (current.max_connections, settings.max_connections);
+        if new > old {
+            self.limiter.add_permits(new - old);
+        } else if new < old {
+            // Permits held by in-flight requests can't be taken back; claim them as they're released.
+            let short = (old - new) - self.limiter.forget_permits(old - new);
+            if short > 0 {
+                let limiter = self.limiter.clone();
+                tauri::async_runtime::spawn(async move {
+                    if let Ok(permits) = limiter.acquire_many_owned(short as u32).await {
+                        permits.forget();
+                    }
+                });
+            }
+        }
+        *current = settings;
+    }
+}
+
+/// Resolves an optional connection id from a command to the id it refers to.
+pub fn connection_key(id: Option<&str>) -> &str {
+    id.filter(|i| !i.is_empty()).unwrap_or(DEFAULT_CONNECTION)
+}
+
+pub fn connection(state: &AppState, id: Option<&str>) -> Result<Connection, AppError> {
+    let connections = state.connections.lock().unwrap();
+    connections.get(connection_key(id)).cloned().ok_or_else(|| AppError::new(ErrorKind::NotConnected, "Client not initialized"))
+}
+
+/// Returns the connection's client. Each request it sends takes its own permit from the shared
+/// request limiter, so callers can run as many requests side by side as they like.
+pub fn acquire_client(state: &AppState, id: Option<&str>) -> Result<Client, AppError> {
+    Ok(connection(state, id)?.client)
+}
+
+pub fn ensure_writable(state: &AppState, id: Option<&str>) -> Result<(), AppError> {
+    if connection(state, id)?.read_only {
+        return Err(AppError::new(ErrorKind::ReadOnly, "This connection is read-only; changes are disabled by its profile"));
+    }
+    Ok(())
+}
+
+/// Like `acquire_client`, for commands that modify the bucket.
+pub fn acquire_write_client(state: &AppState, id: Option<&str>) -> Result<Client, AppError> {
+    ensure_writable(state, id)?;
+    acquire_client(state, id)
+}
+
+/// How many tasks to run side by side, at most `wanted` and never more than the request limiter
+/// allows at once.
+pub fn concurrency(state: &AppState, wanted: usize) -> usize {
+    wanted.min(state.settings.lock().unwrap().max_connections).max(1)
+}
+
+/// Returns once transfers aren't paused.
+pub async fn wait_if_paused(state: &AppState) {
+    if !*state.paused.borrow() {
+        return;
+    }
+    let _ = state.paused.subscribe().wait_for(|paused| !*paused).await;
+}
+
+/// Builds an S3 client for any S3-compatible endpoint with static or temporary credentials,
+/// using the network settings current in `state`.
+pub async fn build_client(
+    state: &AppState,
+    endpoint: &str,
+    region: Option<String>,
+    credentials: &Credentials,
+    force_path_style: bool,
+) -> Client {
+    let (http_client, timeouts) = {
+        let settings = state.settings.lock().unwrap();
+        (network::limited(network::http_client(&settings), state.limiter.clone()), network::timeout_config(&settings))
+    };
+    let s3_config = client::client_config(endpoint, region, credentials, force_path_style, http_client, timeouts)
+        .await
+        .interceptor(logging::RequestLogger::new(endpoint))
+        .build();
+
+    Client::from_conf(s3_config)
 }
 
+/// `session_token` and `expires_at` (unix seconds) are for temporary credentials; the UI is
+/// warned via `credentials://expiring` shortly before they lapse.
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn init_r2(
     account_id: String,
     access_key: String,
     secret_key: String,
+    jurisdiction: Option<String>,
+    session_token: Option<String>,
+    expires_at: Option<i64>,
+    connection_id: Option<String>,
+    app: AppHandle,
     state: State<'_, AppState>,
-) -> Result<String, String> {
-    let region_provider = RegionProviderChain::default_provider().or_else(Region::new("auto"));
-    let creds = aws_credential_types::Credentials::new(
-        access_key.clone(),
-        secret_key.clone(),
-        None,
-        None,
-        "Static",
-    );
-
-    let config = aws_config::from_env()
-        .region(region_provider)
-        .endpoint_url(format!("https://{}.r2.cloudflarestorage.com", account_id))
-        .credentials_provider(creds)
-        .load()
-        .await;
-
-    let client = Client::new(&config);
-
-    *state.client.lock().unwrap() = Some(client);
-    *state.credentials.lock().unwrap() = Some((account_id, access_key, secret_key));
+) -> Result<String, AppError> {
+    let id = connection_key(connection_id.as_deref()).to_string();
+    let endpoint = r2_endpoint(&account_id, jurisdiction.as_deref())?;
+    let credentials = Credentials {
+        session_token: session_token.filter(|t| !t.is_empty()),
+        expires_at,
+        ..Credentials::new(account_id, access_key, secret_key)
+    };
+    let client = build_client(&state, &endpoint, None, &credentials, false).await;
+    activate(&app, &state, &id, client, &endpoint, credentials, false);
+    profiles::forget_session(&app, &id);
 
     Ok("Initialized".to_string())
 }
 
-#[tauri::command]
-pub async fn list_buckets(state: State<'_, AppState>) -> Result<Vec<String>, String> {
-    let client = {
-        let guard = state.client.lock().unwrap();
-        guard.as_ref().ok_or("Client not initialized")?.clone()
+/// Opens (or replaces) the connection `id`.
+pub fn activate(
+    app: &AppHandle,
+    state: &AppState,
+    id: &str,
+    client: Client,
+    endpoint: &str,
+    credentials: Credentials,
+    read_only: bool,
+) {
+    let connection = Connection {
+        client,
+        endpoint: endpoint.to_string(),
+        credentials,
+        capabilities: capabilities::resolve(app, endpoint),
+        read_only,
     };
+    state.connections.lock().unwrap().insert(id.to_string(), connection);
+}
+
+#[tauri::command]
+pub fn list_connections(state: State<'_, AppState>) -> Vec<ConnectionInfo> {
+    let mut connections: Vec<ConnectionInfo> = state.connections.lock().unwrap()
+        .iter()
+        .map(|(id, c)| ConnectionInfo {
+            connection_id: id.clone(),
+            endpoint: c.endpoint.clone(),
+            account_id: c.credentials.account_id.clone(),
+            provider: c.capabilities.provider.clone(),
+            read_only: c.read_only,
+            expires_at: c.credentials.expires_at,
+        })
+        .collect();
+    connections.sort_by(|a, b| a.connection_id.cmp(&b.connection_id));
+    connections
+}
+
+#[tauri::command]
+pub fn close_connection(connection_id: Option<String>, app: AppHandle, state: State<'_, AppState>) -> bool {
+    let id = connection_key(connection_id.as_deref()).to_string();
+    profiles::forget_session(&app, &id);
+    state.connections.lock().unwrap().remove(&id).is_some()
+}
+
+#[tauri::command]
+pub async fn list_buckets(connection_id: Option<String>, state: State<'_, AppState>) -> Result<Vec<String>, AppError> {
+    let client = acquire_client(&state, connection_id.as_deref())?;
 
-    let resp = client.list_buckets().send().await.map_err(|e| e.to_string())?;
+    let resp = client.list_buckets().send().await?;
     
     let buckets = resp
         .buckets()
@@ -60,188 +245,661 @@ pub async fn list_buckets(state: State<'_, AppState>) -> Result<Vec<String>, Str
     Ok(buckets)
 }
 
-use aws_sdk_s3::types::{ObjectIdentifier, Delete};
+use aws_sdk_s3::types::{ObjectIdentifier, Delete, Object};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ObjectEntry {
+    pub key: String,
+    pub size: u64,
+    /// Unix seconds; 0 if the endpoint didn't report it.
+    pub last_modified: i64,
+    pub etag: String,
+    pub storage_class: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FolderEntry {
+    pub prefix: String,
+}
+
+/// One folder listing. `truncated` is set when the listing stopped at the configured cap.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ListObjectsResult {
+    pub files: Vec<ObjectEntry>,
+    pub folders: Vec<FolderEntry>,
+    pub truncated: bool,
+}
+
+/// Emitted as `list://page` for each page of a streamed listing.
+#[derive(Debug, Clone, Serialize)]
+pub struct ListingPage {
+    pub listing_id: String,
+    pub files: Vec<ObjectEntry>,
+    pub folders: Vec<FolderEntry>,
+    pub done: bool,
+    pub truncated: bool,
+}
+
+impl From<&Object> for ObjectEntry {
+    fn from(o: &Object) -> Self {
+        ObjectEntry {
+            key: o.key().unwrap_or_default().to_string(),
+            size: o.size().unwrap_or_default().max(0) as u64,
+            last_modified: o.last_modified().map(|d| d.secs()).unwrap_or_default(),
+            etag: o.e_tag().unwrap_or_default().trim_matches('"').to_string(),
+            storage_class: o.storage_class().map(|c| c.as_str().to_string()),
+        }
+    }
+}
+
+fn folder_entry(prefix: &str) -> FolderEntry {
+    FolderEntry { prefix: prefix.to_string() }
+}
 
+/// Lists a folder, following continuation tokens until the listing is complete or holds
+/// `max_listing_items` entries. With a `listing_id`, each page is also emitted as `list://page`
+/// so large folders can be shown while they load. `filter` is applied to every page before it
+/// counts towards the cap; `sort` orders the final result (streamed pages arrive unsorted).
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn list_objects(
     bucket: String, 
     prefix: Option<String>, 
     delimiter: Option<String>,
+    listing_id: Option<String>,
+    filter: Option<ListingFilter>,
+    sort: Option<ListingSort>,
+    app: AppHandle,
+    connection_id: Option<String>,
     state: State<'_, AppState>
-) -> Result<HashMap<String, Vec<HashMap<String, String>>>, String> {
-    let client = {
-        let guard = state.client.lock().unwrap();
-        guard.as_ref().ok_or("Client not initialized")?.clone()
-    };
+) -> Result<ListObjectsResult, AppError> {
+    let client = acquire_client(&state, connection_id.as_deref())?;
+    let cap = state.settings.lock().unwrap().max_listing_items;
+    let filter = filter.unwrap_or_default();
+
+    let mut result = ListObjectsResult::default();
+    let mut seen_folders: HashSet<String> = HashSet::new();
+    let mut continuation_token = None;
+
+    loop {
+        let resp = client.list_objects_v2()
+            .bucket(&bucket)
+            .set_prefix(prefix.clone())
+            .set_delimiter(delimiter.clone())
+            .set_continuation_token(continuation_token)
+            .send()
+            .await?;
+
+        let files: Vec<ObjectEntry> = resp
+            .contents()
+            .iter()
+            .filter(|o| !is_folder_marker(o.key().unwrap_or_default(), o.size().unwrap_or_default()))
+            .map(ObjectEntry::from)
+            .filter(|e| filter.matches_file(e))
+            .collect();
+
+        // Without a delimiter markers show up as plain objects; surface them as folders instead,
+        // except the marker of the prefix being listed.
+        let markers = resp.contents()
+            .iter()
+            .filter(|o| is_folder_marker(o.key().unwrap_or_default(), o.size().unwrap_or_default()))
+            .filter_map(|o| o.key())
+            .filter(|key| Some(*key) != prefix.as_deref());
+        let folders: Vec<FolderEntry> = resp
+            .common_prefixes()
+            .iter()
+            .filter_map(|p| p.prefix())
+            .chain(markers)
+            .filter(|key| seen_folders.insert(key.to_string()))
+            .map(folder_entry)
+            .filter(|e| filter.matches_folder(e))
+            .collect();
+
+        continuation_token = resp.next_continuation_token().map(|t| t.to_string());
+        let more = resp.is_truncated().unwrap_or(false) && continuation_token.is_some();
+        let total = result.files.len() + result.folders.len() + files.len() + folders.len();
+        let truncated = more && total >= cap;
+        let done = !more || truncated;
+
+        if let Some(id) = &listing_id {
+            let _ = app.emit("list://page", ListingPage {
+                listing_id: id.clone(),
+                files: files.clone(),
+                folders: folders.clone(),
+                done,
+                truncated,
+            });
+        }
+        result.files.extend(files);
+        result.folders.extend(folders);
+        result.truncated = truncated;
+
+        if done {
+            break;
+        }
+    }
+
+    if let Some(sort) = sort {
+        listing::sort(&mut result, sort);
+    }
+    Ok(result)
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ObjectPage {
+    pub files: Vec<ObjectEntry>,
+    pub folders: Vec<FolderEntry>,
+    /// Pass back as `continuation_token` for the next page; `None` on the last page.
+    pub next_token: Option<String>,
+}
+
+/// A single page of a folder listing, for infinite scrolling.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn list_objects_page(
+    bucket: String,
+    prefix: Option<String>,
+    delimiter: Option<String>,
+    max_keys: Option<i32>,
+    continuation_token: Option<String>,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<ObjectPage, AppError> {
+    let client = acquire_client(&state, connection_id.as_deref())?;
 
     let resp = client.list_objects_v2()
-        .bucket(bucket)
-        .set_prefix(prefix)
+        .bucket(&bucket)
+        .set_prefix(prefix.clone())
         .set_delimiter(delimiter)
+        .set_max_keys(max_keys.map(|m| m.clamp(1, 1000)))
+        .set_continuation_token(continuation_token.filter(|t| !t.is_empty()))
         .send()
-        .await
-        .map_err(|e| e.to_string())?;
+        .await?;
 
-    let objects: Vec<HashMap<String, String>> = resp
-        .contents()
-        .iter()
-        .map(|o| {
-            let mut map = HashMap::new();
-            map.insert("key".to_string(), o.key().unwrap_or_default().to_string());
-            map.insert("size".to_string(), o.size().unwrap_or_default().to_string());
-            map.insert("last_modified".to_string(), o.last_modified().unwrap().to_string());
-            map.insert("type".to_string(), "file".to_string());
-            map
-        })
-        .collect();
-    
-    let folders: Vec<HashMap<String, String>> = resp
-        .common_prefixes()
+    let (markers, objects): (Vec<&Object>, Vec<&Object>) = resp.contents()
         .iter()
-        .map(|p| {
-             let mut map = HashMap::new();
-             map.insert("key".to_string(), p.prefix().unwrap_or_default().to_string());
-             map.insert("type".to_string(), "folder".to_string());
-             map
-        })
-        .collect();
-
-    let mut result = HashMap::new();
-    result.insert("files".to_string(), objects);
-    result.insert("folders".to_string(), folders);
+        .partition(|o| is_folder_marker(o.key().unwrap_or_default(), o.size().unwrap_or_default()));
+    let mut folder_keys: Vec<String> = resp.common_prefixes().iter().filter_map(|p| p.prefix()).map(|p| p.to_string()).collect();
+    for key in markers.iter().filter_map(|o| o.key()) {
+        if Some(key) != prefix.as_deref() && !folder_keys.iter().any(|f| f == key) {
+            folder_keys.push(key.to_string());
+        }
+    }
 
-    Ok(result)
+    Ok(ObjectPage {
+        files: objects.into_iter().map(ObjectEntry::from).collect(),
+        folders: folder_keys.iter().map(|k| folder_entry(k)).collect(),
+        next_token: resp.next_continuation_token().filter(|_| resp.is_truncated().unwrap_or(false)).map(|t| t.to_string()),
+    })
 }
 
 #[tauri::command]
-pub async fn create_folder(bucket: String, key: String, state: State<'_, AppState>) -> Result<(), String> {
-    let client = {
-       let guard = state.client.lock().unwrap();
-       guard.as_ref().ok_or("Client not initialized")?.clone()
-    };
+pub async fn create_folder(
+    bucket: String,
+    key: String,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    let client = acquire_write_client(&state, connection_id.as_deref())?;
     
-    // Ensure key ends with /
-    let folder_key = if key.ends_with('/') { key } else { format!("{}/", key) };
+    if key.trim_matches('/').is_empty() {
+        return Err(AppError::new(ErrorKind::InvalidInput, "Folder name cannot be empty"));
+    }
+    let folder_key = folder_marker_key(&key);
 
     client.put_object()
         .bucket(bucket)
         .key(folder_key)
         .body(ByteStream::from_static(&[]))
         .send()
-        .await
-        .map_err(|e| e.to_string())?;
+        .await?;
         
     Ok(())
 }
 
 #[tauri::command]
-pub async fn delete_objects(bucket: String, keys: Vec<String>, state: State<'_, AppState>) -> Result<(), String> {
-    let client = {
-        let guard = state.client.lock().unwrap();
-        guard.as_ref().ok_or("Client not initialized")?.clone()
-    };
+pub async fn delete_objects(
+    bucket: String,
+    keys: Vec<String>,
+    app: AppHandle,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    let count = keys.len();
+    let job = jobs::start("delete_objects", Some(&bucket), json!({ "count": count }));
+    let result: Result<(), AppError> = async {
+        let client = acquire_write_client(&state, connection_id.as_deref())?;
+
+        let mut object_ids = Vec::new();
+        for k in keys {
+            object_ids.push(ObjectIdentifier::builder().key(k).build().unwrap());
+        }
 
-    let mut object_ids = Vec::new();
-    for k in keys {
-        object_ids.push(ObjectIdentifier::builder().key(k).build().unwrap());
-    }
+        /* 
+           Note: delete_objects is limited to 1000 items per call by AWS. 
+           For "military grade", we should chunk this.
+        */
+        for chunk in object_ids.chunks(1000) {
+            let delete = Delete::builder().set_objects(Some(chunk.to_vec())).build().unwrap();
+            client.delete_objects()
+                .bucket(&bucket)
+                .delete(delete)
+                .send()
+                .await?;
+        }
 
-    /* 
-       Note: delete_objects is limited to 1000 items per call by AWS. 
-       For "military grade", we should chunk this.
-    */
-    for chunk in object_ids.chunks(1000) {
-        let delete = Delete::builder().set_objects(Some(chunk.to_vec())).build().unwrap();
-        client.delete_objects()
-            .bucket(&bucket)
-            .delete(delete)
-            .send()
-            .await
-            .map_err(|e| e.to_string())?;
+        Ok(())
     }
+    .await;
+    job.finish(&app, &result, |_| format!("Deleted {} objects", count));
 
-    Ok(())
+    result
 }
 
-#[tauri::command]
-pub async fn delete_prefix(bucket: String, prefix: String, state: State<'_, AppState>) -> Result<(), String> {
-    let client = {
-        let guard = state.client.lock().unwrap();
-        guard.as_ref().ok_or("Client not initialized")?.clone()
-    };
+#[derive(Debug, Clone, Deserialize)]
+pub struct ExpectedObject {
+    pub key: String,
+    pub etag: Option<String>,
+    pub last_modified: Option<i64>, // epoch seconds as listed
+}
 
-    // List all objects with prefix
-    let mut continuation_token = None;
-    let mut all_keys = Vec::new();
+#[derive(Debug, Clone, Serialize)]
+pub struct SkippedDelete {
+    pub key: String,
+    pub reason: String,
+}
 
-    loop {
-        let resp = client.list_objects_v2()
-            .bucket(&bucket)
-            .prefix(&prefix)
-            .set_continuation_token(continuation_token)
-            .send()
-            .await
-            .map_err(|e| e.to_string())?;
-        
-        for obj in resp.contents() {
-            if let Some(k) = obj.key() {
-                all_keys.push(ObjectIdentifier::builder().key(k).build().unwrap());
+#[derive(Debug, Clone, Serialize)]
+pub struct ConditionalDeleteResult {
+    pub deleted: Vec<String>,
+    pub skipped: Vec<SkippedDelete>,
+}
+
+fn normalize_etag(etag: &str) -> &str {
+    etag.trim_matches('"')
+}
+
+/// Deletes objects only if they still match what the caller listed. Each object is checked with
+/// HeadObject and the delete itself carries If-Match where the provider supports conditional
+/// deletes, closing the window between the check and the delete.
+#[tauri::command]
+pub async fn delete_objects_if_unchanged(
+    bucket: String,
+    objects: Vec<ExpectedObject>,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<ConditionalDeleteResult, AppError> {
+    let conn = connection_id.as_deref();
+    let client = acquire_write_client(&state, conn)?;
+
+    let mut result = ConditionalDeleteResult { deleted: Vec::new(), skipped: Vec::new() };
+
+    for expected in objects {
+        let head = match client.head_object().bucket(&bucket).key(&expected.key).send().await {
+            Ok(h) => h,
+            Err(e) => {
+                let not_found = e.as_service_error().map(|se| se.is_not_found()).unwrap_or(false);
+                if not_found {
+                    result.skipped.push(SkippedDelete { key: expected.key, reason: "Object no longer exists".to_string() });
+                    continue;
+                }
+                return Err(format!("Failed to check {}: {}", expected.key, e).into());
+            }
+        };
+
+        let current_etag = head.e_tag().map(normalize_etag).unwrap_or_default();
+        if let Some(etag) = &expected.etag {
+            if normalize_etag(etag) != current_etag {
+                result.skipped.push(SkippedDelete { key: expected.key, reason: "ETag changed since listing".to_string() });
+                continue;
+            }
+        }
+        if let Some(listed) = expected.last_modified {
+            if head.last_modified().map(|d| d.secs()) != Some(listed) {
+                result.skipped.push(SkippedDelete { key: expected.key, reason: "Modified since listing".to_string() });
+                continue;
             }
         }
 
-        if resp.is_truncated().unwrap_or(false) {
-            continuation_token = resp.next_continuation_token.clone();
-        } else {
-            break;
+        let conditional = !current_etag.is_empty() && capabilities::supports(&state, conn, Capability::ConditionalDeletes);
+        let mut req = client.delete_object().bucket(&bucket).key(&expected.key);
+        if conditional {
+            req = req.if_match(format!("\"{}\"", current_etag));
+        }
+        match req.send().await {
+            Ok(_) => result.deleted.push(expected.key),
+            Err(e) if e.raw_response().map(|r| r.status().as_u16()) == Some(412) => {
+                result.skipped.push(SkippedDelete { key: expected.key, reason: "ETag changed since listing".to_string() });
+            }
+            Err(e) => {
+                let err = capabilities::map_error(&state, conn, Capability::ConditionalDeletes, e);
+                if !conditional || capabilities::supports(&state, conn, Capability::ConditionalDeletes) {
+                    return Err(AppError { message: format!("Failed to delete {}: {}", expected.key, err.message), ..err });
+                }
+                // Provider rejected If-Match on delete; the HeadObject check above still applies
+                client.delete_object()
+                    .bucket(&bucket)
+                    .key(&expected.key)
+                    .send()
+                    .await
+                    .map_err(|e| format!("Failed to delete {}: {}", expected.key, e))?;
+                result.deleted.push(expected.key);
+            }
         }
     }
 
-    if all_keys.is_empty() {
-        return Ok(());
-    }
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn delete_prefix(
+    bucket: String,
+    prefix: String,
+    app: AppHandle,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    let job = jobs::start("delete_prefix", Some(&bucket), json!({ "prefix": prefix }));
+    let result: Result<(), AppError> = async {
+        let client = acquire_write_client(&state, connection_id.as_deref())?;
+
+        // List all objects with prefix
+        let mut continuation_token = None;
+        let mut all_keys = Vec::new();
+
+        loop {
+            let resp = client.list_objects_v2()
+                .bucket(&bucket)
+                .prefix(&prefix)
+                .set_continuation_token(continuation_token)
+                .send()
+                .await?;
+
+            for obj in resp.contents() {
+                if let Some(k) = obj.key() {
+                    all_keys.push(ObjectIdentifier::builder().key(k).build().unwrap());
+                }
+            }
+
+            if resp.is_truncated().unwrap_or(false) {
+                continuation_token = resp.next_continuation_token.clone();
+            } else {
+                break;
+            }
+        }
+
+        // The folder's own marker goes with its contents, even if the prefix was passed without '/'
+        let marker = folder_marker_key(&prefix);
+        if !all_keys.iter().any(|id| id.key() == marker) {
+            all_keys.push(ObjectIdentifier::builder().key(marker).build().unwrap());
+        }
+
+        // Delete in chunks
+        for chunk in all_keys.chunks(1000) {
+             let delete = Delete::builder().set_objects(Some(chunk.to_vec())).build().unwrap();
+             client.delete_objects()
+                 .bucket(&bucket)
+                 .delete(delete)
+                 .send()
+                 .await?;
+        }
 
-    // Delete in chunks
-    for chunk in all_keys.chunks(1000) {
-         let delete = Delete::builder().set_objects(Some(chunk.to_vec())).build().unwrap();
-         client.delete_objects()
-             .bucket(&bucket)
-             .delete(delete)
-             .send()
-             .await
-             .map_err(|e| e.to_string())?;
+        Ok(())
     }
+    .await;
+    job.finish(&app, &result, |_| format!("Deleted everything under {}", prefix));
 
-    Ok(())
+    result
 }
 
 
 
+/// True when nothing but the folder's own marker lives under `prefix`. Used by delete
+/// confirmations to skip the "folder is not empty" warning.
 #[tauri::command]
-pub async fn upload_file(bucket: String, key: String, path: String, state: State<'_, AppState>) -> Result<(), String> {
-    let client = {
-        let guard = state.client.lock().unwrap();
-        guard.as_ref().ok_or("Client not initialized")?.clone()
-    };
+pub async fn is_empty_prefix(
+    bucket: String,
+    prefix: String,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<bool, AppError> {
+    let client = acquire_client(&state, connection_id.as_deref())?;
 
-    let body = ByteStream::from_path(std::path::Path::new(&path)).await.map_err(|e| e.to_string())?;
+    let marker = folder_marker_key(&prefix);
+    let resp = client.list_objects_v2()
+        .bucket(&bucket)
+        .prefix(&marker)
+        .max_keys(2)
+        .send()
+        .await?;
 
-    client.put_object()
+    Ok(resp.contents().iter().all(|o| o.key() == Some(marker.as_str())))
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn upload_file(
+    bucket: String,
+    key: String,
+    path: String,
+    sse_c_key: Option<String>,
+    app: AppHandle,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    let job = jobs::start("upload", Some(&bucket), json!({ "key": key, "path": path }));
+    let result: Result<(), AppError> = async {
+        wait_if_paused(&state).await;
+        let client = acquire_write_client(&state, connection_id.as_deref())?;
+        let sse_c = sse_c::parse_optional(sse_c_key)?;
+
+        let encryption_settings = state.settings.lock().unwrap().encryption.clone();
+        if encryption_settings.enabled {
+            let master = encryption::unlocked_key(&app)?;
+            let (sealed, metadata) = encryption::encrypt_file(&master, Path::new(&path)).await?;
+            let body = ByteStream::from_path(&sealed.0).await.map_err(|e| e.to_string())?;
+            client.put_object()
+                .bucket(&bucket)
+                .key(encryption::object_key(&encryption_settings, &master, &key)?)
+                .body(body)
+                .set_metadata(Some(metadata))
+                .set_sse_customer_algorithm(sse_c.as_ref().map(SseCKey::algorithm))
+                .set_sse_customer_key(sse_c.as_ref().map(SseCKey::key))
+                .set_sse_customer_key_md5(sse_c.as_ref().map(SseCKey::key_md5))
+                .send()
+                .await?;
+            return Ok(());
+        }
+
+        let body = ByteStream::from_path(std::path::Path::new(&path)).await.map_err(|e| e.to_string())?;
+
+        client.put_object()
+            .bucket(&bucket)
+            .key(&key)
+            .body(body)
+            .set_sse_customer_algorithm(sse_c.as_ref().map(SseCKey::algorithm))
+            .set_sse_customer_key(sse_c.as_ref().map(SseCKey::key))
+            .set_sse_customer_key_md5(sse_c.as_ref().map(SseCKey::key_md5))
+            .send()
+            .await?;
+
+        Ok(())
+    }
+    .await;
+    job.finish(&app, &result, |_| format!("Uploaded {}", key));
+
+    result
+}
+
+/// Uploads a local file, returning the new object's ETag. With encryption on the file is
+/// encrypted first, and its name too if name encryption is on, like `upload_file` does. Syncs,
+/// snapshots, folder watches and context-menu and clipboard uploads all go through here.
+pub async fn upload_path(app: &AppHandle, client: &Client, bucket: &str, key: &str, path: &Path) -> Result<Option<String>, String> {
+    let settings = app.state::<AppState>().settings.lock().unwrap().encryption.clone();
+    if !settings.enabled {
+        return r2drive_core::sync::upload(client, bucket, key, path).await;
+    }
+    let master = encryption::unlocked_key(app)?;
+    let (sealed, metadata) = encryption::encrypt_file(&master, path).await?;
+    let body = ByteStream::from_path(&sealed.0).await.map_err(|e| e.to_string())?;
+    let resp = client.put_object()
         .bucket(bucket)
-        .key(key)
+        .key(encryption::object_key(&settings, &master, key)?)
         .body(body)
+        .set_metadata(Some(metadata))
         .send()
         .await
-        .map_err(|e| e.to_string())?;
+        .map_err(|e| AppError::from(e).message)?;
+    Ok(resp.e_tag().map(str::to_string))
+}
 
-    Ok(())
+pub fn default_text_content_type(key: &str) -> &'static str {
+    match key.rsplit('.').next().map(|e| e.to_ascii_lowercase()).as_deref() {
+        Some("md") => "text/markdown; charset=utf-8",
+        Some("json") => "application/json",
+        Some("html") | Some("htm") => "text/html; charset=utf-8",
+        Some("css") => "text/css; charset=utf-8",
+        Some("js") => "text/javascript; charset=utf-8",
+        Some("csv") => "text/csv; charset=utf-8",
+        Some("xml") => "application/xml",
+        Some("yaml") | Some("yml") => "application/yaml",
+        _ => "text/plain; charset=utf-8",
+    }
+}
+
+/// Writes `content` as a new object. Refuses to overwrite an existing key unless asked to.
+#[tauri::command]
+pub async fn create_text_object(
+    bucket: String,
+    key: String,
+    content: String,
+    content_type: Option<String>,
+    overwrite: Option<bool>,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    if key.is_empty() || key.ends_with('/') {
+        return Err(AppError::new(ErrorKind::InvalidInput, "A file name is required"));
+    }
+    if let Some(err) = validate::check_syntax(&key, &content) {
+        return Err(AppError::new(ErrorKind::InvalidInput, err.to_string()));
+    }
+
+    let client = acquire_write_client(&state, connection_id.as_deref())?;
+
+    let content_type = content_type.unwrap_or_else(|| default_text_content_type(&key).to_string());
+    let mut req = client.put_object()
+        .bucket(&bucket)
+        .key(&key)
+        .content_type(content_type)
+        .body(ByteStream::from(content.into_bytes()));
+    if !overwrite.unwrap_or(false) {
+        req = req.if_none_match("*");
+    }
+
+    match req.send().await {
+        Ok(_) => Ok(()),
+        Err(e) if e.raw_response().map(|r| r.status().as_u16()) == Some(412) => {
+            Err(AppError::new(ErrorKind::AlreadyExists, format!("{} already exists", key)))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Creates a zero-byte placeholder file such as `.keep` or `index.html`. Unlike create_folder
+/// the key is used as-is and must not end with '/'.
+#[tauri::command]
+pub async fn create_empty_object(
+    bucket: String,
+    key: String,
+    content_type: Option<String>,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    if key.is_empty() || key.ends_with('/') {
+        return Err(AppError::new(ErrorKind::InvalidInput, "A file name is required"));
+    }
+
+    let client = acquire_write_client(&state, connection_id.as_deref())?;
+
+    let content_type = content_type
+        .unwrap_or_else(|| mime_guess::from_path(&key).first_or_octet_stream().to_string());
+
+    match client.put_object()
+        .bucket(&bucket)
+        .key(&key)
+        .content_type(content_type)
+        .body(ByteStream::from_static(&[]))
+        .if_none_match("*")
+        .send()
+        .await
+    {
+        Ok(_) => Ok(()),
+        Err(e) if e.raw_response().map(|r| r.status().as_u16()) == Some(412) => {
+            Err(AppError::new(ErrorKind::AlreadyExists, format!("{} already exists", key)))
+        }
+        Err(e) => Err(e.into()),
+    }
 }
 
-#[tauri::command]pub async fn get_bucket_stats(bucket: String, state: State<'_, AppState>) -> Result<HashMap<String, String>, String> {
-    let client = {
-        let guard = state.client.lock().unwrap();
-        guard.as_ref().ok_or("Client not initialized")?.clone()
+const APPEND_SIZE_LIMIT: i64 = 5 * 1024 * 1024;
+
+/// Emulates append for small text objects: read, append locally, write back only if the ETag is
+/// unchanged. A missing object is created. Returns the new size.
+#[tauri::command]
+pub async fn append_text(
+    bucket: String,
+    key: String,
+    text: String,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<i64, AppError> {
+    let client = acquire_write_client(&state, connection_id.as_deref())?;
+
+    let existing = match client.get_object().bucket(&bucket).key(&key).send().await {
+        Ok(resp) => Some(resp),
+        Err(e) if e.as_service_error().map(|se| se.is_no_such_key()).unwrap_or(false) => None,
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut req = client.put_object().bucket(&bucket).key(&key);
+    let mut data = match existing {
+        Some(resp) => {
+            if resp.content_length().unwrap_or(0) > APPEND_SIZE_LIMIT {
+                return Err(AppError::new(ErrorKind::InvalidInput, "Object too large to append to (limit 5 MB)"));
+            }
+            let etag = resp.e_tag().ok_or("Object has no ETag")?.to_string();
+            req = req.if_match(etag).set_content_type(resp.content_type().map(|c| c.to_string()));
+            let bytes = resp.body.collect().await.map_err(|e| e.to_string())?.into_bytes();
+            let data = bytes.to_vec();
+            std::str::from_utf8(&data).map_err(|_| "File is not valid text".to_string())?;
+            data
+        }
+        None => {
+            req = req.if_none_match("*").content_type(default_text_content_type(&key));
+            Vec::new()
+        }
     };
 
+    data.extend_from_slice(text.as_bytes());
+    let size = data.len() as i64;
+
+    match req.body(ByteStream::from(data)).send().await {
+        Ok(_) => Ok(size),
+        Err(e) if e.raw_response().map(|r| r.status().as_u16()) == Some(412) => {
+            Err(AppError::new(ErrorKind::PreconditionFailed, "Object was modified concurrently, try again"))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[tauri::command]pub async fn get_bucket_stats(
+    bucket: String,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<HashMap<String, String>, AppError> {
+    let client = acquire_client(&state, connection_id.as_deref())?;
+
     let mut total_size: i64 = 0;
     let mut object_count: i64 = 0;
     let mut continuation_token = None;
@@ -251,10 +909,12 @@ pub async fn upload_file(bucket: String, key: String, path: String, state: State
             .bucket(&bucket)
             .set_continuation_token(continuation_token)
             .send()
-            .await
-            .map_err(|e| e.to_string())?;
+            .await?;
 
         for obj in resp.contents() {
+            if is_folder_marker(obj.key().unwrap_or_default(), obj.size().unwrap_or(0)) {
+                continue;
+            }
             total_size += obj.size().unwrap_or(0);
             object_count += 1;
         }
@@ -273,77 +933,179 @@ pub async fn upload_file(bucket: String, key: String, path: String, state: State
     Ok(result)
 }
 
-#[tauri::command]pub async fn download_file(bucket: String, key: String, save_path: String, state: State<'_, AppState>) -> Result<(), String> {
-    let client = {
-        let guard = state.client.lock().unwrap();
-        guard.as_ref().ok_or("Client not initialized")?.clone()
-    };
+#[derive(Debug, Clone, Serialize)]
+pub struct LargestObject {
+    pub key: String,
+    pub size: i64,
+}
 
-    let resp = client.get_object()
-        .bucket(bucket)
-        .key(key)
-        .send()
-        .await
-        .map_err(|e| e.to_string())?;
+#[derive(Debug, Clone, Serialize)]
+pub struct PrefixStats {
+    pub prefix: String,
+    pub object_count: u64,
+    pub total_size: i64,
+    pub largest: Option<LargestObject>,
+}
 
-    let data = resp.body.collect().await.map_err(|e| e.to_string())?.into_bytes();
-    
-    std::fs::write(save_path, data).map_err(|e| e.to_string())?;
+/// Recursive "folder size": counts every object under `prefix`, folder markers excluded.
+#[tauri::command]
+pub async fn get_prefix_stats(
+    bucket: String,
+    prefix: String,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<PrefixStats, AppError> {
+    let client = acquire_client(&state, connection_id.as_deref())?;
 
-    Ok(())
+    let objects = list_all_objects(&client, &bucket, Some(&prefix)).await?;
+
+    let mut stats = PrefixStats { prefix, object_count: 0, total_size: 0, largest: None };
+    for obj in &objects {
+        let key = obj.key().unwrap_or_default();
+        let size = obj.size().unwrap_or(0);
+        if is_folder_marker(key, size) {
+            continue;
+        }
+        stats.object_count += 1;
+        stats.total_size += size;
+        if stats.largest.as_ref().is_none_or(|l| size > l.size) {
+            stats.largest = Some(LargestObject { key: key.to_string(), size });
+        }
+    }
+
+    Ok(stats)
 }
 
+#[derive(Debug, Clone, Serialize)]
+pub struct PrefixUsage {
+    /// Folder prefix ending in '/', or "" for the bucket root.
+    pub prefix: String,
+    pub depth: usize,
+    pub object_count: u64,
+    pub total_size: i64,
+}
+
+/// Treemap dataset: object counts and sizes for every prefix down to `depth` levels, from a single
+/// pass over the bucket. Objects sitting directly in a folder count toward that folder only.
 #[tauri::command]
-pub async fn read_text_file(bucket: String, key: String, state: State<'_, AppState>) -> Result<String, String> {
-    let client = {
-        let guard = state.client.lock().unwrap();
-        guard.as_ref().ok_or("Client not initialized")?.clone()
-    };
+pub async fn get_bucket_breakdown(
+    bucket: String,
+    depth: usize,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<PrefixUsage>, AppError> {
+    let client = acquire_client(&state, connection_id.as_deref())?;
+
+    let objects = list_all_objects(&client, &bucket, None).await?;
+
+    let mut usage: HashMap<String, PrefixUsage> = HashMap::new();
+    for obj in &objects {
+        let key = obj.key().unwrap_or_default();
+        let size = obj.size().unwrap_or(0);
+        if is_folder_marker(key, size) {
+            continue;
+        }
+
+        // The root plus each enclosing folder up to `depth`
+        let folders: Vec<usize> = key.match_indices('/').map(|(i, _)| i).take(depth).collect();
+        let prefixes = std::iter::once(0).chain(folders.iter().map(|i| i + 1));
+        for (level, end) in prefixes.enumerate() {
+            let prefix = &key[..end];
+            let entry = usage.entry(prefix.to_string()).or_insert_with(|| PrefixUsage {
+                prefix: prefix.to_string(),
+                depth: level,
+                object_count: 0,
+                total_size: 0,
+            });
+            entry.object_count += 1;
+            entry.total_size += size;
+        }
+    }
+
+    let mut result: Vec<PrefixUsage> = usage.into_values().collect();
+    result.sort_by(|a, b| a.depth.cmp(&b.depth).then(b.total_size.cmp(&a.total_size)));
+
+    Ok(result)
+}
 
+/// Streams an object into `target` via a `.r2part` sibling that is fsynced and renamed on success,
+/// so an interrupted download never leaves a truncated file under the final name.
+pub async fn download_to_path(app: &AppHandle, client: &Client, bucket: &str, key: &str, target: &Path) -> Result<u64, String> {
+    download_with_key(app, client, bucket, key, None, target).await
+}
+
+/// `download_to_path` with an SSE-C key for this one object.
+pub async fn download_with_key(
+    app: &AppHandle,
+    client: &Client,
+    bucket: &str,
+    key: &str,
+    sse_c: Option<&SseCKey>,
+    target: &Path,
+) -> Result<u64, String> {
     let resp = client.get_object()
         .bucket(bucket)
         .key(key)
+        .set_sse_customer_algorithm(sse_c.map(SseCKey::algorithm))
+        .set_sse_customer_key(sse_c.map(SseCKey::key))
+        .set_sse_customer_key_md5(sse_c.map(SseCKey::key_md5))
         .send()
         .await
         .map_err(|e| e.to_string())?;
 
-    // Check size first to avoid crashing on huge files
-    if resp.content_length() > Some(1024 * 1024 * 5) { // 5MB limit for preview
-        return Err("File too large for preview".to_string());
-    }
-
-    let data = resp.body.collect().await.map_err(|e| e.to_string())?.into_bytes();
-    let text = String::from_utf8(data.to_vec()).map_err(|_| "File is not valid text".to_string())?;
-
-    Ok(text)
+    let decryptor = Decryptor::for_object(app, resp.metadata())?;
+    save_body(app, resp.body, decryptor, target).await
 }
 
-#[tauri::command]
-pub async fn get_presigned_url(bucket: String, key: String, state: State<'_, AppState>) -> Result<String, String> {
-    let client = {
-        let guard = state.client.lock().unwrap();
-        guard.as_ref().ok_or("Client not initialized")?.clone()
+/// Writes a response body to `target` with the download engine shared with r2drive-cli.
+/// Encrypted objects are decrypted on the way, and writing holds while transfers are paused.
+pub async fn save_body(app: &AppHandle, body: ByteStream, decryptor: Option<Decryptor>, target: &Path) -> Result<u64, String> {
+    let data_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    let options = client::SaveOptions {
+        filter: decryptor.map(|d| Box::new(d) as Box<dyn client::BodyFilter>),
+        paused: Some(app.state::<AppState>().paused.subscribe()),
     };
+    client::save_body(&data_dir, body, options, target).await
+}
 
-    let presigning_config = aws_sdk_s3::presigning::PresigningConfig::expires_in(std::time::Duration::from_secs(3600))
-        .map_err(|e| e.to_string())?;
+#[tauri::command]
+pub async fn download_file(
+    bucket: String,
+    key: String,
+    save_path: String,
+    sse_c_key: Option<String>,
+    app: AppHandle,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    let job = jobs::start("download", Some(&bucket), json!({ "key": key, "save_path": save_path }));
+    let result: Result<(), AppError> = async {
+        let sse_c = sse_c::parse_optional(sse_c_key)?;
+        {
+            let client = acquire_client(&state, connection_id.as_deref())?;
+            download_with_key(&app, &client, &bucket, &key, sse_c.as_ref(), Path::new(&save_path)).await?;
+        }
+        signatures::check_download(&app, &state, connection_id.as_deref(), &bucket, &key, Path::new(&save_path)).await?;
 
-    let presigned_req = client.get_object()
-        .bucket(bucket)
-        .key(key)
-        .presigned(presigning_config)
-        .await
-        .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+    .await;
+    job.finish(&app, &result, |_| format!("Downloaded {}", key));
 
-    Ok(presigned_req.uri().to_string())
+    result
 }
 
 #[tauri::command]
-pub async fn copy_object(bucket: String, source: String, destination: String, state: State<'_, AppState>) -> Result<(), String> {
-    let client = {
-        let guard = state.client.lock().unwrap();
-        guard.as_ref().ok_or("Client not initialized")?.clone()
-    };
+pub async fn copy_object(
+    bucket: String,
+    source: String,
+    destination: String,
+    sse_c_key: Option<String>,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    let client = acquire_write_client(&state, connection_id.as_deref())?;
+    let sse_c = sse_c::parse_optional(sse_c_key)?;
 
     // AWS SDK copy_source must be URL encoded.
     // We encode the key, but we ensure '/' remains '/' so S3 parses structure if needed, 
@@ -361,9 +1123,14 @@ pub async fn copy_object(bucket: String, source: String, destination: String, st
         .bucket(&bucket)
         .copy_source(copy_source)
         .key(&destination)
+        .set_sse_customer_algorithm(sse_c.as_ref().map(SseCKey::algorithm))
+        .set_sse_customer_key(sse_c.as_ref().map(SseCKey::key))
+        .set_sse_customer_key_md5(sse_c.as_ref().map(SseCKey::key_md5))
+        .set_copy_source_sse_customer_algorithm(sse_c.as_ref().map(SseCKey::algorithm))
+        .set_copy_source_sse_customer_key(sse_c.as_ref().map(SseCKey::key))
+        .set_copy_source_sse_customer_key_md5(sse_c.as_ref().map(SseCKey::key_md5))
         .send()
-        .await
-        .map_err(|e| e.to_string())?;
+        .await?;
 
     Ok(())
 }
@@ -373,79 +1140,86 @@ pub async fn rename_folder(
     bucket: String,
     old_prefix: String,
     new_prefix: String,
+    app: AppHandle,
+    connection_id: Option<String>,
     state: State<'_, AppState>,
-) -> Result<usize, String> {
-    let client = {
-        let guard = state.client.lock().unwrap();
-        guard.as_ref().ok_or("Client not initialized")?.clone()
-    };
-
-    // 1. List all objects recursively
-    let mut continuation_token = None;
-    let mut keys_to_move = Vec::new();
-
-    loop {
-        let resp = client.list_objects_v2()
-            .bucket(&bucket)
-            .prefix(&old_prefix)
-            .set_continuation_token(continuation_token)
-            .send()
-            .await
-            .map_err(|e| e.to_string())?;
+) -> Result<usize, AppError> {
+    let job = jobs::start("rename_folder", Some(&bucket), json!({
+        "old_prefix": old_prefix,
+        "new_prefix": new_prefix,
+    }));
+    let result: Result<usize, AppError> = async {
+        let client = acquire_write_client(&state, connection_id.as_deref())?;
+
+        // 1. List all objects recursively
+        let mut continuation_token = None;
+        let mut keys_to_move = Vec::new();
+
+        loop {
+            let resp = client.list_objects_v2()
+                .bucket(&bucket)
+                .prefix(&old_prefix)
+                .set_continuation_token(continuation_token)
+                .send()
+                .await?;
+
+            for obj in resp.contents() {
+                if let Some(k) = obj.key() {
+                    keys_to_move.push(k.to_string());
+                }
+            }
 
-        for obj in resp.contents() {
-            if let Some(k) = obj.key() {
-                keys_to_move.push(k.to_string());
+            if resp.is_truncated().unwrap_or(false) {
+                continuation_token = resp.next_continuation_token;
+            } else {
+                break;
             }
         }
 
-        if resp.is_truncated().unwrap_or(false) {
-            continuation_token = resp.next_continuation_token;
-        } else {
-            break;
+        if keys_to_move.is_empty() {
+            return Ok(0);
         }
-    }
 
-    if keys_to_move.is_empty() {
-        return Ok(0);
-    }
-
-    // 2. Copy Loop
-    let mut moved_count = 0;
-    for k in &keys_to_move {
-        // Replace prefix
-        let new_key = k.replacen(&old_prefix, &new_prefix, 1);
-        
-        let source_encoded = encode(k).to_string();
-        let copy_source = format!("{}/{}", bucket, source_encoded);
+        // 2. Copy Loop
+        let mut moved_count = 0;
+        for k in &keys_to_move {
+            // Replace prefix
+            let new_key = k.replacen(&old_prefix, &new_prefix, 1);
+
+            let source_encoded = encode(k).to_string();
+            let copy_source = format!("{}/{}", bucket, source_encoded);
+
+            // Copy
+            let _ = client.copy_object()
+                .bucket(&bucket)
+                .copy_source(copy_source)
+                .key(new_key)
+                .send()
+                .await
+                .map_err(|e| format!("Failed to copy {}: {}", k, e))?;
+
+            moved_count += 1;
+        }
 
-        // Copy
-        let _ = client.copy_object()
-            .bucket(&bucket)
-            .copy_source(copy_source)
-            .key(new_key)
-            .send()
-            .await
-            .map_err(|e| format!("Failed to copy {}: {}", k, e))?;
-            
-        moved_count += 1;
-    }
+        // 3. Delete Old
+        let mut object_ids = Vec::new();
+        for k in keys_to_move {
+             object_ids.push(ObjectIdentifier::builder().key(k).build().unwrap());
+        }
 
-    // 3. Delete Old
-    let mut object_ids = Vec::new();
-    for k in keys_to_move {
-         object_ids.push(ObjectIdentifier::builder().key(k).build().unwrap());
-    }
+        for chunk in object_ids.chunks(1000) {
+             let delete = Delete::builder().set_objects(Some(chunk.to_vec())).build().unwrap();
+             client.delete_objects()
+                 .bucket(&bucket)
+                 .delete(delete)
+                 .send()
+                 .await?;
+        }
 
-    for chunk in object_ids.chunks(1000) {
-         let delete = Delete::builder().set_objects(Some(chunk.to_vec())).build().unwrap();
-         client.delete_objects()
-             .bucket(&bucket)
-             .delete(delete)
-             .send()
-             .await
-             .map_err(|e| e.to_string())?;
+        Ok(moved_count)
     }
+    .await;
+    job.finish(&app, &result, |moved| format!("Moved {} objects", moved));
 
-    Ok(moved_count)
+    result
 }
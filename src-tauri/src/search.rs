@@ -0,0 +1,289 @@
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::error::{AppError, ErrorKind};
+use crate::jobs;
+use crate::listing::ListingFilter;
+use crate::s3::{acquire_client, is_folder_marker, AppState, ObjectEntry};
+use crate::settings;
+
+const DEFAULT_MAX_RESULTS: usize = 10_000;
+
+#[derive(Default)]
+pub struct SearchState {
+    next_id: AtomicU64,
+    searches: Mutex<HashMap<u64, JoinHandle<()>>>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchMode {
+    #[default]
+    Substring,
+    /// `*`, `?` and `[...]`, matched against the whole key.
+    Glob,
+    Regex,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SearchOptions {
+    /// Only search under this prefix; the whole bucket when unset.
+    pub prefix: Option<String>,
+    pub mode: MatchMode,
+    pub case_sensitive: bool,
+    /// Stop after this many matches.
+    pub max_results: Option<usize>,
+    /// Size, date and extension conditions a match must also meet.
+    pub filter: ListingFilter,
+}
+
+/// A named search kept in settings, e.g. "all .log files older than 30 days".
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedSearch {
+    pub name: String,
+    pub bucket: String,
+    /// Matches every key when unset, leaving only the filter.
+    #[serde(default)]
+    pub query: Option<String>,
+    #[serde(default)]
+    pub options: SearchOptions,
+    /// Relative to when the search runs; override the filter's absolute dates.
+    #[serde(default)]
+    pub older_than_days: Option<u32>,
+    #[serde(default)]
+    pub newer_than_days: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchMatches {
+    pub search_id: u64,
+    pub matches: Vec<ObjectEntry>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchDone {
+    pub search_id: u64,
+    pub scanned: u64,
+    pub matched: usize,
+    /// Stopped at `max_results` before the whole bucket/prefix was scanned.
+    pub truncated: bool,
+}
+
+pub enum Matcher {
+    Substring { needle: String, case_sensitive: bool },
+    Glob { pattern: glob::Pattern, options: glob::MatchOptions },
+    Regex(Regex),
+}
+
+impl Matcher {
+    pub fn new(query: &str, mode: MatchMode, case_sensitive: bool) -> Result<Self, AppError> {
+        if query.is_empty() {
+            return Err(AppError::new(ErrorKind::InvalidInput, "Search query cannot be empty"));
+        }
+        let invalid = |e: String| AppError::new(ErrorKind::InvalidInput, format!("Invalid pattern: {}", e));
+        Ok(match mode {
+            MatchMode::Substring => Matcher::Substring {
+                needle: if case_sensitive { query.to_string() } else { query.to_lowercase() },
+                case_sensitive,
+            },
+            MatchMode::Glob => Matcher::Glob {
+                pattern: glob::Pattern::new(query).map_err(|e| invalid(e.to_string()))?,
+                options: glob::MatchOptions { case_sensitive, ..Default::default() },
+            },
+            MatchMode::Regex => Matcher::Regex(
+                RegexBuilder::new(query)
+                    .case_insensitive(!case_sensitive)
+                    .build()
+                    .map_err(|e| invalid(e.to_string()))?,
+            ),
+        })
+    }
+
+    pub fn is_match(&self, key: &str) -> bool {
+        match self {
+            Matcher::Substring { needle, case_sensitive: true } => key.contains(needle.as_str()),
+            Matcher::Substring { needle, case_sensitive: false } => key.to_lowercase().contains(needle.as_str()),
+            Matcher::Glob { pattern, options } => pattern.matches_with(key, *options),
+            Matcher::Regex(re) => re.is_match(key),
+        }
+    }
+}
+
+async fn run_search(
+    app: &AppHandle,
+    connection_id: Option<&str>,
+    search_id: u64,
+    bucket: &str,
+    matcher: Option<Matcher>,
+    options: &SearchOptions,
+) -> Result<SearchDone, AppError> {
+    let state = app.state::<AppState>();
+    let client = acquire_client(&state, connection_id)?;
+    let max_results = options.max_results.unwrap_or(DEFAULT_MAX_RESULTS).max(1);
+
+    let mut done = SearchDone { search_id, scanned: 0, matched: 0, truncated: false };
+    let mut continuation_token = None;
+    loop {
+        let resp = client.list_objects_v2()
+            .bucket(bucket)
+            .set_prefix(options.prefix.clone())
+            .set_continuation_token(continuation_token)
+            .send()
+            .await?;
+
+        done.scanned += resp.contents().len() as u64;
+        let mut matches: Vec<ObjectEntry> = resp.contents()
+            .iter()
+            .filter(|o| !is_folder_marker(o.key().unwrap_or_default(), o.size().unwrap_or_default()))
+            .filter(|o| matcher.as_ref().is_none_or(|m| m.is_match(o.key().unwrap_or_default())))
+            .map(ObjectEntry::from)
+            .filter(|e| options.filter.matches_file(e))
+            .collect();
+        let remaining = max_results - done.matched;
+        let more = resp.is_truncated().unwrap_or(false);
+        if matches.len() > remaining || (matches.len() == remaining && more) {
+            matches.truncate(remaining);
+            done.truncated = true;
+        }
+        done.matched += matches.len();
+        if !matches.is_empty() {
+            let _ = app.emit("search://matches", SearchMatches { search_id, matches });
+        }
+
+        if done.truncated || !more {
+            break;
+        }
+        continuation_token = resp.next_continuation_token().map(|t| t.to_string());
+    }
+
+    Ok(done)
+}
+
+fn start_search(
+    app: AppHandle,
+    search_state: &SearchState,
+    connection_id: Option<String>,
+    bucket: String,
+    matcher: Option<Matcher>,
+    options: SearchOptions,
+) -> u64 {
+    let search_id = search_state.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+
+    // Hold the registry lock until the handle is stored so a fast search can't finish (and
+    // try to deregister itself) before it's registered
+    let mut searches = search_state.searches.lock().unwrap();
+    let handle = tauri::async_runtime::spawn(async move {
+        match run_search(&app, connection_id.as_deref(), search_id, &bucket, matcher, &options).await {
+            Ok(done) => {
+                let _ = app.emit("search://done", done);
+            }
+            Err(e) => {
+                let _ = app.emit("search://error", (search_id, e));
+            }
+        }
+        app.state::<SearchState>().searches.lock().unwrap().remove(&search_id);
+    });
+    searches.insert(search_id, handle);
+
+    search_id
+}
+
+/// Starts a search of every key in `bucket` (or `options.prefix`) and returns its id right away.
+/// Matches arrive as `search://matches`; the search ends with `search://done` or
+/// `search://error`, or when cancelled with `cancel_search`.
+#[tauri::command]
+pub fn search_objects(
+    bucket: String,
+    query: String,
+    options: Option<SearchOptions>,
+    connection_id: Option<String>,
+    app: AppHandle,
+    search_state: State<'_, SearchState>,
+) -> Result<u64, AppError> {
+    let options = options.unwrap_or_default();
+    let matcher = Matcher::new(&query, options.mode, options.case_sensitive)?;
+    Ok(start_search(app, &search_state, connection_id, bucket, Some(matcher), options))
+}
+
+#[tauri::command]
+pub fn cancel_search(search_id: u64, search_state: State<'_, SearchState>) -> bool {
+    match search_state.searches.lock().unwrap().remove(&search_id) {
+        Some(handle) => {
+            handle.abort();
+            true
+        }
+        None => false,
+    }
+}
+
+#[tauri::command]
+pub fn list_saved_searches(state: State<'_, AppState>) -> Vec<SavedSearch> {
+    state.settings.lock().unwrap().saved_searches.clone()
+}
+
+/// Adds `search`, replacing any saved search with the same name.
+#[tauri::command]
+pub fn save_search(search: SavedSearch, app: AppHandle, state: State<'_, AppState>) -> Result<Vec<SavedSearch>, AppError> {
+    if search.name.trim().is_empty() || search.bucket.is_empty() {
+        return Err(AppError::new(ErrorKind::InvalidInput, "A saved search needs a name and a bucket"));
+    }
+    if let Some(query) = search.query.as_deref().filter(|q| !q.is_empty()) {
+        Matcher::new(query, search.options.mode, search.options.case_sensitive)?;
+    }
+
+    let mut settings = state.settings.lock().unwrap().clone();
+    match settings.saved_searches.iter_mut().find(|s| s.name == search.name) {
+        Some(existing) => *existing = search,
+        None => settings.saved_searches.push(search),
+    }
+    settings::save(&app, &settings)?;
+    state.apply_settings(settings.clone());
+    Ok(settings.saved_searches)
+}
+
+#[tauri::command]
+pub fn delete_saved_search(name: String, app: AppHandle, state: State<'_, AppState>) -> Result<Vec<SavedSearch>, AppError> {
+    let mut settings = state.settings.lock().unwrap().clone();
+    let before = settings.saved_searches.len();
+    settings.saved_searches.retain(|s| s.name != name);
+    if settings.saved_searches.len() == before {
+        return Err(AppError::new(ErrorKind::NotFound, format!("No saved search named '{}'", name)));
+    }
+    settings::save(&app, &settings)?;
+    state.apply_settings(settings.clone());
+    Ok(settings.saved_searches)
+}
+
+/// Runs a saved search like `search_objects`, with its relative dates resolved against now.
+#[tauri::command]
+pub fn run_saved_search(
+    name: String,
+    connection_id: Option<String>,
+    app: AppHandle,
+    state: State<'_, AppState>,
+    search_state: State<'_, SearchState>,
+) -> Result<u64, AppError> {
+    let saved = state.settings.lock().unwrap().saved_searches.iter().find(|s| s.name == name).cloned()
+        .ok_or_else(|| AppError::new(ErrorKind::NotFound, format!("No saved search named '{}'", name)))?;
+
+    let mut options = saved.options;
+    let now = jobs::now_secs();
+    if let Some(days) = saved.older_than_days {
+        options.filter.modified_before = Some(now - days as i64 * 86_400);
+    }
+    if let Some(days) = saved.newer_than_days {
+        options.filter.modified_after = Some(now - days as i64 * 86_400);
+    }
+    let matcher = match saved.query.as_deref().filter(|q| !q.is_empty()) {
+        Some(query) => Some(Matcher::new(query, options.mode, options.case_sensitive)?),
+        None => None,
+    };
+
+    Ok(start_search(app, &search_state, connection_id, saved.bucket, matcher, options))
+}
@@ -0,0 +1,139 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager, State};
+
+use crate::encryption::EncryptionSettings;
+use crate::error::{AppError, ErrorKind};
+use crate::network;
+use crate::local_api::LocalApiSettings;
+use crate::notifications::NotificationSettings;
+use crate::s3::AppState;
+use crate::shell_menu::ShellUploadSettings;
+use crate::folder_watch::FolderWatch;
+use crate::search::SavedSearch;
+use crate::signatures::SignatureSettings;
+use crate::sync_schedule::SyncSchedule;
+use crate::updates::UpdateSettings;
+
+pub use r2drive_core::network::{ProxyMode, ProxySettings, TimeoutSettings};
+
+/// Upper bound on `max_connections`.
+pub const MAX_CONNECTIONS: usize = 64;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MultipartCleanupSettings {
+    pub enabled: bool,
+    pub older_than_days: u32,
+    pub interval_hours: u32,
+}
+
+impl Default for MultipartCleanupSettings {
+    fn default() -> Self {
+        Self { enabled: false, older_than_days: 7, interval_hours: 24 }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Max simultaneous requests to the endpoint across listings, transfers and previews.
+    pub max_connections: usize,
+    /// Most entries a single folder listing returns before it stops and reports itself truncated.
+    pub max_listing_items: usize,
+    pub multipart_cleanup: MultipartCleanupSettings,
+    /// Applies to connections opened after it changes.
+    pub proxy: ProxySettings,
+    /// Applies to connections opened after it changes.
+    pub timeouts: TimeoutSettings,
+    pub saved_searches: Vec<SavedSearch>,
+    /// Local folders uploaded to a prefix as they change.
+    pub folder_watches: Vec<FolderWatch>,
+    pub sync_schedules: Vec<SyncSchedule>,
+    pub encryption: EncryptionSettings,
+    pub signatures: SignatureSettings,
+    /// Closing the window hides it to the tray, leaving transfers running.
+    pub close_to_tray: bool,
+    pub notifications: NotificationSettings,
+    /// Destination for "Upload to R2Drive" in the file manager.
+    pub shell_upload: ShellUploadSettings,
+    pub updates: UpdateSettings,
+    /// JSON-RPC automation API on a localhost port.
+    pub local_api: LocalApiSettings,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            max_connections: 8,
+            max_listing_items: 100_000,
+            multipart_cleanup: MultipartCleanupSettings::default(),
+            proxy: ProxySettings::default(),
+            timeouts: TimeoutSettings::default(),
+            saved_searches: Vec::new(),
+            folder_watches: Vec::new(),
+            sync_schedules: Vec::new(),
+            encryption: EncryptionSettings::default(),
+            signatures: SignatureSettings::default(),
+            close_to_tray: true,
+            notifications: NotificationSettings::default(),
+            shell_upload: ShellUploadSettings::default(),
+            updates: UpdateSettings::default(),
+            local_api: LocalApiSettings::default(),
+        }
+    }
+}
+
+fn settings_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = app.path().app_config_dir().map_err(|e| e.to_string())?;
+    Ok(dir.join("settings.json"))
+}
+
+/// Reads persisted settings, falling back to defaults if the file is missing or unreadable.
+pub fn load(app: &AppHandle) -> Settings {
+    settings_path(app)
+        .ok()
+        .and_then(|p| std::fs::read_to_string(p).ok())
+        .and_then(|raw| serde_json::from_str::<Settings>(&raw).ok())
+        .map(|mut settings| {
+            settings.max_connections = settings.max_connections.clamp(1, MAX_CONNECTIONS);
+            settings
+        })
+        .unwrap_or_default()
+}
+
+pub fn save(app: &AppHandle, settings: &Settings) -> Result<(), String> {
+    let path = settings_path(app)?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let raw = serde_json::to_string_pretty(settings).map_err(|e| e.to_string())?;
+    std::fs::write(path, raw).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_settings(state: State<'_, AppState>) -> Settings {
+    state.settings.lock().unwrap().clone()
+}
+
+#[tauri::command]
+pub fn update_settings(settings: Settings, app: AppHandle, state: State<'_, AppState>) -> Result<Settings, AppError> {
+    if settings.max_connections == 0 || settings.max_connections > MAX_CONNECTIONS {
+        return Err(AppError::new(ErrorKind::InvalidInput, format!("max_connections must be between 1 and {}", MAX_CONNECTIONS)));
+    }
+    if settings.max_listing_items < 1000 {
+        return Err(AppError::new(ErrorKind::InvalidInput, "max_listing_items must be at least 1000"));
+    }
+    if settings.multipart_cleanup.older_than_days == 0 || settings.multipart_cleanup.interval_hours == 0 {
+        return Err(AppError::new(ErrorKind::InvalidInput, "Multipart cleanup age and interval must be at least 1"));
+    }
+    if settings.timeouts.connect_secs > 600 || settings.timeouts.read_secs > 3600 {
+        return Err(AppError::new(ErrorKind::InvalidInput, "Timeouts are limited to 10 minutes to connect and an hour to read"));
+    }
+    network::proxy_config(&settings.proxy)?;
+
+    save(&app, &settings)?;
+    state.apply_settings(settings.clone());
+
+    Ok(settings)
+}
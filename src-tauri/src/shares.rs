@@ -0,0 +1,133 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+
+use crate::error::{AppError, ErrorKind};
+use crate::jobs;
+
+// R2 can't enumerate outstanding presigned URLs, so every link handed out is remembered in
+// shares.json under the app data dir
+static SHARES_LOCK: Mutex<()> = Mutex::new(());
+static SEQUENCE: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Share {
+    pub id: String,
+    pub bucket: String,
+    pub key: String,
+    pub url: String,
+    pub method: String,
+    pub created_at: i64,
+    /// Unset for permanent public links.
+    pub expires_at: Option<i64>,
+    /// Past its expiry, or marked expired by hand (e.g. after rotating the key that signed it).
+    #[serde(default)]
+    pub expired: bool,
+}
+
+fn shares_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    Ok(dir.join("shares.json"))
+}
+
+fn load(app: &AppHandle) -> Vec<Share> {
+    shares_path(app)
+        .ok()
+        .and_then(|p| std::fs::read_to_string(p).ok())
+        .and_then(|raw| serde_json::from_str(&raw).ok())
+        .unwrap_or_default()
+}
+
+fn save(app: &AppHandle, shares: &[Share]) -> Result<(), String> {
+    let path = shares_path(app)?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let raw = serde_json::to_string_pretty(shares).map_err(|e| e.to_string())?;
+    std::fs::write(path, raw).map_err(|e| e.to_string())
+}
+
+/// Loads the shares with `expired` brought up to date.
+fn load_current(app: &AppHandle) -> Vec<Share> {
+    let now = jobs::now_secs();
+    let mut shares = load(app);
+    for share in &mut shares {
+        share.expired |= share.expires_at.is_some_and(|t| t <= now);
+    }
+    shares
+}
+
+/// A link being handed out: key, URL, method and expiry.
+pub type NewShare<'a> = (&'a str, &'a str, &'a str, Option<i64>);
+
+/// Remembers links that were handed out. Best effort; never fails the links themselves.
+pub fn record_all<'a>(app: &AppHandle, bucket: &str, links: impl IntoIterator<Item = NewShare<'a>>) {
+    let _guard = SHARES_LOCK.lock().unwrap();
+    let created_at = jobs::now_secs();
+    let mut shares = load(app);
+    for (key, url, method, expires_at) in links {
+        shares.push(Share {
+            id: format!("{}-{}", created_at, SEQUENCE.fetch_add(1, Ordering::Relaxed)),
+            bucket: bucket.to_string(),
+            key: key.to_string(),
+            url: url.to_string(),
+            method: method.to_string(),
+            created_at,
+            expires_at,
+            expired: false,
+        });
+    }
+    let _ = save(app, &shares);
+}
+
+pub fn record(app: &AppHandle, bucket: &str, key: &str, url: &str, method: &str, expires_at: Option<i64>) {
+    record_all(app, bucket, [(key, url, method, expires_at)]);
+}
+
+/// Recorded links, newest first. Expired ones are included only when asked for.
+#[tauri::command]
+pub fn list_shares(include_expired: Option<bool>, app: AppHandle) -> Vec<Share> {
+    let _guard = SHARES_LOCK.lock().unwrap();
+    let include_expired = include_expired.unwrap_or(false);
+    load_current(&app).into_iter().rev().filter(|s| include_expired || !s.expired).collect()
+}
+
+/// Marks a link as no longer valid before its expiry.
+#[tauri::command]
+pub fn expire_share(id: String, app: AppHandle) -> Result<Share, AppError> {
+    let _guard = SHARES_LOCK.lock().unwrap();
+    let mut shares = load_current(&app);
+    let share = shares.iter_mut().find(|s| s.id == id)
+        .ok_or_else(|| AppError::new(ErrorKind::NotFound, format!("No share with id '{}'", id)))?;
+    share.expired = true;
+    let share = share.clone();
+    save(&app, &shares)?;
+    Ok(share)
+}
+
+/// Forgets a link. It keeps working until it expires; this only drops it from the list.
+#[tauri::command]
+pub fn delete_share(id: String, app: AppHandle) -> Result<(), AppError> {
+    let _guard = SHARES_LOCK.lock().unwrap();
+    let mut shares = load(&app);
+    let before = shares.len();
+    shares.retain(|s| s.id != id);
+    if shares.len() == before {
+        return Err(AppError::new(ErrorKind::NotFound, format!("No share with id '{}'", id)));
+    }
+    save(&app, &shares)?;
+    Ok(())
+}
+
+/// Drops every expired link. Returns how many were removed.
+#[tauri::command]
+pub fn prune_expired_shares(app: AppHandle) -> Result<usize, AppError> {
+    let _guard = SHARES_LOCK.lock().unwrap();
+    let mut shares = load_current(&app);
+    let before = shares.len();
+    shares.retain(|s| !s.expired);
+    save(&app, &shares)?;
+    Ok(before - shares.len())
+}
@@ -0,0 +1,414 @@
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::error::{AppError, ErrorKind};
+use crate::jobs;
+use crate::launch::LaunchRequest;
+use crate::s3::{acquire_write_client, upload_path, wait_if_paused, AppState};
+use crate::sync::{normalize_prefix, SyncFailure};
+
+// "Upload to R2Drive" in the file manager's context menu. The menu entry runs the app with
+// `--upload <paths>`; a running instance receives those through single-instance forwarding (see
+// launch.rs) and uploads them to the default destination, or hands them to the UI to pick one.
+// Entries are per user: the registry under HKCU on Windows, a Quick Action on macOS, and a
+// Nautilus script plus a Dolphin service menu on Linux.
+pub const UPLOAD_FLAG: &str = "--upload";
+const MENU_LABEL: &str = "Upload to R2Drive";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ShellUploadSettings {
+    /// Where context-menu uploads go; unset asks every time.
+    pub bucket: Option<String>,
+    pub prefix: String,
+    pub connection_id: Option<String>,
+    /// Ask for the destination even when a default is set.
+    pub ask: bool,
+}
+
+/// Payload of `shell://uploaded`.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ShellUploadResult {
+    pub bucket: String,
+    pub uploaded: Vec<String>,
+    pub failed: Vec<SyncFailure>,
+}
+
+/// The command the menu entries run, without the paths.
+fn executable() -> Result<PathBuf, String> {
+    // An AppImage runs from a temporary mount; the menu has to point at the image itself
+    if let Some(image) = std::env::var_os("APPIMAGE") {
+        return Ok(PathBuf::from(image));
+    }
+    std::env::current_exe().map_err(|e| e.to_string())
+}
+
+/// Object keys for `path` under `prefix`: the file itself, or everything in a folder under the
+/// folder's name.
+pub fn collect(path: &Path, prefix: &str) -> Vec<(PathBuf, String)> {
+    let Some(name) = path.file_name().map(|n| n.to_string_lossy().to_string()) else { return Vec::new() };
+    if path.is_file() {
+        return vec![(path.to_path_buf(), format!("{}{}", prefix, name))];
+    }
+    walkdir::WalkDir::new(path)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| {
+            let relative = entry.path().strip_prefix(path).ok()?;
+            let parts: Vec<String> = relative.components().map(|c| c.as_os_str().to_string_lossy().to_string()).collect();
+            Some((entry.path().to_path_buf(), format!("{}{}/{}", prefix, name, parts.join("/"))))
+        })
+        .collect()
+}
+
+/// Uploads files and folders to `bucket`/`prefix` as one job and reports it through
+/// `shell://uploaded`.
+async fn upload(
+    app: &AppHandle,
+    paths: &[String],
+    bucket: &str,
+    prefix: &str,
+    connection_id: Option<&str>,
+) -> Result<ShellUploadResult, AppError> {
+    let prefix = normalize_prefix(prefix);
+    let mut outcome = ShellUploadResult { bucket: bucket.to_string(), ..Default::default() };
+    let job = jobs::start("shell_upload", Some(bucket), json!({ "paths": paths, "prefix": prefix }));
+
+    let state = app.state::<AppState>();
+    let result: Result<(), AppError> = async {
+        let client = acquire_write_client(&state, connection_id)?;
+        for path in paths {
+            for (file, key) in collect(Path::new(path), &prefix) {
+                wait_if_paused(&state).await;
+                match upload_path(app, &client, bucket, &key, &file).await {
+                    Ok(_) => outcome.uploaded.push(key),
+                    Err(error) => outcome.failed.push(SyncFailure { relative: file.to_string_lossy().to_string(), error }),
+                }
+            }
+        }
+        match outcome.failed.first() {
+            Some(first) => {
+                let total = outcome.failed.len() + outcome.uploaded.len();
+                let message = format!("{} of {} files failed to upload; {}: {}", outcome.failed.len(), total, first.relative, first.error);
+                Err(AppError::new(ErrorKind::Internal, message))
+            }
+            None => Ok(()),
+        }
+    }
+    .await;
+    job.finish(app, &result, |_| format!("Uploaded {} files from the context menu", outcome.uploaded.len()));
+
+    // Sent even when some files failed, so the UI can list which ones
+    if result.is_ok() || !outcome.failed.is_empty() {
+        let _ = app.emit("shell://uploaded", &outcome);
+    }
+    result?;
+    Ok(outcome)
+}
+
+/// Starts uploading the request's context-menu files when a default destination is set and
+/// leaves the rest of the request for the UI. Without a default they stay in `upload` for the
+/// UI to ask about.
+pub fn upload_to_default(app: &AppHandle, mut request: LaunchRequest) -> LaunchRequest {
+    let destination = app.state::<AppState>().settings.lock().unwrap().shell_upload.clone();
+    let Some(bucket) = destination.bucket.filter(|b| !b.is_empty() && !destination.ask) else { return request };
+    if request.upload.is_empty() {
+        return request;
+    }
+    let paths = std::mem::take(&mut request.upload);
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = upload(&app, &paths, &bucket, &destination.prefix, destination.connection_id.as_deref()).await {
+            let _ = app.emit("shell://error", e.message);
+        }
+    });
+    request
+}
+
+/// Uploads files sent from the context menu to a destination the user picked.
+#[tauri::command]
+pub async fn upload_shell_files(
+    paths: Vec<String>,
+    bucket: String,
+    prefix: Option<String>,
+    app: AppHandle,
+    connection_id: Option<String>,
+) -> Result<ShellUploadResult, AppError> {
+    if paths.is_empty() {
+        return Err(AppError::new(ErrorKind::InvalidInput, "Nothing to upload"));
+    }
+    upload(&app, &paths, &bucket, prefix.as_deref().unwrap_or_default(), connection_id.as_deref()).await
+}
+
+#[cfg(windows)]
+mod platform {
+    use std::path::Path;
+    use winreg::enums::HKEY_CURRENT_USER;
+    use winreg::RegKey;
+
+    use super::{MENU_LABEL, UPLOAD_FLAG};
+
+    // Files and folders; Explorer runs the command once per selected item
+    const KEYS: &[&str] = &[r"Software\Classes\*\shell\R2Drive", r"Software\Classes\Directory\shell\R2Drive"];
+
+    pub fn install(_home: &Path, exe: &Path) -> Result<(), String> {
+        let hkcu = RegKey::predef(HKEY_CURRENT_USER);
+        for path in KEYS {
+            let (key, _) = hkcu.create_subkey(path).map_err(|e| e.to_string())?;
+            key.set_value("", &MENU_LABEL).map_err(|e| e.to_string())?;
+            key.set_value("Icon", &exe.display().to_string()).map_err(|e| e.to_string())?;
+            let (command, _) = key.create_subkey("command").map_err(|e| e.to_string())?;
+            command.set_value("", &format!("\"{}\" {} \"%1\"", exe.display(), UPLOAD_FLAG)).map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    }
+
+    pub fn uninstall(_home: &Path) -> Result<(), String> {
+        let hkcu = RegKey::predef(HKEY_CURRENT_USER);
+        for path in KEYS {
+            match hkcu.delete_subkey_all(path) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.to_string()),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    pub fn installed(_home: &Path) -> bool {
+        RegKey::predef(HKEY_CURRENT_USER).open_subkey(KEYS[0]).is_ok()
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use std::path::{Path, PathBuf};
+
+    use super::{MENU_LABEL, UPLOAD_FLAG};
+
+    // A Finder Quick Action: an Automator workflow with a single "Run Shell Script" action that
+    // receives the selected files as arguments
+    fn workflow(home: &Path) -> PathBuf {
+        home.join("Library/Services").join(format!("{}.workflow", MENU_LABEL))
+    }
+
+    fn escape(text: &str) -> String {
+        text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+    }
+
+    const INFO_PLIST: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+	<key>NSServices</key>
+	<array>
+		<dict>
+			<key>NSMenuItem</key>
+			<dict>
+				<key>default</key>
+				<string>LABEL</string>
+			</dict>
+			<key>NSMessage</key>
+			<string>runWorkflowAsService</string>
+			<key>NSRequiredContext</key>
+			<dict>
+				<key>NSApplicationIdentifier</key>
+				<string>com.apple.finder</string>
+			</dict>
+			<key>NSSendFileTypes</key>
+			<array>
+				<string>public.item</string>
+			</array>
+		</dict>
+	</array>
+</dict>
+</plist>
+"#;
+
+    const DOCUMENT: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+	<key>AMApplicationBuild</key>
+	<string>523</string>
+	<key>AMApplicationVersion</key>
+	<string>2.10</string>
+	<key>AMDocumentVersion</key>
+	<string>2</string>
+	<key>actions</key>
+	<array>
+		<dict>
+			<key>action</key>
+			<dict>
+				<key>AMAccepts</key>
+				<dict>
+					<key>Container</key>
+					<string>List</string>
+					<key>Optional</key>
+					<true/>
+					<key>Types</key>
+					<array>
+						<string>com.apple.cocoa.path</string>
+					</array>
+				</dict>
+				<key>AMActionVersion</key>
+				<string>2.0.3</string>
+				<key>AMApplication</key>
+				<array>
+					<string>Automator</string>
+				</array>
+				<key>AMBundleIdentifier</key>
+				<string>com.apple.RunShellScript</string>
+				<key>AMProvides</key>
+				<dict>
+					<key>Container</key>
+					<string>List</string>
+					<key>Types</key>
+					<array>
+						<string>com.apple.cocoa.string</string>
+					</array>
+				</dict>
+				<key>ActionBundlePath</key>
+				<string>/System/Library/Automator/Run Shell Script.action</string>
+				<key>ActionName</key>
+				<string>Run Shell Script</string>
+				<key>ActionParameters</key>
+				<dict>
+					<key>COMMAND_STRING</key>
+					<string>COMMAND</string>
+					<key>CheckedForUserDefaultShell</key>
+					<true/>
+					<key>inputMethod</key>
+					<integer>1</integer>
+					<key>shell</key>
+					<string>/bin/sh</string>
+					<key>source</key>
+					<string></string>
+				</dict>
+				<key>BundleIdentifier</key>
+				<string>com.apple.RunShellScript</string>
+				<key>CFBundleVersion</key>
+				<string>2.0.3</string>
+				<key>Class Name</key>
+				<string>RunShellScriptAction</string>
+				<key>InputUUID</key>
+				<string>0B7A5E4E-8E0F-4D43-9C5A-6F1D2C3B4A01</string>
+				<key>OutputUUID</key>
+				<string>0B7A5E4E-8E0F-4D43-9C5A-6F1D2C3B4A02</string>
+				<key>UUID</key>
+				<string>0B7A5E4E-8E0F-4D43-9C5A-6F1D2C3B4A03</string>
+			</dict>
+			<key>isViewVisible</key>
+			<true/>
+		</dict>
+	</array>
+	<key>workflowMetaData</key>
+	<dict>
+		<key>serviceInputTypeIdentifier</key>
+		<string>com.apple.Automator.fileSystemObject</string>
+		<key>serviceProcessesInput</key>
+		<integer>0</integer>
+		<key>workflowTypeIdentifier</key>
+		<string>com.apple.Automator.servicesMenu</string>
+	</dict>
+</dict>
+</plist>
+"#;
+
+    pub fn install(home: &Path, exe: &Path) -> Result<(), String> {
+        let contents = workflow(home).join("Contents");
+        std::fs::create_dir_all(&contents).map_err(|e| e.to_string())?;
+        // The files arrive as arguments
+        let command = format!("'{}' {} \"$@\"", exe.display().to_string().replace('\'', r"'\''"), UPLOAD_FLAG);
+        std::fs::write(contents.join("Info.plist"), INFO_PLIST.replace("LABEL", &escape(MENU_LABEL))).map_err(|e| e.to_string())?;
+        std::fs::write(contents.join("document.wflow"), DOCUMENT.replace("COMMAND", &escape(&command))).map_err(|e| e.to_string())
+    }
+
+    pub fn uninstall(home: &Path) -> Result<(), String> {
+        match std::fs::remove_dir_all(workflow(home)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.to_string()),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn installed(home: &Path) -> bool {
+        workflow(home).exists()
+    }
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+mod platform {
+    use std::os::unix::fs::PermissionsExt;
+    use std::path::{Path, PathBuf};
+
+    use super::{MENU_LABEL, UPLOAD_FLAG};
+
+    fn nautilus_script(home: &Path) -> PathBuf {
+        home.join(".local/share/nautilus/scripts").join(MENU_LABEL)
+    }
+
+    fn service_menu(home: &Path) -> PathBuf {
+        home.join(".local/share/kio/servicemenus/r2drive-upload.desktop")
+    }
+
+    /// Writes an executable file; both Nautilus and Dolphin ignore entries that aren't.
+    fn write_executable(path: &Path, contents: &str) -> Result<(), String> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        std::fs::write(path, contents).map_err(|e| e.to_string())?;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755)).map_err(|e| e.to_string())
+    }
+
+    pub fn install(home: &Path, exe: &Path) -> Result<(), String> {
+        let quoted = format!("'{}'", exe.display().to_string().replace('\'', r"'\''"));
+        // Nautilus passes the selection as arguments
+        write_executable(&nautilus_script(home), &format!("#!/bin/sh\nexec {} {} \"$@\"\n", quoted, UPLOAD_FLAG))?;
+        let desktop = format!(
+            "[Desktop Entry]\nType=Service\nMimeType=all/all;\nActions=upload\nX-KDE-ServiceTypes=KonqPopupMenu/Plugin\n\n\
+             [Desktop Action upload]\nName={}\nIcon=r2-drive\nExec={} {} %F\n",
+            MENU_LABEL, quoted, UPLOAD_FLAG
+        );
+        write_executable(&service_menu(home), &desktop)
+    }
+
+    pub fn uninstall(home: &Path) -> Result<(), String> {
+        for path in [nautilus_script(home), service_menu(home)] {
+            match std::fs::remove_file(path) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.to_string()),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    pub fn installed(home: &Path) -> bool {
+        nautilus_script(home).exists() || service_menu(home).exists()
+    }
+}
+
+fn home(app: &AppHandle) -> Result<PathBuf, AppError> {
+    app.path().home_dir().map_err(|e| AppError::new(ErrorKind::Internal, e.to_string()))
+}
+
+/// Whether the "Upload to R2Drive" entry is in the file manager's context menu.
+#[tauri::command]
+pub fn shell_integration_installed(app: AppHandle) -> Result<bool, AppError> {
+    Ok(platform::installed(&home(&app)?))
+}
+
+/// Adds the context-menu entry for the current user, pointing at this executable. Run it again
+/// after moving the app.
+#[tauri::command]
+pub fn install_shell_integration(app: AppHandle) -> Result<(), AppError> {
+    platform::install(&home(&app)?, &executable()?)?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn uninstall_shell_integration(app: AppHandle) -> Result<(), AppError> {
+    platform::uninstall(&home(&app)?)?;
+    Ok(())
+}
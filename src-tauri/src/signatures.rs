@@ -0,0 +1,284 @@
+use pgp::types::PublicKeyTrait;
+use pgp::{Deserializable, Signature, SignedPublicKey, StandaloneSignature};
+use serde::{Deserialize, Serialize};
+use std::io::{BufReader, Read};
+use std::path::Path;
+use tauri::{AppHandle, Emitter, State};
+
+use crate::error::{AppError, ErrorKind};
+use crate::s3::{acquire_client, AppState};
+use crate::settings;
+
+// Detached signatures published next to the files they sign: `<key>.sig` (binary OpenPGP or
+// minisign), `<key>.asc` (armored OpenPGP) or `<key>.minisig`. A signed age file is
+// `<name>.age.sig`. Signatures are checked against public keys the user trusts, kept in settings.
+const SIGNATURE_SUFFIXES: &[&str] = &[".sig", ".asc", ".minisig"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyKind {
+    Openpgp,
+    Minisign,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrustedKey {
+    pub name: String,
+    pub kind: KeyKind,
+    /// OpenPGP fingerprint, or the minisign public key itself.
+    pub fingerprint: String,
+    /// The armored OpenPGP key, or the minisign public key (base64 or its two-line file).
+    pub key: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SignatureSettings {
+    /// Check `download_file` results against a sibling signature when there is one. A bad
+    /// signature, or one no trusted key made, fails the download and removes the file.
+    pub verify_downloads: bool,
+    pub trusted_keys: Vec<TrustedKey>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SignatureStatus {
+    Valid,
+    /// Made by a trusted key but doesn't match the file.
+    Invalid,
+    /// No signature object next to the file.
+    Unsigned,
+    /// Not made by any trusted key, or unreadable.
+    UnknownKey,
+}
+
+/// Payload of `signature://checked`, and result of `verify_signature`.
+#[derive(Debug, Clone, Serialize)]
+pub struct SignatureCheck {
+    pub bucket: String,
+    pub key: String,
+    pub status: SignatureStatus,
+    pub signature_key: Option<String>,
+    /// Name of the trusted key that made the signature.
+    pub signer: Option<String>,
+    pub detail: Option<String>,
+}
+
+fn fingerprint_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02X}", b)).collect()
+}
+
+fn parse_openpgp(key: &str) -> Result<SignedPublicKey, String> {
+    let (key, _) = SignedPublicKey::from_string(key).map_err(|e| format!("Not an OpenPGP public key: {}", e))?;
+    key.verify().map_err(|e| format!("OpenPGP key failed its self-check: {}", e))?;
+    Ok(key)
+}
+
+fn parse_minisign(key: &str) -> Result<minisign_verify::PublicKey, String> {
+    let key = key.trim();
+    let parsed = if key.contains('\n') { minisign_verify::PublicKey::decode(key) } else { minisign_verify::PublicKey::from_base64(key) };
+    parsed.map_err(|e| format!("Not a minisign public key: {}", e))
+}
+
+/// Recognizes an armored OpenPGP key or a minisign public key and returns it ready to trust.
+fn trusted_key(name: String, key: String) -> Result<TrustedKey, String> {
+    if key.contains("BEGIN PGP PUBLIC KEY BLOCK") {
+        let fingerprint = fingerprint_hex(parse_openpgp(&key)?.fingerprint().as_bytes());
+        return Ok(TrustedKey { name, kind: KeyKind::Openpgp, fingerprint, key });
+    }
+    parse_minisign(&key)?;
+    let fingerprint = key.trim().lines().last().unwrap_or_default().trim().to_string();
+    Ok(TrustedKey { name, kind: KeyKind::Minisign, fingerprint, key })
+}
+
+enum Verdict {
+    Valid { signer: String },
+    Invalid(String),
+    UnknownKey(Option<String>),
+}
+
+fn hash_file_into(path: &Path, mut update: impl FnMut(&[u8])) -> Result<(), String> {
+    let mut file = BufReader::new(std::fs::File::open(path).map_err(|e| e.to_string())?);
+    let mut buf = vec![0u8; 1024 * 1024];
+    loop {
+        let n = file.read(&mut buf).map_err(|e| e.to_string())?;
+        if n == 0 {
+            return Ok(());
+        }
+        update(&buf[..n]);
+    }
+}
+
+/// Checks an OpenPGP signature with one key, or returns None if the signature names another.
+fn verify_with(signature: &Signature, key: &impl PublicKeyTrait, path: &Path) -> Result<Option<Result<(), String>>, String> {
+    let issuers = signature.issuer();
+    if !issuers.is_empty() && !issuers.contains(&&key.key_id()) {
+        return Ok(None);
+    }
+    let file = std::fs::File::open(path).map(BufReader::new).map_err(|e| e.to_string())?;
+    Ok(Some(signature.verify(key, file).map_err(|e| e.to_string())))
+}
+
+/// Checks `path` against a detached signature with the trusted keys of the matching kind.
+fn verify_file(path: &Path, signature: &[u8], keys: &[TrustedKey]) -> Result<Verdict, String> {
+    let text = std::str::from_utf8(signature).ok();
+    if let Some(signature) = text.filter(|t| t.starts_with("untrusted comment:")) {
+        let signature = match minisign_verify::Signature::decode(signature) {
+            Ok(signature) => signature,
+            Err(e) => return Ok(Verdict::UnknownKey(Some(format!("Unreadable minisign signature: {}", e)))),
+        };
+        for trusted in keys.iter().filter(|k| k.kind == KeyKind::Minisign) {
+            let public = parse_minisign(&trusted.key)?;
+            // Fails up front when the signature names another key
+            let Ok(mut verifier) = public.verify_stream(&signature) else { continue };
+            hash_file_into(path, |chunk| verifier.update(chunk))?;
+            return Ok(match verifier.finalize() {
+                Ok(()) => Verdict::Valid { signer: trusted.name.clone() },
+                Err(e) => Verdict::Invalid(e.to_string()),
+            });
+        }
+        return Ok(Verdict::UnknownKey(None));
+    }
+
+    let signature = match text.filter(|t| t.contains("BEGIN PGP SIGNATURE")) {
+        Some(armored) => StandaloneSignature::from_string(armored).map(|(s, _)| s),
+        None => StandaloneSignature::from_bytes(signature),
+    };
+    let signature = match signature {
+        Ok(signature) => signature.signature,
+        Err(e) => return Ok(Verdict::UnknownKey(Some(format!("Unreadable OpenPGP signature: {}", e)))),
+    };
+    for trusted in keys.iter().filter(|k| k.kind == KeyKind::Openpgp) {
+        let public = parse_openpgp(&trusted.key)?;
+        // The signature may come from the primary key or any signing subkey
+        let outcome = match verify_with(&signature, &public.primary_key, path)? {
+            Some(outcome) => Some(outcome),
+            None => public.public_subkeys.iter().find_map(|s| verify_with(&signature, &s.key, path).transpose()).transpose()?,
+        };
+        if let Some(outcome) = outcome {
+            return Ok(match outcome {
+                Ok(()) => Verdict::Valid { signer: trusted.name.clone() },
+                Err(e) => Verdict::Invalid(e),
+            });
+        }
+    }
+    Ok(Verdict::UnknownKey(None))
+}
+
+/// Looks for a signature next to `key` and checks the local copy at `path` against it.
+pub async fn check(state: &AppState, connection_id: Option<&str>, bucket: &str, key: &str, path: &Path) -> Result<SignatureCheck, AppError> {
+    let client = acquire_client(state, connection_id)?;
+    let mut check = SignatureCheck {
+        bucket: bucket.to_string(),
+        key: key.to_string(),
+        status: SignatureStatus::Unsigned,
+        signature_key: None,
+        signer: None,
+        detail: None,
+    };
+
+    let mut signature = None;
+    for suffix in SIGNATURE_SUFFIXES {
+        let signature_key = format!("{}{}", key, suffix);
+        match client.get_object().bucket(bucket).key(&signature_key).send().await {
+            Ok(resp) => {
+                signature = Some(resp.body.collect().await.map_err(|e| e.to_string())?.into_bytes());
+                check.signature_key = Some(signature_key);
+                break;
+            }
+            Err(e) => {
+                let e = AppError::from(e);
+                if e.kind != ErrorKind::NotFound {
+                    return Err(e);
+                }
+            }
+        }
+    }
+    let Some(signature) = signature else { return Ok(check) };
+
+    let keys = state.settings.lock().unwrap().signatures.trusted_keys.clone();
+    let path = path.to_path_buf();
+    let verdict = tauri::async_runtime::spawn_blocking(move || verify_file(&path, &signature, &keys))
+        .await
+        .map_err(|e| e.to_string())??;
+    match verdict {
+        Verdict::Valid { signer } => {
+            check.status = SignatureStatus::Valid;
+            check.signer = Some(signer);
+        }
+        Verdict::Invalid(detail) => {
+            check.status = SignatureStatus::Invalid;
+            check.detail = Some(detail);
+        }
+        Verdict::UnknownKey(detail) => {
+            check.status = SignatureStatus::UnknownKey;
+            check.detail = detail;
+        }
+    }
+    Ok(check)
+}
+
+/// Runs after `download_file` when signature checks are on: emits `signature://checked` and
+/// fails, removing the file, if the signature doesn't match or wasn't made by a trusted key.
+pub async fn check_download(app: &AppHandle, state: &AppState, connection_id: Option<&str>, bucket: &str, key: &str, path: &Path) -> Result<(), AppError> {
+    if !state.settings.lock().unwrap().signatures.verify_downloads {
+        return Ok(());
+    }
+    let check = check(state, connection_id, bucket, key, path).await?;
+    let _ = app.emit("signature://checked", &check);
+    let reason = match check.status {
+        SignatureStatus::Valid | SignatureStatus::Unsigned => return Ok(()),
+        SignatureStatus::Invalid => check.detail.unwrap_or_default(),
+        SignatureStatus::UnknownKey => match check.detail {
+            Some(detail) => format!("not signed by a trusted key ({})", detail),
+            None => "not signed by a trusted key".to_string(),
+        },
+    };
+    let _ = std::fs::remove_file(path);
+    Err(AppError::new(ErrorKind::PreconditionFailed, format!("Signature check failed for {}: {}", key, reason)))
+}
+
+/// Checks an already downloaded copy of `key` at `path` against its sibling signature.
+#[tauri::command]
+pub async fn verify_signature(
+    bucket: String,
+    key: String,
+    path: String,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<SignatureCheck, AppError> {
+    check(&state, connection_id.as_deref(), &bucket, &key, Path::new(&path)).await
+}
+
+/// Trusts a public key: an armored OpenPGP key or a minisign public key.
+#[tauri::command]
+pub fn add_trusted_key(name: String, key: String, app: AppHandle, state: State<'_, AppState>) -> Result<TrustedKey, AppError> {
+    if name.trim().is_empty() {
+        return Err(AppError::new(ErrorKind::InvalidInput, "Key name cannot be empty"));
+    }
+    let trusted = trusted_key(name, key).map_err(|e| AppError::new(ErrorKind::InvalidInput, e))?;
+
+    let mut settings = state.settings.lock().unwrap().clone();
+    if settings.signatures.trusted_keys.iter().any(|k| k.fingerprint == trusted.fingerprint) {
+        return Err(AppError::new(ErrorKind::AlreadyExists, "This key is already trusted"));
+    }
+    settings.signatures.trusted_keys.push(trusted.clone());
+    settings::save(&app, &settings)?;
+    state.apply_settings(settings);
+
+    Ok(trusted)
+}
+
+#[tauri::command]
+pub fn remove_trusted_key(fingerprint: String, app: AppHandle, state: State<'_, AppState>) -> Result<(), AppError> {
+    let mut settings = state.settings.lock().unwrap().clone();
+    let before = settings.signatures.trusted_keys.len();
+    settings.signatures.trusted_keys.retain(|k| k.fingerprint != fingerprint);
+    if settings.signatures.trusted_keys.len() == before {
+        return Err(AppError::new(ErrorKind::NotFound, "No trusted key with that fingerprint"));
+    }
+    settings::save(&app, &settings)?;
+    state.apply_settings(settings);
+
+    Ok(())
+}
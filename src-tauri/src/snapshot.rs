@@ -0,0 +1,546 @@
+use aws_sdk_s3::primitives::ByteStream;
+use aws_sdk_s3::types::{Delete, ObjectIdentifier};
+use aws_sdk_s3::Client;
+use chrono::{Local, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter, Manager, State};
+use tokio::task::JoinSet;
+use urlencoding::encode;
+
+use crate::error::{AppError, ErrorKind};
+use crate::jobs;
+use crate::replicate;
+use crate::s3::{self, acquire_client, acquire_write_client, download_to_path, is_folder_marker, list_all_objects, AppState};
+use crate::sync::{self, FileState, SyncFailure};
+
+// A snapshot is a full copy of a local folder under `<base>/<time>/`, with a manifest at
+// `<base>/<time>.manifest.json` recording each file's size and modification time. Files the
+// previous snapshot already holds unchanged are copied server-side from it instead of uploaded.
+// Restores go back to a local folder or to another prefix.
+const NAME_FORMAT: &str = "%Y-%m-%dT%H:%M";
+const MANIFEST_SUFFIX: &str = ".manifest.json";
+const TRANSFER_CONCURRENCY: usize = 8;
+// Largest single CopyObject; bigger unchanged files are uploaded again
+const MAX_COPY_SIZE: u64 = 5 * 1024 * 1024 * 1024;
+
+fn default_base_prefix() -> String {
+    "backups/".to_string()
+}
+
+fn default_reuse_previous() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotSpec {
+    pub local_root: String,
+    pub bucket: String,
+    #[serde(default = "default_base_prefix")]
+    pub base_prefix: String,
+    /// Copy unchanged files from the previous snapshot server-side.
+    #[serde(default = "default_reuse_previous")]
+    pub reuse_previous: bool,
+    /// Gitignore-style patterns, on top of the root's `.r2ignore`.
+    #[serde(default)]
+    pub ignore: Vec<String>,
+    /// Prune to this many snapshots after a successful one.
+    #[serde(default)]
+    pub keep: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotManifest {
+    pub local_root: String,
+    pub created_at: i64,
+    /// Every file in the snapshot by relative path. Files that failed are left out.
+    pub files: BTreeMap<String, FileState>,
+    /// Nothing failed.
+    pub complete: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SnapshotInfo {
+    pub name: String,
+    pub prefix: String,
+    /// From the manifest; unset for a snapshot without one.
+    pub created_at: Option<i64>,
+    pub files: Option<usize>,
+    pub bytes: Option<u64>,
+    pub complete: Option<bool>,
+}
+
+/// Payload of `snapshot://progress`.
+#[derive(Debug, Clone, Serialize)]
+pub struct SnapshotProgress {
+    pub bucket: String,
+    pub name: String,
+    pub done: usize,
+    pub total: usize,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SnapshotResult {
+    pub name: String,
+    pub prefix: String,
+    pub uploaded: usize,
+    /// Copied from the previous snapshot.
+    pub copied: usize,
+    /// Bytes uploaded; copies don't count.
+    pub bytes: u64,
+    pub failed: Vec<SyncFailure>,
+    /// Snapshots removed by retention afterwards.
+    pub pruned: Vec<String>,
+}
+
+/// Where to restore a snapshot to.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RestoreTarget {
+    Local { path: String },
+    /// Copied server-side; both buckets must be reachable with the same credentials.
+    Prefix { bucket: String, prefix: String },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RestoreSize {
+    pub files: usize,
+    pub bytes: u64,
+}
+
+/// Payload of `restore://progress`, after each file.
+#[derive(Debug, Clone, Serialize)]
+pub struct RestoreProgress {
+    pub name: String,
+    pub done: usize,
+    pub total: usize,
+    pub bytes: u64,
+    pub current: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RestoreResult {
+    pub restored: usize,
+    /// Already present at the target, with `skip_existing`.
+    pub skipped: usize,
+    pub bytes: u64,
+    pub failed: Vec<SyncFailure>,
+}
+
+pub fn manifest_key(base: &str, name: &str) -> String {
+    format!("{}{}{}", base, name, MANIFEST_SUFFIX)
+}
+
+/// Snapshot names under `base`, oldest first. Folders not named like a snapshot are left out.
+pub async fn snapshot_names(client: &Client, bucket: &str, base: &str) -> Result<Vec<String>, AppError> {
+    let mut names = Vec::new();
+    let mut continuation_token = None;
+    loop {
+        let resp = client.list_objects_v2()
+            .bucket(bucket)
+            .prefix(base)
+            .delimiter("/")
+            .set_continuation_token(continuation_token)
+            .send()
+            .await?;
+        for prefix in resp.common_prefixes().iter().filter_map(|p| p.prefix()) {
+            let name = prefix.strip_prefix(base).unwrap_or(prefix).trim_end_matches('/');
+            if NaiveDateTime::parse_from_str(name, NAME_FORMAT).is_ok() {
+                names.push(name.to_string());
+            }
+        }
+        if resp.is_truncated().unwrap_or(false) {
+            continuation_token = resp.next_continuation_token;
+        } else {
+            break;
+        }
+    }
+    names.sort();
+    Ok(names)
+}
+
+pub async fn load_manifest(client: &Client, bucket: &str, base: &str, name: &str) -> Result<Option<SnapshotManifest>, AppError> {
+    let resp = match client.get_object().bucket(bucket).key(manifest_key(base, name)).send().await {
+        Ok(resp) => resp,
+        Err(e) if e.as_service_error().is_some_and(|se| se.is_no_such_key()) => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let data = resp.body.collect().await.map_err(|e| e.to_string())?.into_bytes();
+    // An unreadable manifest only costs the reuse of unchanged files
+    Ok(serde_json::from_slice(&data).ok())
+}
+
+async fn delete_keys(client: &Client, bucket: &str, keys: Vec<String>) -> Result<(), AppError> {
+    let ids: Vec<ObjectIdentifier> = keys.into_iter().map(|k| ObjectIdentifier::builder().key(k).build().unwrap()).collect();
+    for chunk in ids.chunks(1000) {
+        let delete = Delete::builder().set_objects(Some(chunk.to_vec())).build().unwrap();
+        client.delete_objects().bucket(bucket).delete(delete).send().await?;
+    }
+    Ok(())
+}
+
+/// Deletes all but the newest `keep` snapshots under `base`, with their manifests. Returns the
+/// names removed.
+pub async fn prune(client: &Client, bucket: &str, base: &str, keep: usize) -> Result<Vec<String>, AppError> {
+    let names = snapshot_names(client, bucket, base).await?;
+    let excess = names.len().saturating_sub(keep.max(1));
+    let pruned: Vec<String> = names.into_iter().take(excess).collect();
+    for name in &pruned {
+        let prefix = format!("{}{}/", base, name);
+        let mut keys: Vec<String> = list_all_objects(client, bucket, Some(&prefix))
+            .await?
+            .iter()
+            .filter_map(|o| o.key().map(str::to_string))
+            .collect();
+        keys.push(manifest_key(base, name));
+        delete_keys(client, bucket, keys).await?;
+    }
+    Ok(pruned)
+}
+
+enum Transfer {
+    Upload,
+    /// Copy from this key of the previous snapshot.
+    Copy(String),
+}
+
+async fn transfer(app: &AppHandle, client: &Client, bucket: &str, key: &str, path: &Path, how: &Transfer) -> Result<Option<String>, String> {
+    match how {
+        Transfer::Upload => s3::upload_path(app, client, bucket, key, path).await,
+        Transfer::Copy(source) => client.copy_object()
+            .bucket(bucket)
+            .key(key)
+            .copy_source(format!("{}/{}", bucket, encode(source)))
+            .send()
+            .await
+            .map(|r| r.copy_object_result().and_then(|c| c.e_tag()).map(str::to_string))
+            .map_err(|e| AppError::from(e).message),
+    }
+}
+
+/// Uploads `spec.local_root` as a new snapshot named after the current minute, then prunes if
+/// `spec.keep` is set. Emits `snapshot://progress`.
+pub async fn create(app: &AppHandle, client: &Client, spec: &SnapshotSpec) -> Result<SnapshotResult, AppError> {
+    sync::check_plain_names(app)?;
+    let base = sync::normalize_prefix(&spec.base_prefix);
+    let name = Local::now().format(NAME_FORMAT).to_string();
+    let prefix = format!("{}{}/", base, name);
+    let names = snapshot_names(client, &spec.bucket, &base).await?;
+    if names.contains(&name) {
+        return Err(AppError::new(ErrorKind::AlreadyExists, format!("Snapshot {} already exists", name)));
+    }
+
+    let root = PathBuf::from(&spec.local_root);
+    let rules = sync::ignore_rules(&root, &spec.ignore).map_err(|e| AppError::new(ErrorKind::InvalidInput, e))?;
+    let scan_root = root.clone();
+    let local = tauri::async_runtime::spawn_blocking(move || sync::scan_local(&scan_root, &rules))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| AppError::new(ErrorKind::InvalidInput, e))?;
+
+    let previous = match names.last() {
+        Some(last) if spec.reuse_previous => load_manifest(client, &spec.bucket, &base, last).await?.map(|m| (last.clone(), m)),
+        _ => None,
+    };
+    let files: Arc<Vec<(String, FileState, Transfer)>> = Arc::new(
+        local
+            .into_iter()
+            .map(|(relative, state)| {
+                let unchanged = previous.as_ref().filter(|(_, manifest)| {
+                    manifest.files.get(&relative).is_some_and(|old| old.size == state.size && old.modified == state.modified)
+                });
+                let how = match unchanged {
+                    Some((old, _)) if state.size <= MAX_COPY_SIZE => Transfer::Copy(format!("{}{}/{}", base, old, relative)),
+                    _ => Transfer::Upload,
+                };
+                (relative, state, how)
+            })
+            .collect(),
+    );
+
+    let mut result = SnapshotResult { name: name.clone(), prefix: prefix.clone(), ..Default::default() };
+    let mut manifest = SnapshotManifest { local_root: spec.local_root.clone(), created_at: jobs::now_secs(), files: BTreeMap::new(), complete: true };
+    let mut progress = SnapshotProgress { bucket: spec.bucket.clone(), name: name.clone(), done: 0, total: files.len(), bytes: 0 };
+    let _ = app.emit("snapshot://progress", &progress);
+    // Each transfer's requests take their own limiter permits; no more transfers run than it allows
+    let concurrency = s3::concurrency(&app.state::<AppState>(), TRANSFER_CONCURRENCY);
+
+    let mut finish = |progress: &mut SnapshotProgress, index: usize, outcome: Result<Option<String>, String>| {
+        let (relative, state, how) = &files[index];
+        match outcome {
+            Ok(etag) => {
+                if let Transfer::Upload = how {
+                    result.uploaded += 1;
+                    result.bytes += state.size;
+                    progress.bytes += state.size;
+                } else {
+                    result.copied += 1;
+                }
+                manifest.files.insert(relative.clone(), FileState { etag, ..state.clone() });
+            }
+            Err(error) => result.failed.push(SyncFailure { relative: relative.clone(), error }),
+        }
+        progress.done += 1;
+        let _ = app.emit("snapshot://progress", &*progress);
+    };
+
+    let mut tasks: JoinSet<(usize, Result<Option<String>, String>)> = JoinSet::new();
+    for index in 0..files.len() {
+        while tasks.len() >= concurrency {
+            if let Some(Ok((index, outcome))) = tasks.join_next().await {
+                finish(&mut progress, index, outcome);
+            }
+        }
+        let (app, client, files, bucket, root, prefix) = (app.clone(), client.clone(), files.clone(), spec.bucket.clone(), root.clone(), prefix.clone());
+        tasks.spawn(async move {
+            let (relative, _, how) = &files[index];
+            let outcome = match sync::local_path(&root, relative) {
+                Some(path) => transfer(&app, &client, &bucket, &format!("{}{}", prefix, relative), &path, how).await,
+                None => Err("Not a valid local path".to_string()),
+            };
+            (index, outcome)
+        });
+    }
+    while let Some(joined) = tasks.join_next().await {
+        if let Ok((index, outcome)) = joined {
+            finish(&mut progress, index, outcome);
+        }
+    }
+
+    manifest.complete = result.failed.is_empty();
+    let body = serde_json::to_vec_pretty(&manifest).map_err(|e| e.to_string())?;
+    client.put_object()
+        .bucket(&spec.bucket)
+        .key(manifest_key(&base, &name))
+        .content_type("application/json")
+        .body(ByteStream::from(body))
+        .send()
+        .await?;
+
+    // Never prune on the back of a snapshot that didn't fully make it
+    if let Some(keep) = spec.keep.filter(|_| manifest.complete) {
+        result.pruned = prune(client, &spec.bucket, &base, keep).await?;
+    }
+    Ok(result)
+}
+
+pub fn summary(result: &SnapshotResult) -> String {
+    format!(
+        "Snapshot {}: {} uploaded, {} copied, {} failed, {} pruned",
+        result.name, result.uploaded, result.copied, result.failed.len(), result.pruned.len()
+    )
+}
+
+/// Backs up a local folder to a new timestamped prefix, e.g. `backups/2024-06-01T02:00/`.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn create_snapshot(
+    local_root: String,
+    bucket: String,
+    base_prefix: Option<String>,
+    reuse_previous: Option<bool>,
+    ignore: Option<Vec<String>>,
+    keep: Option<usize>,
+    app: AppHandle,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<SnapshotResult, AppError> {
+    let spec = SnapshotSpec {
+        local_root,
+        bucket,
+        base_prefix: base_prefix.unwrap_or_else(default_base_prefix),
+        reuse_previous: reuse_previous.unwrap_or(true),
+        ignore: ignore.unwrap_or_default(),
+        keep,
+    };
+    let job = jobs::start("snapshot", Some(&spec.bucket), json!({ "local_root": spec.local_root, "base_prefix": spec.base_prefix }));
+    let result: Result<SnapshotResult, AppError> = async {
+        let client = acquire_write_client(&state, connection_id.as_deref())?;
+        create(&app, &client, &spec).await
+    }
+    .await;
+    job.finish(&app, &result, summary);
+
+    result
+}
+
+/// Snapshots under `base_prefix`, oldest first, with totals from their manifests.
+#[tauri::command]
+pub async fn list_snapshots(
+    bucket: String,
+    base_prefix: Option<String>,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<SnapshotInfo>, AppError> {
+    let base = sync::normalize_prefix(&base_prefix.unwrap_or_else(default_base_prefix));
+    let client = acquire_client(&state, connection_id.as_deref())?;
+    let mut snapshots = Vec::new();
+    for name in snapshot_names(&client, &bucket, &base).await? {
+        let manifest = load_manifest(&client, &bucket, &base, &name).await?;
+        snapshots.push(SnapshotInfo {
+            prefix: format!("{}{}/", base, name),
+            created_at: manifest.as_ref().map(|m| m.created_at),
+            files: manifest.as_ref().map(|m| m.files.len()),
+            bytes: manifest.as_ref().map(|m| m.files.values().map(|f| f.size).sum()),
+            complete: manifest.as_ref().map(|m| m.complete),
+            name,
+        });
+    }
+    Ok(snapshots)
+}
+
+/// Deletes all but the newest `keep` snapshots. Returns the names removed.
+#[tauri::command]
+pub async fn prune_snapshots(
+    bucket: String,
+    base_prefix: Option<String>,
+    keep: usize,
+    app: AppHandle,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<String>, AppError> {
+    let base = sync::normalize_prefix(&base_prefix.unwrap_or_else(default_base_prefix));
+    let job = jobs::start("prune_snapshots", Some(&bucket), json!({ "base_prefix": base, "keep": keep }));
+    let result: Result<Vec<String>, AppError> = async {
+        let client = acquire_write_client(&state, connection_id.as_deref())?;
+        prune(&client, &bucket, &base, keep).await
+    }
+    .await;
+    job.finish(&app, &result, |pruned| format!("Pruned {} snapshots", pruned.len()));
+
+    result
+}
+
+/// Files of a snapshot as (relative path, size), narrowed to `paths` when given: a relative path
+/// selects that file, one ending in '/' a whole folder.
+async fn snapshot_files(client: &Client, bucket: &str, base: &str, name: &str, paths: &[String]) -> Result<Vec<(String, u64)>, AppError> {
+    let prefix = format!("{}{}/", base, name);
+    let objects = list_all_objects(client, bucket, Some(&prefix)).await?;
+    Ok(objects
+        .iter()
+        .filter(|o| !is_folder_marker(o.key().unwrap_or_default(), o.size().unwrap_or(0)))
+        .filter_map(|o| Some((o.key()?.strip_prefix(&prefix)?.to_string(), o.size().unwrap_or(0).max(0) as u64)))
+        .filter(|(relative, _)| {
+            paths.is_empty() || paths.iter().any(|p| p == relative || (p.ends_with('/') && relative.starts_with(p.as_str())))
+        })
+        .collect())
+}
+
+/// How much restoring a snapshot, or part of it, would transfer.
+#[tauri::command]
+pub async fn snapshot_restore_size(
+    bucket: String,
+    name: String,
+    base_prefix: Option<String>,
+    paths: Option<Vec<String>>,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<RestoreSize, AppError> {
+    let base = sync::normalize_prefix(&base_prefix.unwrap_or_else(default_base_prefix));
+    let client = acquire_client(&state, connection_id.as_deref())?;
+    let files = snapshot_files(&client, &bucket, &base, &name, &paths.unwrap_or_default()).await?;
+    Ok(RestoreSize { files: files.len(), bytes: files.iter().map(|(_, size)| size).sum() })
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn restore_file(app: &AppHandle, client: &Client, bucket: &str, key: &str, target: &RestoreTarget, relative: &str, size: u64, modified: Option<i64>) -> Result<(), String> {
+    match target {
+        RestoreTarget::Local { path } => {
+            let path = sync::local_path(Path::new(path), relative).ok_or("Not a valid local path")?;
+            if let Some(dir) = path.parent() {
+                tokio::fs::create_dir_all(dir).await.map_err(|e| e.to_string())?;
+            }
+            download_to_path(app, client, bucket, key, &path).await?;
+            // Back to the time it had when it was backed up
+            if let Some(secs) = modified.filter(|s| *s > 0) {
+                let file = std::fs::File::options().write(true).open(&path).map_err(|e| e.to_string())?;
+                let _ = file.set_modified(UNIX_EPOCH + Duration::from_secs(secs as u64));
+            }
+            Ok(())
+        }
+        RestoreTarget::Prefix { bucket: dst_bucket, prefix } => {
+            let dst_key = format!("{}{}", sync::normalize_prefix(prefix), relative);
+            replicate::copy(client, (bucket, key), (dst_bucket, &dst_key), size as i64).await
+        }
+    }
+}
+
+/// Restores a snapshot, or the files and folders in `paths`, to a local folder or another prefix.
+/// With `skip_existing`, files already at the target are left as they are. Emits
+/// `restore://progress`.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn restore_snapshot(
+    bucket: String,
+    name: String,
+    target: RestoreTarget,
+    base_prefix: Option<String>,
+    paths: Option<Vec<String>>,
+    skip_existing: Option<bool>,
+    app: AppHandle,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<RestoreResult, AppError> {
+    let base = sync::normalize_prefix(&base_prefix.unwrap_or_else(default_base_prefix));
+    let job = jobs::start("restore_snapshot", Some(&bucket), json!({ "name": name, "base_prefix": base, "target": target }));
+    let result: Result<RestoreResult, AppError> = async {
+        let client = match &target {
+            RestoreTarget::Local { .. } => acquire_client(&state, connection_id.as_deref())?,
+            RestoreTarget::Prefix { .. } => acquire_write_client(&state, connection_id.as_deref())?,
+        };
+        let files = snapshot_files(&client, &bucket, &base, &name, &paths.unwrap_or_default()).await?;
+        if files.is_empty() {
+            return Err(AppError::new(ErrorKind::NotFound, format!("Nothing to restore in snapshot {}", name)));
+        }
+        let manifest = load_manifest(&client, &bucket, &base, &name).await?;
+
+        let mut result = RestoreResult::default();
+        let existing: HashSet<String> = match (&target, skip_existing.unwrap_or(false)) {
+            (RestoreTarget::Local { path }, true) => files
+                .iter()
+                .filter(|(relative, _)| sync::local_path(Path::new(path), relative).is_some_and(|p| p.exists()))
+                .map(|(relative, _)| relative.clone())
+                .collect(),
+            (RestoreTarget::Prefix { bucket, prefix }, true) => {
+                let prefix = sync::normalize_prefix(prefix);
+                list_all_objects(&client, bucket, Some(&prefix))
+                    .await?
+                    .iter()
+                    .filter_map(|o| Some(o.key()?.strip_prefix(&prefix)?.to_string()))
+                    .collect()
+            }
+            (_, false) => HashSet::new(),
+        };
+
+        let mut progress = RestoreProgress { name: name.clone(), done: 0, total: files.len(), bytes: 0, current: String::new() };
+        for (relative, size) in files {
+            if existing.contains(&relative) {
+                result.skipped += 1;
+            } else {
+                let key = format!("{}{}/{}", base, name, relative);
+                let modified = manifest.as_ref().and_then(|m| m.files.get(&relative)).map(|f| f.modified);
+                match restore_file(&app, &client, &bucket, &key, &target, &relative, size, modified).await {
+                    Ok(()) => {
+                        result.restored += 1;
+                        result.bytes += size;
+                        progress.bytes += size;
+                    }
+                    Err(error) => result.failed.push(SyncFailure { relative: relative.clone(), error }),
+                }
+            }
+            progress.done += 1;
+            progress.current = relative;
+            let _ = app.emit("restore://progress", &progress);
+        }
+        Ok(result)
+    }
+    .await;
+    job.finish(&app, &result, |r| format!("Restored {} files from snapshot {}, {} skipped, {} failed", r.restored, name, r.skipped, r.failed.len()));
+
+    result
+}
@@ -0,0 +1,15 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use rand::RngCore;
+
+use crate::error::AppError;
+
+pub use r2drive_core::sse_c::{parse_optional, SseCKey};
+
+/// A new random SSE-C key, base64. Whoever loses it loses the objects encrypted with it.
+#[tauri::command]
+pub fn generate_sse_c_key() -> Result<String, AppError> {
+    let mut key = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut key);
+    Ok(STANDARD.encode(key))
+}
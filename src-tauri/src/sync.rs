@@ -0,0 +1,374 @@
+use aws_sdk_s3::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter, Manager, State};
+use tokio::task::JoinSet;
+
+use crate::encryption;
+use crate::error::{AppError, ErrorKind};
+use crate::jobs;
+use crate::s3::{self, acquire_client, acquire_write_client, download_to_path, upload_path, wait_if_paused, AppState};
+use crate::sync_state;
+
+pub use r2drive_core::sync::{
+    ignore_rules, is_ignored, local_path, normalize_prefix, plan, scan_local, scan_remote, FileState,
+    SyncAction, SyncActionKind, SyncConflict, SyncDirection, SyncOptions, SyncPlan, SyncedState, IGNORE_FILE,
+};
+
+// Sync planning lives in r2drive_core::sync; this side scans through the app's connections,
+// applies plans and records what both sides looked like after each sync in `sync_state`.
+const TRANSFER_CONCURRENCY: usize = 8;
+
+/// Payload of `sync://progress`.
+#[derive(Debug, Clone, Serialize)]
+pub struct SyncProgress {
+    pub bucket: String,
+    pub prefix: String,
+    pub done: usize,
+    pub total: usize,
+    pub bytes: u64,
+    pub current: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncFailure {
+    pub relative: String,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SyncResult {
+    pub uploaded: usize,
+    pub downloaded: usize,
+    pub deleted: usize,
+    pub skipped: usize,
+    pub bytes: u64,
+    /// Failed files; the rest of the plan still runs.
+    pub failed: Vec<SyncFailure>,
+    pub conflicts: Vec<SyncConflict>,
+}
+
+/// Sync and snapshots match local paths to keys one to one, which encrypted names would break.
+pub fn check_plain_names(app: &AppHandle) -> Result<(), AppError> {
+    let encryption = app.state::<AppState>().settings.lock().unwrap().encryption.clone();
+    if encryption.enabled && encryption.encrypt_names {
+        return Err(AppError::new(ErrorKind::Unsupported, "Folders can't be synced or snapshotted while file names are encrypted; turn off name encryption first"));
+    }
+    Ok(())
+}
+
+/// Encrypted objects are listed at their ciphertext size; this puts the size of the plaintext
+/// they decrypt to in its place, so they compare with local files. Sizes recorded at the last
+/// sync are reused while the ETag is the same; other objects are checked with a HEAD. Nothing
+/// is checked until an encryption key has been set up, as nothing can be encrypted before.
+async fn plaintext_sizes(
+    app: &AppHandle,
+    client: &Client,
+    bucket: &str,
+    prefix: &str,
+    remote: &mut BTreeMap<String, FileState>,
+    synced: &HashMap<String, SyncedState>,
+) -> Result<(), AppError> {
+    let state = app.state::<AppState>();
+    if state.settings.lock().unwrap().encryption.key_id.is_none() {
+        return Ok(());
+    }
+    let mut unknown = Vec::new();
+    for (relative, file) in remote.iter_mut() {
+        match synced.get(relative).and_then(|s| s.remote.as_ref()) {
+            Some(last) if last.etag.is_some() && last.etag == file.etag => file.size = last.size,
+            _ => unknown.push(relative.clone()),
+        }
+    }
+
+    let concurrency = s3::concurrency(&state, TRANSFER_CONCURRENCY);
+    let mut tasks: JoinSet<Result<(String, Option<u64>), AppError>> = JoinSet::new();
+    let mut sizes = Vec::new();
+    for relative in unknown {
+        while tasks.len() >= concurrency {
+            if let Some(Ok(checked)) = tasks.join_next().await {
+                sizes.push(checked?);
+            }
+        }
+        let (client, bucket, key) = (client.clone(), bucket.to_string(), format!("{}{}", prefix, relative));
+        tasks.spawn(async move {
+            match client.head_object().bucket(&bucket).key(&key).send().await {
+                Ok(head) => Ok((relative, encryption::plaintext_size(head.metadata()))),
+                // Deleted since the listing; the plan deals with it
+                Err(e) if e.as_service_error().is_some_and(|se| se.is_not_found()) => Ok((relative, None)),
+                Err(e) => Err(e.into()),
+            }
+        });
+    }
+    while let Some(joined) = tasks.join_next().await {
+        if let Ok(checked) = joined {
+            sizes.push(checked?);
+        }
+    }
+    for (relative, size) in sizes {
+        if let (Some(size), Some(file)) = (size, remote.get_mut(&relative)) {
+            file.size = size;
+        }
+    }
+    Ok(())
+}
+
+pub async fn build_plan(app: &AppHandle, client: &Client, local_root: &str, bucket: &str, prefix: &str, direction: SyncDirection, options: &SyncOptions) -> Result<SyncPlan, AppError> {
+    check_plain_names(app)?;
+    let prefix = normalize_prefix(prefix);
+    let root = PathBuf::from(local_root);
+    let rules = ignore_rules(&root, &options.ignore).map_err(|e| AppError::new(ErrorKind::InvalidInput, e))?;
+    let walk_rules = rules.clone();
+    let local = tauri::async_runtime::spawn_blocking(move || scan_local(&root, &walk_rules))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| AppError::new(ErrorKind::InvalidInput, e))?;
+    let mut remote = scan_remote(client, bucket, &prefix).await?;
+    // Otherwise ignored files would be downloaded, or deleted from the bucket
+    remote.retain(|relative, _| !is_ignored(&rules, relative, false));
+    let synced = sync_state::load(app, local_root, bucket, &prefix)?;
+    plaintext_sizes(app, client, bucket, &prefix, &mut remote, &synced).await?;
+    let (actions, conflicts) = plan(direction, &local, &remote, &synced, options);
+    Ok(SyncPlan {
+        local_root: local_root.to_string(),
+        bucket: bucket.to_string(),
+        prefix,
+        direction,
+        actions,
+        conflicts,
+    })
+}
+
+async fn download(app: &AppHandle, client: &Client, bucket: &str, key: &str, path: &Path) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        tokio::fs::create_dir_all(dir).await.map_err(|e| e.to_string())?;
+    }
+    download_to_path(app, client, bucket, key, path).await?;
+    // Carry the object's time over so the next plan sees the two as equal
+    let head = client.head_object().bucket(bucket).key(key).send().await.map_err(|e| e.to_string())?;
+    if let Some(secs) = head.last_modified().map(|d| d.secs()).filter(|s| *s > 0) {
+        let file = std::fs::File::options().write(true).open(path).map_err(|e| e.to_string())?;
+        let _ = file.set_modified(UNIX_EPOCH + Duration::from_secs(secs as u64));
+    }
+    Ok(())
+}
+
+/// Carries out one action. Returns the ETag of what was uploaded, if anything.
+async fn run_action(app: &AppHandle, client: &Client, plan: &SyncPlan, action: &SyncAction) -> Result<Option<String>, String> {
+    let key = format!("{}{}", plan.prefix, action.relative);
+    let root = Path::new(&plan.local_root);
+    let path = local_path(root, &action.relative).ok_or("Not a valid local path")?;
+    match action.action {
+        SyncActionKind::Upload => upload_path(app, client, &plan.bucket, &key, &path).await,
+        SyncActionKind::Download => download(app, client, &plan.bucket, &key, &path).await.map(|_| None),
+        SyncActionKind::DeleteRemote => client.delete_object()
+            .bucket(&plan.bucket)
+            .key(&key)
+            .send()
+            .await
+            .map(|_| None)
+            .map_err(|e| AppError::from(e).message),
+        SyncActionKind::DeleteLocal => match tokio::fs::remove_file(&path).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.to_string()),
+            _ => Ok(None),
+        },
+        SyncActionKind::KeepBoth => {
+            let copy = action.conflict_copy.as_deref().ok_or("No name for the conflict copy")?;
+            let copy_path = local_path(root, copy).ok_or("Not a valid local path")?;
+            tokio::fs::rename(&path, &copy_path).await.map_err(|e| e.to_string())?;
+            download(app, client, &plan.bucket, &key, &path).await?;
+            upload_path(app, client, &plan.bucket, &format!("{}{}", plan.prefix, copy), &copy_path).await
+        }
+        SyncActionKind::Skip => Ok(None),
+    }
+}
+
+/// What to record for a file once its action succeeded; None forgets it.
+fn synced_states(action: &SyncAction, etag: Option<String>) -> Vec<(String, Option<SyncedState>)> {
+    let state = |local: Option<FileState>, remote: Option<FileState>| Some(SyncedState { local, remote });
+    // The remote time of a fresh upload isn't known without another request; its ETag is what counts
+    let uploaded = |from: &FileState, etag| FileState { size: from.size, modified: jobs::now_secs(), etag };
+    // Downloads carry the object's time over to the file
+    let downloaded = |from: &FileState| FileState { size: from.size, modified: from.modified, etag: None };
+    let relative = action.relative.clone();
+    match action.action {
+        SyncActionKind::Upload => match &action.local {
+            Some(local) => vec![(relative, state(Some(local.clone()), Some(uploaded(local, etag))))],
+            None => Vec::new(),
+        },
+        SyncActionKind::Download => match &action.remote {
+            Some(remote) => vec![(relative, state(Some(downloaded(remote)), Some(remote.clone())))],
+            None => Vec::new(),
+        },
+        SyncActionKind::DeleteRemote | SyncActionKind::DeleteLocal => vec![(relative, None)],
+        SyncActionKind::KeepBoth => match (&action.local, &action.remote, &action.conflict_copy) {
+            (Some(local), Some(remote), Some(copy)) => vec![
+                (relative, state(Some(downloaded(remote)), Some(remote.clone()))),
+                (copy.clone(), state(Some(local.clone()), Some(uploaded(local, etag)))),
+            ],
+            _ => Vec::new(),
+        },
+        // Only files both sides agree on become the new baseline; a copy skipped for being newer
+        // on one side is still out of sync
+        SyncActionKind::Skip => match (&action.local, &action.remote) {
+            (Some(local), Some(remote)) if action.reason == "unchanged" && local.size == remote.size => {
+                vec![(relative, state(Some(local.clone()), Some(remote.clone())))]
+            }
+            _ => Vec::new(),
+        },
+    }
+}
+
+/// Carries out a plan, a few transfers at a time. Each transfer's requests take their own limiter
+/// permits, and no more transfers run than the limiter allows. A failed file is reported and the
+/// rest go on. Files that ended up in sync are recorded as the baseline for the next plan.
+pub async fn apply(app: &AppHandle, client: &Client, plan: SyncPlan) -> SyncResult {
+    let plan = std::sync::Arc::new(plan);
+    let mut result = SyncResult { conflicts: plan.conflicts.clone(), ..Default::default() };
+    let mut synced = Vec::new();
+    let mut progress = SyncProgress {
+        bucket: plan.bucket.clone(),
+        prefix: plan.prefix.clone(),
+        done: 0,
+        total: plan.actions.iter().filter(|a| a.action != SyncActionKind::Skip).count(),
+        bytes: 0,
+        current: None,
+    };
+    let _ = app.emit("sync://progress", &progress);
+    let concurrency = s3::concurrency(&app.state::<AppState>(), TRANSFER_CONCURRENCY);
+
+    let mut finish = |progress: &mut SyncProgress, action: SyncAction, outcome: Result<Option<String>, String>| {
+        match outcome {
+            Ok(etag) => {
+                match action.action {
+                    SyncActionKind::Upload => result.uploaded += 1,
+                    SyncActionKind::Download => result.downloaded += 1,
+                    SyncActionKind::DeleteRemote | SyncActionKind::DeleteLocal => result.deleted += 1,
+                    SyncActionKind::KeepBoth => {
+                        result.downloaded += 1;
+                        result.uploaded += 1;
+                    }
+                    SyncActionKind::Skip => {}
+                }
+                synced.extend(synced_states(&action, etag));
+                result.bytes += action.size;
+                progress.bytes += action.size;
+            }
+            Err(error) => result.failed.push(SyncFailure { relative: action.relative, error }),
+        }
+        progress.done += 1;
+        let _ = app.emit("sync://progress", &*progress);
+    };
+
+    let mut tasks: JoinSet<(usize, Result<Option<String>, String>)> = JoinSet::new();
+    for (index, action) in plan.actions.iter().enumerate() {
+        if action.action == SyncActionKind::Skip {
+            continue;
+        }
+        while tasks.len() >= concurrency {
+            if let Some(Ok((index, outcome))) = tasks.join_next().await {
+                finish(&mut progress, plan.actions[index].clone(), outcome);
+            }
+        }
+        wait_if_paused(&app.state::<AppState>()).await;
+        progress.current = Some(action.relative.clone());
+        let (app, client, plan) = (app.clone(), client.clone(), plan.clone());
+        tasks.spawn(async move {
+            let outcome = run_action(&app, &client, &plan, &plan.actions[index]).await;
+            (index, outcome)
+        });
+    }
+    while let Some(joined) = tasks.join_next().await {
+        if let Ok((index, outcome)) = joined {
+            finish(&mut progress, plan.actions[index].clone(), outcome);
+        }
+    }
+    let skipped: Vec<&SyncAction> = plan.actions.iter().filter(|a| a.action == SyncActionKind::Skip).collect();
+    result.skipped = skipped.len();
+    synced.extend(skipped.into_iter().flat_map(|a| synced_states(a, None)));
+    if let Err(error) = sync_state::save(app, &plan.local_root, &plan.bucket, &plan.prefix, synced) {
+        result.failed.push(SyncFailure { relative: String::new(), error: format!("Sync state was not saved: {}", error) });
+    }
+    result
+}
+
+/// Whether applying the plan changes the bucket, and so needs a write client.
+pub fn writes_remote(plan: &SyncPlan) -> bool {
+    plan.actions.iter().any(|a| matches!(a.action, SyncActionKind::Upload | SyncActionKind::DeleteRemote | SyncActionKind::KeepBoth))
+}
+
+fn summary(result: &SyncResult) -> String {
+    format!(
+        "Synced: {} uploaded, {} downloaded, {} deleted, {} failed",
+        result.uploaded, result.downloaded, result.deleted, result.failed.len()
+    )
+}
+
+/// Works out what a sync would do without changing anything: every file with the action it
+/// would get (upload, download, delete or skip) and why.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn plan_sync(
+    local_root: String,
+    bucket: String,
+    prefix: String,
+    direction: SyncDirection,
+    options: Option<SyncOptions>,
+    app: AppHandle,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<SyncPlan, AppError> {
+    let client = acquire_client(&state, connection_id.as_deref())?;
+    build_plan(&app, &client, &local_root, &bucket, &prefix, direction, &options.unwrap_or_default()).await
+}
+
+/// Carries out a plan from `plan_sync`, possibly edited. Emits `sync://progress`.
+#[tauri::command]
+pub async fn apply_sync_plan(
+    plan: SyncPlan,
+    app: AppHandle,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<SyncResult, AppError> {
+    let job = jobs::start("sync", Some(&plan.bucket), json!({
+        "local_root": plan.local_root,
+        "prefix": plan.prefix,
+        "direction": plan.direction,
+        "actions": plan.actions.len(),
+    }));
+    let result: Result<SyncResult, AppError> = async {
+        check_plain_names(&app)?;
+        let client = if writes_remote(&plan) {
+            acquire_write_client(&state, connection_id.as_deref())?
+        } else {
+            acquire_client(&state, connection_id.as_deref())?
+        };
+        Ok(apply(&app, &client, plan).await)
+    }
+    .await;
+    job.finish(&app, &result, summary);
+
+    result
+}
+
+/// Plans and applies in one go.
+#[tauri::command]
+pub async fn sync_folder(
+    local_root: String,
+    bucket: String,
+    prefix: String,
+    direction: SyncDirection,
+    options: Option<SyncOptions>,
+    app: AppHandle,
+    connection_id: Option<String>,
+) -> Result<SyncResult, AppError> {
+    let state = app.state::<AppState>();
+    let plan = {
+        let client = acquire_client(&state, connection_id.as_deref())?;
+        build_plan(&app, &client, &local_root, &bucket, &prefix, direction, &options.unwrap_or_default()).await?
+    };
+    apply_sync_plan(plan, app.clone(), connection_id, state).await
+}
@@ -0,0 +1,381 @@
+use chrono::{DateTime, Datelike, Duration as ChronoDuration, Local, Timelike};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::error::{AppError, ErrorKind};
+use crate::jobs::{self, JobRecord};
+use crate::replicate::{self, ReplicateResult, Replication};
+use crate::s3::{acquire_client, acquire_write_client, AppState};
+use crate::settings;
+use crate::snapshot::{self, SnapshotResult, SnapshotSpec};
+use crate::sync::{self, SyncDirection, SyncOptions, SyncResult};
+
+// Saved syncs, replications and snapshot backups run on a cron schedule, evaluated in local time, while the app is
+// running. Runs missed while it was closed are not caught up. Each run is archived as a
+// `scheduled_sync` job, which is also its history.
+const TICK: Duration = Duration::from_secs(20);
+// Far enough ahead for yearly schedules
+const NEXT_RUN_HORIZON_MINUTES: i64 = 366 * 24 * 60;
+static SEQUENCE: AtomicU64 = AtomicU64::new(0);
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// What a schedule runs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ScheduledTask {
+    Sync {
+        local_root: String,
+        bucket: String,
+        #[serde(default)]
+        prefix: String,
+        direction: SyncDirection,
+        #[serde(default)]
+        options: SyncOptions,
+    },
+    Replicate(Replication),
+    Snapshot(SnapshotSpec),
+}
+
+impl ScheduledTask {
+    fn bucket(&self) -> &str {
+        match self {
+            ScheduledTask::Sync { bucket, .. } => bucket,
+            ScheduledTask::Replicate(r) => &r.dst_bucket,
+            ScheduledTask::Snapshot(spec) => &spec.bucket,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncSchedule {
+    /// Assigned on first save.
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    /// Five cron fields (minute hour day-of-month month day-of-week) or @hourly, @daily,
+    /// @weekly, @monthly.
+    pub cron: String,
+    #[serde(flatten)]
+    pub task: ScheduledTask,
+    #[serde(default)]
+    pub connection_id: Option<String>,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SyncScheduleStatus {
+    #[serde(flatten)]
+    pub schedule: SyncSchedule,
+    pub running: bool,
+    /// Unix seconds of the next run, if enabled.
+    pub next_run: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum ScheduleOutcome {
+    Sync(SyncResult),
+    Replicate(ReplicateResult),
+    Snapshot(SnapshotResult),
+}
+
+/// Payload of `schedule://finished`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ScheduleFinished {
+    pub schedule_id: String,
+    pub result: Option<ScheduleOutcome>,
+    pub error: Option<String>,
+}
+
+#[derive(Default)]
+pub struct ScheduleState {
+    running: Mutex<HashSet<String>>,
+    /// Minute (Unix seconds / 60) each schedule last fired in, so a tick can't fire it twice.
+    fired: Mutex<HashMap<String, i64>>,
+}
+
+struct Cron {
+    minutes: Vec<bool>,
+    hours: Vec<bool>,
+    days: Vec<bool>,
+    months: Vec<bool>,
+    weekdays: Vec<bool>,
+    days_restricted: bool,
+    weekdays_restricted: bool,
+}
+
+/// One cron field as a table of allowed values. Supports `*`, `n`, `a-b`, `*/s`, `a-b/s` and
+/// comma-separated lists of those.
+fn parse_field(field: &str, min: u32, max: u32) -> Result<Vec<bool>, String> {
+    let mut allowed = vec![false; max as usize + 1];
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>().map_err(|_| format!("Invalid step in '{}'", part))?),
+            None => (part, 1),
+        };
+        let (start, end) = match range {
+            "*" => (min, max),
+            r => match r.split_once('-') {
+                Some((a, b)) => (
+                    a.parse().map_err(|_| format!("Invalid value in '{}'", part))?,
+                    b.parse().map_err(|_| format!("Invalid value in '{}'", part))?,
+                ),
+                // "5/15" means from 5 to the end in steps of 15
+                None => {
+                    let n = r.parse().map_err(|_| format!("Invalid value in '{}'", part))?;
+                    (n, if step > 1 { max } else { n })
+                }
+            },
+        };
+        if step == 0 || start < min || end > max || start > end {
+            return Err(format!("'{}' is out of range {}-{}", part, min, max));
+        }
+        for value in (start..=end).step_by(step as usize) {
+            allowed[value as usize] = true;
+        }
+    }
+    Ok(allowed)
+}
+
+impl Cron {
+    fn parse(expression: &str) -> Result<Self, String> {
+        let expression = match expression.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            e => e,
+        };
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            return Err("A schedule needs five fields: minute hour day month weekday".to_string());
+        };
+        let mut weekdays = parse_field(weekday, 0, 7)?;
+        // Both 0 and 7 are Sunday
+        weekdays[0] |= weekdays[7];
+        Ok(Self {
+            minutes: parse_field(minute, 0, 59)?,
+            hours: parse_field(hour, 0, 23)?,
+            days: parse_field(day, 1, 31)?,
+            months: parse_field(month, 1, 12)?,
+            weekdays,
+            days_restricted: day != "*",
+            weekdays_restricted: weekday != "*",
+        })
+    }
+
+    fn matches(&self, time: &DateTime<Local>) -> bool {
+        let day = self.days[time.day() as usize];
+        let weekday = self.weekdays[time.weekday().num_days_from_sunday() as usize];
+        // As in cron, a restricted day-of-month and day-of-week match when either does
+        let date = match (self.days_restricted, self.weekdays_restricted) {
+            (true, true) => day || weekday,
+            _ => day && weekday,
+        };
+        date && self.minutes[time.minute() as usize] && self.hours[time.hour() as usize] && self.months[time.month() as usize]
+    }
+
+    fn next_after(&self, time: DateTime<Local>) -> Option<DateTime<Local>> {
+        let start = time.with_second(0)?.with_nanosecond(0)?;
+        (1..=NEXT_RUN_HORIZON_MINUTES).map(|m| start + ChronoDuration::minutes(m)).find(|t| self.matches(t))
+    }
+}
+
+async fn run_sync(app: &AppHandle, connection_id: Option<&str>, local_root: &str, bucket: &str, prefix: &str, direction: SyncDirection, options: &SyncOptions) -> Result<SyncResult, AppError> {
+    let state = app.state::<AppState>();
+    let plan = {
+        let client = acquire_client(&state, connection_id)?;
+        sync::build_plan(app, &client, local_root, bucket, prefix, direction, options).await?
+    };
+    let client = if sync::writes_remote(&plan) {
+        acquire_write_client(&state, connection_id)?
+    } else {
+        acquire_client(&state, connection_id)?
+    };
+    Ok(sync::apply(app, &client, plan).await)
+}
+
+async fn run(app: &AppHandle, schedule: &SyncSchedule) -> Result<ScheduleOutcome, AppError> {
+    let mut params = serde_json::to_value(&schedule.task).unwrap_or_default();
+    params["schedule_id"] = json!(schedule.id);
+    params["name"] = json!(schedule.name);
+    let job = jobs::start("scheduled_sync", Some(schedule.task.bucket()), params);
+    let connection_id = schedule.connection_id.as_deref();
+    let result: Result<ScheduleOutcome, AppError> = match &schedule.task {
+        ScheduledTask::Sync { local_root, bucket, prefix, direction, options } => {
+            run_sync(app, connection_id, local_root, bucket, prefix, *direction, options).await.map(ScheduleOutcome::Sync)
+        }
+        ScheduledTask::Replicate(replication) => async {
+            let client = acquire_write_client(&app.state::<AppState>(), connection_id)?;
+            replicate::replicate_prefix(app, &client, replication.clone()).await
+        }
+        .await
+        .map(ScheduleOutcome::Replicate),
+        ScheduledTask::Snapshot(spec) => async {
+            let client = acquire_write_client(&app.state::<AppState>(), connection_id)?;
+            snapshot::create(app, &client, spec).await
+        }
+        .await
+        .map(ScheduleOutcome::Snapshot),
+    };
+    job.finish(app, &result, |outcome| match outcome {
+        ScheduleOutcome::Sync(r) => {
+            format!("{}: {} uploaded, {} downloaded, {} deleted, {} failed", schedule.name, r.uploaded, r.downloaded, r.deleted, r.failed.len())
+        }
+        ScheduleOutcome::Replicate(r) => format!("{}: {}", schedule.name, replicate::summary(r)),
+        ScheduleOutcome::Snapshot(r) => format!("{}: {}", schedule.name, snapshot::summary(r)),
+    });
+    result
+}
+
+/// Runs a schedule unless it is already running, then reports it as `schedule://finished`.
+async fn run_exclusive(app: &AppHandle, schedule: SyncSchedule) -> Result<ScheduleOutcome, AppError> {
+    let schedules = app.state::<ScheduleState>();
+    if !schedules.running.lock().unwrap().insert(schedule.id.clone()) {
+        return Err(AppError::new(ErrorKind::AlreadyExists, format!("'{}' is already running", schedule.name)));
+    }
+    let result = run(app, &schedule).await;
+    schedules.running.lock().unwrap().remove(&schedule.id);
+
+    let _ = app.emit("schedule://finished", ScheduleFinished {
+        schedule_id: schedule.id.clone(),
+        result: result.as_ref().ok().cloned(),
+        error: result.as_ref().err().map(|e| e.message.clone()),
+    });
+    result
+}
+
+/// Background loop that starts enabled schedules when their time comes.
+pub async fn run_scheduler(app: AppHandle) {
+    loop {
+        let now = Local::now();
+        let minute = now.timestamp() / 60;
+        let schedules = app.state::<AppState>().settings.lock().unwrap().sync_schedules.clone();
+        for schedule in schedules.into_iter().filter(|s| s.enabled) {
+            let Ok(cron) = Cron::parse(&schedule.cron) else { continue };
+            if !cron.matches(&now) {
+                continue;
+            }
+            let state = app.state::<ScheduleState>();
+            if state.fired.lock().unwrap().insert(schedule.id.clone(), minute) == Some(minute) {
+                continue;
+            }
+            let app = app.clone();
+            tauri::async_runtime::spawn(async move {
+                let _ = run_exclusive(&app, schedule).await;
+            });
+        }
+        tokio::time::sleep(TICK).await;
+    }
+}
+
+fn validate(schedule: &SyncSchedule) -> Result<(), AppError> {
+    if schedule.name.trim().is_empty() {
+        return Err(AppError::new(ErrorKind::InvalidInput, "A schedule needs a name"));
+    }
+    match &schedule.task {
+        ScheduledTask::Sync { local_root, bucket, .. } | ScheduledTask::Snapshot(SnapshotSpec { local_root, bucket, .. }) => {
+            if bucket.is_empty() {
+                return Err(AppError::new(ErrorKind::InvalidInput, "A bucket is required"));
+            }
+            if !std::path::Path::new(local_root).is_dir() {
+                return Err(AppError::new(ErrorKind::InvalidInput, format!("{} is not a folder", local_root)));
+            }
+        }
+        ScheduledTask::Replicate(r) => {
+            if r.src_bucket.is_empty() || r.dst_bucket.is_empty() {
+                return Err(AppError::new(ErrorKind::InvalidInput, "Source and destination buckets are required"));
+            }
+        }
+    }
+    Cron::parse(&schedule.cron).map_err(|e| AppError::new(ErrorKind::InvalidInput, e))?;
+    Ok(())
+}
+
+fn find(state: &AppState, id: &str) -> Result<SyncSchedule, AppError> {
+    state.settings.lock().unwrap()
+        .sync_schedules
+        .iter()
+        .find(|s| s.id == id)
+        .cloned()
+        .ok_or_else(|| AppError::new(ErrorKind::NotFound, format!("No schedule with id '{}'", id)))
+}
+
+#[tauri::command]
+pub fn list_sync_schedules(state: State<'_, AppState>, schedules: State<'_, ScheduleState>) -> Vec<SyncScheduleStatus> {
+    let running = schedules.running.lock().unwrap();
+    let now = Local::now();
+    state.settings.lock().unwrap()
+        .sync_schedules
+        .iter()
+        .map(|s| SyncScheduleStatus {
+            running: running.contains(&s.id),
+            next_run: s.enabled
+                .then(|| Cron::parse(&s.cron).ok()?.next_after(now))
+                .flatten()
+                .map(|t| t.timestamp()),
+            schedule: s.clone(),
+        })
+        .collect()
+}
+
+/// Creates a schedule, or updates the one with the same id.
+#[tauri::command]
+pub fn save_sync_schedule(mut schedule: SyncSchedule, app: AppHandle, state: State<'_, AppState>) -> Result<SyncSchedule, AppError> {
+    validate(&schedule)?;
+    if schedule.id.is_empty() {
+        schedule.id = format!("{}-{}", jobs::now_secs(), SEQUENCE.fetch_add(1, Ordering::Relaxed));
+    }
+    let mut settings = state.settings.lock().unwrap().clone();
+    match settings.sync_schedules.iter_mut().find(|s| s.id == schedule.id) {
+        Some(existing) => *existing = schedule.clone(),
+        None => settings.sync_schedules.push(schedule.clone()),
+    }
+    settings::save(&app, &settings)?;
+    state.apply_settings(settings);
+    Ok(schedule)
+}
+
+#[tauri::command]
+pub fn set_sync_schedule_enabled(id: String, enabled: bool, app: AppHandle, state: State<'_, AppState>) -> Result<SyncSchedule, AppError> {
+    let mut schedule = find(&state, &id)?;
+    schedule.enabled = enabled;
+    save_sync_schedule(schedule, app, state)
+}
+
+#[tauri::command]
+pub fn delete_sync_schedule(id: String, app: AppHandle, state: State<'_, AppState>) -> Result<(), AppError> {
+    find(&state, &id)?;
+    let mut settings = state.settings.lock().unwrap().clone();
+    settings.sync_schedules.retain(|s| s.id != id);
+    settings::save(&app, &settings)?;
+    state.apply_settings(settings);
+    Ok(())
+}
+
+/// Runs a schedule now, outside its timetable.
+#[tauri::command]
+pub async fn run_sync_schedule_now(id: String, app: AppHandle) -> Result<ScheduleOutcome, AppError> {
+    let schedule = find(&app.state::<AppState>(), &id)?;
+    run_exclusive(&app, schedule).await
+}
+
+/// Past runs of a schedule, newest first.
+#[tauri::command]
+pub fn sync_schedule_history(id: String, limit: Option<usize>, app: AppHandle) -> Vec<JobRecord> {
+    jobs::load_all(&app)
+        .into_iter()
+        .rev()
+        .filter(|j| j.kind == "scheduled_sync" && j.params.get("schedule_id").and_then(|v| v.as_str()) == Some(id.as_str()))
+        .take(limit.unwrap_or(50))
+        .collect()
+}
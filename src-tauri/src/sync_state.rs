@@ -0,0 +1,106 @@
+use rusqlite::{params, Connection};
+use std::collections::HashMap;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+use crate::error::AppError;
+use crate::jobs;
+use crate::sync::{normalize_prefix, FileState, SyncedState};
+
+// What each file looked like on both sides after it was last synced, so a later sync can tell
+// which side changed since. One database for every synced folder, keyed by pair: the local root,
+// bucket and prefix together.
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS files (
+        pair TEXT NOT NULL,
+        relative TEXT NOT NULL,
+        local_size INTEGER,
+        local_modified INTEGER,
+        remote_size INTEGER,
+        remote_modified INTEGER,
+        remote_etag TEXT,
+        synced_at INTEGER NOT NULL,
+        PRIMARY KEY (pair, relative)
+    ) WITHOUT ROWID;
+";
+
+fn pair(local_root: &str, bucket: &str, prefix: &str) -> String {
+    format!("{}\n{}\n{}", local_root, bucket, prefix)
+}
+
+fn open(app: &AppHandle) -> Result<Connection, String> {
+    let dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let db = Connection::open(dir.join("sync-state.sqlite")).map_err(|e| e.to_string())?;
+    db.busy_timeout(Duration::from_secs(5)).map_err(|e| e.to_string())?;
+    db.execute_batch(SCHEMA).map_err(|e| e.to_string())?;
+    Ok(db)
+}
+
+fn side(size: Option<i64>, modified: Option<i64>, etag: Option<String>) -> Option<FileState> {
+    Some(FileState { size: size?.max(0) as u64, modified: modified.unwrap_or(0), etag })
+}
+
+/// Every recorded file of a pair by relative path. Empty before its first sync.
+pub fn load(app: &AppHandle, local_root: &str, bucket: &str, prefix: &str) -> Result<HashMap<String, SyncedState>, String> {
+    let db = open(app)?;
+    let mut stmt = db.prepare(
+        "SELECT relative, local_size, local_modified, remote_size, remote_modified, remote_etag
+         FROM files WHERE pair = ?1",
+    ).map_err(|e| e.to_string())?;
+    let rows = stmt.query_map([pair(local_root, bucket, prefix)], |r| {
+        let state = SyncedState {
+            local: side(r.get(1)?, r.get(2)?, None),
+            remote: side(r.get(3)?, r.get(4)?, r.get(5)?),
+        };
+        Ok((r.get::<_, String>(0)?, state))
+    }).map_err(|e| e.to_string())?;
+    rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+}
+
+/// Records the outcome of a sync: a state replaces the file's row, None forgets the file.
+pub fn save(app: &AppHandle, local_root: &str, bucket: &str, prefix: &str, changes: Vec<(String, Option<SyncedState>)>) -> Result<(), String> {
+    let mut db = open(app)?;
+    let pair = pair(local_root, bucket, prefix);
+    let now = jobs::now_secs();
+    let tx = db.transaction().map_err(|e| e.to_string())?;
+    {
+        let mut upsert = tx.prepare_cached(
+            "INSERT INTO files (pair, relative, local_size, local_modified, remote_size, remote_modified, remote_etag, synced_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+             ON CONFLICT (pair, relative) DO UPDATE SET local_size = excluded.local_size,
+                local_modified = excluded.local_modified, remote_size = excluded.remote_size,
+                remote_modified = excluded.remote_modified, remote_etag = excluded.remote_etag,
+                synced_at = excluded.synced_at",
+        ).map_err(|e| e.to_string())?;
+        let mut delete = tx.prepare_cached("DELETE FROM files WHERE pair = ?1 AND relative = ?2").map_err(|e| e.to_string())?;
+        for (relative, state) in changes {
+            match state {
+                Some(SyncedState { local, remote }) if local.is_some() || remote.is_some() => upsert.execute(params![
+                    pair,
+                    relative,
+                    local.as_ref().map(|l| l.size as i64),
+                    local.as_ref().map(|l| l.modified),
+                    remote.as_ref().map(|r| r.size as i64),
+                    remote.as_ref().map(|r| r.modified),
+                    remote.as_ref().and_then(|r| r.etag.clone()),
+                    now,
+                ]),
+                _ => delete.execute(params![pair, relative]),
+            }
+            .map_err(|e| e.to_string())?;
+        }
+    }
+    tx.commit().map_err(|e| e.to_string())
+}
+
+
+/// Forgets everything recorded for a folder and prefix, so the next sync treats both sides as
+/// new. A two-way sync then reports every differing file as a conflict and deletes nothing.
+#[tauri::command]
+pub fn reset_sync_state(local_root: String, bucket: String, prefix: String, app: AppHandle) -> Result<(), AppError> {
+    let db = open(&app)?;
+    db.execute("DELETE FROM files WHERE pair = ?1", [pair(&local_root, &bucket, &normalize_prefix(&prefix))])
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
@@ -0,0 +1,208 @@
+use aws_sdk_s3::Client;
+use bytes::Bytes;
+use parquet::file::metadata::ParquetMetaDataReader;
+use parquet::file::reader::{ChunkReader, FileReader, Length};
+use parquet::file::serialized_reader::SerializedFileReader;
+use serde::Serialize;
+use serde_json::Value;
+use std::io::Cursor;
+use tauri::State;
+
+use crate::error::{AppError, ErrorKind};
+use crate::inventory::parse_csv;
+use crate::s3::{acquire_client, AppState};
+use crate::text;
+
+const DEFAULT_ROWS: usize = 100;
+const MAX_ROWS: usize = 1000;
+// Enough for a thousand rows of most CSVs; the partial last line is dropped
+const CSV_BYTES: u64 = 1024 * 1024;
+const PARQUET_TAIL_BYTES: u64 = 64 * 1024;
+const MAX_ROW_GROUP_BYTES: u64 = 64 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TableFormat {
+    Csv,
+    Parquet,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TablePreview {
+    pub format: TableFormat,
+    pub columns: Vec<String>,
+    /// One array per row, in column order. CSV cells are strings; Parquet keeps its types.
+    pub rows: Vec<Vec<Value>>,
+    /// Rows in the whole object, when the format records it (Parquet).
+    pub total_rows: Option<u64>,
+    /// More rows exist than were returned.
+    pub truncated: bool,
+}
+
+fn format_of(key: &str) -> Option<TableFormat> {
+    match key.rsplit('.').next().map(|e| e.to_ascii_lowercase()).as_deref() {
+        Some("csv") | Some("tsv") | Some("psv") => Some(TableFormat::Csv),
+        Some("parquet") | Some("parq") | Some("pq") => Some(TableFormat::Parquet),
+        _ => None,
+    }
+}
+
+/// Picks the delimiter that appears most in the header line, outside quotes.
+fn sniff_delimiter(text: &str) -> char {
+    let mut counts = [(',', 0), (';', 0), ('\t', 0), ('|', 0)];
+    let mut quoted = false;
+    for c in text.chars() {
+        if c == '\n' && !quoted {
+            break;
+        }
+        if c == '"' {
+            quoted = !quoted;
+        } else if !quoted {
+            if let Some(count) = counts.iter_mut().find(|(d, _)| *d == c) {
+                count.1 += 1;
+            }
+        }
+    }
+    counts.iter().max_by_key(|(_, n)| *n).filter(|(_, n)| *n > 0).map(|(d, _)| *d).unwrap_or(',')
+}
+
+async fn preview_csv(client: &Client, bucket: &str, key: &str, max_rows: usize) -> Result<TablePreview, AppError> {
+    let resp = client.get_object()
+        .bucket(bucket)
+        .key(key)
+        .range(format!("bytes=0-{}", CSV_BYTES - 1))
+        .send()
+        .await?;
+    let size = text::object_size(resp.content_range());
+    let data = resp.body.collect().await.map_err(|e| e.to_string())?.into_bytes();
+    let cut = size.is_some_and(|s| s > data.len() as u64);
+    let (text, _) = text::decode(&data, cut).map_err(|e| AppError::new(ErrorKind::Unsupported, e))?;
+
+    let delimiter = if key.to_ascii_lowercase().ends_with(".tsv") { '\t' } else { sniff_delimiter(&text) };
+    let mut records = parse_csv(&text, delimiter);
+    if cut {
+        records.pop();
+    }
+    let mut records = records.into_iter();
+    let columns = records.next().unwrap_or_default();
+    let rows: Vec<Vec<Value>> = records.by_ref().take(max_rows).map(|r| r.into_iter().map(Value::String).collect()).collect();
+    let truncated = records.next().is_some() || cut;
+    Ok(TablePreview { format: TableFormat::Csv, columns, rows, total_rows: None, truncated })
+}
+
+/// The parts of a Parquet file that were fetched: the footer and the first row group. Reads
+/// outside them fail, which never happens for the first row group's rows.
+struct Sparse {
+    len: u64,
+    segments: Vec<(u64, Bytes)>,
+}
+
+impl Sparse {
+    fn find(&self, start: u64, length: u64) -> parquet::errors::Result<Bytes> {
+        self.segments
+            .iter()
+            .find(|(at, bytes)| start >= *at && start + length <= at + bytes.len() as u64)
+            .map(|(at, bytes)| bytes.slice((start - at) as usize..(start - at + length) as usize))
+            .ok_or_else(|| parquet::errors::ParquetError::General(format!("Range {}+{} was not fetched", start, length)))
+    }
+}
+
+impl Length for Sparse {
+    fn len(&self) -> u64 {
+        self.len
+    }
+}
+
+impl ChunkReader for Sparse {
+    type T = Cursor<Bytes>;
+
+    fn get_read(&self, start: u64) -> parquet::errors::Result<Self::T> {
+        let (at, bytes) = self.segments
+            .iter()
+            .find(|(at, bytes)| start >= *at && start < at + bytes.len() as u64)
+            .ok_or_else(|| parquet::errors::ParquetError::General(format!("Offset {} was not fetched", start)))?;
+        Ok(Cursor::new(bytes.slice((start - at) as usize..)))
+    }
+
+    fn get_bytes(&self, start: u64, length: usize) -> parquet::errors::Result<Bytes> {
+        self.find(start, length as u64)
+    }
+}
+
+async fn fetch(client: &Client, bucket: &str, key: &str, range: String) -> Result<(Bytes, Option<u64>), AppError> {
+    let resp = client.get_object().bucket(bucket).key(key).range(range).send().await?;
+    let size = text::object_size(resp.content_range());
+    Ok((resp.body.collect().await.map_err(|e| e.to_string())?.into_bytes(), size))
+}
+
+fn invalid_parquet(e: impl std::fmt::Display) -> AppError {
+    AppError::new(ErrorKind::Unsupported, format!("Cannot read Parquet: {}", e))
+}
+
+async fn preview_parquet(client: &Client, bucket: &str, key: &str, max_rows: usize) -> Result<TablePreview, AppError> {
+    // Footer: metadata, its length and the PAR1 magic, all at the end
+    let (mut tail, size) = fetch(client, bucket, key, format!("bytes=-{}", PARQUET_TAIL_BYTES)).await?;
+    let len = size.unwrap_or(tail.len() as u64);
+    if tail.len() < 8 || &tail[tail.len() - 4..] != b"PAR1" {
+        return Err(invalid_parquet("not a Parquet file"));
+    }
+    let metadata_len = u32::from_le_bytes(tail[tail.len() - 8..tail.len() - 4].try_into().unwrap()) as u64;
+    if metadata_len + 8 > tail.len() as u64 {
+        let start = len.checked_sub(metadata_len + 8).ok_or_else(|| invalid_parquet("footer is larger than the file"))?;
+        tail = fetch(client, bucket, key, format!("bytes={}-", start)).await?.0;
+    }
+    let mut sparse = Sparse { len, segments: vec![(len - tail.len() as u64, tail)] };
+    let metadata = ParquetMetaDataReader::new().parse_and_finish(&sparse).map_err(invalid_parquet)?;
+
+    let columns: Vec<String> = metadata.file_metadata().schema_descr().root_schema().get_fields().iter().map(|f| f.name().to_string()).collect();
+    let total_rows = metadata.file_metadata().num_rows().max(0) as u64;
+    let Some(group) = metadata.row_groups().first() else {
+        return Ok(TablePreview { format: TableFormat::Parquet, columns, rows: Vec::new(), total_rows: Some(0), truncated: false });
+    };
+
+    let (start, end) = group.columns().iter().map(|c| c.byte_range()).fold((u64::MAX, 0), |(start, end), (at, n)| (start.min(at), end.max(at + n)));
+    if end.saturating_sub(start) > MAX_ROW_GROUP_BYTES {
+        return Err(AppError::new(ErrorKind::Unsupported, "First row group is too large to preview"));
+    }
+    if end > start {
+        sparse.segments.push((start, fetch(client, bucket, key, format!("bytes={}-{}", start, end - 1)).await?.0));
+    }
+
+    let rows = tauri::async_runtime::spawn_blocking(move || {
+        let reader = SerializedFileReader::new(sparse).map_err(invalid_parquet)?;
+        let group = reader.get_row_group(0).map_err(invalid_parquet)?;
+        let mut rows = Vec::new();
+        for row in group.get_row_iter(None).map_err(invalid_parquet)?.take(max_rows) {
+            let row = row.map_err(invalid_parquet)?;
+            rows.push(row.get_column_iter().map(|(_, field)| field.to_json_value()).collect());
+        }
+        Ok::<Vec<Vec<Value>>, AppError>(rows)
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+
+    let truncated = (rows.len() as u64) < total_rows;
+    Ok(TablePreview { format: TableFormat::Parquet, columns, rows, total_rows: Some(total_rows), truncated })
+}
+
+/// Column names and the first `max_rows` rows (100 by default, up to 1000) of a CSV/TSV or
+/// Parquet object, for a data-grid preview. Only the head of a CSV is read; for Parquet just the
+/// footer and the first row group.
+#[tauri::command]
+pub async fn preview_table(
+    bucket: String,
+    key: String,
+    max_rows: Option<usize>,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<TablePreview, AppError> {
+    let max_rows = max_rows.unwrap_or(DEFAULT_ROWS).clamp(1, MAX_ROWS);
+    let format = format_of(&key)
+        .ok_or_else(|| AppError::new(ErrorKind::Unsupported, "Only CSV, TSV and Parquet files can be previewed as a table"))?;
+    let client = acquire_client(&state, connection_id.as_deref())?;
+
+    match format {
+        TableFormat::Csv => preview_csv(&client, &bucket, &key, max_rows).await,
+        TableFormat::Parquet => preview_parquet(&client, &bucket, &key, max_rows).await,
+    }
+}
@@ -0,0 +1,421 @@
+use aws_sdk_s3::Client;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use aws_sdk_s3::primitives::ByteStream;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::error::{AppError, ErrorKind};
+use crate::s3::{acquire_client, acquire_write_client, default_text_content_type, AppState};
+use crate::validate;
+
+const DEFAULT_PREVIEW_BYTES: u64 = 256 * 1024;
+const MAX_PREVIEW_BYTES: u64 = 5 * 1024 * 1024;
+const DEFAULT_TAIL_BYTES: u64 = 64 * 1024;
+const DEFAULT_TAIL_INTERVAL_MS: u64 = 2000;
+const MIN_TAIL_INTERVAL_MS: u64 = 500;
+const MAX_BINARY_RANGE: u64 = 1024 * 1024;
+
+#[derive(Default)]
+pub struct TailState {
+    next_id: AtomicU64,
+    tails: Mutex<HashMap<u64, JoinHandle<()>>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TailStart {
+    /// Set when following; pass to `stop_tail`.
+    pub tail_id: Option<u64>,
+    pub text: String,
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BinaryRange {
+    pub offset: u64,
+    /// Base64 of the bytes read; shorter than asked for at the end of the object.
+    pub data: String,
+    /// Size of the whole object.
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SavedText {
+    /// New ETag, to pass as `expected_etag` on the next save.
+    pub etag: String,
+    pub size: u64,
+}
+
+/// Payload of `tail://data` (appended text) and `tail://reset` (the object shrank, was replaced
+/// or grew by more than `max_bytes` between polls; `text` is its new tail).
+#[derive(Debug, Clone, Serialize)]
+pub struct TailData {
+    pub tail_id: u64,
+    pub text: String,
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TextEncoding {
+    #[serde(rename = "utf-8")]
+    Utf8,
+    #[serde(rename = "utf-16le")]
+    Utf16le,
+    #[serde(rename = "utf-16be")]
+    Utf16be,
+    /// Anything that isn't valid UTF-8 is read as ISO-8859-1, which never fails.
+    #[serde(rename = "latin-1")]
+    Latin1,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TextPreview {
+    pub text: String,
+    pub encoding: TextEncoding,
+    /// Only the start of the object was read.
+    pub truncated: bool,
+    /// Size of the whole object.
+    pub size: u64,
+    /// For saving edits back with `write_text_file`.
+    pub etag: Option<String>,
+}
+
+fn utf16(bytes: &[u8], little_endian: bool) -> String {
+    let units: Vec<u16> = bytes
+        .chunks_exact(2)
+        .map(|pair| if little_endian { u16::from_le_bytes([pair[0], pair[1]]) } else { u16::from_be_bytes([pair[0], pair[1]]) })
+        .collect();
+    // A cut-off surrogate pair at the end just becomes a replacement character
+    String::from_utf16_lossy(&units)
+}
+
+/// Guesses UTF-16 without a BOM from the zero bytes ASCII text leaves in every other position.
+fn sniff_utf16(bytes: &[u8]) -> Option<bool> {
+    let sample = &bytes[..bytes.len().min(4096) & !1];
+    if sample.len() < 4 {
+        return None;
+    }
+    let pairs = sample.len() / 2;
+    let zero_odd = sample.iter().skip(1).step_by(2).filter(|b| **b == 0).count();
+    let zero_even = sample.iter().step_by(2).filter(|b| **b == 0).count();
+    if zero_odd * 10 >= pairs * 7 && zero_even * 10 < pairs {
+        Some(true)
+    } else if zero_even * 10 >= pairs * 7 && zero_odd * 10 < pairs {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+/// Decodes text in UTF-8, UTF-16 (by BOM or sniffing) or Latin-1. When `truncated`, a character
+/// cut off at the end is dropped rather than treated as invalid. Fails on binary data.
+pub fn decode(bytes: &[u8], truncated: bool) -> Result<(String, TextEncoding), String> {
+    if let Some(rest) = bytes.strip_prefix(&[0xEF, 0xBB, 0xBF]) {
+        return decode_utf8(rest, truncated).map(|t| (t, TextEncoding::Utf8)).ok_or_else(|| "File is not valid UTF-8".to_string());
+    }
+    if let Some(rest) = bytes.strip_prefix(&[0xFF, 0xFE]) {
+        return Ok((utf16(rest, true), TextEncoding::Utf16le));
+    }
+    if let Some(rest) = bytes.strip_prefix(&[0xFE, 0xFF]) {
+        return Ok((utf16(rest, false), TextEncoding::Utf16be));
+    }
+    if let Some(little_endian) = sniff_utf16(bytes) {
+        let encoding = if little_endian { TextEncoding::Utf16le } else { TextEncoding::Utf16be };
+        return Ok((utf16(bytes, little_endian), encoding));
+    }
+
+    // NULs don't occur in 8-bit text
+    if bytes.contains(&0) {
+        return Err("File is not valid text".to_string());
+    }
+    match decode_utf8(bytes, truncated) {
+        Some(text) => Ok((text, TextEncoding::Utf8)),
+        None => Ok((bytes.iter().map(|b| *b as char).collect(), TextEncoding::Latin1)),
+    }
+}
+
+fn decode_utf8(bytes: &[u8], truncated: bool) -> Option<String> {
+    match std::str::from_utf8(bytes) {
+        Ok(text) => Some(text.to_string()),
+        // `error_len` is None when the input just ends mid-character
+        Err(e) if truncated && e.error_len().is_none() => Some(String::from_utf8_lossy(&bytes[..e.valid_up_to()]).into_owned()),
+        Err(_) => None,
+    }
+}
+
+/// Encodes edited text back into the encoding it was read in. UTF-16 gets a BOM so it is
+/// recognised again on the next read.
+pub fn encode(text: &str, encoding: TextEncoding) -> Result<Vec<u8>, String> {
+    match encoding {
+        TextEncoding::Utf8 => Ok(text.as_bytes().to_vec()),
+        TextEncoding::Utf16le => Ok([0xFF, 0xFE].into_iter().chain(text.encode_utf16().flat_map(u16::to_le_bytes)).collect()),
+        TextEncoding::Utf16be => Ok([0xFE, 0xFF].into_iter().chain(text.encode_utf16().flat_map(u16::to_be_bytes)).collect()),
+        TextEncoding::Latin1 => text
+            .chars()
+            .map(|c| u8::try_from(c).map_err(|_| format!("'{}' cannot be saved as Latin-1", c)))
+            .collect(),
+    }
+}
+
+/// The full object size from a ranged response's Content-Range ("bytes 0-N/total").
+pub fn object_size(content_range: Option<&str>) -> Option<u64> {
+    content_range?.rsplit('/').next()?.parse().ok()
+}
+
+/// Previews a text object by reading at most `max_bytes` (256 KB by default, up to 5 MB) from
+/// its start. Big logs and CSVs come back cut short with `truncated` set.
+#[tauri::command]
+pub async fn read_text_file(
+    bucket: String,
+    key: String,
+    max_bytes: Option<u64>,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<TextPreview, AppError> {
+    let max_bytes = max_bytes.unwrap_or(DEFAULT_PREVIEW_BYTES).clamp(1, MAX_PREVIEW_BYTES);
+    let client = acquire_client(&state, connection_id.as_deref())?;
+
+    let resp = match client.get_object()
+        .bucket(&bucket)
+        .key(&key)
+        .range(format!("bytes=0-{}", max_bytes - 1))
+        .send()
+        .await
+    {
+        Ok(resp) => resp,
+        // Any range is unsatisfiable on an empty object
+        Err(e) if e.raw_response().is_some_and(|r| r.status().as_u16() == 416) => {
+            let head = client.head_object().bucket(&bucket).key(&key).send().await?;
+            let etag = head.e_tag().map(|e| e.trim_matches('"').to_string());
+            return Ok(TextPreview { text: String::new(), encoding: TextEncoding::Utf8, truncated: false, size: 0, etag });
+        }
+        Err(e) => return Err(e.into()),
+    };
+    let total = object_size(resp.content_range());
+    let etag = resp.e_tag().map(|e| e.trim_matches('"').to_string());
+    let data = resp.body.collect().await.map_err(|e| e.to_string())?.into_bytes();
+    let size = total.unwrap_or(data.len() as u64);
+    let truncated = size > data.len() as u64;
+
+    let (text, encoding) = decode(&data, truncated).map_err(|e| AppError::new(ErrorKind::Unsupported, e))?;
+    Ok(TextPreview { text, encoding, truncated, size, etag })
+}
+
+/// Raw bytes from anywhere in an object, for a hex inspector. At most 1 MB per call; reading
+/// past the end returns no data.
+#[tauri::command]
+pub async fn read_binary_range(
+    bucket: String,
+    key: String,
+    offset: u64,
+    length: u64,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<BinaryRange, AppError> {
+    if length == 0 || length > MAX_BINARY_RANGE {
+        return Err(AppError::new(ErrorKind::InvalidInput, format!("Length must be between 1 and {} bytes", MAX_BINARY_RANGE)));
+    }
+    let client = acquire_client(&state, connection_id.as_deref())?;
+
+    let resp = match client.get_object()
+        .bucket(&bucket)
+        .key(&key)
+        .range(format!("bytes={}-{}", offset, offset.saturating_add(length - 1)))
+        .send()
+        .await
+    {
+        Ok(resp) => resp,
+        // Offset at or past the end
+        Err(e) if e.raw_response().is_some_and(|r| r.status().as_u16() == 416) => {
+            let size = size_of(&client, &bucket, &key).await?;
+            return Ok(BinaryRange { offset, data: String::new(), size });
+        }
+        Err(e) => return Err(e.into()),
+    };
+    let total = object_size(resp.content_range());
+    let data = resp.body.collect().await.map_err(|e| e.to_string())?.into_bytes();
+    let size = total.unwrap_or(offset + data.len() as u64);
+    Ok(BinaryRange { offset, data: STANDARD.encode(&data), size })
+}
+
+/// Saves edited text over an object opened with `read_text_file`, but only if its ETag is still
+/// `expected_etag`; a concurrent change fails with PreconditionFailed instead of being lost.
+/// Content type and metadata are kept. `encoding` defaults to UTF-8.
+#[tauri::command]
+pub async fn write_text_file(
+    bucket: String,
+    key: String,
+    content: String,
+    expected_etag: String,
+    encoding: Option<TextEncoding>,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<SavedText, AppError> {
+    if let Some(err) = validate::check_syntax(&key, &content) {
+        return Err(AppError::new(ErrorKind::InvalidInput, err.to_string()));
+    }
+    let data = encode(&content, encoding.unwrap_or(TextEncoding::Utf8)).map_err(|e| AppError::new(ErrorKind::InvalidInput, e))?;
+    let expected = expected_etag.trim_matches('"');
+    let client = acquire_write_client(&state, connection_id.as_deref())?;
+
+    let modified = || AppError::new(ErrorKind::PreconditionFailed, "The file was changed or deleted since it was opened");
+    let head = match client.head_object().bucket(&bucket).key(&key).send().await {
+        Ok(head) => head,
+        Err(e) if e.as_service_error().is_some_and(|se| se.is_not_found()) => return Err(modified()),
+        Err(e) => return Err(e.into()),
+    };
+    if head.e_tag().map(|e| e.trim_matches('"')) != Some(expected) {
+        return Err(modified());
+    }
+
+    let size = data.len() as u64;
+    let content_type = head.content_type().map(|c| c.to_string()).unwrap_or_else(|| default_text_content_type(&key).to_string());
+    // If-Match closes the gap between the check above and the write
+    let resp = client.put_object()
+        .bucket(&bucket)
+        .key(&key)
+        .if_match(format!("\"{}\"", expected))
+        .content_type(content_type)
+        .set_metadata(head.metadata().cloned())
+        .set_cache_control(head.cache_control().map(|c| c.to_string()))
+        .set_content_disposition(head.content_disposition().map(|c| c.to_string()))
+        .set_content_encoding(head.content_encoding().map(|c| c.to_string()))
+        .body(ByteStream::from(data))
+        .send()
+        .await;
+    match resp {
+        Ok(out) => Ok(SavedText { etag: out.e_tag().unwrap_or_default().trim_matches('"').to_string(), size }),
+        Err(e) if e.raw_response().is_some_and(|r| r.status().as_u16() == 412) => Err(modified()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+async fn size_of(client: &Client, bucket: &str, key: &str) -> Result<u64, AppError> {
+    let head = client.head_object().bucket(bucket).key(key).send().await?;
+    Ok(head.content_length().unwrap_or(0).max(0) as u64)
+}
+
+async fn read_range(client: &Client, bucket: &str, key: &str, start: u64, end: u64) -> Result<Vec<u8>, AppError> {
+    if end <= start {
+        return Ok(Vec::new());
+    }
+    let resp = client.get_object()
+        .bucket(bucket)
+        .key(key)
+        .range(format!("bytes={}-{}", start, end - 1))
+        .send()
+        .await?;
+    Ok(resp.body.collect().await.map_err(|e| e.to_string())?.into_bytes().to_vec())
+}
+
+/// Splits off a UTF-8 character cut at the end of `bytes`, to be completed by the next read.
+fn take_complete(bytes: &mut Vec<u8>) -> String {
+    let complete = match std::str::from_utf8(bytes) {
+        Err(e) if e.error_len().is_none() => e.valid_up_to(),
+        _ => bytes.len(),
+    };
+    let rest = bytes.split_off(complete);
+    let text = String::from_utf8_lossy(bytes).into_owned();
+    *bytes = rest;
+    text
+}
+
+/// The last `max_bytes` of the object as text, starting at a line boundary when one is near.
+async fn read_tail(client: &Client, bucket: &str, key: &str, size: u64, max_bytes: u64) -> Result<(String, Vec<u8>), AppError> {
+    let start = size.saturating_sub(max_bytes);
+    let mut bytes = read_range(client, bucket, key, start, size).await?;
+    if start > 0 {
+        // Drop the partial first line so the tail doesn't open mid-character or mid-line
+        if let Some(newline) = bytes.iter().position(|b| *b == b'\n') {
+            bytes.drain(..=newline);
+        }
+    }
+    let text = take_complete(&mut bytes);
+    Ok((text, bytes))
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn follow(app: &AppHandle, connection_id: Option<&str>, tail_id: u64, bucket: &str, key: &str, mut size: u64, mut carry: Vec<u8>, max_bytes: u64, interval: Duration) -> Result<(), AppError> {
+    let state = app.state::<AppState>();
+    loop {
+        tokio::time::sleep(interval).await;
+        // Only hold a connection slot while polling
+        let client = acquire_client(&state, connection_id)?;
+        let current = size_of(&client, bucket, key).await?;
+
+        // Shrinking means it was replaced; growing by more than a tail is too much to send as
+        // appended text, so both start over from the new tail
+        if current < size || current - size > max_bytes {
+            let (text, rest) = read_tail(&client, bucket, key, current, max_bytes).await?;
+            carry = rest;
+            let _ = app.emit("tail://reset", TailData { tail_id, text, size: current });
+        } else if current > size {
+            carry.extend(read_range(&client, bucket, key, size, current).await?);
+            let text = take_complete(&mut carry);
+            if !text.is_empty() {
+                let _ = app.emit("tail://data", TailData { tail_id, text, size: current });
+            }
+        }
+        size = current;
+    }
+}
+
+/// The last `max_bytes` (64 KB by default) of a text object such as a log. With `follow`, the
+/// object is polled every `interval_ms` and appended text arrives as `tail://data` until
+/// `stop_tail`; a failure ends it with `tail://error`.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn tail_object(
+    bucket: String,
+    key: String,
+    follow: bool,
+    max_bytes: Option<u64>,
+    interval_ms: Option<u64>,
+    app: AppHandle,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+    tail_state: State<'_, TailState>,
+) -> Result<TailStart, AppError> {
+    let max_bytes = max_bytes.unwrap_or(DEFAULT_TAIL_BYTES).clamp(1, MAX_PREVIEW_BYTES);
+    let (size, text, carry) = {
+        let client = acquire_client(&state, connection_id.as_deref())?;
+        let size = size_of(&client, &bucket, &key).await?;
+        let (text, carry) = read_tail(&client, &bucket, &key, size, max_bytes).await?;
+        (size, text, carry)
+    };
+    if !follow {
+        return Ok(TailStart { tail_id: None, text, size });
+    }
+
+    let interval = Duration::from_millis(interval_ms.unwrap_or(DEFAULT_TAIL_INTERVAL_MS).max(MIN_TAIL_INTERVAL_MS));
+    let tail_id = tail_state.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+
+    // Registered before it can finish, as with searches
+    let mut tails = tail_state.tails.lock().unwrap();
+    let handle = tauri::async_runtime::spawn(async move {
+        let result = self::follow(&app, connection_id.as_deref(), tail_id, &bucket, &key, size, carry, max_bytes, interval).await;
+        if let Err(e) = result {
+            let _ = app.emit("tail://error", (tail_id, e));
+        }
+        app.state::<TailState>().tails.lock().unwrap().remove(&tail_id);
+    });
+    tails.insert(tail_id, handle);
+
+    Ok(TailStart { tail_id: Some(tail_id), text, size })
+}
+
+#[tauri::command]
+pub fn stop_tail(tail_id: u64, tail_state: State<'_, TailState>) -> bool {
+    match tail_state.tails.lock().unwrap().remove(&tail_id) {
+        Some(handle) => {
+            handle.abort();
+            true
+        }
+        None => false,
+    }
+}
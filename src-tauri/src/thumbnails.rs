@@ -0,0 +1,131 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use image::{DynamicImage, ImageFormat};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::io::Cursor;
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager, State};
+
+use crate::error::{AppError, ErrorKind};
+use crate::s3::{acquire_client, AppState};
+
+// Thumbnails are cached under thumbnails/ in the app cache dir, keyed by bucket, key, ETag and
+// size, so a changed object never serves a stale thumbnail
+pub const MAX_SOURCE_BYTES: i64 = 50 * 1024 * 1024;
+const DEFAULT_DIMENSION: u32 = 256;
+const MAX_DIMENSION: u32 = 2048;
+const JPEG_QUALITY: u8 = 80;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Thumbnail {
+    /// Cached file on disk.
+    pub path: String,
+    pub content_type: String,
+    /// Base64 of the file, ready for a data: URL.
+    pub data: String,
+    pub width: u32,
+    pub height: u32,
+    pub cached: bool,
+}
+
+pub fn cache_dir(app: &AppHandle, kind: &str) -> Result<PathBuf, String> {
+    let dir = app.path().app_cache_dir().map_err(|e| e.to_string())?.join(kind);
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    Ok(dir)
+}
+
+pub fn clamp_dimension(max_dimension: Option<u32>) -> u32 {
+    max_dimension.unwrap_or(DEFAULT_DIMENSION).clamp(16, MAX_DIMENSION)
+}
+
+pub fn cache_name(bucket: &str, key: &str, etag: &str, variant: &str) -> String {
+    format!("{:x}", Sha256::digest(format!("{}\0{}\0{}\0{}", bucket, key, etag, variant).as_bytes()))
+}
+
+/// Scales `source` to fit within `max_dimension` (never up) and encodes it: PNG when it has
+/// transparency, JPEG otherwise.
+pub fn encode_thumbnail(source: DynamicImage, max_dimension: u32) -> Result<Vec<u8>, String> {
+    let scaled = if source.width() > max_dimension || source.height() > max_dimension {
+        source.thumbnail(max_dimension, max_dimension)
+    } else {
+        source
+    };
+
+    let mut out = Vec::new();
+    if scaled.color().has_alpha() {
+        scaled.write_to(&mut Cursor::new(&mut out), ImageFormat::Png).map_err(|e| e.to_string())?;
+    } else {
+        let encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(&mut out, JPEG_QUALITY);
+        scaled.to_rgb8().write_with_encoder(encoder).map_err(|e| e.to_string())?;
+    }
+    Ok(out)
+}
+
+pub fn from_encoded(path: PathBuf, bytes: &[u8], cached: bool) -> Result<Thumbnail, String> {
+    let format = image::guess_format(bytes).map_err(|e| e.to_string())?;
+    let (width, height) = image::ImageReader::with_format(Cursor::new(bytes), format)
+        .into_dimensions()
+        .map_err(|e| e.to_string())?;
+    Ok(Thumbnail {
+        path: path.to_string_lossy().to_string(),
+        content_type: format.to_mime_type().to_string(),
+        data: STANDARD.encode(bytes),
+        width,
+        height,
+        cached,
+    })
+}
+
+/// A resized JPEG/PNG of an image object, for grid views. Decodes JPEG, PNG, GIF, WebP, BMP and
+/// TIFF up to 50 MB.
+#[tauri::command]
+pub async fn get_thumbnail(
+    bucket: String,
+    key: String,
+    max_dimension: Option<u32>,
+    app: AppHandle,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Thumbnail, AppError> {
+    let max_dimension = clamp_dimension(max_dimension);
+    let client = acquire_client(&state, connection_id.as_deref())?;
+
+    let head = client.head_object().bucket(&bucket).key(&key).send().await?;
+    let etag = head.e_tag().unwrap_or_default().trim_matches('"').to_string();
+    let path = cache_dir(&app, "thumbnails")?.join(cache_name(&bucket, &key, &etag, &max_dimension.to_string()));
+    if let Ok(bytes) = tokio::fs::read(&path).await {
+        return Ok(from_encoded(path, &bytes, true)?);
+    }
+
+    if head.content_length().unwrap_or(0) > MAX_SOURCE_BYTES {
+        return Err(AppError::new(ErrorKind::InvalidInput, "Image too large to thumbnail"));
+    }
+    let resp = client.get_object().bucket(&bucket).key(&key).send().await?;
+    let source = resp.body.collect().await.map_err(|e| e.to_string())?.into_bytes();
+
+    let encoded = tauri::async_runtime::spawn_blocking(move || {
+        let image = image::load_from_memory(&source)
+            .map_err(|e| AppError::new(ErrorKind::Unsupported, format!("Cannot decode image: {}", e)))?;
+        Ok::<_, AppError>(encode_thumbnail(image, max_dimension)?)
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+
+    tokio::fs::write(&path, &encoded).await.map_err(|e| e.to_string())?;
+    Ok(from_encoded(path, &encoded, false)?)
+}
+
+/// Deletes every cached thumbnail. Returns the bytes freed.
+#[tauri::command]
+pub fn clear_thumbnail_cache(app: AppHandle) -> Result<u64, AppError> {
+    let dir = cache_dir(&app, "thumbnails")?;
+    let mut freed = 0;
+    for entry in std::fs::read_dir(&dir).map_err(|e| e.to_string())?.flatten() {
+        let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+        if std::fs::remove_file(entry.path()).is_ok() {
+            freed += size;
+        }
+    }
+    Ok(freed)
+}
@@ -0,0 +1,146 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use tauri::menu::{Menu, MenuEvent, MenuItem, PredefinedMenuItem, Submenu};
+use tauri::tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent};
+use tauri::{AppHandle, CloseRequestApi, Emitter, Manager, State, Window, Wry};
+
+use crate::error::AppError;
+use crate::jobs::{self, JobRecord};
+use crate::s3::AppState;
+
+// Tray icon that keeps the app reachable while its window is closed: transfers, syncs and
+// watches carry on in the backend, and the menu can reopen the window, pause everything or show
+// how the last few transfers went.
+const TRAY_ID: &str = "main";
+const OPEN_ID: &str = "open";
+const PAUSE_ID: &str = "pause";
+const QUIT_ID: &str = "quit";
+const RECENT_LIMIT: usize = 5;
+
+pub struct TrayState {
+    pause: MenuItem<Wry>,
+    recent: Submenu<Wry>,
+    /// Menu labels, newest first.
+    recent_labels: Mutex<VecDeque<String>>,
+}
+
+pub fn show_window(app: &AppHandle) {
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.unminimize();
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+}
+
+fn on_menu_event(app: &AppHandle, event: MenuEvent) {
+    match event.id().as_ref() {
+        OPEN_ID => show_window(app),
+        PAUSE_ID => {
+            let paused = *app.state::<AppState>().paused.borrow();
+            set_paused(app, !paused);
+        }
+        QUIT_ID => app.exit(0),
+        _ => {}
+    }
+}
+
+/// Adds the tray icon. Platforms without a tray (or without the libraries for one) simply go
+/// without, and closing the window then quits as usual.
+pub fn init(app: &AppHandle) -> tauri::Result<()> {
+    let pause = MenuItem::with_id(app, PAUSE_ID, "Pause all transfers", true, None::<&str>)?;
+    let none = MenuItem::new(app, "No transfers yet", false, None::<&str>)?;
+    let recent = Submenu::with_items(app, "Recent transfers", true, &[&none])?;
+    let menu = Menu::with_items(app, &[
+        &MenuItem::with_id(app, OPEN_ID, "Open R2Drive", true, None::<&str>)?,
+        &pause,
+        &recent,
+        &PredefinedMenuItem::separator(app)?,
+        &MenuItem::with_id(app, QUIT_ID, "Quit", true, None::<&str>)?,
+    ])?;
+
+    let mut tray = TrayIconBuilder::with_id(TRAY_ID)
+        .tooltip("R2Drive")
+        .menu(&menu)
+        .show_menu_on_left_click(false)
+        .on_menu_event(on_menu_event)
+        .on_tray_icon_event(|tray, event| {
+            if let TrayIconEvent::Click { button: MouseButton::Left, button_state: MouseButtonState::Up, .. } = event {
+                show_window(tray.app_handle());
+            }
+        });
+    if let Some(icon) = app.default_window_icon() {
+        tray = tray.icon(icon.clone());
+    }
+    tray.build(app)?;
+
+    app.manage(TrayState { pause, recent, recent_labels: Mutex::new(VecDeque::new()) });
+    Ok(())
+}
+
+/// Hides the window instead of closing it when there is a tray to come back from and
+/// `close_to_tray` is on.
+pub fn close_requested(window: &Window, api: &CloseRequestApi) {
+    let app = window.app_handle();
+    if app.try_state::<TrayState>().is_none() || !app.state::<AppState>().settings.lock().unwrap().close_to_tray {
+        return;
+    }
+    api.prevent_close();
+    let _ = window.hide();
+}
+
+/// Adds a finished transfer job to the tray's recent list.
+pub fn record(app: &AppHandle, job: &JobRecord) {
+    if !jobs::TRANSFER_KINDS.contains(&job.kind.as_str()) {
+        return;
+    }
+    let Some(tray) = app.try_state::<TrayState>() else { return };
+    let label = match &job.error {
+        None => format!("✓ {}", job.summary),
+        Some(error) => format!("✗ {}: {}", job.summary, error.lines().next().unwrap_or_default()),
+    };
+    let label: String = if label.chars().count() > 80 { format!("{}…", label.chars().take(79).collect::<String>()) } else { label };
+
+    let labels: Vec<String> = {
+        let mut recent = tray.recent_labels.lock().unwrap();
+        recent.push_front(label);
+        recent.truncate(RECENT_LIMIT);
+        recent.iter().cloned().collect()
+    };
+    // Rebuilt wholesale; the list is only a handful of items
+    if let Ok(items) = tray.recent.items() {
+        for item in items {
+            let _ = tray.recent.remove(&item);
+        }
+    }
+    for label in labels {
+        if let Ok(item) = MenuItem::new(app, label, false, None::<&str>) {
+            let _ = tray.recent.append(&item);
+        }
+    }
+}
+
+/// Pauses or resumes every transfer, keeping the tray menu in step, and tells the UI through
+/// `transfers://paused`.
+pub fn set_paused(app: &AppHandle, paused: bool) {
+    app.state::<AppState>().paused.send_replace(paused);
+    if let Some(tray) = app.try_state::<TrayState>() {
+        let _ = tray.pause.set_text(if paused { "Resume transfers" } else { "Pause all transfers" });
+    }
+    if let Some(icon) = app.tray_by_id(TRAY_ID) {
+        let _ = icon.set_tooltip(Some(if paused { "R2Drive (paused)" } else { "R2Drive" }));
+    }
+    let _ = app.emit("transfers://paused", paused);
+}
+
+/// Pauses transfers before their next file or chunk, or lets them go on. Requests already
+/// under way finish first.
+#[tauri::command]
+pub fn set_transfers_paused(paused: bool, app: AppHandle) -> Result<(), AppError> {
+    set_paused(&app, paused);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn transfers_paused(state: State<'_, AppState>) -> bool {
+    *state.paused.borrow()
+}
@@ -0,0 +1,224 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager, State, Url};
+use tauri_plugin_updater::{Update, UpdaterExt};
+
+use crate::error::{AppError, ErrorKind};
+use crate::s3::AppState;
+use crate::settings::{self, ProxyMode};
+
+// Self-updates through the Tauri updater plugin. Each channel has its own manifest: stable is the
+// latest GitHub release, beta a rolling "beta" release whose manifest every prerelease replaces.
+// A manifest may carry a `rollout` percentage; installs are placed in a fixed cohort (0-99) on
+// their first check and only see the release once the rollout reaches them. Update signatures are
+// checked against the key baked in at build time through R2DRIVE_UPDATER_PUBKEY; builds without
+// one don't update themselves.
+pub const PUBKEY: Option<&str> = option_env!("R2DRIVE_UPDATER_PUBKEY");
+const STABLE_ENDPOINT: &str = "https://github.com/flamacore/R2Drive/releases/latest/download/latest.json";
+const BETA_ENDPOINT: &str = "https://github.com/flamacore/R2Drive/releases/download/beta/latest.json";
+/// Bytes between `update://progress` events.
+const PROGRESS_STEP: u64 = 512 * 1024;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReleaseChannel {
+    #[default]
+    Stable,
+    Beta,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UpdateSettings {
+    pub channel: ReleaseChannel,
+    /// Check at startup and announce a new version with `update://available`.
+    pub auto_check: bool,
+    /// This install's place in staged rollouts, assigned on the first check.
+    pub cohort: Option<u8>,
+}
+
+impl Default for UpdateSettings {
+    fn default() -> Self {
+        Self { channel: ReleaseChannel::Stable, auto_check: true, cohort: None }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UpdateInfo {
+    pub version: String,
+    pub current_version: String,
+    pub notes: Option<String>,
+    /// Unix seconds.
+    pub date: Option<i64>,
+    pub channel: ReleaseChannel,
+    /// Downloaded and ready to install.
+    pub downloaded: bool,
+}
+
+/// Payload of `update://progress`.
+#[derive(Debug, Clone, Serialize)]
+pub struct UpdateProgress {
+    pub version: String,
+    pub downloaded: u64,
+    pub total: Option<u64>,
+}
+
+#[derive(Default)]
+pub struct UpdateState {
+    /// The update found by the last check.
+    available: Mutex<Option<(Update, ReleaseChannel)>>,
+    /// Verified installer bytes and the version they are for.
+    downloaded: Mutex<Option<(String, Vec<u8>)>>,
+    downloading: AtomicBool,
+}
+
+fn info(update: &Update, channel: ReleaseChannel, downloaded: bool) -> UpdateInfo {
+    UpdateInfo {
+        version: update.version.clone(),
+        current_version: update.current_version.clone(),
+        notes: update.body.clone(),
+        date: update.date.map(|d| d.unix_timestamp()),
+        channel,
+        downloaded,
+    }
+}
+
+/// The cohort from settings, assigned and saved the first time.
+fn cohort(app: &AppHandle) -> Result<u8, AppError> {
+    let state = app.state::<AppState>();
+    if let Some(cohort) = state.settings.lock().unwrap().updates.cohort {
+        return Ok(cohort);
+    }
+    let cohort = rand::thread_rng().gen_range(0..100);
+    let mut settings = state.settings.lock().unwrap().clone();
+    settings.updates.cohort = Some(cohort);
+    settings::save(app, &settings)?;
+    state.apply_settings(settings);
+    Ok(cohort)
+}
+
+/// Whether a release with this manifest has reached `cohort`. Manifests without a `rollout`
+/// are out for everyone.
+fn reached(update: &Update, cohort: u8) -> bool {
+    match update.raw_json.get("rollout").and_then(|r| r.as_f64()) {
+        Some(percent) => f64::from(cohort) < percent,
+        None => true,
+    }
+}
+
+async fn check(app: &AppHandle) -> Result<Option<UpdateInfo>, AppError> {
+    if PUBKEY.is_none() {
+        return Err(AppError::new(ErrorKind::Unsupported, "This build doesn't update itself"));
+    }
+    let (channel, proxy) = {
+        let settings = app.state::<AppState>().settings.lock().unwrap().clone();
+        (settings.updates.channel, settings.proxy)
+    };
+    let endpoint = match channel {
+        ReleaseChannel::Stable => STABLE_ENDPOINT,
+        ReleaseChannel::Beta => BETA_ENDPOINT,
+    };
+    let mut builder = app.updater_builder()
+        .endpoints(vec![Url::parse(endpoint).map_err(|e| e.to_string())?])
+        .map_err(|e| e.to_string())?;
+    builder = match proxy.mode {
+        ProxyMode::Off => builder.no_proxy(),
+        ProxyMode::Manual => builder.proxy(Url::parse(&proxy.url).map_err(|e| AppError::new(ErrorKind::InvalidInput, format!("Invalid proxy URL: {}", e)))?),
+        ProxyMode::System => builder,
+    };
+    let update = builder.build().map_err(|e| e.to_string())?.check().await.map_err(|e| e.to_string())?;
+    let update = match update {
+        Some(update) if reached(&update, cohort(app)?) => update,
+        _ => return Ok(None),
+    };
+
+    let state = app.state::<UpdateState>();
+    let downloaded = state.downloaded.lock().unwrap().as_ref().is_some_and(|(version, _)| *version == update.version);
+    let found = info(&update, channel, downloaded);
+    *state.available.lock().unwrap() = Some((update, channel));
+    Ok(Some(found))
+}
+
+/// Checks for an update in the background at startup, when `auto_check` is on, and announces one
+/// with `update://available`.
+pub fn init(app: &AppHandle) {
+    if PUBKEY.is_none() || !app.state::<AppState>().settings.lock().unwrap().updates.auto_check {
+        return;
+    }
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Ok(Some(update)) = check(&app).await {
+            let _ = app.emit("update://available", &update);
+        }
+    });
+}
+
+/// Checks the release channel for a newer version. None when up to date, or when a staged
+/// rollout hasn't reached this install yet.
+#[tauri::command]
+pub async fn check_for_update(app: AppHandle) -> Result<Option<UpdateInfo>, AppError> {
+    check(&app).await
+}
+
+/// Downloads the update found by the last check in the background. Progress arrives as
+/// `update://progress`, the end as `update://ready` (the UpdateInfo) or `update://error`.
+#[tauri::command]
+pub fn download_update(app: AppHandle, updates: State<'_, UpdateState>) -> Result<(), AppError> {
+    let Some((update, channel)) = updates.available.lock().unwrap().clone() else {
+        return Err(AppError::new(ErrorKind::NotFound, "No update to download; check for one first"));
+    };
+    if updates.downloaded.lock().unwrap().as_ref().is_some_and(|(version, _)| *version == update.version) {
+        let _ = app.emit("update://ready", info(&update, channel, true));
+        return Ok(());
+    }
+    if updates.downloading.swap(true, Ordering::SeqCst) {
+        return Err(AppError::new(ErrorKind::AlreadyExists, "The update is already downloading"));
+    }
+
+    tauri::async_runtime::spawn(async move {
+        let mut progress = UpdateProgress { version: update.version.clone(), downloaded: 0, total: None };
+        let mut reported = 0;
+        let result = update
+            .download(
+                |chunk, total| {
+                    progress.downloaded += chunk as u64;
+                    progress.total = total;
+                    if progress.downloaded - reported >= PROGRESS_STEP || total == Some(progress.downloaded) {
+                        reported = progress.downloaded;
+                        let _ = app.emit("update://progress", &progress);
+                    }
+                },
+                || {},
+            )
+            .await;
+
+        let state = app.state::<UpdateState>();
+        state.downloading.store(false, Ordering::SeqCst);
+        match result {
+            Ok(bytes) => {
+                *state.downloaded.lock().unwrap() = Some((update.version.clone(), bytes));
+                let _ = app.emit("update://ready", info(&update, channel, true));
+            }
+            Err(e) => {
+                let _ = app.emit("update://error", e.to_string());
+            }
+        }
+    });
+    Ok(())
+}
+
+/// Installs the downloaded update and restarts into it. On Windows the installer closes the app
+/// itself.
+#[tauri::command]
+pub fn install_update(app: AppHandle, updates: State<'_, UpdateState>) -> Result<(), AppError> {
+    let update = updates.available.lock().unwrap().clone().map(|(update, _)| update);
+    let downloaded = updates.downloaded.lock().unwrap().take();
+    let (update, bytes) = match (update, downloaded) {
+        (Some(update), Some((version, bytes))) if update.version == version => (update, bytes),
+        _ => return Err(AppError::new(ErrorKind::NotFound, "No downloaded update to install")),
+    };
+    update.install(bytes).map_err(|e| e.to_string())?;
+    app.restart()
+}
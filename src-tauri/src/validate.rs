@@ -0,0 +1,50 @@
+use serde::Serialize;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SyntaxError {
+    pub format: String,
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl std::fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Invalid {} at line {}, column {}: {}", self.format, self.line, self.column, self.message)
+    }
+}
+
+/// 1-based line/column for a byte offset.
+fn line_col(content: &str, offset: usize) -> (usize, usize) {
+    let before = &content[..offset.min(content.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().map(|l| l.chars().count()).unwrap_or(0) + 1;
+    (line, column)
+}
+
+/// Checks config-style files by extension. Other extensions always pass.
+pub fn check_syntax(key: &str, content: &str) -> Option<SyntaxError> {
+    let ext = key.rsplit('.').next().unwrap_or_default().to_ascii_lowercase();
+    match ext.as_str() {
+        "json" => serde_json::from_str::<serde_json::Value>(content).err().map(|e| SyntaxError {
+            format: "JSON".to_string(),
+            line: e.line(),
+            column: e.column(),
+            message: e.to_string(),
+        }),
+        "yaml" | "yml" => serde_yaml::from_str::<serde_yaml::Value>(content).err().map(|e| {
+            let (line, column) = e.location().map(|l| (l.line(), l.column())).unwrap_or((0, 0));
+            SyntaxError { format: "YAML".to_string(), line, column, message: e.to_string() }
+        }),
+        "toml" => content.parse::<toml::Table>().err().map(|e| {
+            let (line, column) = e.span().map(|s| line_col(content, s.start)).unwrap_or((0, 0));
+            SyntaxError { format: "TOML".to_string(), line, column, message: e.message().to_string() }
+        }),
+        _ => None,
+    }
+}
+
+#[tauri::command]
+pub fn validate_text(key: String, content: String) -> Option<SyntaxError> {
+    check_syntax(&key, &content)
+}
@@ -0,0 +1,166 @@
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, State};
+
+use crate::error::AppError;
+use crate::hashing;
+use crate::s3::{acquire_client, AppState};
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct UploadedItem {
+    pub path: String,
+    pub key: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum VerifyMode {
+    /// Hash every file.
+    Full,
+    /// Hash a random `sample_percent` of files plus every file of at least `size_threshold`
+    /// bytes; the rest only get a size check.
+    Sample {
+        sample_percent: f64,
+        size_threshold: u64,
+        seed: Option<u64>,
+    },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VerifyMismatch {
+    pub key: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VerificationReport {
+    pub total: usize,
+    pub hashed: usize,
+    pub size_checked: usize,
+    pub sampled_randomly: usize,
+    pub above_threshold: usize,
+    pub seed: Option<u64>,
+    pub mismatches: Vec<VerifyMismatch>,
+}
+
+/// Picks which items get a full content check. Returns (indices, random picks, threshold picks).
+fn select_for_hashing(sizes: &[u64], mode: &VerifyMode, seed: Option<u64>) -> (HashSet<usize>, usize, usize) {
+    match mode {
+        VerifyMode::Full => ((0..sizes.len()).collect(), 0, 0),
+        VerifyMode::Sample { sample_percent, size_threshold, .. } => {
+            let large: HashSet<usize> = (0..sizes.len()).filter(|&i| sizes[i] >= *size_threshold).collect();
+            let mut rest: Vec<usize> = (0..sizes.len()).filter(|i| !large.contains(i)).collect();
+
+            let count = ((rest.len() as f64) * sample_percent.clamp(0.0, 100.0) / 100.0).ceil() as usize;
+            let mut rng = StdRng::seed_from_u64(seed.unwrap_or_default());
+            rest.shuffle(&mut rng);
+
+            let above = large.len();
+            let mut selected = large;
+            selected.extend(rest.into_iter().take(count));
+            (selected, count, above)
+        }
+    }
+}
+
+pub async fn remote_sha256(client: &aws_sdk_s3::Client, bucket: &str, key: &str) -> Result<String, String> {
+    let resp = client.get_object().bucket(bucket).key(key).send().await.map_err(|e| e.to_string())?;
+    let mut body = resp.body;
+    let mut hasher = Sha256::new();
+    while let Some(chunk) = body.try_next().await.map_err(|e| e.to_string())? {
+        hasher.update(&chunk);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+#[tauri::command]
+pub async fn verify_uploads(
+    bucket: String,
+    items: Vec<UploadedItem>,
+    mode: VerifyMode,
+    app: AppHandle,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<VerificationReport, AppError> {
+    let mut sizes = Vec::with_capacity(items.len());
+    for item in &items {
+        sizes.push(std::fs::metadata(&item.path).map_err(|e| format!("{}: {}", item.path, e))?.len());
+    }
+
+    let seed = match &mode {
+        VerifyMode::Full => None,
+        // Kept within u32 so the seed survives a round trip through JS numbers
+        VerifyMode::Sample { seed, .. } => Some(seed.unwrap_or_else(|| rand::random::<u32>() as u64)),
+    };
+    let (selected, sampled_randomly, above_threshold) = select_for_hashing(&sizes, &mode, seed);
+
+    // Local digests for the selected files, computed on the hashing pool
+    let selected_paths: Vec<PathBuf> = items
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| selected.contains(i))
+        .map(|(_, item)| PathBuf::from(&item.path))
+        .collect();
+    let hash_app = app.clone();
+    let local_hashes = tauri::async_runtime::spawn_blocking(move || {
+        hashing::hash_files(&hash_app, Path::new(""), &selected_paths)
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+
+    let client = acquire_client(&state, connection_id.as_deref())?;
+
+    let mut report = VerificationReport {
+        total: items.len(),
+        hashed: 0,
+        size_checked: 0,
+        sampled_randomly,
+        above_threshold,
+        seed,
+        mismatches: Vec::new(),
+    };
+
+    for (i, item) in items.iter().enumerate() {
+        let head = match client.head_object().bucket(&bucket).key(&item.key).send().await {
+            Ok(h) => h,
+            Err(e) => {
+                report.mismatches.push(VerifyMismatch { key: item.key.clone(), reason: format!("Missing: {}", e) });
+                continue;
+            }
+        };
+
+        let remote_size = head.content_length().unwrap_or(0) as u64;
+        if remote_size != sizes[i] {
+            report.mismatches.push(VerifyMismatch {
+                key: item.key.clone(),
+                reason: format!("Size differs: local {} bytes, remote {} bytes", sizes[i], remote_size),
+            });
+            continue;
+        }
+
+        if !selected.contains(&i) {
+            report.size_checked += 1;
+            continue;
+        }
+
+        let Some(local) = local_hashes.iter().find(|h| h.path == item.path) else { continue };
+        let etag = head.e_tag().unwrap_or_default().trim_matches('"').to_string();
+        // Single-part ETags are the content MD5; multipart ones need the bytes re-read
+        let matches = if !etag.is_empty() && !etag.contains('-') {
+            etag == local.md5
+        } else {
+            remote_sha256(&client, &bucket, &item.key).await? == local.sha256
+        };
+        report.hashed += 1;
+        if !matches {
+            report.mismatches.push(VerifyMismatch { key: item.key.clone(), reason: "Content hash differs".to_string() });
+        }
+    }
+
+    Ok(report)
+}
@@ -0,0 +1,365 @@
+use aws_sdk_s3::types::{BucketVersioningStatus, Delete, ObjectIdentifier, VersioningConfiguration};
+use aws_sdk_s3::Client;
+use serde::Serialize;
+use serde_json::json;
+use std::path::Path;
+use tauri::{AppHandle, Emitter, State};
+use urlencoding::encode;
+
+use crate::capabilities::{self, Capability};
+use crate::encryption::Decryptor;
+use crate::error::{AppError, ErrorKind};
+use crate::jobs;
+use crate::s3::{acquire_client, acquire_write_client, save_body, AppState};
+
+// Largest single CopyObject
+const MAX_COPY_SIZE: i64 = 5 * 1024 * 1024 * 1024;
+// DeleteObjects limit per request
+const DELETE_BATCH: usize = 1000;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ObjectVersion {
+    pub key: String,
+    /// "null" for objects written while versioning was off or suspended.
+    pub version_id: String,
+    /// The current version of its key, or the marker that hides it.
+    pub is_latest: bool,
+    pub is_delete_marker: bool,
+    pub size: u64,
+    /// Unix seconds.
+    pub last_modified: i64,
+    /// Unset for delete markers.
+    pub etag: Option<String>,
+    pub storage_class: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ObjectVersionsResult {
+    /// By key, newest first within a key.
+    pub versions: Vec<ObjectVersion>,
+    /// Stopped at the configured listing cap.
+    pub truncated: bool,
+}
+
+/// Turns versioning on, or suspends it. Once enabled, a bucket can't go back to unversioned;
+/// suspending keeps existing versions and stops creating new ones.
+#[tauri::command]
+pub async fn set_bucket_versioning(
+    bucket: String,
+    enabled: bool,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    let conn = connection_id.as_deref();
+    capabilities::require(&state, conn, Capability::Versioning)?;
+    let client = acquire_write_client(&state, conn)?;
+
+    let status = if enabled { BucketVersioningStatus::Enabled } else { BucketVersioningStatus::Suspended };
+    client.put_bucket_versioning()
+        .bucket(&bucket)
+        .versioning_configuration(VersioningConfiguration::builder().status(status).build())
+        .send()
+        .await
+        .map_err(|e| capabilities::map_error(&state, conn, Capability::Versioning, e))?;
+
+    Ok(())
+}
+
+/// Pages through versions and delete markers under `prefix`, stopping early once `cap` entries
+/// are collected.
+async fn list_versions(
+    client: &Client,
+    state: &AppState,
+    conn: Option<&str>,
+    bucket: &str,
+    prefix: Option<String>,
+    cap: Option<usize>,
+) -> Result<ObjectVersionsResult, AppError> {
+    let mut result = ObjectVersionsResult::default();
+    let mut key_marker = None;
+    let mut version_id_marker = None;
+    loop {
+        let resp = client.list_object_versions()
+            .bucket(bucket)
+            .set_prefix(prefix.clone())
+            .set_key_marker(key_marker)
+            .set_version_id_marker(version_id_marker)
+            .send()
+            .await
+            .map_err(|e| capabilities::map_error(state, conn, Capability::Versioning, e))?;
+
+        result.versions.extend(resp.versions().iter().map(|v| ObjectVersion {
+            key: v.key().unwrap_or_default().to_string(),
+            version_id: v.version_id().unwrap_or("null").to_string(),
+            is_latest: v.is_latest().unwrap_or(false),
+            is_delete_marker: false,
+            size: v.size().unwrap_or_default().max(0) as u64,
+            last_modified: v.last_modified().map(|d| d.secs()).unwrap_or_default(),
+            etag: v.e_tag().map(|e| e.trim_matches('"').to_string()),
+            storage_class: v.storage_class().map(|c| c.as_str().to_string()),
+        }));
+        result.versions.extend(resp.delete_markers().iter().map(|m| ObjectVersion {
+            key: m.key().unwrap_or_default().to_string(),
+            version_id: m.version_id().unwrap_or("null").to_string(),
+            is_latest: m.is_latest().unwrap_or(false),
+            is_delete_marker: true,
+            size: 0,
+            last_modified: m.last_modified().map(|d| d.secs()).unwrap_or_default(),
+            etag: None,
+            storage_class: None,
+        }));
+
+        let more = resp.is_truncated().unwrap_or(false);
+        if !more {
+            break;
+        }
+        if cap.is_some_and(|cap| result.versions.len() >= cap) {
+            result.truncated = true;
+            break;
+        }
+        key_marker = resp.next_key_marker().map(str::to_string);
+        version_id_marker = resp.next_version_id_marker().map(str::to_string);
+    }
+
+    // Versions and markers come back in separate lists; interleave them into each key's history
+    result.versions.sort_by(|a, b| a.key.cmp(&b.key).then(b.is_latest.cmp(&a.is_latest)).then(b.last_modified.cmp(&a.last_modified)));
+    Ok(result)
+}
+
+/// Every version and delete marker under `prefix`, up to the listing cap.
+#[tauri::command]
+pub async fn list_object_versions(
+    bucket: String,
+    prefix: Option<String>,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<ObjectVersionsResult, AppError> {
+    let conn = connection_id.as_deref();
+    capabilities::require(&state, conn, Capability::Versioning)?;
+    let client = acquire_client(&state, conn)?;
+    let cap = state.settings.lock().unwrap().max_listing_items;
+
+    list_versions(&client, &state, conn, &bucket, prefix, Some(cap)).await
+}
+
+/// Downloads one version of an object, whether or not it is the current one.
+#[tauri::command]
+pub async fn download_object_version(
+    bucket: String,
+    key: String,
+    version_id: String,
+    save_path: String,
+    app: AppHandle,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    let job = jobs::start("download", Some(&bucket), json!({ "key": key, "version_id": version_id, "save_path": save_path }));
+    let result: Result<(), AppError> = async {
+        let client = acquire_client(&state, connection_id.as_deref())?;
+        let resp = client.get_object().bucket(&bucket).key(&key).version_id(&version_id).send().await?;
+        let decryptor = Decryptor::for_object(&app, resp.metadata())?;
+        save_body(&app, resp.body, decryptor, Path::new(&save_path)).await?;
+        Ok(())
+    }
+    .await;
+    job.finish(&app, &result, |_| format!("Downloaded {} (version {})", key, version_id));
+
+    result
+}
+
+/// Makes an older version current again by copying it over the key. The versions in between
+/// stay in the history. Returns the id of the new current version.
+#[tauri::command]
+pub async fn restore_version(
+    bucket: String,
+    key: String,
+    version_id: String,
+    app: AppHandle,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Option<String>, AppError> {
+    let conn = connection_id.as_deref();
+    let job = jobs::start("restore_version", Some(&bucket), json!({ "key": key, "version_id": version_id }));
+    let result: Result<Option<String>, AppError> = async {
+        let client = acquire_write_client(&state, conn)?;
+        let head = client.head_object().bucket(&bucket).key(&key).version_id(&version_id).send().await?;
+        if head.content_length().unwrap_or(0) > MAX_COPY_SIZE {
+            return Err(AppError::new(ErrorKind::Unsupported, "Versions over 5 GiB can't be restored in place; download it instead"));
+        }
+        let resp = client.copy_object()
+            .bucket(&bucket)
+            .key(&key)
+            .copy_source(format!("{}/{}?versionId={}", bucket, encode(&key), encode(&version_id)))
+            .send()
+            .await
+            .map_err(|e| capabilities::map_error(&state, conn, Capability::Versioning, e))?;
+        Ok(resp.version_id().map(str::to_string))
+    }
+    .await;
+    job.finish(&app, &result, |_| format!("Restored {} to version {}", key, version_id));
+
+    result
+}
+
+/// Permanently deletes one version or delete marker. Removing the marker that hides a key
+/// brings its previous version back.
+#[tauri::command]
+pub async fn delete_object_version(
+    bucket: String,
+    key: String,
+    version_id: String,
+    app: AppHandle,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), AppError> {
+    let conn = connection_id.as_deref();
+    let job = jobs::start("delete_version", Some(&bucket), json!({ "key": key, "version_id": version_id }));
+    let result: Result<(), AppError> = async {
+        let client = acquire_write_client(&state, conn)?;
+        client.delete_object()
+            .bucket(&bucket)
+            .key(&key)
+            .version_id(&version_id)
+            .send()
+            .await
+            .map_err(|e| capabilities::map_error(&state, conn, Capability::Versioning, e))?;
+        Ok(())
+    }
+    .await;
+    job.finish(&app, &result, |_| format!("Deleted version {} of {}", version_id, key));
+
+    result
+}
+
+/// Payload of `version-cleanup://progress`.
+#[derive(Debug, Clone, Serialize)]
+pub struct VersionCleanupProgress {
+    pub bucket: String,
+    pub done: usize,
+    pub total: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VersionCleanupFailure {
+    pub key: String,
+    pub version_id: String,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct VersionCleanupResult {
+    /// What was removed, or on a dry run what would be.
+    pub removed: Vec<ObjectVersion>,
+    pub delete_markers: usize,
+    pub noncurrent_versions: usize,
+    /// Storage freed by the removed noncurrent versions.
+    pub bytes: u64,
+    pub failed: Vec<VersionCleanupFailure>,
+    pub dry_run: bool,
+}
+
+/// Picks what a cleanup removes from a key's history (newest first):
+/// - noncurrent versions that have been noncurrent for at least `noncurrent_days`, timed from
+///   when the next newer version replaced them, like lifecycle rules do;
+/// - delete markers that aren't the latest, which hide nothing;
+/// - a latest delete marker once nothing else of the key is left, since removing it while older
+///   versions remain would bring the object back.
+fn cleanup_candidates(history: &[ObjectVersion], noncurrent_days: Option<u32>, now: i64) -> Vec<ObjectVersion> {
+    let mut removed = Vec::new();
+    for (i, version) in history.iter().enumerate().skip(1) {
+        let expired = noncurrent_days.is_some_and(|days| now - history[i - 1].last_modified >= days as i64 * 86_400);
+        if version.is_delete_marker || expired {
+            removed.push(version.clone());
+        }
+    }
+    if let Some(latest) = history.first().filter(|v| v.is_latest && v.is_delete_marker) {
+        if removed.len() == history.len() - 1 {
+            removed.push(latest.clone());
+        }
+    }
+    removed
+}
+
+async fn delete_versions(
+    app: &AppHandle,
+    client: &Client,
+    versions: &[ObjectVersion],
+    progress: &mut VersionCleanupProgress,
+    failed: &mut Vec<VersionCleanupFailure>,
+) -> Result<(), AppError> {
+    for chunk in versions.chunks(DELETE_BATCH) {
+        let ids = chunk
+            .iter()
+            .map(|v| ObjectIdentifier::builder().key(&v.key).version_id(&v.version_id).build().unwrap())
+            .collect();
+        let delete = Delete::builder().set_objects(Some(ids)).quiet(true).build().unwrap();
+        let out = client.delete_objects().bucket(&progress.bucket).delete(delete).send().await?;
+        failed.extend(out.errors().iter().map(|e| VersionCleanupFailure {
+            key: e.key().unwrap_or_default().to_string(),
+            version_id: e.version_id().unwrap_or_default().to_string(),
+            error: e.message().unwrap_or_default().to_string(),
+        }));
+        progress.done += chunk.len();
+        let _ = app.emit("version-cleanup://progress", &*progress);
+    }
+    Ok(())
+}
+
+/// Removes delete markers and, when `noncurrent_days` is set, noncurrent versions older than
+/// that from a versioned bucket. Current versions are never touched. With `dry_run` nothing is
+/// deleted and the result lists what would be.
+#[tauri::command]
+pub async fn clean_up_versions(
+    bucket: String,
+    prefix: Option<String>,
+    noncurrent_days: Option<u32>,
+    dry_run: bool,
+    app: AppHandle,
+    connection_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<VersionCleanupResult, AppError> {
+    let conn = connection_id.as_deref();
+    capabilities::require(&state, conn, Capability::Versioning)?;
+    let job = jobs::start("clean_up_versions", Some(&bucket), json!({
+        "prefix": prefix,
+        "noncurrent_days": noncurrent_days,
+        "dry_run": dry_run,
+    }));
+    let result: Result<VersionCleanupResult, AppError> = async {
+        let client = if dry_run { acquire_client(&state, conn)? } else { acquire_write_client(&state, conn)? };
+        let listing = list_versions(&client, &state, conn, &bucket, prefix, None).await?;
+
+        let now = jobs::now_secs();
+        let mut result = VersionCleanupResult { dry_run, ..Default::default() };
+        for history in listing.versions.chunk_by(|a, b| a.key == b.key) {
+            result.removed.extend(cleanup_candidates(history, noncurrent_days, now));
+        }
+        if !dry_run {
+            // Latest markers go last and only once the rest of their key is gone, or a failed
+            // delete would leave an older version to resurface
+            let (last, first): (Vec<_>, Vec<_>) = result.removed.iter().cloned().partition(|v| v.is_latest);
+            let mut progress = VersionCleanupProgress { bucket: bucket.clone(), done: 0, total: result.removed.len() };
+            let _ = app.emit("version-cleanup://progress", &progress);
+            delete_versions(&app, &client, &first, &mut progress, &mut result.failed).await?;
+            let (last, kept): (Vec<_>, Vec<_>) = last.into_iter().partition(|v| !result.failed.iter().any(|f| f.key == v.key));
+            delete_versions(&app, &client, &last, &mut progress, &mut result.failed).await?;
+
+            let failed = &result.failed;
+            result.removed.retain(|v| {
+                !kept.iter().any(|k| k.key == v.key && k.version_id == v.version_id)
+                    && !failed.iter().any(|f| f.key == v.key && f.version_id == v.version_id)
+            });
+        }
+        result.delete_markers = result.removed.iter().filter(|v| v.is_delete_marker).count();
+        result.noncurrent_versions = result.removed.len() - result.delete_markers;
+        result.bytes = result.removed.iter().map(|v| v.size).sum();
+        Ok(result)
+    }
+    .await;
+    job.finish(&app, &result, |r| {
+        let verb = if r.dry_run { "Would remove" } else { "Removed" };
+        format!("{} {} delete markers and {} noncurrent versions", verb, r.delete_markers, r.noncurrent_versions)
+    });
+
+    result
+}
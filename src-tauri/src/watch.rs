@@ -0,0 +1,180 @@
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::jobs;
+use crate::s3::{acquire_client, list_all_objects, AppState};
+
+const MIN_INTERVAL_SECS: u64 = 5;
+// Recent diffs kept in memory for the activity feed
+const HISTORY_LIMIT: usize = 500;
+
+#[derive(Default)]
+pub struct WatchState {
+    next_id: AtomicU64,
+    watches: Mutex<HashMap<u64, JoinHandle<()>>>,
+    history: Mutex<VecDeque<(i64, WatchChanges)>>,
+}
+
+impl WatchState {
+    fn record(&self, changes: &WatchChanges) {
+        let mut history = self.history.lock().unwrap();
+        if history.len() == HISTORY_LIMIT {
+            history.pop_front();
+        }
+        history.push_back((jobs::now_secs(), changes.clone()));
+    }
+
+    /// Diffs seen for `bucket` at or after `since`, oldest first, with the time they were seen.
+    pub fn recent_changes(&self, bucket: &str, since: i64) -> Vec<(i64, WatchChanges)> {
+        self.history
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(seen, c)| *seen >= since && c.bucket == bucket)
+            .cloned()
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct WatchedObject {
+    pub key: String,
+    pub size: i64,
+    pub etag: String,
+    pub last_modified: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WatchChanges {
+    pub watch_id: u64,
+    pub bucket: String,
+    pub prefix: String,
+    pub added: Vec<WatchedObject>,
+    pub removed: Vec<String>,
+    pub changed: Vec<WatchedObject>,
+}
+
+async fn snapshot(
+    app: &AppHandle,
+    connection_id: Option<&str>,
+    bucket: &str,
+    prefix: &str,
+) -> Result<HashMap<String, WatchedObject>, String> {
+    let state = app.state::<AppState>();
+    let client = acquire_client(&state, connection_id)?;
+    let objects = list_all_objects(&client, bucket, Some(prefix)).await?;
+
+    Ok(objects
+        .into_iter()
+        .map(|o| {
+            let key = o.key().unwrap_or_default().to_string();
+            let entry = WatchedObject {
+                key: key.clone(),
+                size: o.size().unwrap_or(0),
+                etag: o.e_tag().unwrap_or_default().trim_matches('"').to_string(),
+                last_modified: o.last_modified().map(|d| d.secs()),
+            };
+            (key, entry)
+        })
+        .collect())
+}
+
+fn diff(
+    watch_id: u64,
+    bucket: &str,
+    prefix: &str,
+    before: &HashMap<String, WatchedObject>,
+    after: &HashMap<String, WatchedObject>,
+) -> WatchChanges {
+    let mut changes = WatchChanges {
+        watch_id,
+        bucket: bucket.to_string(),
+        prefix: prefix.to_string(),
+        added: Vec::new(),
+        removed: Vec::new(),
+        changed: Vec::new(),
+    };
+
+    for (key, obj) in after {
+        match before.get(key) {
+            None => changes.added.push(obj.clone()),
+            Some(prev) if prev.etag != obj.etag || prev.size != obj.size => changes.changed.push(obj.clone()),
+            _ => {}
+        }
+    }
+    for key in before.keys() {
+        if !after.contains_key(key) {
+            changes.removed.push(key.clone());
+        }
+    }
+
+    changes
+}
+
+async fn run_watch(
+    app: AppHandle,
+    connection_id: Option<String>,
+    watch_id: u64,
+    bucket: String,
+    prefix: String,
+    interval: Duration,
+) {
+    // Listing failures (network blips, expired credentials) keep the previous baseline
+    let mut known = snapshot(&app, connection_id.as_deref(), &bucket, &prefix).await.ok();
+
+    loop {
+        tokio::time::sleep(interval).await;
+
+        let current = match snapshot(&app, connection_id.as_deref(), &bucket, &prefix).await {
+            Ok(c) => c,
+            Err(e) => {
+                let _ = app.emit("watch://error", (watch_id, e));
+                continue;
+            }
+        };
+
+        if let Some(before) = &known {
+            let changes = diff(watch_id, &bucket, &prefix, before, &current);
+            if !changes.added.is_empty() || !changes.removed.is_empty() || !changes.changed.is_empty() {
+                app.state::<WatchState>().record(&changes);
+                let _ = app.emit("watch://changes", &changes);
+            }
+        }
+        known = Some(current);
+    }
+}
+
+/// Starts polling `prefix` every `interval` seconds; returns an id for `unwatch_prefix`.
+#[tauri::command]
+pub fn watch_prefix(
+    bucket: String,
+    prefix: String,
+    interval: u64,
+    connection_id: Option<String>,
+    app: AppHandle,
+    watch_state: State<'_, WatchState>,
+) -> u64 {
+    let interval = Duration::from_secs(interval.max(MIN_INTERVAL_SECS));
+    let watch_id = watch_state.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+
+    let handle = tauri::async_runtime::spawn(run_watch(app, connection_id, watch_id, bucket, prefix, interval));
+    watch_state.watches.lock().unwrap().insert(watch_id, handle);
+
+    watch_id
+}
+
+#[tauri::command]
+pub fn unwatch_prefix(watch_id: u64, watch_state: State<'_, WatchState>) -> bool {
+    match watch_state.watches.lock().unwrap().remove(&watch_id) {
+        Some(handle) => {
+            handle.abort();
+            true
+        }
+        None => false,
+    }
+}
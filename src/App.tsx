@@ -2,7 +2,7 @@ import { useState, useEffect } from "react";
 import { Button } from "./components/ui/button";
 import { Input } from "./components/ui/input";
 import { Table, TableBody, TableCell, TableHead, TableHeader, TableRow } from "./components/ui/table";
-import { initR2Client, listBuckets, listObjects, uploadObject, downloadObject, createFolder, deleteObjects, deletePrefix, getBucketStats, readTextFile, getPresignedUrl, copyObject, renameFolder } from "./services/r2Service";
+import { initR2Client, listBuckets, listObjects, uploadObject, downloadObject, createFolder, deleteObjects, deletePrefix, getBucketStats, readTextFile, getPresignedUrl, copyObject, renameFolder, errorMessage } from "./services/r2Service";
 import { open, save, message } from "@tauri-apps/plugin-dialog";
 import { getCurrentWindow } from "@tauri-apps/api/window"; // Add this import
 import { readDir, stat } from "@tauri-apps/plugin-fs"; // Add this import
@@ -221,7 +221,7 @@ function App() {
             successCount++;
         } catch (e) {
             failCount++;
-            console.error("Failed to upload " + item.relativeKey + ": " + e);
+            console.error("Failed to upload " + item.relativeKey + ": " + errorMessage(e));
         }
       }
       
@@ -277,13 +277,13 @@ function App() {
       
       const fileItems: FileItem[] = result.files.map(obj => ({
         key: obj.key,
-        size: parseInt(obj.size) || 0,
-        lastModified: new Date(obj.last_modified),
+        size: obj.size,
+        lastModified: new Date(obj.last_modified * 1000),
         type: "file" as const
       })).filter(f => f.key !== prefix); // Filter out the folder placeholder itself
 
       const folderItems: FileItem[] = result.folders.map(obj => ({
-        key: obj.key,
+        key: obj.prefix,
         type: "folder" as const
       }));
 
@@ -358,7 +358,7 @@ function App() {
           await createFolder(currentBucket, newKey);
           loadFiles(currentBucket, currentPath);
       } catch (e) {
-          await message("Failed to create folder: " + e, { kind: 'error' });
+          await message("Failed to create folder: " + errorMessage(e), { kind: 'error' });
       }
   }
 
@@ -418,7 +418,7 @@ function App() {
            loadFiles(currentBucket, currentPath);
            setSelection(new Set());
        } catch (e) {
-           await message("Move failed: " + e, { kind: 'error' });
+           await message("Move failed: " + errorMessage(e), { kind: 'error' });
        } finally {
            setLoading(false);
        }
@@ -501,7 +501,7 @@ function App() {
           loadFiles(currentBucket, currentPath);
           setSelection(new Set());
       } catch (e) {
-          await message("Rename failed: " + e, { kind: 'error' });
+          await message("Rename failed: " + errorMessage(e), { kind: 'error' });
       } finally {
           setLoading(false);
       }
@@ -608,13 +608,13 @@ function App() {
               const url = await getPresignedUrl(bucket, key);
               setPreview({ key, type, content: url, loading: false });
           } else if (type === 'text' || type === 'code') {
-              const text = await readTextFile(bucket, key);
+              const { text } = await readTextFile(bucket, key);
               setPreview({ key, type, content: text, loading: false });
           } else {
               setPreview({ key, type: 'none', content: null, loading: false });
           }
       } catch (error) {
-          setPreview({ key, type, content: null, loading: false, error: (error as Error).toString() });
+          setPreview({ key, type, content: null, loading: false, error: errorMessage(error) });
       }
   }
 
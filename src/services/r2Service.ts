@@ -1,76 +1,2218 @@
 import { invoke } from "@tauri-apps/api/core";
 
-export const initR2Client = async (accountId: string, accessKey: string, secretKey: string) => {
-  await invoke("init_r2", { accountId, accessKey, secretKey });
+export type ErrorKind =
+  | "not_connected"
+  | "read_only"
+  | "invalid_input"
+  | "not_found"
+  | "already_exists"
+  | "precondition_failed"
+  | "access_denied"
+  | "invalid_credentials"
+  | "clock_skew"
+  | "throttled"
+  | "timeout"
+  | "network"
+  | "unsupported"
+  | "cancelled"
+  | "service"
+  | "internal";
+
+/** What every command rejects with. */
+export interface AppError {
+  kind: ErrorKind;
+  message: string;
+  retryable: boolean;
+  http_status: number | null;
+  request_id: string | null;
+}
+
+export const isAppError = (e: unknown): e is AppError =>
+  typeof e === "object" && e !== null && "kind" in e && "message" in e;
+
+export const errorMessage = (e: unknown): string =>
+  isAppError(e) ? e.message : e instanceof Error ? e.message : String(e);
+
+export const initR2Client = async (
+  accountId: string,
+  accessKey: string,
+  secretKey: string,
+  jurisdiction?: Jurisdiction,
+  connectionId?: string,
+  sessionToken?: string,
+  expiresAt?: number
+) => {
+  await invoke("init_r2", {
+    accountId,
+    accessKey,
+    secretKey,
+    jurisdiction: jurisdiction ?? null,
+    sessionToken: sessionToken ?? null,
+    expiresAt: expiresAt ?? null,
+    connectionId,
+  });
 };
 
-export const listBuckets = async () => {
-  const buckets = await invoke<string[]>("list_buckets");
+export const listBuckets = async (connectionId?: string) => {
+  const buckets = await invoke<string[]>("list_buckets", { connectionId });
   return buckets;
 };
 
 export interface R2Object {
   key: string;
-  size: string;
-  last_modified: string;
-  type: "file";
+  size: number;
+  /** Unix seconds; 0 when unknown. */
+  last_modified: number;
+  etag: string;
+  storage_class: string | null;
 }
 
 export interface R2Folder {
-  key: string;
-  type: "folder";
+  prefix: string;
 }
 
 export interface ListObjectsResult {
   files: R2Object[];
   folders: R2Folder[];
+  /** The folder holds more than `max_listing_items` entries; only the first ones are included. */
+  truncated: boolean;
+}
+
+/** Payload of `list://page`, emitted per page when listObjects is given a listingId. */
+export interface ListingPage {
+  listing_id: string;
+  files: R2Object[];
+  folders: R2Folder[];
+  done: boolean;
+  truncated: boolean;
+}
+
+export interface ListingFilter {
+  name_contains?: string | null;
+  /** Without the dot, e.g. ["jpg", "png"]. */
+  extensions?: string[];
+  min_size?: number | null;
+  max_size?: number | null;
+  /** Unix seconds. */
+  modified_after?: number | null;
+  modified_before?: number | null;
+}
+
+export interface ListingSort {
+  field: "name" | "size" | "last_modified" | "extension";
+  direction: "asc" | "desc";
+}
+
+export const listObjects = async (
+  bucket: string,
+  prefix = "",
+  delimiter = "/",
+  connectionId?: string,
+  listingId?: string,
+  filter?: ListingFilter,
+  sort?: ListingSort
+) => {
+  return await invoke<ListObjectsResult>("list_objects", {
+    bucket,
+    prefix,
+    delimiter,
+    listingId: listingId ?? null,
+    filter: filter ?? null,
+    sort: sort ?? null,
+    connectionId,
+  });
+};
+
+export interface ObjectPage {
+  files: R2Object[];
+  folders: R2Folder[];
+  next_token: string | null;
 }
 
-export const listObjects = async (bucket: string, prefix = "", delimiter = "/") => {
-  return await invoke<ListObjectsResult>("list_objects", { bucket, prefix, delimiter });
+export const listObjectsPage = async (
+  bucket: string,
+  prefix = "",
+  delimiter = "/",
+  maxKeys?: number,
+  continuationToken?: string | null,
+  connectionId?: string
+) => {
+  return await invoke<ObjectPage>("list_objects_page", {
+    bucket,
+    prefix,
+    delimiter,
+    maxKeys: maxKeys ?? null,
+    continuationToken: continuationToken ?? null,
+    connectionId,
+  });
 };
 
-export const uploadObject = async (bucket: string, key: string, filePath: string) => {
-  await invoke("upload_file", { bucket, key, path: filePath });
+/** `sseCKey` (base64) encrypts this upload with SSE-C instead of the profile's key, if any. */
+export const uploadObject = async (bucket: string, key: string, filePath: string, connectionId?: string, sseCKey?: string) => {
+  await invoke("upload_file", { bucket, key, path: filePath, sseCKey: sseCKey ?? null, connectionId });
 };
 
-export const createFolder = async (bucket: string, key: string) => {
-  await invoke("create_folder", { bucket, key });
+export const createFolder = async (bucket: string, key: string, connectionId?: string) => {
+  await invoke("create_folder", { bucket, key, connectionId });
 };
 
-export const downloadObject = async (bucket: string, key: string, savePath: string) => {
-  await invoke("download_file", { bucket, key, savePath });
+export const downloadObject = async (bucket: string, key: string, savePath: string, connectionId?: string, sseCKey?: string) => {
+  await invoke("download_file", { bucket, key, savePath, sseCKey: sseCKey ?? null, connectionId });
 };
 
-export const deleteObjects = async (bucket: String, keys: string[]) => {
-  await invoke("delete_objects", { bucket, keys });
+export const deleteObjects = async (bucket: String, keys: string[], connectionId?: string) => {
+  await invoke("delete_objects", { bucket, keys, connectionId });
 };
 
-export const deletePrefix = async (bucket: String, prefix: string) => {
-  await invoke("delete_prefix", { bucket, prefix });
+export const deletePrefix = async (bucket: String, prefix: string, connectionId?: string) => {
+  await invoke("delete_prefix", { bucket, prefix, connectionId });
 };
 
-export const getBucketStats = async (bucket: string) => {
-  const res = await invoke<{size: string, count: string}>("get_bucket_stats", { bucket });
+export const getBucketStats = async (bucket: string, connectionId?: string) => {
+  const res = await invoke<{size: string, count: string}>("get_bucket_stats", { bucket, connectionId });
   return {
       size: parseInt(res.size),
       count: parseInt(res.count)
   };
 };
 
-export const readTextFile = async (bucket: string, key: string) => {
-  return await invoke<string>("read_text_file", { bucket, key });
+export type TextEncoding = "utf-8" | "utf-16le" | "utf-16be" | "latin-1";
+
+export interface TextPreview {
+  text: string;
+  encoding: TextEncoding;
+  /** Only the start of the object was read. */
+  truncated: boolean;
+  /** Size of the whole object. */
+  size: number;
+  /** For saving edits back with writeTextFile. */
+  etag: string | null;
+}
+
+export interface SavedText {
+  /** New ETag, to pass as expectedEtag on the next save. */
+  etag: string;
+  size: number;
+}
+
+/** Reads up to maxBytes (256 KB by default, max 5 MB) from the start of a text object. */
+export const readTextFile = async (bucket: string, key: string, connectionId?: string, maxBytes?: number) => {
+  return await invoke<TextPreview>("read_text_file", { bucket, key, maxBytes: maxBytes ?? null, connectionId });
+};
+
+export interface BinaryRange {
+  offset: number;
+  /** Base64 of the bytes read; shorter than asked for at the end of the object. */
+  data: string;
+  /** Size of the whole object. */
+  size: number;
+}
+
+/** Up to 1 MB of raw bytes starting at offset, for the hex viewer. */
+export const readBinaryRange = async (bucket: string, key: string, offset: number, length: number, connectionId?: string) => {
+  return await invoke<BinaryRange>("read_binary_range", { bucket, key, offset, length, connectionId });
+};
+
+export interface TailStart {
+  /** Set when following; pass to stopTail. */
+  tail_id: number | null;
+  text: string;
+  size: number;
+}
+
+/**
+ * Payload of `tail://data` (appended text) and `tail://reset` (the object shrank or grew by more
+ * than maxBytes between polls; text is its new tail).
+ */
+export interface TailData {
+  tail_id: number;
+  text: string;
+  size: number;
+}
+
+/** The last maxBytes (64 KB by default) of a log; with follow, appended text arrives as `tail://data`. */
+export const tailObject = async (bucket: string, key: string, follow: boolean, connectionId?: string, maxBytes?: number, intervalMs?: number) => {
+  return await invoke<TailStart>("tail_object", { bucket, key, follow, maxBytes: maxBytes ?? null, intervalMs: intervalMs ?? null, connectionId });
+};
+
+export const stopTail = async (tailId: number) => {
+  return await invoke<boolean>("stop_tail", { tailId });
+};
+
+/** Saves edited text only if the object's ETag is still expectedEtag; fails with PreconditionFailed otherwise. */
+export const writeTextFile = async (bucket: string, key: string, content: string, expectedEtag: string, encoding?: TextEncoding, connectionId?: string) => {
+  return await invoke<SavedText>("write_text_file", { bucket, key, content, expectedEtag, encoding: encoding ?? null, connectionId });
+};
+
+export const getPresignedUrl = async (bucket: string, key: string, connectionId?: string) => {
+  return await invoke<string>("get_presigned_url", { bucket, key, connectionId });
+};
+
+/** A presigned request; `headers` must be sent exactly as given. */
+export interface PresignedLink {
+  url: string;
+  method: string;
+  headers: Record<string, string>;
+  expires_at: number;
+}
+
+export type PresignMethod = "GET" | "HEAD" | "PUT" | "DELETE";
+
+export interface PresignOptions {
+  /** Seconds, from a minute up to 7 days; an hour when omitted. */
+  expires_in?: number | null;
+  method?: PresignMethod;
+  /** Content-Type the uploader must send (PUT only). */
+  content_type?: string | null;
+  /** Served Content-Type override (GET/HEAD only). */
+  response_content_type?: string | null;
+  /** Served Content-Disposition override, e.g. "attachment" (GET/HEAD only). */
+  response_content_disposition?: string | null;
+}
+
+/** Presigns a request and records it as a share. */
+export const presignObject = async (bucket: string, key: string, options: PresignOptions, connectionId?: string) => {
+  return await invoke<PresignedLink>("presign_object", { bucket, key, options, connectionId });
+};
+
+export const getPresignedUploadUrl = async (bucket: string, key: string, expiresIn?: number, contentType?: string, connectionId?: string) => {
+  return await invoke<PresignedLink>("get_presigned_upload_url", {
+    bucket,
+    key,
+    expiresIn: expiresIn ?? null,
+    contentType: contentType ?? null,
+    connectionId,
+  });
+};
+
+/** With `sseCKey`, the source must be encrypted with that key too. */
+export const copyObject = async (bucket: string, source: string, destination: string, connectionId?: string, sseCKey?: string) => {
+  await invoke("copy_object", { bucket, source, destination, sseCKey: sseCKey ?? null, connectionId });
+};
+
+export const renameFolder = async (bucket: string, oldPrefix: string, newPrefix: string, connectionId?: string) => {
+  return await invoke<number>("rename_folder", { bucket, oldPrefix, newPrefix, connectionId });
+};
+
+
+export interface RenamePattern {
+  find?: string;
+  replace?: string;
+  prefix?: string;
+  suffix?: string;
+  sequence?: { start: number; padding: number; separator?: string };
+}
+
+export interface RenameMapping {
+  old_key: string;
+  new_key: string;
+}
+
+export interface RenameFailure {
+  key: string;
+  error: string;
+}
+
+export interface RenameResult {
+  mappings: RenameMapping[];
+  failed: RenameFailure[];
+}
+
+export const bulkRename = async (
+  bucket: string,
+  keys: string[],
+  pattern: RenamePattern,
+  dryRun: boolean,
+  overwrite?: boolean,
+  connectionId?: string
+) => {
+  return await invoke<RenameResult>("bulk_rename", { bucket, keys, pattern, dryRun, overwrite: overwrite ?? null, connectionId });
+};
+
+export type ProxyMode = "off" | "system" | "manual";
+
+export interface Settings {
+  max_connections: number;
+  max_listing_items: number;
+  multipart_cleanup: {
+    enabled: boolean;
+    older_than_days: number;
+    interval_hours: number;
+  };
+  proxy: {
+    mode: ProxyMode;
+    url: string;
+    no_proxy: string;
+  };
+  timeouts: {
+    connect_secs: number;
+    read_secs: number;
+    idle_secs: number;
+  };
+  saved_searches: SavedSearch[];
+  folder_watches: FolderWatch[];
+  sync_schedules: SyncSchedule[];
+  encryption: {
+    /** Encrypt files uploaded with uploadFile; encrypted downloads are always decrypted. */
+    enabled: boolean;
+    /** Also encrypt file names (not folder names). */
+    encrypt_names: boolean;
+    kdf_salt: string | null;
+    key_id: string | null;
+  };
+  signatures: {
+    /** Check downloads against a sibling .sig/.asc/.minisig; a bad or untrusted signature fails the download. */
+    verify_downloads: boolean;
+    trusted_keys: TrustedKey[];
+  };
+  /** Closing the window hides it to the tray, leaving transfers running. */
+  close_to_tray: boolean;
+  notifications: {
+    /** `problems`: failures and warnings; `all`: finished transfers too, while the window is in the background. */
+    level: "off" | "problems" | "all";
+    /** Warn when the Cloudflare account stores more than this many bytes (needs an API token). */
+    storage_limit: number | null;
+    /** Warn when the Cloudflare account holds more than this many objects (needs an API token). */
+    object_limit: number | null;
+  };
+  /** Destination for "Upload to R2Drive" in the file manager; without a bucket the UI asks. */
+  shell_upload: {
+    bucket: string | null;
+    prefix: string;
+    connection_id: string | null;
+    /** Ask even when a bucket is set. */
+    ask: boolean;
+  };
+  updates: {
+    channel: ReleaseChannel;
+    /** Check at startup; a new version is announced with `update://available`. */
+    auto_check: boolean;
+    /** This install's place (0-99) in staged rollouts, assigned on the first check. */
+    cohort: number | null;
+  };
+  /** JSON-RPC automation API on 127.0.0.1; change it with setLocalApi. */
+  local_api: {
+    enabled: boolean;
+    /** 0 picks a free port on each start. */
+    port: number;
+  };
+}
+
+export const getSettings = async () => {
+  return await invoke<Settings>("get_settings");
+};
+
+export const updateSettings = async (settings: Settings) => {
+  return await invoke<Settings>("update_settings", { settings });
+};
+
+export interface DuplicateSet {
+  size: number;
+  fingerprint: string;
+  objects: { key: string; etag: string; last_modified: number | null }[];
+  reclaimable: number;
+}
+
+export const findDuplicates = async (bucket: string, prefix?: string, hashAmbiguous = false, connectionId?: string) => {
+  return await invoke<DuplicateSet[]>("find_duplicates", { bucket, prefix, hashAmbiguous, connectionId });
+};
+
+export interface EmptyBucketProgress {
+  bucket: string;
+  phase: "objects" | "multipart" | "done";
+  deleted_objects: number;
+  aborted_uploads: number;
+}
+
+export const emptyBucket = async (bucket: string, connectionId?: string) => {
+  return await invoke<{ deleted_objects: number; aborted_uploads: number }>("empty_bucket", { bucket, connectionId });
+};
+
+export interface WatchedObject {
+  key: string;
+  size: number;
+  etag: string;
+  last_modified: number | null;
+}
+
+export interface WatchChanges {
+  watch_id: number;
+  bucket: string;
+  prefix: string;
+  added: WatchedObject[];
+  removed: string[];
+  changed: WatchedObject[];
+}
+
+export const watchPrefix = async (bucket: string, prefix: string, interval: number, connectionId?: string) => {
+  return await invoke<number>("watch_prefix", { bucket, prefix, interval, connectionId });
+};
+
+export const unwatchPrefix = async (watchId: number) => {
+  return await invoke<boolean>("unwatch_prefix", { watchId });
+};
+
+export interface ExpectedObject {
+  key: string;
+  etag?: string;
+  last_modified?: number;
+}
+
+export interface ConditionalDeleteResult {
+  deleted: string[];
+  skipped: { key: string; reason: string }[];
+}
+
+export const deleteObjectsIfUnchanged = async (bucket: string, objects: ExpectedObject[], connectionId?: string) => {
+  return await invoke<ConditionalDeleteResult>("delete_objects_if_unchanged", { bucket, objects, connectionId });
+};
+
+export const isEmptyPrefix = async (bucket: string, prefix: string, connectionId?: string) => {
+  return await invoke<boolean>("is_empty_prefix", { bucket, prefix, connectionId });
+};
+
+export interface LocalFileHash {
+  path: string;
+  relative_path: string;
+  size: number;
+  modified_ms: number;
+  md5: string;
+  sha256: string;
+  cached: boolean;
+}
+
+export const hashLocalFiles = async (root: string) => {
+  return await invoke<LocalFileHash[]>("hash_local_files", { root });
+};
+
+export interface PrefixStats {
+  prefix: string;
+  object_count: number;
+  total_size: number;
+  largest: { key: string; size: number } | null;
+}
+
+export const getPrefixStats = async (bucket: string, prefix: string, connectionId?: string) => {
+  return await invoke<PrefixStats>("get_prefix_stats", { bucket, prefix, connectionId });
+};
+
+export const concatenateObjects = async (bucket: string, sources: string[], destination: string, contentType?: string, connectionId?: string) => {
+  await invoke("concatenate_objects", { bucket, sources, destination, contentType, connectionId });
+};
+
+export const createTextObject = async (bucket: string, key: string, content: string, contentType?: string, overwrite = false, connectionId?: string) => {
+  await invoke("create_text_object", { bucket, key, content, contentType, overwrite, connectionId });
+};
+
+export interface ConfigSyntaxError {
+  format: string;
+  line: number;
+  column: number;
+  message: string;
+}
+
+export const validateText = async (key: string, content: string) => {
+  return await invoke<ConfigSyntaxError | null>("validate_text", { key, content });
+};
+
+export const createEmptyObject = async (bucket: string, key: string, contentType?: string, connectionId?: string) => {
+  await invoke("create_empty_object", { bucket, key, contentType, connectionId });
+};
+
+export const appendText = async (bucket: string, key: string, text: string, connectionId?: string) => {
+  return await invoke<number>("append_text", { bucket, key, text, connectionId });
 };
 
-export const getPresignedUrl = async (bucket: string, key: string) => {
-  return await invoke<string>("get_presigned_url", { bucket, key });
+export interface RemoteEndpoint {
+  endpoint: string;
+  region?: string;
+  access_key: string;
+  secret_key: string;
+  session_token?: string;
+  force_path_style?: boolean;
+}
+
+export interface RelayProgress {
+  src_key: string;
+  dst_key: string;
+  transferred: number;
+  total: number | null;
+}
+
+export const relayTransfer = async (srcBucket: string, srcKey: string, destination: RemoteEndpoint, dstBucket: string, dstKey: string, connectionId?: string) => {
+  return await invoke<number>("relay_transfer", { srcBucket, srcKey, destination, dstBucket, dstKey, connectionId });
+};
+
+export interface DiffHunk {
+  old_start: number;
+  old_len: number;
+  new_start: number;
+  new_len: number;
+  lines: { tag: "equal" | "insert" | "delete"; text: string }[];
+}
+
+export type ObjectDiff =
+  | { kind: "text"; identical: boolean; added: number; removed: number; hunks: DiffHunk[] }
+  | {
+      kind: "binary";
+      identical: boolean;
+      size_a: number;
+      size_b: number;
+      differing_ranges: { start: number; end: number }[];
+      truncated: boolean;
+    };
+
+export const diffObjects = async (bucket: string, keyA: string, keyB: string, connectionId?: string) => {
+  return await invoke<ObjectDiff>("diff_objects", { bucket, keyA, keyB, connectionId });
+};
+
+export const diffLocalRemote = async (localPath: string, bucket: string, key: string, connectionId?: string) => {
+  return await invoke<ObjectDiff>("diff_local_remote", { localPath, bucket, key, connectionId });
+};
+
+export type Capability =
+  | "create_bucket"
+  | "checksums"
+  | "tagging"
+  | "versioning"
+  | "object_lock"
+  | "lifecycle"
+  | "cors"
+  | "encryption"
+  | "conditional_deletes";
+
+export interface CapabilityMap {
+  provider: string;
+  endpoint: string;
+  supported: Partial<Record<Capability, boolean>>;
+}
+
+export const getCapabilities = async (connectionId?: string) => {
+  return await invoke<CapabilityMap>("get_capabilities", { connectionId });
+};
+
+export const setCapability = async (capability: Capability, supported: boolean, connectionId?: string) => {
+  return await invoke<CapabilityMap>("set_capability", { capability, supported, connectionId });
+};
+
+export interface CleanupReport {
+  started_at: number;
+  older_than_days: number;
+  dry_run: boolean;
+  aborted: { bucket: string; key: string; upload_id: string; initiated: number | null }[];
+  errors: string[];
+}
+
+export const cleanupMultipartUploads = async (olderThanDays: number, dryRun: boolean, connectionId?: string) => {
+  return await invoke<CleanupReport>("cleanup_multipart_uploads", { olderThanDays, dryRun, connectionId });
+};
+
+export const getLastCleanupReport = async () => {
+  return await invoke<CleanupReport | null>("get_last_cleanup_report");
+};
+
+export type R2LocationHint = "wnam" | "enam" | "weur" | "eeur" | "apac" | "oc";
+
+export const createBucket = async (bucket: string, locationHint?: R2LocationHint, connectionId?: string) => {
+  await invoke("create_bucket", { bucket, locationHint, connectionId });
+};
+
+export const deleteBucket = async (bucket: string, force = false, connectionId?: string) => {
+  await invoke("delete_bucket", { bucket, force, connectionId });
+};
+
+export interface CorsRule {
+  id?: string | null;
+  allowed_origins: string[];
+  allowed_methods: ("GET" | "PUT" | "POST" | "DELETE" | "HEAD")[];
+  allowed_headers: string[];
+  expose_headers: string[];
+  max_age_seconds?: number | null;
+}
+
+export const getBucketCors = async (bucket: string, connectionId?: string) => {
+  return await invoke<CorsRule[]>("get_bucket_cors", { bucket, connectionId });
+};
+
+export const putBucketCors = async (bucket: string, rules: CorsRule[], connectionId?: string) => {
+  await invoke("put_bucket_cors", { bucket, rules, connectionId });
+};
+
+export interface JobRecord {
+  id: string;
+  kind: string;
+  bucket: string | null;
+  params: Record<string, unknown>;
+  started_at: number;
+  finished_at: number;
+  succeeded: boolean;
+  summary: string;
+  error: string | null;
+}
+
+export interface JobQuery {
+  bucket?: string;
+  kind?: string;
+  since?: number;
+  until?: number;
+  text?: string;
+  succeeded?: boolean;
+  limit?: number;
+}
+
+export const searchJobs = async (query: JobQuery = {}) => {
+  return await invoke<JobRecord[]>("search_jobs", { query });
+};
+
+export interface LifecycleRule {
+  id: string;
+  prefix: string;
+  enabled: boolean;
+  expiration_days?: number | null;
+  abort_incomplete_multipart_days?: number | null;
+}
+
+export const getBucketLifecycle = async (bucket: string, connectionId?: string) => {
+  return await invoke<LifecycleRule[]>("get_bucket_lifecycle", { bucket, connectionId });
+};
+
+export const putBucketLifecycle = async (bucket: string, rules: LifecycleRule[], connectionId?: string) => {
+  await invoke("put_bucket_lifecycle", { bucket, rules, connectionId });
+};
+
+export interface ConnectDescriptor {
+  version: number;
+  label: string | null;
+  account_id: string;
+  endpoint: string;
+  bucket: string | null;
+  prefix: string | null;
+  token_name: string | null;
+}
+
+export const createConnectLink = async (options: {
+  label?: string;
+  bucket?: string;
+  prefix?: string;
+  tokenName?: string;
+  connectionId?: string;
+}) => {
+  return await invoke<{ link: string; qr_svg: string }>("create_connect_link", options);
+};
+
+export const importConnectLink = async (link: string) => {
+  return await invoke<ConnectDescriptor>("import_connect_link", { link });
+};
+
+export interface BucketInfo {
+  name: string;
+  creation_date: number | null;
+  location: string | null;
+  encryption: string | null;
+  versioning: "Enabled" | "Suspended" | "Disabled" | null;
+  object_lock: boolean | null;
+  unavailable: string[];
+}
+
+export const getBucketInfo = async (bucket: string, connectionId?: string) => {
+  return await invoke<BucketInfo>("get_bucket_info", { bucket, connectionId });
+};
+
+export type VerifyMode =
+  | { mode: "full" }
+  | { mode: "sample"; sample_percent: number; size_threshold: number; seed?: number };
+
+export interface VerificationReport {
+  total: number;
+  hashed: number;
+  size_checked: number;
+  sampled_randomly: number;
+  above_threshold: number;
+  seed: number | null;
+  mismatches: { key: string; reason: string }[];
+}
+
+export const verifyUploads = async (bucket: string, items: { path: string; key: string }[], mode: VerifyMode, connectionId?: string) => {
+  return await invoke<VerificationReport>("verify_uploads", { bucket, items, mode, connectionId });
+};
+
+export interface PrefixUsage {
+  prefix: string;
+  depth: number;
+  object_count: number;
+  total_size: number;
+}
+
+export const getBucketBreakdown = async (bucket: string, depth: number, connectionId?: string) => {
+  return await invoke<PrefixUsage[]>("get_bucket_breakdown", { bucket, depth, connectionId });
+};
+
+export type GrantAccess = "read_only" | "read_write";
+
+export interface ApiGrant {
+  id: string;
+  name: string;
+  bucket: string;
+  prefix: string | null;
+  access: GrantAccess;
+  connection_id: string | null;
+  created_at: number;
+  expires_at: number;
+}
+
+export interface IssuedGrant {
+  grant: ApiGrant;
+  token: string;
+}
+
+export const createApiGrant = async (
+  name: string,
+  bucket: string,
+  prefix: string | null,
+  access: GrantAccess,
+  ttlMinutes: number,
+  connectionId?: string
+) => {
+  return await invoke<IssuedGrant>("create_api_grant", { name, bucket, prefix, access, ttlMinutes, connectionId });
+};
+
+export const listApiGrants = async () => {
+  return await invoke<ApiGrant[]>("list_api_grants");
+};
+
+export const revokeApiGrant = async (id: string) => {
+  return await invoke<void>("revoke_api_grant", { id });
+};
+
+export type ActivitySource = "operation" | "transfer" | "watch";
+
+export interface ActivityEntry {
+  timestamp: number;
+  source: ActivitySource;
+  kind: string;
+  key: string | null;
+  summary: string;
+  succeeded: boolean;
+}
+
+export const getActivity = async (bucket: string, since: number) => {
+  return await invoke<ActivityEntry[]>("get_activity", { bucket, since });
+};
+
+export type Jurisdiction = "default" | "eu" | "fedramp";
+
+export interface CloudflareBucket {
+  name: string;
+  creation_date: string | null;
+  location: string | null;
+  jurisdiction: Jurisdiction;
+  storage_class: string | null;
+}
+
+export interface CloudflareBucketUsage {
+  bucket: string;
+  jurisdiction: Jurisdiction;
+  payload_size: number;
+  metadata_size: number;
+  object_count: number;
+  upload_count: number;
+}
+
+export interface AccountUsage {
+  buckets: CloudflareBucketUsage[];
+  total_payload_size: number;
+  total_object_count: number;
+}
+
+export const setCloudflareToken = async (token: string, accountId?: string, connectionId?: string) => {
+  return await invoke<void>("set_cloudflare_token", { token, accountId: accountId ?? null, connectionId });
+};
+
+export const clearCloudflareToken = async () => {
+  return await invoke<void>("clear_cloudflare_token");
+};
+
+export const cloudflareListBuckets = async () => {
+  return await invoke<CloudflareBucket[]>("cloudflare_list_buckets");
+};
+
+export const cloudflareCreateBucket = async (
+  name: string,
+  jurisdiction?: Jurisdiction,
+  locationHint?: string,
+  storageClass?: string
+) => {
+  return await invoke<CloudflareBucket>("cloudflare_create_bucket", {
+    name,
+    jurisdiction: jurisdiction ?? null,
+    locationHint: locationHint ?? null,
+    storageClass: storageClass ?? null,
+  });
+};
+
+export const cloudflareDeleteBucket = async (name: string, jurisdiction?: Jurisdiction) => {
+  return await invoke<void>("cloudflare_delete_bucket", { name, jurisdiction: jurisdiction ?? null });
+};
+
+export const cloudflareGetUsage = async () => {
+  return await invoke<AccountUsage>("cloudflare_get_usage");
+};
+
+export interface CustomDomain {
+  domain: string;
+  enabled: boolean;
+  zone_id: string | null;
+  ownership_status: string | null;
+  ssl_status: string | null;
+}
+
+export interface PublicAccess {
+  bucket: string;
+  r2_dev_enabled: boolean;
+  r2_dev_domain: string | null;
+  custom_domains: CustomDomain[];
+  public_base_url: string | null;
+}
+
+export const cloudflareGetPublicAccess = async (bucket: string, jurisdiction?: Jurisdiction) => {
+  return await invoke<PublicAccess>("cloudflare_get_public_access", { bucket, jurisdiction: jurisdiction ?? null });
+};
+
+export const cloudflareSetR2Dev = async (bucket: string, enabled: boolean, jurisdiction?: Jurisdiction) => {
+  return await invoke<PublicAccess>("cloudflare_set_r2_dev", { bucket, enabled, jurisdiction: jurisdiction ?? null });
+};
+
+export const cloudflareAddCustomDomain = async (
+  bucket: string,
+  domain: string,
+  zoneId: string,
+  jurisdiction?: Jurisdiction
+) => {
+  return await invoke<PublicAccess>("cloudflare_add_custom_domain", {
+    bucket,
+    domain,
+    zoneId,
+    jurisdiction: jurisdiction ?? null,
+  });
+};
+
+export const cloudflareRemoveCustomDomain = async (bucket: string, domain: string, jurisdiction?: Jurisdiction) => {
+  return await invoke<PublicAccess>("cloudflare_remove_custom_domain", {
+    bucket,
+    domain,
+    jurisdiction: jurisdiction ?? null,
+  });
+};
+
+export type HealthStatus = "ok" | "degraded";
+
+export interface HealthCheck {
+  name: string;
+  status: HealthStatus;
+  reason: string | null;
+  affects: string[];
+}
+
+export interface HealthReport {
+  status: HealthStatus;
+  checked_at: number;
+  checks: HealthCheck[];
+}
+
+export const getHealthReport = async () => {
+  return await invoke<HealthReport>("get_health_report");
 };
 
-export const copyObject = async (bucket: string, source: string, destination: string) => {
-  await invoke("copy_object", { bucket, source, destination });
+export const runHealthCheck = async () => {
+  return await invoke<HealthReport>("run_health_check");
 };
 
-export const renameFolder = async (bucket: string, oldPrefix: string, newPrefix: string) => {
-  return await invoke<number>("rename_folder", { bucket, oldPrefix, newPrefix });
+export type Provider = "r2" | "s3";
+
+export interface Profile {
+  name: string;
+  provider: Provider;
+  account_id: string;
+  jurisdiction: Jurisdiction | null;
+  endpoint: string | null;
+  region: string | null;
+  access_key: string;
+  /** Kept in the OS keychain and never returned; omit on update to keep the stored secret. */
+  secret_key?: string;
+  force_path_style: boolean;
+  read_only: boolean;
+  /** Bucket -> base URL it's publicly served from, when not discoverable via the Cloudflare API. */
+  public_urls?: Record<string, string>;
+  /** Send an SSE-C key on every request that reads or writes object data. */
+  sse_c?: boolean;
+  /** Base64 SSE-C key; write-only like `secret_key`. Omit on update to keep the stored key. */
+  sse_c_key?: string;
+}
+
+export const listProfiles = async () => {
+  return await invoke<Profile[]>("list_profiles");
+};
+
+export const createProfile = async (profile: Profile) => {
+  return await invoke<Profile>("create_profile", { profile });
+};
+
+export const updateProfile = async (name: string, profile: Profile) => {
+  return await invoke<Profile>("update_profile", { name, profile });
 };
 
+export const deleteProfile = async (name: string) => {
+  return await invoke<void>("delete_profile", { name });
+};
+
+export const connectProfile = async (name: string, connectionId?: string) => {
+  return await invoke<string>("connect_profile", { name, connectionId });
+};
+
+export type ConnectionIssue =
+  | "dns"
+  | "tls"
+  | "timeout"
+  | "network"
+  | "invalid_credentials"
+  | "clock_skew"
+  | "permission_denied"
+  | "no_such_bucket"
+  | "unknown";
+
+export interface ConnectionTest {
+  ok: boolean;
+  endpoint: string;
+  latency_ms: number;
+  issue: ConnectionIssue | null;
+  message: string | null;
+  hint: string | null;
+}
+
+export const testConnection = async (bucket?: string, connectionId?: string) => {
+  return await invoke<ConnectionTest>("test_connection", { bucket: bucket ?? null, connectionId });
+};
+
+export type Permission = "allowed" | "denied" | "skipped" | "unknown";
+
+export interface PermissionProbe {
+  bucket: string;
+  list: Permission;
+  read: Permission;
+  write: Permission;
+  delete: Permission;
+  errors: string[];
+}
+
+export const probePermissions = async (bucket: string, connectionId?: string) => {
+  return await invoke<PermissionProbe>("probe_permissions", { bucket, connectionId });
+};
+
+export interface RestoredConnection {
+  connection_id: string;
+  profile: string;
+  endpoint: string;
+}
+
+export interface ConnectionInfo {
+  connection_id: string;
+  endpoint: string;
+  account_id: string;
+  provider: string;
+  read_only: boolean;
+  expires_at: number | null;
+}
+
+export const listConnections = async () => {
+  return await invoke<ConnectionInfo[]>("list_connections");
+};
+
+export const closeConnection = async (connectionId?: string) => {
+  return await invoke<boolean>("close_connection", { connectionId });
+};
+
+export const transferBetweenAccounts = async (
+  srcConn: string,
+  srcBucket: string,
+  srcKey: string,
+  dstConn: string,
+  dstBucket: string,
+  dstKey: string
+) => {
+  return await invoke<number>("transfer_between_accounts", { srcConn, srcBucket, srcKey, dstConn, dstBucket, dstKey });
+};
+
+export interface AssumeRoleRequest {
+  role_arn: string;
+  session_name?: string | null;
+  duration_seconds?: number | null;
+  external_id?: string | null;
+  region?: string | null;
+  sts_endpoint?: string | null;
+  force_path_style?: boolean;
+}
+
+export interface CredentialsExpiring {
+  connection_id: string;
+  expires_at: number;
+  seconds_left: number;
+}
+
+export const assumeRole = async (
+  request: AssumeRoleRequest,
+  connectionId?: string,
+  targetConnectionId?: string
+) => {
+  return await invoke<number>("assume_role", { request, connectionId, targetConnectionId: targetConnectionId ?? null });
+};
+
+export interface LogEntry {
+  timestamp: number;
+  operation: string;
+  endpoint: string;
+  bucket: string | null;
+  key: string | null;
+  duration_ms: number;
+  succeeded: boolean;
+  status: number | null;
+  request_id: string | null;
+  error: string | null;
+}
+
+export const getRecentLogs = async (n: number) => {
+  return await invoke<LogEntry[]>("get_recent_logs", { n });
+};
+
+export interface SearchOptions {
+  prefix?: string | null;
+  mode?: "substring" | "glob" | "regex";
+  case_sensitive?: boolean;
+  max_results?: number | null;
+  filter?: ListingFilter;
+}
+
+/** A named search kept in settings, e.g. "all .log files older than 30 days". */
+export interface SavedSearch {
+  name: string;
+  bucket: string;
+  query?: string | null;
+  options?: SearchOptions;
+  older_than_days?: number | null;
+  newer_than_days?: number | null;
+}
+
+/** Payload of `search://matches`. */
+export interface SearchMatches {
+  search_id: number;
+  matches: R2Object[];
+}
+
+/** Payload of `search://done`. */
+export interface SearchDone {
+  search_id: number;
+  scanned: number;
+  matched: number;
+  truncated: boolean;
+}
+
+export const searchObjects = async (bucket: string, query: string, options?: SearchOptions, connectionId?: string) => {
+  return await invoke<number>("search_objects", { bucket, query, options: options ?? null, connectionId });
+};
+
+export const cancelSearch = async (searchId: number) => {
+  return await invoke<boolean>("cancel_search", { searchId });
+};
+
+export const listSavedSearches = async () => {
+  return await invoke<SavedSearch[]>("list_saved_searches");
+};
+
+export const saveSearch = async (search: SavedSearch) => {
+  return await invoke<SavedSearch[]>("save_search", { search });
+};
+
+export const deleteSavedSearch = async (name: string) => {
+  return await invoke<SavedSearch[]>("delete_saved_search", { name });
+};
+
+export const runSavedSearch = async (name: string, connectionId?: string) => {
+  return await invoke<number>("run_saved_search", { name, connectionId });
+};
+
+export interface IndexStatus {
+  bucket: string;
+  objects: number;
+  total_size: number;
+  /** When the last full pass finished; null while the first one is still running. */
+  indexed_at: number | null;
+  running: boolean;
+}
+
+/** Payload of `index://progress`. */
+export interface IndexProgress {
+  bucket: string;
+  indexed: number;
+}
+
+/** Payload of `index://done`, emitted after every pass. */
+export interface IndexDone {
+  bucket: string;
+  objects: number;
+  removed: number;
+}
+
+export interface IndexedFolderSize {
+  prefix: string;
+  objects: number;
+  total_size: number;
+  indexed_at: number | null;
+}
+
+export const startIndexing = async (bucket: string, intervalSecs?: number, connectionId?: string) => {
+  return await invoke<void>("start_indexing", { bucket, intervalSecs: intervalSecs ?? null, connectionId });
+};
+
+export const stopIndexing = async (bucket: string, connectionId?: string) => {
+  return await invoke<boolean>("stop_indexing", { bucket, connectionId });
+};
+
+export const dropIndex = async (bucket: string, connectionId?: string) => {
+  return await invoke<void>("drop_index", { bucket, connectionId });
+};
+
+export const indexStatus = async (connectionId?: string) => {
+  return await invoke<IndexStatus[]>("index_status", { connectionId });
+};
+
+export const searchIndex = async (bucket: string, query: string, options?: SearchOptions, connectionId?: string) => {
+  return await invoke<R2Object[]>("search_index", { bucket, query, options: options ?? null, connectionId });
+};
+
+export const indexFolderSize = async (bucket: string, prefix: string, connectionId?: string) => {
+  return await invoke<IndexedFolderSize>("index_folder_size", { bucket, prefix, connectionId });
+};
+
+export type InventoryFormat = "csv" | "json";
+
+export interface InventorySummary {
+  path: string;
+  objects: number;
+  total_size: number;
+}
+
+export const exportInventory = async (bucket: string, prefix: string | null, format: InventoryFormat, path: string, connectionId?: string) => {
+  return await invoke<InventorySummary>("export_inventory", { bucket, prefix, format, path, connectionId });
+};
+
+export interface ChangedObject {
+  key: string;
+  before: R2Object;
+  after: R2Object;
+}
+
+export interface InventoryDiff {
+  added: R2Object[];
+  removed: R2Object[];
+  changed: ChangedObject[];
+  unchanged: number;
+}
+
+/** Compares two inventories, or an inventory with the live bucket when targetPath is omitted. */
+export const diffInventory = async (basePath: string, targetPath?: string, bucket?: string, prefix?: string, connectionId?: string) => {
+  return await invoke<InventoryDiff>("diff_inventory", {
+    basePath,
+    targetPath: targetPath ?? null,
+    bucket: bucket ?? null,
+    prefix: prefix ?? null,
+    connectionId,
+  });
+};
+
+export interface KeyedLink extends PresignedLink {
+  key: string;
+}
+
+/** GET links for many keys, in order; also written to csvPath when given. */
+export const getPresignedUrls = async (bucket: string, keys: string[], expiresIn?: number, csvPath?: string, connectionId?: string) => {
+  return await invoke<KeyedLink[]>("get_presigned_urls", {
+    bucket,
+    keys,
+    expiresIn: expiresIn ?? null,
+    csvPath: csvPath ?? null,
+    connectionId,
+  });
+};
+
+export interface PresignedPart extends PresignedLink {
+  part_number: number;
+  /** Byte range of the file this part carries, end exclusive. */
+  start: number;
+  end: number;
+}
+
+export interface PresignedMultipartUpload {
+  upload_id: string;
+  part_size: number;
+  parts: PresignedPart[];
+  expires_at: number;
+}
+
+export interface UploadedPart {
+  part_number: number;
+  etag: string;
+}
+
+export const presignMultipartUpload = async (
+  bucket: string,
+  key: string,
+  size: number,
+  partSize?: number,
+  expiresIn?: number,
+  contentType?: string,
+  connectionId?: string,
+) => {
+  return await invoke<PresignedMultipartUpload>("presign_multipart_upload", {
+    bucket,
+    key,
+    size,
+    partSize: partSize ?? null,
+    expiresIn: expiresIn ?? null,
+    contentType: contentType ?? null,
+    connectionId,
+  });
+};
+
+export const completePresignedUpload = async (bucket: string, key: string, uploadId: string, parts: UploadedPart[], connectionId?: string) => {
+  return await invoke<void>("complete_presigned_upload", { bucket, key, uploadId, parts, connectionId });
+};
+
+export const abortPresignedUpload = async (bucket: string, key: string, uploadId: string, connectionId?: string) => {
+  return await invoke<void>("abort_presigned_upload", { bucket, key, uploadId, connectionId });
+};
+
+/** A link handed out earlier, as remembered locally. */
+export interface Share {
+  id: string;
+  bucket: string;
+  key: string;
+  url: string;
+  method: string;
+  created_at: number;
+  /** null for permanent public links. */
+  expires_at: number | null;
+  expired: boolean;
+}
+
+export const listShares = async (includeExpired?: boolean) => {
+  return await invoke<Share[]>("list_shares", { includeExpired: includeExpired ?? null });
+};
+
+export const expireShare = async (id: string) => {
+  return await invoke<Share>("expire_share", { id });
+};
+
+export const deleteShare = async (id: string) => {
+  return await invoke<void>("delete_share", { id });
+};
+
+export const pruneExpiredShares = async () => {
+  return await invoke<number>("prune_expired_shares");
+};
+
+/** Permanent public link for a key in a public bucket (r2.dev or custom domain). */
+export const getPublicUrl = async (bucket: string, key: string, connectionId?: string) => {
+  return await invoke<string>("get_public_url", { bucket, key, connectionId });
+};
+
+export interface Thumbnail {
+  /** Cached file on disk. */
+  path: string;
+  content_type: string;
+  /** Base64 image data, for a data: URL. */
+  data: string;
+  width: number;
+  height: number;
+  cached: boolean;
+}
+
+export const getThumbnail = async (bucket: string, key: string, maxDimension?: number, connectionId?: string) => {
+  return await invoke<Thumbnail>("get_thumbnail", { bucket, key, maxDimension: maxDimension ?? null, connectionId });
+};
+
+export const clearThumbnailCache = async () => {
+  return await invoke<number>("clear_thumbnail_cache");
+};
+
+/** URL the webview can stream an object from with seeking, e.g. a <video> or <audio> src. */
+export const getMediaUrl = async (bucket: string, key: string, connectionId?: string) => {
+  return await invoke<string>("get_media_url", { bucket, key, connectionId });
+};
+
+/** First page of a PDF, or the preview embedded in an Office/OpenDocument file. */
+export const getDocumentPreview = async (bucket: string, key: string, maxDimension?: number, connectionId?: string) => {
+  return await invoke<Thumbnail>("get_document_preview", { bucket, key, maxDimension: maxDimension ?? null, connectionId });
+};
+
+export interface ArchiveEntry {
+  name: string;
+  size: number;
+  compressed_size: number;
+  /** "stored", "deflate" or the raw method number. */
+  method: string;
+  crc32: number;
+  is_dir: boolean;
+  encrypted: boolean;
+}
+
+export interface ArchiveListing {
+  entries: ArchiveEntry[];
+  /** Size of the whole archive. */
+  size: number;
+}
+
+/** Lists a remote ZIP by reading only its central directory. */
+export const listArchive = async (bucket: string, key: string, connectionId?: string) => {
+  return await invoke<ArchiveListing>("list_archive", { bucket, key, connectionId });
+};
+
+/** Extracts one member of a remote ZIP to savePath. Returns the bytes written. */
+export const extractArchiveEntry = async (bucket: string, key: string, entry: string, savePath: string, connectionId?: string) => {
+  return await invoke<number>("extract_archive_entry", { bucket, key, entry, savePath, connectionId });
+};
+
+export type ZipCompression = "store" | "deflate";
+
+/** Payload of `zip://progress`. */
+export interface ZipProgress {
+  save_path: string;
+  files_done: number;
+  files_total: number;
+  bytes_done: number;
+  bytes_total: number;
+  current: string | null;
+}
+
+export interface ZipSummary {
+  files: number;
+  bytes: number;
+  archive_size: number;
+}
+
+/** Downloads keys (folders end in "/") into one ZIP at savePath; progress arrives as `zip://progress`. */
+export const downloadAsZip = async (bucket: string, keys: string[], savePath: string, compression?: ZipCompression, connectionId?: string) => {
+  return await invoke<ZipSummary>("download_as_zip", { bucket, keys, savePath, compression: compression ?? null, connectionId });
+};
+
+/** Payload of `extract://progress`; archives are streamed, so there is no total. */
+export interface ExtractProgress {
+  path: string;
+  files_done: number;
+  bytes_done: number;
+  current: string | null;
+}
+
+export interface ExtractSummary {
+  files: number;
+  bytes: number;
+  /** Members whose path would have escaped the prefix. */
+  skipped: string[];
+}
+
+/** Unpacks a local .zip/.tar.gz/.tgz/.tar under prefix, uploading members concurrently. */
+export const uploadArchive = async (bucket: string, prefix: string, path: string, concurrency?: number, connectionId?: string) => {
+  return await invoke<ExtractSummary>("upload_archive", { bucket, prefix, path, concurrency: concurrency ?? null, connectionId });
+};
+
+export interface TablePreview {
+  format: "csv" | "parquet";
+  columns: string[];
+  /** One array per row in column order; CSV cells are strings, Parquet keeps its types. */
+  rows: unknown[][];
+  /** Rows in the whole object, when the format records it (Parquet). */
+  total_rows: number | null;
+  truncated: boolean;
+}
+
+/** Column names and the first maxRows rows (100 by default) of a CSV/TSV or Parquet object. */
+export const previewTable = async (bucket: string, key: string, maxRows?: number, connectionId?: string) => {
+  return await invoke<TablePreview>("preview_table", { bucket, key, maxRows: maxRows ?? null, connectionId });
+};
+
+export interface MediaInfo {
+  kind: "image" | "video" | "audio" | null;
+  width: number | null;
+  height: number | null;
+  duration_secs: number | null;
+  /** Codec identifiers as the container names them, e.g. "avc1", "mp4a", "pcm". */
+  codecs: string[];
+  sample_rate: number | null;
+  channels: number | null;
+  /** EXIF tags by name, formatted for display. */
+  exif: Record<string, string>;
+}
+
+/** EXIF and basic media properties, read from just the needed byte ranges. */
+export const getMediaInfo = async (bucket: string, key: string, connectionId?: string) => {
+  return await invoke<MediaInfo>("get_media_info", { bucket, key, connectionId });
+};
+
+/** "both" carries changes and deletions since the last sync in either direction. */
+export type SyncDirection = "upload" | "download" | "both";
+
+export interface SyncOptions {
+  /** Delete files on the destination that the source doesn't have. */
+  delete?: boolean;
+  /** Gitignore-style patterns, added to those in the local root's `.r2ignore`; matches are left alone on both sides. */
+  ignore?: string[];
+  /** Which side wins when a file changed on both sides since the last sync. Defaults to "keep_newer". */
+  conflict?: ConflictPolicy;
+}
+
+export type ConflictPolicy = "keep_newer" | "keep_local" | "keep_remote" | "keep_both";
+
+export type SyncActionKind = "upload" | "download" | "delete_remote" | "delete_local" | "keep_both" | "skip";
+
+export interface SyncFileState {
+  size: number;
+  modified: number;
+  etag: string | null;
+}
+
+export interface SyncAction {
+  action: SyncActionKind;
+  /** Path below the local root and the prefix, with "/" separators. */
+  relative: string;
+  size: number;
+  reason: string;
+  local?: SyncFileState | null;
+  remote?: SyncFileState | null;
+  /** Where the local version is kept, for "keep_both". */
+  conflict_copy?: string | null;
+}
+
+/** A file changed on both sides since the last sync, and how it was resolved. */
+export interface SyncConflict {
+  relative: string;
+  local: SyncFileState;
+  remote: SyncFileState;
+  resolution: ConflictPolicy;
+}
+
+export interface SyncPlan {
+  local_root: string;
+  bucket: string;
+  prefix: string;
+  direction: SyncDirection;
+  actions: SyncAction[];
+  conflicts: SyncConflict[];
+}
+
+/** Payload of `sync://progress`. */
+export interface SyncProgress {
+  bucket: string;
+  prefix: string;
+  done: number;
+  total: number;
+  bytes: number;
+  current: string | null;
+}
+
+export interface SyncResult {
+  uploaded: number;
+  downloaded: number;
+  deleted: number;
+  skipped: number;
+  bytes: number;
+  failed: { relative: string; error: string }[];
+  conflicts: SyncConflict[];
+}
+
+/** Forgets what was recorded at past syncs of this folder and prefix; the next sync treats both sides as new. */
+export const resetSyncState = async (localRoot: string, bucket: string, prefix: string) => {
+  return await invoke<void>("reset_sync_state", { localRoot, bucket, prefix });
+};
+
+/** Dry run: every file with the action a sync would take and why. Nothing is changed. */
+export const planSync = async (localRoot: string, bucket: string, prefix: string, direction: SyncDirection, options?: SyncOptions, connectionId?: string) => {
+  return await invoke<SyncPlan>("plan_sync", { localRoot, bucket, prefix, direction, options: options ?? null, connectionId });
+};
+
+/** Carries out a plan from planSync, possibly edited. */
+export const applySyncPlan = async (plan: SyncPlan, connectionId?: string) => {
+  return await invoke<SyncResult>("apply_sync_plan", { plan, connectionId });
+};
+
+export const syncFolder = async (localRoot: string, bucket: string, prefix: string, direction: SyncDirection, options?: SyncOptions, connectionId?: string) => {
+  return await invoke<SyncResult>("sync_folder", { localRoot, bucket, prefix, direction, options: options ?? null, connectionId });
+};
+
+export interface FolderWatch {
+  /** Assigned on first save. */
+  id?: string;
+  local_root: string;
+  bucket: string;
+  prefix?: string;
+  connection_id?: string | null;
+  /** Gitignore-style patterns, on top of the root's `.r2ignore`. */
+  ignore?: string[];
+  debounce_ms?: number;
+  enabled?: boolean;
+}
+
+/** Payload of `folder-watch://synced`. */
+export interface FolderWatchBatch {
+  watch_id: string;
+  uploaded: string[];
+  failed: { relative: string; error: string }[];
+}
+
+export const listFolderWatches = async () => {
+  return await invoke<(FolderWatch & { running: boolean })[]>("list_folder_watches");
+};
+
+/** Adds or updates a watched folder; changed files upload to the prefix once the folder goes quiet. */
+export const saveFolderWatch = async (watch: FolderWatch) => {
+  return await invoke<FolderWatch>("save_folder_watch", { watch });
+};
+
+export const deleteFolderWatch = async (id: string) => {
+  return await invoke<void>("delete_folder_watch", { id });
+};
+
+/** What a schedule runs: a folder sync, a bucket-to-bucket replication or a snapshot backup. */
+export type ScheduledTask =
+  | { kind: "sync"; local_root: string; bucket: string; prefix?: string; direction: SyncDirection; options?: SyncOptions }
+  | ({ kind: "replicate" } & Replication)
+  | ({ kind: "snapshot" } & SnapshotSpec);
+
+export type SyncSchedule = {
+  /** Assigned on first save. */
+  id?: string;
+  name: string;
+  /** Five cron fields (minute hour day month weekday) in local time, or @hourly, @daily, @weekly, @monthly. */
+  cron: string;
+  connection_id?: string | null;
+  enabled?: boolean;
+} & ScheduledTask;
+
+export type ScheduleOutcome = SyncResult | ReplicateResult | SnapshotResult;
+
+/** Payload of `schedule://finished`. */
+export interface ScheduleFinished {
+  schedule_id: string;
+  result: ScheduleOutcome | null;
+  error: string | null;
+}
+
+export const listSyncSchedules = async () => {
+  return await invoke<(SyncSchedule & { running: boolean; next_run: number | null })[]>("list_sync_schedules");
+};
+
+/** Creates a schedule, or updates the one with the same id. */
+export const saveSyncSchedule = async (schedule: SyncSchedule) => {
+  return await invoke<SyncSchedule>("save_sync_schedule", { schedule });
+};
+
+export const setSyncScheduleEnabled = async (id: string, enabled: boolean) => {
+  return await invoke<SyncSchedule>("set_sync_schedule_enabled", { id, enabled });
+};
+
+export const deleteSyncSchedule = async (id: string) => {
+  return await invoke<void>("delete_sync_schedule", { id });
+};
+
+export const runSyncScheduleNow = async (id: string) => {
+  return await invoke<ScheduleOutcome>("run_sync_schedule_now", { id });
+};
+
+/** Past runs of a schedule, newest first. */
+export const syncScheduleHistory = async (id: string, limit?: number) => {
+  return await invoke<JobRecord[]>("sync_schedule_history", { id, limit: limit ?? null });
+};
+
+export interface Replication {
+  src_bucket: string;
+  src_prefix?: string;
+  dst_bucket: string;
+  dst_prefix?: string;
+  /** Delete objects under the destination prefix that the source doesn't have. */
+  delete?: boolean;
+}
+
+/** Payload of `replicate://progress`. */
+export interface ReplicateProgress {
+  src_bucket: string;
+  dst_bucket: string;
+  done: number;
+  total: number;
+  bytes: number;
+}
+
+export interface ReplicateResult {
+  copied: number;
+  deleted: number;
+  unchanged: number;
+  bytes: number;
+  failed: { key: string; error: string }[];
+}
+
+/** Server-side copies new and changed objects (by ETag and size) from one prefix to another, e.g. staging to production. */
+export const replicate = async (srcBucket: string, srcPrefix: string, dstBucket: string, dstPrefix: string, deleteExtra?: boolean, connectionId?: string) => {
+  return await invoke<ReplicateResult>("replicate", { srcBucket, srcPrefix, dstBucket, dstPrefix, delete: deleteExtra ?? null, connectionId });
+};
+
+export interface SnapshotSpec {
+  local_root: string;
+  bucket: string;
+  /** Defaults to "backups/". */
+  base_prefix?: string;
+  /** Copy unchanged files from the previous snapshot server-side. Defaults to true. */
+  reuse_previous?: boolean;
+  /** Gitignore-style patterns, on top of the root's `.r2ignore`. */
+  ignore?: string[];
+  /** Prune to this many snapshots after a successful one. */
+  keep?: number | null;
+}
+
+export interface SnapshotInfo {
+  name: string;
+  prefix: string;
+  created_at: number | null;
+  files: number | null;
+  bytes: number | null;
+  complete: boolean | null;
+}
+
+/** Payload of `snapshot://progress`. */
+export interface SnapshotProgress {
+  bucket: string;
+  name: string;
+  done: number;
+  total: number;
+  bytes: number;
+}
+
+export interface SnapshotResult {
+  name: string;
+  prefix: string;
+  uploaded: number;
+  copied: number;
+  bytes: number;
+  failed: { relative: string; error: string }[];
+  pruned: string[];
+}
+
+/** Backs up a local folder to a new timestamped prefix such as `backups/2024-06-01T02:00/`. */
+export const createSnapshot = async (spec: SnapshotSpec, connectionId?: string) => {
+  return await invoke<SnapshotResult>("create_snapshot", {
+    localRoot: spec.local_root,
+    bucket: spec.bucket,
+    basePrefix: spec.base_prefix ?? null,
+    reusePrevious: spec.reuse_previous ?? null,
+    ignore: spec.ignore ?? null,
+    keep: spec.keep ?? null,
+    connectionId,
+  });
+};
+
+export const listSnapshots = async (bucket: string, basePrefix?: string, connectionId?: string) => {
+  return await invoke<SnapshotInfo[]>("list_snapshots", { bucket, basePrefix: basePrefix ?? null, connectionId });
+};
+
+/** Deletes all but the newest `keep` snapshots; returns the names removed. */
+export const pruneSnapshots = async (bucket: string, keep: number, basePrefix?: string, connectionId?: string) => {
+  return await invoke<string[]>("prune_snapshots", { bucket, keep, basePrefix: basePrefix ?? null, connectionId });
+};
+
+/** Where to restore a snapshot to; "prefix" copies server-side. */
+export type RestoreTarget = { kind: "local"; path: string } | { kind: "prefix"; bucket: string; prefix: string };
+
+/** Payload of `restore://progress`, after each file. */
+export interface RestoreProgress {
+  name: string;
+  done: number;
+  total: number;
+  bytes: number;
+  current: string;
+}
+
+export interface RestoreResult {
+  restored: number;
+  skipped: number;
+  bytes: number;
+  failed: { relative: string; error: string }[];
+}
+
+/** Files and bytes a restore would transfer. `paths` narrows it to files, or folders ending in "/". */
+export const snapshotRestoreSize = async (bucket: string, name: string, basePrefix?: string, paths?: string[], connectionId?: string) => {
+  return await invoke<{ files: number; bytes: number }>("snapshot_restore_size", { bucket, name, basePrefix: basePrefix ?? null, paths: paths ?? null, connectionId });
+};
+
+export const restoreSnapshot = async (
+  bucket: string,
+  name: string,
+  target: RestoreTarget,
+  options?: { basePrefix?: string; paths?: string[]; skipExisting?: boolean },
+  connectionId?: string,
+) => {
+  return await invoke<RestoreResult>("restore_snapshot", {
+    bucket,
+    name,
+    target,
+    basePrefix: options?.basePrefix ?? null,
+    paths: options?.paths ?? null,
+    skipExisting: options?.skipExisting ?? null,
+    connectionId,
+  });
+};
+
+export interface ObjectVersion {
+  key: string;
+  /** "null" for objects written while versioning was off or suspended. */
+  version_id: string;
+  is_latest: boolean;
+  is_delete_marker: boolean;
+  size: number;
+  last_modified: number;
+  etag: string | null;
+  storage_class: string | null;
+}
+
+/** Enables versioning, or suspends it (existing versions are kept). */
+export const setBucketVersioning = async (bucket: string, enabled: boolean, connectionId?: string) => {
+  return await invoke<void>("set_bucket_versioning", { bucket, enabled, connectionId });
+};
+
+/** Versions and delete markers under a prefix, by key and newest first. */
+export const listObjectVersions = async (bucket: string, prefix?: string, connectionId?: string) => {
+  return await invoke<{ versions: ObjectVersion[]; truncated: boolean }>("list_object_versions", { bucket, prefix: prefix ?? null, connectionId });
+};
+
+export const downloadObjectVersion = async (bucket: string, key: string, versionId: string, savePath: string, connectionId?: string) => {
+  return await invoke<void>("download_object_version", { bucket, key, versionId, savePath, connectionId });
+};
+
+/** Copies a version over the key, making it current. Returns the new version id. */
+export const restoreVersion = async (bucket: string, key: string, versionId: string, connectionId?: string) => {
+  return await invoke<string | null>("restore_version", { bucket, key, versionId, connectionId });
+};
+
+/** Permanently deletes one version or delete marker. */
+export const deleteObjectVersion = async (bucket: string, key: string, versionId: string, connectionId?: string) => {
+  return await invoke<void>("delete_object_version", { bucket, key, versionId, connectionId });
+};
+
+export interface VersionCleanupResult {
+  /** What was removed, or on a dry run what would be. */
+  removed: ObjectVersion[];
+  delete_markers: number;
+  noncurrent_versions: number;
+  bytes: number;
+  failed: { key: string; version_id: string; error: string }[];
+  dry_run: boolean;
+}
+
+/**
+ * Removes delete markers and, with `noncurrentDays`, noncurrent versions older than that.
+ * Emits `version-cleanup://progress` while deleting.
+ */
+export const cleanUpVersions = async (
+  bucket: string,
+  options: { prefix?: string; noncurrentDays?: number; dryRun: boolean },
+  connectionId?: string,
+) => {
+  return await invoke<VersionCleanupResult>("clean_up_versions", {
+    bucket,
+    prefix: options.prefix ?? null,
+    noncurrentDays: options.noncurrentDays ?? null,
+    dryRun: options.dryRun,
+    connectionId,
+  });
+};
+
+export interface EncryptionStatus {
+  enabled: boolean;
+  encrypt_names: boolean;
+  unlocked: boolean;
+  key_id: string | null;
+}
+
+export const encryptionStatus = async () => {
+  return await invoke<EncryptionStatus>("encryption_status");
+};
+
+/**
+ * Unlocks client-side encryption with a passphrase or a keyfile path. `replace` switches to a
+ * different key than the one used before.
+ */
+export const unlockEncryption = async (secret: { passphrase: string } | { keyfile: string }, replace?: boolean) => {
+  return await invoke<EncryptionStatus>("unlock_encryption", {
+    passphrase: "passphrase" in secret ? secret.passphrase : null,
+    keyfile: "keyfile" in secret ? secret.keyfile : null,
+    replace: replace ?? null,
+  });
+};
+
+export const lockEncryption = async () => {
+  return await invoke<void>("lock_encryption");
+};
+
+/** Writes a new random keyfile. */
+export const createKeyfile = async (path: string) => {
+  return await invoke<void>("create_keyfile", { path });
+};
+
+/** Plaintext file names for listed keys; null where a name isn't encrypted. */
+export const decryptKeyNames = async (keys: string[]) => {
+  return await invoke<(string | null)[]>("decrypt_key_names", { keys });
+};
+
+/** A new random base64 SSE-C key. */
+export const generateSseCKey = async () => {
+  return await invoke<string>("generate_sse_c_key");
+};
+
+export type AuditMode = "full" | "sample";
+
+export interface AuditReport {
+  total: number;
+  recorded: number;
+  verified: number;
+  /** Rewritten since recorded (new ETag or size). */
+  changed: { key: string; reason: string }[];
+  /** Same ETag and size but different bytes. */
+  corrupted: { key: string; reason: string }[];
+  missing: string[];
+  failed: { key: string; reason: string }[];
+  bytes: number;
+}
+
+/**
+ * Hashes objects under a prefix and compares them with digests from earlier audits. `sample`
+ * only reads the first, middle and last MiB of each object. Emits `audit://progress`.
+ */
+export const auditObjects = async (bucket: string, prefix?: string, mode?: AuditMode, connectionId?: string) => {
+  return await invoke<AuditReport>("audit", { bucket, prefix: prefix ?? null, mode: mode ?? null, connectionId });
+};
+
+/** Forgets recorded digests under a prefix. Returns how many were removed. */
+export const clearAuditRecords = async (bucket: string, prefix?: string, connectionId?: string) => {
+  return await invoke<number>("clear_audit_records", { bucket, prefix: prefix ?? null, connectionId });
+};
+
+export type ChecksumSource = { kind: "remote" } | { kind: "local"; root: string };
+
+export interface ManifestResult {
+  manifest_key: string;
+  files: number;
+  /** Hashed from the local folder rather than streamed. */
+  from_local: number;
+  failed: { path: string; error: string }[];
+}
+
+export interface ManifestVerification {
+  ok: number;
+  mismatched: string[];
+  missing: string[];
+  unlisted: string[];
+  failed: { path: string; error: string }[];
+}
+
+/**
+ * Writes a SHA256SUMS manifest (or `name`) into `prefix`. With a local source, files matching
+ * their object's size are hashed from disk. Emits `checksums://progress`.
+ */
+export const createChecksumManifest = async (
+  bucket: string,
+  prefix: string,
+  options?: { name?: string; source?: ChecksumSource },
+  connectionId?: string,
+) => {
+  return await invoke<ManifestResult>("create_checksum_manifest", {
+    bucket,
+    prefix,
+    name: options?.name ?? null,
+    source: options?.source ?? null,
+    connectionId,
+  });
+};
+
+/** Checks the objects a manifest lists against it. Emits `checksums://progress`. */
+export const verifyChecksumManifest = async (bucket: string, manifestKey: string, connectionId?: string) => {
+  return await invoke<ManifestVerification>("verify_checksum_manifest", { bucket, manifestKey, connectionId });
+};
+
+export interface TrustedKey {
+  name: string;
+  kind: "openpgp" | "minisign";
+  /** OpenPGP fingerprint, or the minisign public key. */
+  fingerprint: string;
+  key: string;
+}
+
+export interface SignatureCheck {
+  bucket: string;
+  key: string;
+  status: "valid" | "invalid" | "unsigned" | "unknown_key";
+  signature_key: string | null;
+  /** Name of the trusted key that made the signature. */
+  signer: string | null;
+  detail: string | null;
+}
+
+/** Checks a downloaded copy of `key` at `path` against the signature stored next to it. */
+export const verifySignature = async (bucket: string, key: string, path: string, connectionId?: string) => {
+  return await invoke<SignatureCheck>("verify_signature", { bucket, key, path, connectionId });
+};
+
+/** Trusts an armored OpenPGP public key or a minisign public key. */
+export const addTrustedKey = async (name: string, key: string) => {
+  return await invoke<TrustedKey>("add_trusted_key", { name, key });
+};
+
+export const removeTrustedKey = async (fingerprint: string) => {
+  return await invoke<void>("remove_trusted_key", { fingerprint });
+};
+
+export interface MountInfo {
+  id: string;
+  bucket: string;
+  prefix: string;
+  mountpoint: string;
+  connection_id: string | null;
+  read_only: boolean;
+}
+
+/**
+ * Mounts a bucket (or a prefix of it) at an empty local folder, or on Windows at a free drive
+ * letter such as `R:` or a folder that doesn't exist yet. Files written through the mount are
+ * uploaded when closed; listen for `mount://uploaded` to follow them. Fails with `unsupported`
+ * on builds without mount support, or on Windows when WinFsp isn't installed.
+ */
+export const mountBucket = async (
+  bucket: string,
+  mountpoint: string,
+  prefix?: string,
+  readOnly?: boolean,
+  connectionId?: string
+) => {
+  return await invoke<MountInfo>("mount_bucket", {
+    bucket,
+    prefix: prefix ?? null,
+    mountpoint,
+    readOnly: readOnly ?? null,
+    connectionId,
+  });
+};
+
+export const unmountBucket = async (id: string) => {
+  return await invoke<void>("unmount_bucket", { id });
+};
+
+export const listMounts = async () => {
+  return await invoke<MountInfo[]>("list_mounts");
+};
+
+/**
+ * Pauses transfers before their next file or chunk, or resumes them. The tray menu offers the
+ * same; `transfers://paused` reports changes from either.
+ */
+export const setTransfersPaused = async (paused: boolean) => {
+  return await invoke<void>("set_transfers_paused", { paused });
+};
+
+export const getTransfersPaused = async () => {
+  return await invoke<boolean>("transfers_paused");
+};
+
+export interface BrowseTarget {
+  profile: string | null;
+  bucket: string;
+  /** Folder to open, ending in "/" (empty for the bucket root). */
+  prefix: string;
+  /** Object to select, when the URL named one. */
+  key: string | null;
+}
+
+export interface DownloadTarget {
+  profile: string | null;
+  bucket: string;
+  key: string;
+}
+
+export interface LaunchRequest {
+  /** Files and folders passed on the command line or through "Open with". */
+  files: string[];
+  /** From an r2://bucket/prefix URL or an r2drive://open link. */
+  browse: BrowseTarget | null;
+  /** Sent with "Upload to R2Drive" while no default destination is set; pass to uploadShellFiles. */
+  upload: string[];
+  /** From an r2drive://download link; confirm with the user before starting it. */
+  download: DownloadTarget | null;
+  /** An r2drive:connect: link to pass to importConnectLink. */
+  connect_link: string | null;
+  unrecognized: string[];
+}
+
+/**
+ * Takes the pending launch request, if any. Call once at startup and again on every
+ * `launch://request` event, which fires when another launch forwards its arguments here.
+ */
+export const takeLaunchRequest = async () => {
+  return await invoke<LaunchRequest | null>("take_launch_request");
+};
+
+export interface EditingFile {
+  id: string;
+  bucket: string;
+  key: string;
+  connection_id: string | null;
+  local_path: string;
+  etag: string;
+  uploads: number;
+  /** The object changed in the bucket meanwhile; saves are held back until stopEditing decides. */
+  conflict: boolean;
+  error: string | null;
+}
+
+/**
+ * Downloads an object and opens it in its default application. Each save there is uploaded back;
+ * `editing://status` reports every attempt with the updated EditingFile.
+ */
+export const openForEditing = async (bucket: string, key: string, connectionId?: string) => {
+  return await invoke<EditingFile>("open_for_editing", { bucket, key, connectionId });
+};
+
+export const listEditingFiles = async () => {
+  return await invoke<EditingFile[]>("list_editing_files");
+};
+
+/**
+ * Uploads outstanding edits and removes the local copy. Fails with `precondition_failed` on a
+ * conflict unless `overwrite` or `discard` is set.
+ */
+export const stopEditing = async (id: string, overwrite?: boolean, discard?: boolean) => {
+  return await invoke<void>("stop_editing", {
+    id,
+    overwrite: overwrite ?? null,
+    discard: discard ?? null,
+  });
+};
+
+export interface ShellUploadResult {
+  bucket: string;
+  uploaded: string[];
+  /** `relative` is the local path of the file that failed. */
+  failed: { relative: string; error: string }[];
+}
+
+/**
+ * Uploads paths sent from the file manager's context menu to the destination the user picked.
+ * Rejects if any file failed; the per-file outcome still arrives through `shell://uploaded`,
+ * which also reports uploads to the default destination that happen without the UI.
+ */
+export const uploadShellFiles = async (
+  paths: string[],
+  bucket: string,
+  prefix?: string,
+  connectionId?: string
+) => {
+  return await invoke<ShellUploadResult>("upload_shell_files", {
+    paths,
+    bucket,
+    prefix: prefix ?? null,
+    connectionId,
+  });
+};
+
+export const isShellIntegrationInstalled = async () => {
+  return await invoke<boolean>("shell_integration_installed");
+};
+
+/** Adds "Upload to R2Drive" to the file manager's context menu for the current user. */
+export const installShellIntegration = async () => {
+  return await invoke<void>("install_shell_integration");
+};
+
+export const uninstallShellIntegration = async () => {
+  return await invoke<void>("uninstall_shell_integration");
+};
+
+export type ReleaseChannel = "stable" | "beta";
+
+export interface UpdateInfo {
+  version: string;
+  current_version: string;
+  notes: string | null;
+  /** Unix seconds. */
+  date: number | null;
+  channel: ReleaseChannel;
+  /** Downloaded and ready for installUpdate. */
+  downloaded: boolean;
+}
+
+/** Payload of `update://progress`. */
+export interface UpdateProgress {
+  version: string;
+  downloaded: number;
+  total: number | null;
+}
+
+/**
+ * Checks the release channel for a newer version; null when up to date. Fails with
+ * `unsupported` on builds that don't update themselves.
+ */
+export const checkForUpdate = async () => {
+  return await invoke<UpdateInfo | null>("check_for_update");
+};
+
+/**
+ * Starts downloading the update found by checkForUpdate. Follow it through `update://progress`,
+ * then `update://ready` or `update://error`.
+ */
+export const downloadUpdate = async () => {
+  return await invoke<void>("download_update");
+};
+
+/** Installs the downloaded update and restarts the app. */
+export const installUpdate = async () => {
+  return await invoke<void>("install_update");
+};
+
+export interface PastedUpload {
+  kind: "image" | "files";
+  /** Keys written, in clipboard order; for an image, the one key to share. */
+  keys: string[];
+}
+
+/**
+ * Uploads the clipboard's image (as a timestamped PNG) or copied files to `prefix`, never
+ * overwriting anything. Fails with `invalid_input` when the clipboard holds neither.
+ */
+export const pasteToBucket = async (bucket: string, prefix?: string, connectionId?: string) => {
+  return await invoke<PastedUpload>("paste_to_bucket", {
+    bucket,
+    prefix: prefix ?? null,
+    connectionId,
+  });
+};
+
+export interface LocalApiStatus {
+  enabled: boolean;
+  running: boolean;
+  port: number | null;
+  /** Why the server couldn't start, if it didn't. */
+  error: string | null;
+}
+
+export const getLocalApiStatus = async () => {
+  return await invoke<LocalApiStatus>("local_api_status");
+};
+
+/**
+ * Turns the automation API on or off. Clients connect to the port in the status (also written
+ * to local-api.json in the config dir) and authenticate with a token from createApiGrant.
+ */
+export const setLocalApi = async (enabled: boolean, port?: number) => {
+  return await invoke<LocalApiStatus>("set_local_api", { enabled, port: port ?? null });
+};